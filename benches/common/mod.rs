@@ -0,0 +1,74 @@
+//! Setup shared by the benchmarks, which are plain programs (`harness = false`) so that they run
+//! on stable Rust with `cargo bench`.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use hrtracker::{
+    schedule::RegularSchedule,
+    store::{DirStore, save_named},
+};
+
+/// A data directory of its own under the temporary directory, removed when dropped.
+pub struct Dir(pub PathBuf);
+impl Dir {
+    pub fn new(bench: &str) -> Self {
+        let path = env::temp_dir().join(format!("hrtracker-bench-{bench}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("the temporary directory is writable");
+        Self(path)
+    }
+    pub fn store(&self) -> DirStore {
+        DirStore::new(self.0.clone())
+    }
+}
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Saves `count` daily schedules in `store`, each stepped `steps` times so that it has a history
+/// to decode.
+pub fn fill(store: &DirStore, count: usize, steps: usize) {
+    let start = Utc.with_ymd_and_hms(2020, 1, 1, 9, 0, 0).unwrap();
+    for index in 0..count {
+        let mut schedule = RegularSchedule::builder()
+            .start(start)
+            .interval(TimeDelta::days(1))
+            .build()
+            .expect("the schedule is valid");
+        for _ in 0..steps {
+            let due = schedule.next;
+            schedule.step(due).expect("the schedule can be stepped");
+        }
+        save_named(store, &format!("schedule-{index:05}"), &schedule)
+            .expect("the schedule can be saved");
+    }
+}
+
+/// The fastest of `runs` runs of `f`, which is less noisy than the mean.
+pub fn fastest(runs: usize, mut f: impl FnMut()) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let started = Instant::now();
+            f();
+            started.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Prints one result line, so that the benchmarks all read alike.
+pub fn report(what: &str, took: Duration, count: usize) {
+    println!(
+        "{what:<40} {:>9.2} ms ({:.2} µs per schedule)",
+        took.as_secs_f64() * 1000.0,
+        took.as_secs_f64() * 1_000_000.0 / count as f64
+    );
+}
@@ -0,0 +1,71 @@
+//! Compares ways of decoding a data directory of a few thousand schedules: straight from each
+//! `File`, where every byte of a varint is its own `read` call, through a `BufReader`, and from the
+//! whole file read into memory first, which is what `DirStore` does.
+
+mod common;
+
+use std::{
+    fs::{self, File},
+    hint::black_box,
+    io::BufReader,
+    path::PathBuf,
+};
+
+use decent::{Decodable, PrimitiveRepr, Version};
+use hrtracker::{
+    schedule::{RegularSchedule, decode_schedule},
+    store::{Store, load_all},
+};
+
+use common::{Dir, fastest, fill, report};
+
+const SCHEDULES: usize = 2000;
+const STEPS: usize = 200;
+const RUNS: usize = 5;
+
+fn main() {
+    let dir = Dir::new("decode");
+    let store = dir.store();
+    fill(&store, SCHEDULES, STEPS);
+    let paths: Vec<PathBuf> = store
+        .list()
+        .expect("the schedules are listed")
+        .iter()
+        .map(|name| store.path(name))
+        .collect();
+    let decode_each = |open: &dyn Fn(&PathBuf) -> RegularSchedule| {
+        for path in &paths {
+            black_box(open(path));
+        }
+    };
+
+    let unbuffered = fastest(RUNS, || {
+        decode_each(&|path| {
+            let mut file = File::open(path).expect("the schedule opens");
+            RegularSchedule::decode(&mut file, Version::ZERO, PrimitiveRepr::Varint)
+                .expect("the schedule decodes")
+        })
+    });
+    let buffered = fastest(RUNS, || {
+        decode_each(&|path| {
+            let mut file = BufReader::new(File::open(path).expect("the schedule opens"));
+            RegularSchedule::decode(&mut file, Version::ZERO, PrimitiveRepr::Varint)
+                .expect("the schedule decodes")
+        })
+    });
+    let in_memory = fastest(RUNS, || {
+        decode_each(&|path| {
+            decode_schedule(&fs::read(path).expect("the schedule is read"))
+                .expect("the schedule decodes")
+        })
+    });
+    let threaded = fastest(RUNS, || {
+        black_box(load_all::<RegularSchedule>(&store).expect("the schedules load"));
+    });
+
+    println!("decoding {SCHEDULES} schedules with {STEPS} steps each, fastest of {RUNS}:");
+    report("from each file, unbuffered", unbuffered, SCHEDULES);
+    report("through a BufReader", buffered, SCHEDULES);
+    report("read into memory first", in_memory, SCHEDULES);
+    report("with store::load_all", threaded, SCHEDULES);
+}
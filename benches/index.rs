@@ -0,0 +1,38 @@
+//! Compares listing a data directory of thousands of schedules by decoding every file, as `list`
+//! did before the index, with listing it through an up-to-date `.index`, as it does now. Each run
+//! starts from nothing in memory, like a new `hrtracker list` would.
+
+mod common;
+
+use std::{hint::black_box, thread};
+
+use hrtracker::{index, schedule::RegularSchedule, store::load_all};
+
+use common::{Dir, fastest, fill, report};
+
+const SCHEDULES: usize = 5000;
+const STEPS: usize = 30;
+const RUNS: usize = 10;
+
+fn main() {
+    let dir = Dir::new("index");
+    let store = dir.store();
+    fill(&store, SCHEDULES, STEPS);
+    // entries are only kept for files that haven't just changed
+    thread::sleep(index::SETTLE);
+
+    let decoded = fastest(RUNS, || {
+        let loaded = load_all::<RegularSchedule>(&store).expect("the schedules load");
+        black_box(loaded);
+    });
+    // the first run builds the index, and the rest only read it
+    index::load_all(&store).expect("the index is built");
+    let indexed = fastest(RUNS, || {
+        let loaded = index::load_all(&store).expect("the index loads");
+        black_box(loaded);
+    });
+
+    println!("listing {SCHEDULES} schedules with {STEPS} steps each, fastest of {RUNS}:");
+    report("decoding every schedule", decoded, SCHEDULES);
+    report("reading the index", indexed, SCHEDULES);
+}
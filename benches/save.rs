@@ -0,0 +1,47 @@
+//! Compares the ways `DirStore` can save a schedule: a plain `fs::write` over the old file, as it
+//! did before saves were atomic, through a temporary file renamed over it (`write_atomic`, the
+//! default), and the same with the file and the directory synced (`write_synced`, with
+//! `--durable`).
+
+mod common;
+
+use std::{fs, hint::black_box, path::Path};
+
+use hrtracker::{
+    error::{HrtError, Result},
+    store::{Store, write_atomic, write_synced},
+};
+
+use common::{Dir, fastest, fill, report};
+
+const SAVES: usize = 200;
+const STEPS: usize = 30;
+const RUNS: usize = 5;
+
+fn main() {
+    let dir = Dir::new("save");
+    let store = dir.store();
+    fill(&store, 1, STEPS);
+    let bytes = store.load("schedule-00000").expect("the schedule loads");
+    let path = dir.0.join("schedule-00000");
+    let time = |write: fn(&Path, &[u8]) -> Result<()>| {
+        fastest(RUNS, || {
+            for _ in 0..SAVES {
+                write(&path, black_box(&bytes)).expect("the schedule is saved");
+            }
+        })
+    };
+
+    let plain =
+        time(|path, bytes| fs::write(path, bytes).map_err(|error| HrtError::io(path, error)));
+    let atomic = time(write_atomic);
+    let synced = time(write_synced);
+
+    println!(
+        "saving a schedule of {} bytes {SAVES} times, fastest of {RUNS}:",
+        bytes.len()
+    );
+    report("overwriting it in place", plain, SAVES);
+    report("renaming a temporary file over it", atomic, SAVES);
+    report("the same, synced (--durable)", synced, SAVES);
+}
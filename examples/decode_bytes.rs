@@ -0,0 +1,26 @@
+//! Decodes a schedule from a byte buffer and forecasts it, without touching the filesystem. This
+//! builds with `--no-default-features`, as it would for a dashboard compiled to wasm.
+
+use chrono::{TimeDelta, Utc};
+use hrtracker::{
+    display::FormattedInterval,
+    schedule::{RegularSchedule, decode_schedule, encode_schedule},
+};
+
+fn main() {
+    // stands in for bytes fetched from wherever the schedule file is kept
+    let bytes = encode_schedule(
+        &RegularSchedule::builder()
+            .start(Utc::now())
+            .interval(TimeDelta::hours(12))
+            .build()
+            .expect("the schedule is valid"),
+    )
+    .expect("the schedule encodes");
+
+    let schedule: RegularSchedule = decode_schedule(&bytes).expect("the bytes decode");
+    println!("every {}:", FormattedInterval(schedule.interval));
+    for occurrence in schedule.occurrences().take(3) {
+        println!("    {occurrence}");
+    }
+}
@@ -0,0 +1,43 @@
+/* C bindings for hrtracker's schedule format, built with `cargo build --features ffi`. */
+
+#ifndef HRTRACKER_H
+#define HRTRACKER_H
+
+#include <stdint.h>
+
+#define HRT_OK 0
+/* A pointer argument was null. */
+#define HRT_ERR_NULL -1
+/* A path wasn't valid UTF-8. */
+#define HRT_ERR_PATH -2
+#define HRT_ERR_NOT_FOUND -3
+#define HRT_ERR_DECODE -4
+#define HRT_ERR_IO -5
+#define HRT_ERR_PANIC -6
+/* Anything else, such as an interval that overflows when stepping. */
+#define HRT_ERR_OTHER -7
+
+/* What the getters return when passed a null schedule. */
+#define HRT_INVALID INT64_MIN
+
+/* An opened schedule, owned by the caller until it is passed to `hrt_free`. */
+typedef struct HrtSchedule HrtSchedule;
+
+/* Opens the schedule file at `path`, returning null if it can't be read. */
+HrtSchedule *hrt_open(const char *path);
+
+/* When `schedule` is next due, in seconds since the Unix epoch. */
+int64_t hrt_next_unix(const HrtSchedule *schedule);
+
+/* The interval of `schedule`, in whole seconds. */
+int64_t hrt_interval_seconds(const HrtSchedule *schedule);
+
+/* Advances `schedule` by its interval and saves it to `path`, returning `HRT_OK` or an error code.
+ * The history is pruned to the latest 1024 steps, and the in-memory schedule is only changed if
+ * saving succeeds. */
+int32_t hrt_step(HrtSchedule *schedule, const char *path);
+
+/* Frees a schedule returned by `hrt_open`. Null is ignored. */
+void hrt_free(HrtSchedule *schedule);
+
+#endif /* HRTRACKER_H */
@@ -0,0 +1,540 @@
+//! How well schedules were kept to over a window of time, for `report` and `summary`.
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeDelta, TimeZone, Utc};
+
+use crate::{
+    display::{self, neglect_count, plural},
+    drift::{self, Streaks},
+    porcelain,
+    schedule::{RegularSchedule, Schedule},
+    style, table,
+};
+
+/// The start of the week `now` is in, at midnight on Monday in `now`'s timezone.
+pub fn week_start<Tz: TimeZone>(now: &DateTime<Tz>) -> Option<DateTime<Utc>> {
+    let days = now.weekday().num_days_from_monday();
+    let monday = now.date_naive().checked_sub_days(Days::new(days.into()))?;
+    midnight(&now.timezone(), monday)
+}
+
+/// The start of the month `now` is in, at midnight on the first in `now`'s timezone.
+pub fn month_start<Tz: TimeZone>(now: &DateTime<Tz>) -> Option<DateTime<Utc>> {
+    midnight(&now.timezone(), now.date_naive().with_day(1)?)
+}
+
+/// The start of `date` in `tz`, which is an hour past midnight where a daylight saving change
+/// skips midnight itself.
+fn midnight<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> Option<DateTime<Utc>> {
+    (0..=1).find_map(|hour| {
+        let start = tz
+            .from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+            .earliest()?;
+        Some(start.with_timezone(&Utc))
+    })
+}
+
+/// How many whole intervals fit between `from` and `to`, which is how many occurrences a schedule
+/// was expected to have in that window. Nothing is expected of an empty or reversed window.
+pub fn expected(interval: TimeDelta, from: DateTime<Utc>, to: DateTime<Utc>) -> u64 {
+    let window = to.signed_duration_since(from).num_milliseconds();
+    match (window, interval.num_milliseconds()) {
+        (1.., interval @ 1..) => (window / interval) as u64,
+        _ => 0,
+    }
+}
+
+/// When `schedule` starts being expected to occur in a window starting at `since`. Schedules don't
+/// record when they were created, so one that has never been stepped counts from its first
+/// occurrence, and one whose whole history is known counts from its first recorded occurrence or
+/// step; a schedule with pruned steps is assumed to have existed for the whole window.
+pub fn window_start(schedule: &RegularSchedule, since: DateTime<Utc>) -> DateTime<Utc> {
+    let first = match schedule.history().first() {
+        None => schedule.next,
+        Some(_) if schedule.total_steps() > schedule.history().len() as u64 => return since,
+        Some(step) => schedule
+            .satisfied()
+            .first()
+            .map_or(step.at, |due| step.at.min(*due)),
+    };
+    since.max(first)
+}
+
+/// Steps against expected occurrences for one schedule, or for all of them.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Adherence {
+    pub name: String,
+    /// Steps recorded in the window.
+    pub steps: u64,
+    /// Occurrences expected in the window.
+    pub expected: u64,
+}
+impl Adherence {
+    /// Measures `schedule` over the window from `since` to `now`.
+    pub fn of(
+        name: &str,
+        schedule: &RegularSchedule,
+        since: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            steps: schedule
+                .history()
+                .iter()
+                .filter(|step| (since..=now).contains(&step.at))
+                .count() as u64,
+            expected: expected(schedule.interval, window_start(schedule, since), now),
+        }
+    }
+    /// The steps as a percentage of the expected occurrences, which may be over 100 when a
+    /// schedule was stepped more often than it was due. `None` when nothing was expected.
+    pub fn percent(&self) -> Option<f64> {
+        (self.expected > 0).then(|| self.steps as f64 * 100.0 / self.expected as f64)
+    }
+    /// Orders the worst kept first, then by name; those with nothing expected come last.
+    pub fn worst_first(&self, other: &Self) -> Ordering {
+        match (self.percent(), other.percent()) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        }
+        .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+/// The sums over every schedule in `adherences`, under the name `total`.
+pub fn total(adherences: &[Adherence]) -> Adherence {
+    Adherence {
+        name: "total".to_string(),
+        steps: adherences.iter().map(|adherence| adherence.steps).sum(),
+        expected: adherences.iter().map(|adherence| adherence.expected).sum(),
+    }
+}
+
+/// How long `schedule` was overdue between `since` and `until`: how late the steps made in that
+/// time were (counting only the lateness within it), plus how long it has been overdue since
+/// `next`, if that is before `until`.
+pub fn overdue_within(
+    schedule: &RegularSchedule,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> TimeDelta {
+    let stepped = schedule.stepped_at();
+    let late: TimeDelta = drift::pairs(&stepped, schedule.satisfied())
+        .filter(|(stepped, _)| (since..=until).contains(stepped))
+        .map(|(stepped, due)| {
+            stepped
+                .signed_duration_since(due.max(since))
+                .max(TimeDelta::zero())
+        })
+        .sum();
+    let pending = match schedule.next < until {
+        true => until.signed_duration_since(schedule.next.max(since)),
+        false => TimeDelta::zero(),
+    };
+    late + pending
+}
+
+/// What happened to a schedule's latest streak between two times.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StreakChange {
+    /// Occurrences added to the streak in that time.
+    pub gained: usize,
+    /// The length of the streak at the end of that time.
+    pub length: usize,
+    /// Whether the streak had been broken by then.
+    pub broken: bool,
+}
+impl StreakChange {
+    /// The change between `since` and `until`, as the steps recorded up to `until` show it, or
+    /// `None` if the schedule's streak neither grew nor broke in that time.
+    pub fn of(
+        schedule: &RegularSchedule,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Option<Self> {
+        let stepped = schedule.stepped_at();
+        // leave out the steps after `until`, along with the occurrences they satisfied
+        let later = stepped.iter().rev().take_while(|at| **at > until).count();
+        let history = &stepped[..stepped.len() - later];
+        let satisfied = schedule.satisfied();
+        let satisfied = &satisfied[..satisfied.len().saturating_sub(later)];
+        let next = match later {
+            0 => Some(schedule.next),
+            _ => satisfied
+                .last()
+                .and_then(|due| due.checked_add_signed(schedule.interval)),
+        }?;
+        let streaks = Streaks::of(history, satisfied, schedule.interval, next, until);
+        let latest = streaks.latest?;
+        let gained = latest.since(since, schedule.interval);
+        let broken = streaks.current.is_none();
+        // the streak breaks once the occurrence after its last one goes unstepped until the next
+        let broke_within = broken
+            && latest
+                .last
+                .checked_add_signed(schedule.interval * 2)
+                .is_some_and(|broke| (since..=until).contains(&broke));
+        (gained > 0 || broke_within).then_some(Self {
+            gained,
+            length: latest.length,
+            broken,
+        })
+    }
+}
+
+/// Everything `summary` shows of the window from `since` to `until`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Summary {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// The schedules stepped in the window, in the order they were given.
+    pub stepped: Vec<Adherence>,
+    /// The schedules that were expected in the window but never stepped.
+    pub missed: Vec<Adherence>,
+    /// The schedules whose streaks grew or broke in the window.
+    pub streaks: Vec<(String, StreakChange)>,
+    /// How long schedules were overdue in the window, added up as `overdue_within` measures it.
+    pub overdue: TimeDelta,
+}
+impl Summary {
+    pub fn of(
+        schedules: &[(String, RegularSchedule)],
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Self {
+        let adherences: Vec<_> = schedules
+            .iter()
+            .map(|(name, schedule)| Adherence::of(name, schedule, since, until))
+            .collect();
+        let (stepped, unstepped): (Vec<_>, Vec<_>) =
+            adherences.into_iter().partition(|row| row.steps > 0);
+        Self {
+            since,
+            until,
+            stepped,
+            missed: unstepped
+                .into_iter()
+                .filter(|row| row.expected > 0)
+                .collect(),
+            streaks: schedules
+                .iter()
+                .filter_map(|(name, schedule)| {
+                    StreakChange::of(schedule, since, until).map(|change| (name.clone(), change))
+                })
+                .collect(),
+            overdue: schedules
+                .iter()
+                .map(|(_, schedule)| overdue_within(schedule, since, until))
+                .sum(),
+        }
+    }
+
+    /// The summary as people read it: a section for each of `stepped`, `missed`, and `streaks`,
+    /// then the time overdue, with datetimes and durations printed as `options` say.
+    pub fn render(&self, options: &display::Options, color: bool) -> String {
+        let mut text = format!(
+            "summary from {} to {}\n",
+            options.datetime(&self.since),
+            options.datetime(&self.until)
+        );
+        let mut section = |title: &str, rows: Vec<Vec<table::Cell>>| {
+            text.push('\n');
+            text.push_str(&style::paint(title, style::BOLD, color));
+            text.push('\n');
+            match rows.is_empty() {
+                true => text.push_str("nothing\n"),
+                false => text.push_str(&table::render(None, &rows, color)),
+            }
+        };
+        section(
+            "stepped",
+            self.stepped
+                .iter()
+                .map(|row| {
+                    vec![
+                        table::Cell::plain(&row.name),
+                        table::Cell::plain(plural(row.steps as i64, "time")),
+                        table::Cell::plain(format!("of {} expected", row.expected)),
+                    ]
+                })
+                .collect(),
+        );
+        section(
+            "due but never stepped",
+            self.missed
+                .iter()
+                .map(|row| {
+                    vec![
+                        table::Cell::plain(&row.name),
+                        table::Cell::plain(format!("{} expected", row.expected)),
+                    ]
+                })
+                .collect(),
+        );
+        section(
+            "streaks",
+            self.streaks
+                .iter()
+                .map(|(name, change)| {
+                    let status = match change.broken {
+                        true => format!("+{}, then broken after {}", change.gained, change.length),
+                        false => format!("+{}, now {}", change.gained, change.length),
+                    };
+                    vec![table::Cell::plain(name), table::Cell::plain(status)]
+                })
+                .collect(),
+        );
+        text.push_str(&format!(
+            "\n{} overdue in total, counting late steps and what is still overdue\n",
+            options.interval(self.overdue)
+        ));
+        text
+    }
+}
+
+/// Everything `report` shows: how well each schedule was kept to from `since` until `until`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Report {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// A row for each schedule, ordered by `Adherence::worst_first`.
+    pub rows: Vec<Adherence>,
+    /// The sum of `rows`, as `total` adds them up.
+    pub total: Adherence,
+    /// How many schedules have never been stepped, though they were due an interval ago.
+    pub neglected: usize,
+}
+impl Report {
+    pub fn of(
+        schedules: &[(String, RegularSchedule)],
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Self {
+        let mut rows: Vec<_> = schedules
+            .iter()
+            .map(|(name, schedule)| Adherence::of(name, schedule, since, until))
+            .collect();
+        rows.sort_by(Adherence::worst_first);
+        Self {
+            since,
+            until,
+            total: total(&rows),
+            rows,
+            neglected: schedules
+                .iter()
+                .filter(|(_, schedule)| schedule.describe().neglected(until))
+                .count(),
+        }
+    }
+
+    /// A porcelain line for each row and then the total: the name, steps, expected occurrences,
+    /// and the adherence as a whole percentage, empty when nothing was expected.
+    pub fn porcelain(&self) -> String {
+        self.rows
+            .iter()
+            .chain([&self.total])
+            .map(|adherence| {
+                let fields = [
+                    adherence.name.clone(),
+                    adherence.steps.to_string(),
+                    adherence.expected.to_string(),
+                    adherence
+                        .percent()
+                        .map(|percent| format!("{percent:.0}"))
+                        .unwrap_or_default(),
+                ];
+                porcelain::line(&fields) + "\n"
+            })
+            .collect()
+    }
+
+    /// The rows and the total as a table, with a header if `header` is set, followed by how many
+    /// schedules were neglected, if any were.
+    pub fn render(&self, header: bool, color: bool) -> String {
+        let percent = |adherence: &Adherence| match adherence.percent() {
+            Some(percent) => format!("{percent:.0}%"),
+            None => "-".to_string(),
+        };
+        let mut rows: Vec<_> = self
+            .rows
+            .iter()
+            .map(|adherence| {
+                vec![
+                    table::Cell::styled(&adherence.name, style::BOLD),
+                    table::Cell::plain(adherence.steps),
+                    table::Cell::plain(adherence.expected),
+                    table::Cell::plain(percent(adherence)),
+                ]
+            })
+            .collect();
+        rows.push(vec![
+            table::Cell::plain("total"),
+            table::Cell::plain(self.total.steps),
+            table::Cell::plain(self.total.expected),
+            table::Cell::plain(percent(&self.total)),
+        ]);
+        let titles: &[&str] = &["NAME", "STEPS", "EXPECTED", "ADHERENCE"];
+        let mut text = table::render(header.then_some(titles), &rows, color);
+        if self.neglected > 0 {
+            text.push_str(&format!(
+                "{} never been stepped, though first due over an interval ago\n",
+                neglect_count(self.neglected)
+            ));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    /// The week from 2025-03-01 if `water`, due daily, was stepped on time twice and then half a
+    /// day late, `pills`, due every other day, was never stepped, and `rest` isn't due yet.
+    fn week() -> Vec<(String, RegularSchedule)> {
+        let mut water = RegularSchedule::create(date("2025-03-01T09:00:00Z"), TimeDelta::days(1));
+        for at in [
+            "2025-03-01T09:00:00Z",
+            "2025-03-02T09:00:00Z",
+            "2025-03-03T21:00:00Z",
+        ] {
+            water.step(date(at)).unwrap();
+        }
+        vec![
+            ("water".to_string(), water),
+            (
+                "pills".to_string(),
+                RegularSchedule::create(date("2025-03-02T09:00:00Z"), TimeDelta::days(2)),
+            ),
+            (
+                "rest".to_string(),
+                RegularSchedule::create(date("2025-03-20T09:00:00Z"), TimeDelta::days(7)),
+            ),
+        ]
+    }
+
+    const SINCE: &str = "2025-03-01T00:00:00Z";
+    const UNTIL: &str = "2025-03-08T00:00:00Z";
+
+    fn row(name: &str, steps: u64, expected: u64) -> Adherence {
+        Adherence {
+            name: name.to_string(),
+            steps,
+            expected,
+        }
+    }
+
+    #[test]
+    fn periods_start_at_local_midnight() {
+        let tz = FixedOffset::east_opt(3600).unwrap();
+        // a Wednesday, already Thursday in that timezone
+        let now = date("2025-03-05T23:30:00Z").with_timezone(&tz);
+        assert_eq!(week_start(&now), Some(date("2025-03-02T23:00:00Z")));
+        assert_eq!(month_start(&now), Some(date("2025-02-28T23:00:00Z")));
+    }
+
+    #[test]
+    fn only_whole_intervals_are_expected() {
+        let day = TimeDelta::days(1);
+        assert_eq!(expected(day, date(SINCE), date(UNTIL)), 7);
+        assert_eq!(expected(day, date(SINCE), date("2025-03-01T23:59:59Z")), 0);
+        assert_eq!(expected(day, date(UNTIL), date(SINCE)), 0);
+        assert_eq!(expected(TimeDelta::zero(), date(SINCE), date(UNTIL)), 0);
+    }
+
+    #[test]
+    fn the_worst_kept_come_first() {
+        let mut rows = vec![
+            row("b", 1, 2),
+            row("none", 0, 0),
+            row("a", 1, 2),
+            row("c", 0, 1),
+        ];
+        rows.sort_by(Adherence::worst_first);
+        let names: Vec<_> = rows.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(names, ["c", "a", "b", "none"]);
+        assert_eq!(total(&rows), row("total", 2, 5));
+        assert_eq!(row("more", 3, 2).percent(), Some(150.0));
+    }
+
+    #[test]
+    fn summaries_sort_schedules_into_sections() {
+        let summary = Summary::of(&week(), date(SINCE), date(UNTIL));
+        // `water` counts from its first occurrence, so six of its days are in the window
+        assert_eq!(summary.stepped, [row("water", 3, 6)]);
+        assert_eq!(summary.missed, [row("pills", 0, 2)]);
+        assert_eq!(
+            summary.streaks,
+            [(
+                "water".to_string(),
+                StreakChange {
+                    gained: 3,
+                    length: 3,
+                    broken: true,
+                }
+            )]
+        );
+        // `water` was 12 hours late once and has been due since the 4th at 09:00, and `pills`
+        // since the 2nd at 09:00
+        assert_eq!(summary.overdue, TimeDelta::hours(12 + 87 + 135));
+    }
+
+    #[test]
+    fn summaries_render_each_section() {
+        let summary = Summary::of(&week(), date(SINCE), date(UNTIL));
+        assert_eq!(
+            summary.render(&display::Options::default(), false),
+            "summary from 2025-03-01 00:00:00 UTC to 2025-03-08 00:00:00 UTC\n\
+             \n\
+             stepped\n\
+             water  3 times  of 6 expected\n\
+             \n\
+             due but never stepped\n\
+             pills  2 expected\n\
+             \n\
+             streaks\n\
+             water  +3, then broken after 3\n\
+             \n\
+             234h00m00s overdue in total, counting late steps and what is still overdue\n"
+        );
+        let empty = Summary::of(&[], date(SINCE), date(UNTIL));
+        assert!(
+            empty
+                .render(&display::Options::default(), false)
+                .contains("\nstreaks\nnothing\n")
+        );
+    }
+
+    #[test]
+    fn reports_rank_schedules_and_count_the_neglected() {
+        let report = Report::of(&week(), date(SINCE), date(UNTIL));
+        assert_eq!(
+            report.rows,
+            [row("pills", 0, 2), row("water", 3, 6), row("rest", 0, 0)]
+        );
+        assert_eq!(report.total, row("total", 3, 8));
+        assert_eq!(report.neglected, 1);
+        assert_eq!(
+            report.porcelain(),
+            "pills\t0\t2\t0\nwater\t3\t6\t50\nrest\t0\t0\t\ntotal\t3\t8\t38\n"
+        );
+        assert_eq!(
+            report.render(false, false),
+            "pills  0  2  0%\n\
+             water  3  6  50%\n\
+             rest   0  0  -\n\
+             total  3  8  38%\n\
+             1 schedule has never been stepped, though first due over an interval ago\n"
+        );
+        let header = report.render(true, false);
+        assert!(header.starts_with("NAME   STEPS  EXPECTED  ADHERENCE\n"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Short names that stand for schedules, kept in the store as `short = target` lines.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use crate::{
+    error::{HrtError, Result},
+    store::{Stamp, Store, StoreLock},
+};
+
+/// The name aliases are kept under in the store, which stores never list as a schedule.
+pub const FILE: &str = ".aliases";
+
+/// Every alias, by its short name.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Aliases(pub BTreeMap<String, String>);
+impl Aliases {
+    /// Reads `short = target` lines, skipping blank ones and `#` comments. Fails with the number of
+    /// the first line that isn't a pair.
+    pub fn parse(text: &str) -> std::result::Result<Self, usize> {
+        let mut aliases = BTreeMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (short, target) = line.split_once('=').ok_or(index + 1)?;
+            aliases.insert(short.trim().to_string(), target.trim().to_string());
+        }
+        Ok(Self(aliases))
+    }
+    pub fn render(&self) -> String {
+        self.0
+            .iter()
+            .map(|(short, target)| format!("{short} = {target}\n"))
+            .collect()
+    }
+    /// The aliases kept in `store`, or none if it has none.
+    pub fn read(store: &impl Store) -> Result<Self> {
+        let bytes = match store.load(FILE) {
+            Ok(bytes) => bytes,
+            Err(HrtError::NotFound { .. }) => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+        let malformed = |reason| HrtError::Config {
+            path: PathBuf::from(FILE),
+            reason,
+        };
+        let text = String::from_utf8(bytes).map_err(|_| malformed("not UTF-8".to_string()))?;
+        Self::parse(&text)
+            .map_err(|line| malformed(format!("line {line} is not a `short = target` pair")))
+    }
+    pub fn write(&self, store: &impl Store) -> Result<()> {
+        store.save(FILE, self.render().as_bytes())
+    }
+    /// Whether `short` is an alias.
+    pub fn contains(&self, short: &str) -> bool {
+        self.0.contains_key(short)
+    }
+    /// Points every alias that leads to `old` at `new` instead, returning whether any did.
+    pub fn retarget(&mut self, old: &str, new: &str) -> bool {
+        let mut changed = false;
+        for target in self.0.values_mut() {
+            if target == old {
+                *target = new.to_string();
+                changed = true;
+            }
+        }
+        changed
+    }
+    /// Removes every alias that resolves to `name`, and returns their short names.
+    pub fn remove_resolving_to(&mut self, name: &str) -> Vec<String> {
+        let removed: Vec<String> = self
+            .0
+            .keys()
+            .filter(|short| self.resolve(short).is_ok_and(|target| target == name))
+            .cloned()
+            .collect();
+        for short in &removed {
+            self.0.remove(short);
+        }
+        removed
+    }
+    /// Follows `name` through aliases until it reaches a name that isn't one, which is returned
+    /// as is if `name` isn't an alias. Fails on a chain that loops.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Result<&'a str> {
+        let mut seen = BTreeSet::new();
+        let mut name = name;
+        while let Some(target) = self.0.get(name) {
+            if !seen.insert(name) {
+                return Err(HrtError::Usage(format!(
+                    "the alias `{name}` points back to itself through other aliases"
+                )));
+            }
+            name = target;
+        }
+        Ok(name)
+    }
+}
+
+/// A store that looks names up as aliases when it has no schedule by that exact name. The
+/// aliases are read from the wrapped store each time, so that changes to them are seen at once.
+#[derive(Debug)]
+pub struct Aliased<S> {
+    pub store: S,
+}
+impl<S: Store> Aliased<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+    /// Runs `operation` on `name`, and again on what it resolves to if there is no schedule
+    /// called `name` but it is an alias.
+    fn resolving<T>(&self, name: &str, operation: impl Fn(&str) -> Result<T>) -> Result<T> {
+        match operation(name) {
+            Err(error @ HrtError::NotFound { .. }) => {
+                let aliases = Aliases::read(&self.store)?;
+                if !aliases.contains(name) {
+                    return Err(error);
+                }
+                operation(aliases.resolve(name)?).map_err(|error| match error {
+                    HrtError::NotFound { .. } => HrtError::NotFound {
+                        name: name.to_string(),
+                    },
+                    error => error,
+                })
+            }
+            result => result,
+        }
+    }
+}
+impl<S: Store> Store for Aliased<S> {
+    fn list(&self) -> Result<Vec<String>> {
+        self.store.list()
+    }
+    fn load(&self, name: &str) -> Result<Vec<u8>> {
+        self.resolving(name, |name| self.store.load(name))
+    }
+    fn save(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        // saving never fails with `NotFound`, so check for the schedule first
+        match self.store.load(name) {
+            Err(HrtError::NotFound { .. }) => {
+                let aliases = Aliases::read(&self.store)?;
+                self.store.save(aliases.resolve(name)?, bytes)
+            }
+            _ => self.store.save(name, bytes),
+        }
+    }
+    fn delete(&self, name: &str) -> Result<()> {
+        self.resolving(name, |name| self.store.delete(name))
+    }
+    fn stamp(&self, name: &str) -> Result<Option<Stamp>> {
+        // listings only ever stamp the names the store lists, which are never aliases
+        self.store.stamp(name)
+    }
+    fn lock(&self) -> Result<StoreLock> {
+        self.store.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemStore;
+
+    fn aliases(pairs: &[(&str, &str)]) -> Aliases {
+        Aliases(
+            pairs
+                .iter()
+                .map(|(short, target)| (short.to_string(), target.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn aliases_are_read_as_pairs() {
+        let parsed = Aliases::parse("# mine\n\n w = water \np=pills\n").unwrap();
+        assert_eq!(parsed, aliases(&[("w", "water"), ("p", "pills")]));
+        assert_eq!(parsed.render(), "p = pills\nw = water\n");
+        assert_eq!(Aliases::parse("w = water\nnonsense\n"), Err(2));
+    }
+
+    #[test]
+    fn chains_are_followed_to_the_end() {
+        let aliases = aliases(&[("w", "drink"), ("drink", "water"), ("x", "y"), ("y", "x")]);
+        assert_eq!(aliases.resolve("w").unwrap(), "water");
+        assert_eq!(aliases.resolve("water").unwrap(), "water");
+        assert!(matches!(aliases.resolve("x"), Err(HrtError::Usage(_))));
+    }
+
+    #[test]
+    fn aliases_follow_their_schedules() {
+        let mut aliases = aliases(&[("w", "drink"), ("drink", "water"), ("p", "pills")]);
+        assert!(aliases.retarget("water", "hydrate"));
+        assert!(!aliases.retarget("water", "hydrate"));
+        assert_eq!(aliases.resolve("w").unwrap(), "hydrate");
+        assert_eq!(aliases.remove_resolving_to("hydrate"), ["drink", "w"]);
+        assert_eq!(aliases, self::aliases(&[("p", "pills")]));
+    }
+
+    #[test]
+    fn aliases_are_kept_out_of_listings() {
+        let store = MemStore::new();
+        assert_eq!(Aliases::read(&store).unwrap(), Aliases::default());
+        aliases(&[("w", "water")]).write(&store).unwrap();
+        assert_eq!(store.load(FILE).unwrap(), b"w = water\n");
+        assert!(store.list().unwrap().is_empty());
+        store.save(FILE, b"w = water\nwhat\n").unwrap();
+        let error = Aliases::read(&store).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "in .aliases: line 2 is not a `short = target` pair"
+        );
+        store.save(FILE, b"\xff").unwrap();
+        assert!(Aliases::read(&store).is_err());
+    }
+
+    #[test]
+    fn aliased_stores_look_aliases_up_last() {
+        let aliased = Aliased::new(MemStore::new());
+        aliases(&[("w", "water"), ("gone", "nothing")])
+            .write(&aliased.store)
+            .unwrap();
+        aliased.store.save("water", b"water").unwrap();
+        // a schedule is found by its own name before any alias
+        aliased.store.save("w", b"w").unwrap();
+        assert_eq!(aliased.load("w").unwrap(), b"w");
+        aliased.store.delete("w").unwrap();
+        assert_eq!(aliased.load("w").unwrap(), b"water");
+        aliased.save("w", b"saved").unwrap();
+        assert_eq!(aliased.store.load("water").unwrap(), b"saved");
+        // what isn't there is reported under the name that was asked for
+        match aliased.load("gone") {
+            Err(HrtError::NotFound { name }) => assert_eq!(name, "gone"),
+            other => panic!("{other:?}"),
+        }
+        assert!(matches!(
+            aliased.load("missing"),
+            Err(HrtError::NotFound { .. })
+        ));
+        aliased.delete("w").unwrap();
+        assert_eq!(aliased.list().unwrap(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,36 @@
+//! `at(1)` jobs that run a command once, at a reminder time.
+
+use chrono::NaiveDateTime;
+
+use crate::cron::shell_quote;
+
+/// `time` in the `[[CC]YY]MMDDhhmm[.ss]` form `at -t` takes, which every `at` accepts, unlike
+/// its many free-form time formats. `time` should be in the local timezone, as `at` reads it.
+pub fn time(time: NaiveDateTime) -> String {
+    time.format("%Y%m%d%H%M.%S").to_string()
+}
+
+/// The shell command line that schedules `command` to run at `time` (from `time`).
+pub fn invocation(command: &str, time: &str) -> String {
+    format!("echo {} | at -t {time}", shell_quote(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn jobs_are_scheduled_with_at_t() {
+        let reminder = NaiveDate::from_ymd_opt(2025, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 5, 30)
+            .unwrap();
+        assert_eq!(time(reminder), "202503010905.30");
+        assert_eq!(
+            invocation("hrtracker step water", &time(reminder)),
+            "echo 'hrtracker step water' | at -t 202503010905.30"
+        );
+    }
+}
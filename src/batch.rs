@@ -0,0 +1,112 @@
+//! Running a command line from each line of some input, for `batch`.
+
+use std::io::BufRead;
+
+use crate::{error::Result, porcelain};
+
+/// A line that `run` ran, and how it went.
+#[derive(Debug)]
+pub struct Outcome<'a> {
+    /// Counting from 1, including the lines that were skipped.
+    pub number: usize,
+    /// Without the whitespace around it.
+    pub line: &'a str,
+    pub result: Result<()>,
+}
+impl Outcome<'_> {
+    /// The porcelain line for the outcome: the line number, then `ok` or `failed` and the error.
+    pub fn porcelain(&self) -> String {
+        let (status, error) = match &self.result {
+            Ok(()) => ("ok", String::new()),
+            Err(error) => ("failed", error.to_string()),
+        };
+        porcelain::line(&[self.number.to_string(), status.to_string(), error])
+    }
+    /// The outcome as people read it: the line after `+` if it worked, and after `!` with the
+    /// error if it didn't.
+    pub fn render(&self) -> String {
+        match &self.result {
+            Ok(()) => format!("+ {}", self.line),
+            Err(error) => format!("! {}: {error}", self.line),
+        }
+    }
+}
+
+/// Runs each line of `input` with `execute`, skipping blank lines and those starting with `#`,
+/// and gives `report` the outcome of each. Every line is attempted unless `stop_on_error` is set.
+/// Returns whether they all worked.
+pub fn run(
+    input: impl BufRead,
+    stop_on_error: bool,
+    mut execute: impl FnMut(&str) -> Result<()>,
+    mut report: impl FnMut(&Outcome),
+) -> Result<bool> {
+    let mut failed = false;
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let outcome = Outcome {
+            number: index + 1,
+            line: trimmed,
+            result: execute(trimmed),
+        };
+        report(&outcome);
+        if outcome.result.is_err() {
+            failed = true;
+            if stop_on_error {
+                break;
+            }
+        }
+    }
+    Ok(!failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::HrtError;
+
+    use super::*;
+
+    const INPUT: &[u8] = b"step water\n\n  # a comment\n  fail here  \nstep pills\n";
+
+    /// Runs `INPUT`, failing the lines that start with `fail`, and returns what it reported.
+    fn outcomes(stop_on_error: bool) -> (bool, Vec<String>) {
+        let mut reported = Vec::new();
+        let worked = run(
+            INPUT,
+            stop_on_error,
+            |line| match line.starts_with("fail") {
+                true => Err(HrtError::Usage("it failed".to_string())),
+                false => Ok(()),
+            },
+            |outcome| reported.push(format!("{}\n{}", outcome.porcelain(), outcome.render())),
+        )
+        .unwrap();
+        (worked, reported)
+    }
+
+    #[test]
+    fn every_line_is_attempted() {
+        let (worked, reported) = outcomes(false);
+        assert!(!worked);
+        assert_eq!(
+            reported,
+            [
+                "1\tok\t\n+ step water",
+                "4\tfailed\tit failed\n! fail here: it failed",
+                "5\tok\t\n+ step pills",
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_after_a_failure_can_be_left_alone() {
+        let (worked, reported) = outcomes(true);
+        assert!(!worked);
+        assert_eq!(reported.len(), 2);
+        assert!(run(&b"# nothing\n"[..], true, |_| Ok(()), |_| {}).unwrap());
+    }
+}
@@ -0,0 +1,361 @@
+//! Month grids for `cal`.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate, TimeZone, Weekday};
+
+use crate::{porcelain, schedule::RegularSchedule, style};
+
+/// Highlights today when colors are enabled.
+pub const TODAY: &str = style::REVERSE;
+
+/// The first day of `month` in `year`, or `None` if either is out of range.
+pub fn first_of(year: i32, month: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, 1)
+}
+
+/// The first day of the month after the one `date` is in.
+pub fn next_month(date: NaiveDate) -> Option<NaiveDate> {
+    match date.month() {
+        12 => first_of(date.year() + 1, 1),
+        month => first_of(date.year(), month + 1),
+    }
+}
+
+/// The days of the month starting at `first`, as weeks starting on `week_start`. Days that belong
+/// to the previous or next month are `None`.
+pub fn weeks(first: NaiveDate, week_start: Weekday) -> Vec<[Option<u32>; 7]> {
+    let days = next_month(first)
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day());
+    let offset = first.weekday().days_since(week_start) as usize;
+    let mut weeks = Vec::new();
+    let mut week = [None; 7];
+    for day in 1..=days {
+        let column = (offset + day as usize - 1) % 7;
+        week[column] = Some(day);
+        if column == 6 {
+            weeks.push(week);
+            week = [None; 7];
+        }
+    }
+    if week.iter().any(Option::is_some) {
+        weeks.push(week);
+    }
+    weeks
+}
+
+/// Renders the month starting at `first` as a title, a row of weekday names, and one row per week.
+/// Each day is followed by its character in `marks`, if it has one, and `today` is highlighted:
+/// in reverse video when `color` is set, and with a leading `>` otherwise.
+pub fn render_month(
+    first: NaiveDate,
+    week_start: Weekday,
+    marks: &BTreeMap<u32, char>,
+    today: Option<u32>,
+    color: bool,
+) -> String {
+    const WIDTH: usize = 7 * 4;
+    let mut text = format!("{:^WIDTH$}\n", first.format("%B %Y").to_string());
+    let mut weekday = week_start;
+    for _ in 0..7 {
+        text.push_str(&format!(" {:<3}", &weekday.to_string()[..2]));
+        weekday = weekday.succ();
+    }
+    text.push('\n');
+    for week in weeks(first, week_start) {
+        let row: String = week
+            .iter()
+            .map(|day| {
+                let Some(day) = *day else {
+                    return "    ".to_string();
+                };
+                let mark = marks.get(&day).copied().unwrap_or(' ');
+                match today == Some(day) {
+                    true if color => style::paint(format!(" {day:>2}{mark}"), TODAY, true),
+                    true => format!(">{day:>2}{mark}"),
+                    false => format!(" {day:>2}{mark}"),
+                }
+            })
+            .collect();
+        text.push_str(row.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// The days some schedules occur on over a run of months, as `cal` shows them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Calendar {
+    /// The schedules, in the order they were given.
+    pub names: Vec<String>,
+    /// The day it is, which is highlighted.
+    pub today: NaiveDate,
+    /// The first day of each month shown, in order.
+    pub months: Vec<NaiveDate>,
+    /// The indices into `names` of the schedules occurring on each day, in order.
+    pub occurring: BTreeMap<NaiveDate, Vec<usize>>,
+}
+impl Calendar {
+    /// The markers days get when there are few enough schedules for each to have its own.
+    pub const MARKERS: &str = "123456789";
+    /// Keeps schedules with tiny intervals from taking forever; their days are all marked by then.
+    pub const MAX_OCCURRENCES: usize = 100_000;
+
+    /// `months` months from the one `today` is in (at least that one), with the days in `tz`
+    /// that each of `schedules` occurs on. `None` if the month `today` is in can't be represented.
+    pub fn of<Tz: TimeZone>(
+        schedules: &[(String, RegularSchedule)],
+        today: NaiveDate,
+        months: usize,
+        tz: &Tz,
+    ) -> Option<Self> {
+        let start = first_of(today.year(), today.month())?;
+        let mut firsts = vec![start];
+        for _ in 1..months {
+            match firsts.last().copied().and_then(next_month) {
+                Some(next) => firsts.push(next),
+                None => break,
+            }
+        }
+        let end = firsts.last().copied().and_then(next_month);
+        let mut occurring: BTreeMap<NaiveDate, Vec<usize>> = BTreeMap::new();
+        for (index, (_, schedule)) in schedules.iter().enumerate() {
+            let days = schedule
+                .occurrences()
+                .take(Self::MAX_OCCURRENCES)
+                .map(|occurrence| occurrence.with_timezone(tz).date_naive())
+                .take_while(|day| end.is_none_or(|end| *day < end))
+                .filter(|day| *day >= start);
+            for day in days {
+                let indices = occurring.entry(day).or_default();
+                if indices.last() != Some(&index) {
+                    indices.push(index);
+                }
+            }
+        }
+        Some(Self {
+            names: schedules.iter().map(|(name, _)| name.clone()).collect(),
+            today,
+            months: firsts,
+            occurring,
+        })
+    }
+
+    /// One porcelain line for each schedule on each day it occurs on: the date, then the name.
+    pub fn porcelain(&self) -> String {
+        self.occurring
+            .iter()
+            .flat_map(|(day, indices)| {
+                indices.iter().map(move |index| {
+                    let date = day.format("%Y-%m-%d").to_string();
+                    porcelain::line(&[date, self.names[*index].clone()]) + "\n"
+                })
+            })
+            .collect()
+    }
+
+    /// Renders every month with `render_month`, marking each day a schedule occurs on: with its
+    /// own marker and a line in the legend after the months if there are few enough schedules,
+    /// with `*` otherwise, and with `+` where several occur on the same day.
+    pub fn render(&self, color: bool) -> String {
+        let legend = self.names.len() <= Self::MARKERS.len();
+        let marks: BTreeMap<NaiveDate, char> = self
+            .occurring
+            .iter()
+            .map(|(day, indices)| {
+                let mark = match indices[..] {
+                    [index] if legend => Self::MARKERS.chars().nth(index).unwrap_or('*'),
+                    [_] => '*',
+                    _ => '+',
+                };
+                (*day, mark)
+            })
+            .collect();
+        let mut text = String::new();
+        for (index, first) in self.months.iter().enumerate() {
+            if index > 0 {
+                text.push('\n');
+            }
+            let month_marks = marks
+                .range(*first..)
+                .take_while(|(day, _)| day.month() == first.month())
+                .map(|(day, mark)| (day.day(), *mark))
+                .collect();
+            let this_month =
+                (self.today.year(), self.today.month()) == (first.year(), first.month());
+            text.push_str(&render_month(
+                *first,
+                Weekday::Mon,
+                &month_marks,
+                this_month.then_some(self.today.day()),
+                color,
+            ));
+        }
+        if legend && !self.names.is_empty() {
+            text.push('\n');
+            for (name, marker) in self.names.iter().zip(Self::MARKERS.chars()) {
+                text.push_str(&format!("{marker}  {name}\n"));
+            }
+            if marks.values().any(|mark| *mark == '+') {
+                text.push_str("+  more than one\n");
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// `water` weekly from Monday 2025-03-03, and `pills` fortnightly a week later.
+    fn march() -> Vec<(String, RegularSchedule)> {
+        vec![
+            (
+                "water".to_string(),
+                RegularSchedule::create(date("2025-03-03T09:00:00Z"), TimeDelta::days(7)),
+            ),
+            (
+                "pills".to_string(),
+                RegularSchedule::create(date("2025-03-10T09:00:00Z"), TimeDelta::days(14)),
+            ),
+        ]
+    }
+
+    #[test]
+    fn weeks_are_laid_out_from_the_week_start() {
+        // 2025-03-01 is a Saturday
+        let weeks = weeks(day(2025, 3, 1), Weekday::Mon);
+        assert_eq!(weeks.len(), 6);
+        assert_eq!(weeks[0], [None, None, None, None, None, Some(1), Some(2)]);
+        assert_eq!(weeks[5], [Some(31), None, None, None, None, None, None]);
+        // February 2026 starts on a Sunday, so its 28 days fill four weeks exactly
+        let weeks = super::weeks(day(2026, 2, 1), Weekday::Sun);
+        assert_eq!(weeks.len(), 4);
+        assert_eq!(weeks[3][6], Some(28));
+    }
+
+    #[test]
+    fn months_roll_over_into_the_next_year() {
+        assert_eq!(next_month(day(2025, 12, 31)), Some(day(2026, 1, 1)));
+        assert_eq!(next_month(day(2025, 3, 15)), Some(day(2025, 4, 1)));
+        assert_eq!(first_of(2025, 13), None);
+    }
+
+    #[test]
+    fn days_are_marked_by_schedule() {
+        let calendar = Calendar::of(&march(), day(2025, 3, 12), 1, &Utc).unwrap();
+        assert_eq!(calendar.months, [day(2025, 3, 1)]);
+        assert_eq!(
+            calendar.occurring,
+            BTreeMap::from([
+                (day(2025, 3, 3), vec![0]),
+                (day(2025, 3, 10), vec![0, 1]),
+                (day(2025, 3, 17), vec![0]),
+                (day(2025, 3, 24), vec![0, 1]),
+                (day(2025, 3, 31), vec![0]),
+            ])
+        );
+        assert_eq!(
+            calendar.porcelain(),
+            "2025-03-03\twater\n2025-03-10\twater\n2025-03-10\tpills\n2025-03-17\twater\n\
+             2025-03-24\twater\n2025-03-24\tpills\n2025-03-31\twater\n"
+        );
+        let text = calendar.render(false);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[0].trim(), "March 2025");
+        assert_eq!(lines[1], " Mo  Tu  We  Th  Fr  Sa  Su ");
+        assert_eq!(lines[3], "  31  4   5   6   7   8   9");
+        // today is marked with `>`, and the 10th has both schedules
+        assert_eq!(lines[4], " 10+ 11 >12  13  14  15  16");
+        assert!(text.ends_with("\n\n1  water\n2  pills\n+  more than one\n"));
+        let colored = calendar.render(true);
+        assert!(colored.contains(&style::paint(" 12 ", TODAY, true)));
+    }
+
+    #[test]
+    fn later_months_stop_at_the_last_one() {
+        let schedules = [(
+            "water".to_string(),
+            RegularSchedule::create(date("2025-11-20T12:00:00Z"), TimeDelta::days(30)),
+        )];
+        let calendar = Calendar::of(&schedules, day(2025, 12, 20), 2, &Utc).unwrap();
+        assert_eq!(calendar.months, [day(2025, 12, 1), day(2026, 1, 1)]);
+        // the occurrence before this month and the one after the last are left out
+        assert_eq!(
+            calendar.occurring.keys().copied().collect::<Vec<_>>(),
+            [day(2025, 12, 20), day(2026, 1, 19)]
+        );
+        let text = calendar.render(false);
+        assert!(text.contains("December 2025") && text.contains("January 2026"));
+        assert_eq!(text.matches('>').count(), 1);
+        assert!(text.contains(">201"));
+        assert!(text.ends_with("\n\n1  water\n"));
+    }
+
+    #[test]
+    fn many_schedules_share_a_marker_and_have_no_legend() {
+        let schedules: Vec<_> = (0..10)
+            .map(|index| {
+                let schedule = RegularSchedule::builder()
+                    .start(date("2025-03-01T09:00:00Z") + TimeDelta::days(index))
+                    .interval(TimeDelta::days(1))
+                    .count(1)
+                    .build()
+                    .unwrap();
+                (format!("schedule-{index}"), schedule)
+            })
+            .collect();
+        let text = Calendar::of(&schedules, day(2025, 3, 20), 1, &Utc)
+            .unwrap()
+            .render(false);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[3], "  3*  4*  5*  6*  7*  8*  9*");
+        assert_eq!(lines.last(), Some(&" 31"));
+        assert!(!text.contains("schedule-"));
+    }
+
+    #[test]
+    fn days_are_in_the_given_timezone() {
+        let schedules = [(
+            "water".to_string(),
+            RegularSchedule::create(date("2025-03-03T23:30:00Z"), TimeDelta::days(7)),
+        )];
+        let east = FixedOffset::east_opt(3600).unwrap();
+        let calendar = Calendar::of(&schedules, day(2025, 3, 1), 1, &east).unwrap();
+        assert_eq!(
+            calendar.occurring.keys().copied().collect::<Vec<_>>(),
+            [
+                day(2025, 3, 4),
+                day(2025, 3, 11),
+                day(2025, 3, 18),
+                day(2025, 3, 25)
+            ]
+        );
+    }
+
+    #[test]
+    fn tiny_intervals_stop_after_the_most_occurrences() {
+        let schedules = [(
+            "spin".to_string(),
+            RegularSchedule::create(date("2025-03-01T00:00:00Z"), TimeDelta::seconds(1)),
+        )];
+        let calendar = Calendar::of(&schedules, day(2025, 3, 1), 1, &Utc).unwrap();
+        // 100,000 seconds is a little over a day
+        assert_eq!(
+            calendar.occurring.keys().copied().collect::<Vec<_>>(),
+            [day(2025, 3, 1), day(2025, 3, 2)]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Noticing when the system clock has jumped, from the last time hrtracker saw it, kept in the
+//! data directory as an RFC 3339 timestamp.
+
+use std::fmt::Display;
+#[cfg(feature = "std-fs")]
+use std::{fs, io::ErrorKind, path::Path};
+
+#[cfg(feature = "std-fs")]
+use chrono::SecondsFormat;
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::display::FormattedInterval;
+#[cfg(feature = "std-fs")]
+use crate::error::{HrtError, Result};
+
+/// The file the last time seen is kept in, relative to the data directory.
+pub const FILE: &str = ".last-seen";
+
+/// How far past the last time seen the clock may be before it counts as having jumped, unless
+/// `--clock-jump` says otherwise.
+pub const THRESHOLD: TimeDelta = TimeDelta::days(90);
+
+/// A change of the clock too large to be time passing normally.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Jump {
+    /// The clock is earlier than it was last seen, by this much.
+    Backward(TimeDelta),
+    /// The clock is this far past when it was last seen, which is more than the threshold.
+    Forward(TimeDelta),
+}
+impl Jump {
+    /// How the clock moved from `last_seen` to `now`, if it went backwards or further forwards
+    /// than `threshold`.
+    pub fn between(
+        last_seen: DateTime<Utc>,
+        now: DateTime<Utc>,
+        threshold: TimeDelta,
+    ) -> Option<Self> {
+        let elapsed = now.signed_duration_since(last_seen);
+        if elapsed < TimeDelta::zero() {
+            Some(Self::Backward(-elapsed))
+        } else if elapsed > threshold {
+            Some(Self::Forward(elapsed))
+        } else {
+            None
+        }
+    }
+}
+impl Display for Jump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Backward(by) => write!(
+                f,
+                "the clock is {} earlier than when hrtracker last ran",
+                FormattedInterval(*by)
+            ),
+            Self::Forward(by) => write!(
+                f,
+                "the clock is {} later than when hrtracker last ran",
+                FormattedInterval(*by)
+            ),
+        }
+    }
+}
+
+/// The last time seen in `path`, or `None` if it doesn't exist or can't be read as a timestamp,
+/// in which case there is nothing to compare against.
+#[cfg(feature = "std-fs")]
+pub fn load(path: impl AsRef<Path>) -> Result<Option<DateTime<Utc>>> {
+    let path = path.as_ref();
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(DateTime::parse_from_rfc3339(text.trim())
+            .ok()
+            .map(|seen| seen.with_timezone(&Utc))),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(HrtError::io(path, error)),
+    }
+}
+
+#[cfg(feature = "std-fs")]
+pub fn save(path: impl AsRef<Path>, now: DateTime<Utc>) -> Result<()> {
+    let text = format!("{}\n", now.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    crate::store::write_atomic(path.as_ref(), text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    #[test]
+    fn only_large_or_backward_changes_are_jumps() {
+        let seen = date("2025-03-01T09:00:00Z");
+        let between = |now| Jump::between(seen, date(now), THRESHOLD);
+        assert_eq!(between("2025-03-01T09:00:00Z"), None);
+        assert_eq!(between("2025-05-30T09:00:00Z"), None);
+        assert_eq!(
+            between("2025-05-30T09:00:01Z"),
+            Some(Jump::Forward(THRESHOLD + TimeDelta::seconds(1)))
+        );
+        let backward = between("2025-03-01T07:00:00Z").unwrap();
+        assert_eq!(backward, Jump::Backward(TimeDelta::hours(2)));
+        assert_eq!(
+            backward.to_string(),
+            "the clock is 02h00m00s earlier than when hrtracker last ran"
+        );
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn the_last_time_seen_is_kept_in_a_file() {
+        let path = std::env::temp_dir().join(format!("hrtracker-last-seen-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(load(&path).unwrap(), None);
+        let now = date("2025-03-01T09:00:00.5Z");
+        save(&path, now).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2025-03-01T09:00:00.500Z\n"
+        );
+        assert_eq!(load(&path).unwrap(), Some(now));
+        // an unreadable marker means there is nothing to compare against
+        fs::write(&path, "yesterday").unwrap();
+        assert_eq!(load(&path).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+//! Crontab lines that approximate a schedule.
+
+use chrono::{Datelike, NaiveDateTime, TimeDelta, Timelike};
+
+/// The five-field cron expression that fires every `interval`, lined up with `next`, which should
+/// be in the timezone the cron daemon runs in. Cron can only count minutes within an hour and hours
+/// within a day, so this is `None` unless `interval` is a whole number of minutes that divides an
+/// hour, of hours that divides a day, or exactly a day or a week.
+pub fn expression(next: NaiveDateTime, interval: TimeDelta) -> Option<String> {
+    if interval <= TimeDelta::zero() || interval.subsec_nanos() != 0 {
+        return None;
+    }
+    let seconds = interval.num_seconds();
+    if seconds % 60 != 0 {
+        return None;
+    }
+    let (minute, hour) = (next.minute() as i64, next.hour() as i64);
+    let minutes = seconds / 60;
+    let expression = match minutes {
+        1 => "* * * * *".to_string(),
+        2..60 if 60 % minutes == 0 => format!("{}-59/{minutes} * * * *", minute % minutes),
+        60 => format!("{minute} * * * *"),
+        _ if minutes % 60 != 0 => return None,
+        _ => match minutes / 60 {
+            hours @ 2..24 if 24 % hours == 0 => {
+                format!("{minute} {}-23/{hours} * * *", hour % hours)
+            }
+            24 => format!("{minute} {hour} * * *"),
+            168 => format!(
+                "{minute} {hour} * * {}",
+                next.weekday().num_days_from_sunday()
+            ),
+            _ => return None,
+        },
+    };
+    Some(expression)
+}
+
+/// Quotes `text` for `sh`, leaving it alone if it has nothing the shell would interpret.
+pub fn shell_quote(text: &str) -> String {
+    let plain = |char: char| char.is_ascii_alphanumeric() || "-_./:=@+,".contains(char);
+    if !text.is_empty() && text.chars().all(plain) {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+/// A crontab line running `command` on `expression`. Cron turns an unescaped `%` in the command
+/// into a newline, so those are escaped.
+pub fn line(expression: &str, command: &str) -> String {
+    format!("{expression} {}", command.replace('%', r"\%"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    /// A Saturday morning.
+    fn next() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 25, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn expressions_line_up_with_next() {
+        let expression = |interval| expression(next(), interval);
+        assert_eq!(expression(TimeDelta::minutes(1)).unwrap(), "* * * * *");
+        assert_eq!(
+            expression(TimeDelta::minutes(15)).unwrap(),
+            "10-59/15 * * * *"
+        );
+        assert_eq!(expression(TimeDelta::hours(1)).unwrap(), "25 * * * *");
+        assert_eq!(expression(TimeDelta::hours(6)).unwrap(), "25 3-23/6 * * *");
+        assert_eq!(expression(TimeDelta::days(1)).unwrap(), "25 9 * * *");
+        assert_eq!(expression(TimeDelta::weeks(1)).unwrap(), "25 9 * * 6");
+    }
+
+    #[test]
+    fn intervals_cron_cant_count_have_no_expression() {
+        for interval in [
+            TimeDelta::zero(),
+            TimeDelta::seconds(30),
+            TimeDelta::seconds(90),
+            TimeDelta::minutes(7),
+            TimeDelta::hours(5),
+            TimeDelta::days(2),
+            TimeDelta::minutes(1) + TimeDelta::nanoseconds(1),
+        ] {
+            assert_eq!(expression(next(), interval), None, "{interval}");
+        }
+    }
+
+    #[test]
+    fn commands_are_quoted_for_the_shell() {
+        assert_eq!(shell_quote("/usr/bin/hrtracker"), "/usr/bin/hrtracker");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("morning pills"), "'morning pills'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(line("* * * * *", "date +%s"), r"* * * * * date +\%s");
+    }
+}
@@ -0,0 +1,136 @@
+//! CSV (RFC 4180) output, and the rows of `export --csv` and `history --csv`.
+
+use std::borrow::Cow;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    porcelain,
+    schedule::{RegularSchedule, Step, dotted},
+};
+
+/// The columns of `export --csv`.
+pub const SCHEDULE_HEADER: [&str; 5] = [
+    "name",
+    "next_rfc3339",
+    "interval_seconds",
+    "overdue",
+    "version",
+];
+/// The columns of `history --csv`.
+pub const STEP_HEADER: [&str; 4] = ["schedule", "timestamp_rfc3339", "note", "lateness_seconds"];
+
+/// `text` as a CSV field, quoted if it contains a comma, a quote, or a line break, with quotes
+/// inside doubled.
+pub fn field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// One record, ending in the CRLF that RFC 4180 asks for.
+pub fn row<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut row = fields
+        .iter()
+        .map(|text| field(text.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// The schedule called `name` as a row of `export --csv`, overdue if `next` is before `now`.
+pub fn schedule(name: &str, schedule: &RegularSchedule, now: DateTime<Utc>) -> String {
+    row(&[
+        name.to_string(),
+        porcelain::timestamp(&schedule.next),
+        porcelain::seconds(&schedule.interval),
+        (schedule.next < now).to_string(),
+        dotted(schedule.version()),
+    ])
+}
+
+/// One step of the schedule called `name` as a row of `history --csv`, with `lateness` empty for
+/// a step that recorded no occurrence.
+pub fn step(name: &str, step: &Step, lateness: Option<TimeDelta>) -> String {
+    row(&[
+        name.to_string(),
+        porcelain::timestamp(&step.at),
+        step.note.clone().unwrap_or_default(),
+        lateness
+            .map(|lateness| porcelain::seconds(&lateness))
+            .unwrap_or_default(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schedule::LATEST;
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    #[test]
+    fn fields_are_only_quoted_when_they_need_to_be() {
+        assert!(matches!(field("water"), Cow::Borrowed("water")));
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(field("cr\r"), "\"cr\r\"");
+        assert_eq!(row(&["a", "b,c", ""]), "a,\"b,c\",\r\n");
+    }
+
+    #[test]
+    fn schedules_are_exported_as_rows() {
+        let schedule = RegularSchedule::create(date("2025-03-01T09:00:00Z"), TimeDelta::days(1));
+        assert_eq!(
+            schedule_row(&schedule, "2025-03-02T00:00:00Z"),
+            format!(
+                "water,2025-03-01T09:00:00Z,86400,true,{}\r\n",
+                dotted(LATEST)
+            )
+        );
+        // due exactly now isn't overdue yet
+        assert_eq!(
+            schedule_row(&schedule, "2025-03-01T09:00:00Z"),
+            format!(
+                "water,2025-03-01T09:00:00Z,86400,false,{}\r\n",
+                dotted(LATEST)
+            )
+        );
+        assert_eq!(SCHEDULE_HEADER.len(), 5);
+    }
+
+    fn schedule_row(schedule: &RegularSchedule, now: &str) -> String {
+        super::schedule("water", schedule, date(now))
+    }
+
+    #[test]
+    fn steps_are_exported_as_rows() {
+        let noted = Step {
+            at: date("2025-03-01T10:00:00Z"),
+            note: Some("watered, finally".to_string()),
+        };
+        assert_eq!(
+            step("water", &noted, Some(TimeDelta::seconds(-90))),
+            "water,2025-03-01T10:00:00Z,\"watered, finally\",-90\r\n"
+        );
+        let bare = Step {
+            at: date("2025-03-01T10:00:00Z"),
+            note: None,
+        };
+        assert_eq!(
+            step("water", &bare, None),
+            "water,2025-03-01T10:00:00Z,,\r\n"
+        );
+        assert_eq!(
+            row(&STEP_HEADER),
+            "schedule,timestamp_rfc3339,note,lateness_seconds\r\n"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Field-by-field comparison of two schedules, for `diff`.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use decent::Version;
+
+use crate::{schedule::RegularSchedule, text};
+
+/// A field of `RegularSchedule` as `diff` shows it.
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub show: fn(&RegularSchedule) -> String,
+}
+
+/// Every field that is compared, in the order differences are listed. A field added to
+/// `RegularSchedule` needs an entry here to show up in diffs.
+pub const FIELDS: &[Field] = &[
+    Field {
+        name: "version",
+        show: |schedule| {
+            let Version(major, minor, patch) = schedule.version();
+            format!("{major}.{minor}.{patch}")
+        },
+    },
+    Field {
+        name: "next",
+        show: |schedule| timestamp(schedule.next),
+    },
+    Field {
+        name: "interval",
+        show: |schedule| text::interval(schedule.interval),
+    },
+    Field {
+        name: "priority",
+        show: |schedule| {
+            schedule
+                .priority()
+                .map_or_else(|| "default".to_string(), |priority| priority.to_string())
+        },
+    },
+    Field {
+        name: "webhook",
+        show: |schedule| schedule.webhook().unwrap_or("none").to_string(),
+    },
+    Field {
+        name: "tags",
+        show: |schedule| schedule.tags().join(", "),
+    },
+    Field {
+        name: "note",
+        show: |schedule| schedule.note().unwrap_or("none").to_string(),
+    },
+    Field {
+        name: "history",
+        show: |schedule| dates(&schedule.stepped_at()),
+    },
+    Field {
+        name: "satisfied",
+        show: |schedule| dates(schedule.satisfied()),
+    },
+];
+
+fn timestamp(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// A list of dates as its length and its last entry, since whole histories are too long to read.
+fn dates(dates: &[DateTime<Utc>]) -> String {
+    match dates.last() {
+        Some(last) => format!("{} entries, the last at {}", dates.len(), timestamp(*last)),
+        None => "0 entries".to_string(),
+    }
+}
+
+/// A field whose value differs between two schedules.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Difference {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// The fields in `FIELDS` that differ between `old` and `new`, in order.
+pub fn diff(old: &RegularSchedule, new: &RegularSchedule) -> Vec<Difference> {
+    FIELDS
+        .iter()
+        .filter_map(|field| {
+            let (old, new) = ((field.show)(old), (field.show)(new));
+            (old != new).then_some(Difference {
+                field: field.name,
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    fn difference(field: &'static str, old: &str, new: &str) -> Difference {
+        Difference {
+            field,
+            old: old.to_string(),
+            new: new.to_string(),
+        }
+    }
+
+    #[test]
+    fn only_changed_fields_are_listed() {
+        let old = RegularSchedule::create(date("2025-03-01T09:00:00Z"), TimeDelta::days(1));
+        assert!(diff(&old, &old.clone()).is_empty());
+        let mut new = old.clone();
+        new.set_priority(Some(2)).unwrap();
+        new.step(date("2025-03-01T10:00:00Z")).unwrap();
+        assert_eq!(
+            diff(&old, &new),
+            [
+                difference("next", "2025-03-01T09:00:00Z", "2025-03-02T09:00:00Z"),
+                difference("priority", "default", "2"),
+                difference(
+                    "history",
+                    "0 entries",
+                    "1 entries, the last at 2025-03-01T10:00:00Z"
+                ),
+                difference(
+                    "satisfied",
+                    "0 entries",
+                    "1 entries, the last at 2025-03-01T09:00:00Z"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_field_has_a_distinct_name() {
+        let mut names: Vec<_> = FIELDS.iter().map(|field| field.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), FIELDS.len());
+    }
+}
@@ -0,0 +1,442 @@
+//! Human-readable phrasing of intervals, relative times, and progress.
+
+use std::fmt::Display;
+
+use chrono::{
+    DateTime, TimeDelta, TimeZone, Utc,
+    format::{Item, StrftimeItems},
+};
+
+use crate::{
+    error::{HrtError, Result},
+    porcelain,
+};
+
+/// An exact interval, printed as `HHhMMmSSs`, or `HHhMMmSS.mmms` if it has a fraction of a second.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FormattedInterval(pub TimeDelta);
+impl Display for FormattedInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let magnitude = self.0.abs();
+        write!(
+            f,
+            "{}{:02}h{:02}m{:02}",
+            if self.0 < TimeDelta::zero() { "-" } else { "" },
+            magnitude.num_hours(),
+            magnitude.num_minutes() % 60,
+            magnitude.num_seconds() % 60,
+        )?;
+        match magnitude.subsec_nanos() {
+            0 => write!(f, "s"),
+            nanos => write!(f, ".{:03}s", nanos / 1_000_000),
+        }
+    }
+}
+
+/// An exact interval as an ISO 8601 duration, like `PT1H30M` or `-P2DT3H`, counting days as 24
+/// hours. An empty interval is `PT0S`.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IsoDuration(pub TimeDelta);
+impl Display for IsoDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let magnitude = self.0.abs();
+        if self.0 < TimeDelta::zero() {
+            write!(f, "-")?;
+        }
+        write!(f, "P")?;
+        if magnitude.num_days() > 0 {
+            write!(f, "{}D", magnitude.num_days())?;
+        }
+        let (hours, minutes) = (magnitude.num_hours() % 24, magnitude.num_minutes() % 60);
+        let (seconds, nanos) = (magnitude.num_seconds() % 60, magnitude.subsec_nanos());
+        if magnitude.num_days() > 0 && hours == 0 && minutes == 0 && seconds == 0 && nanos == 0 {
+            return Ok(());
+        }
+        write!(f, "T")?;
+        if hours > 0 {
+            write!(f, "{hours}H")?;
+        }
+        if minutes > 0 {
+            write!(f, "{minutes}M")?;
+        }
+        match nanos {
+            0 if seconds > 0 || magnitude.is_zero() => write!(f, "{seconds}S"),
+            0 => Ok(()),
+            nanos => {
+                let fraction = format!("{nanos:09}");
+                write!(f, "{seconds}.{}S", fraction.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+/// `count` followed by `unit`, pluralized with an `s` unless `count` is 1.
+pub fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("{count} {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+/// `count` schedules, with the verb `have` agreeing with it, for saying how many were neglected.
+pub fn neglect_count(count: usize) -> String {
+    match count {
+        1 => "1 schedule has".to_string(),
+        count => format!("{count} schedules have"),
+    }
+}
+
+/// Rounded, human-readable relative phrasing such as `in 3 hours` or `2 days 4 hours overdue`,
+/// using the largest one or two significant units.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HumanDelta(pub TimeDelta);
+impl HumanDelta {
+    /// The largest one or two units of `magnitude`, like `2 days 4 hours`, or `None` if it is
+    /// less than a minute.
+    fn phrase(magnitude: TimeDelta) -> Option<String> {
+        if magnitude < TimeDelta::minutes(1) {
+            return None;
+        }
+        let days = magnitude.num_days();
+        let hours = magnitude.num_hours() % 24;
+        let minutes = magnitude.num_minutes() % 60;
+        let (major, minor) = if days > 0 {
+            (
+                plural(days, "day"),
+                (hours > 0).then(|| plural(hours, "hour")),
+            )
+        } else if hours > 0 {
+            (
+                plural(hours, "hour"),
+                (minutes > 0).then(|| plural(minutes, "minute")),
+            )
+        } else {
+            (plural(minutes, "minute"), None)
+        };
+        Some(match minor {
+            Some(minor) => format!("{major} {minor}"),
+            None => major,
+        })
+    }
+}
+impl Display for HumanDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(phrase) = Self::phrase(self.0.abs()) else {
+            return write!(f, "just now");
+        };
+        if self.0 < TimeDelta::zero() {
+            write!(f, "{phrase} overdue")
+        } else {
+            write!(f, "in {phrase}")
+        }
+    }
+}
+
+/// Like `HumanDelta`, but for how long ago something happened, such as `2 days 4 hours ago`.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HumanAgo(pub TimeDelta);
+impl Display for HumanAgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match HumanDelta::phrase(self.0.abs()) {
+            Some(phrase) => write!(f, "{phrase} ago"),
+            None => write!(f, "just now"),
+        }
+    }
+}
+
+/// How far through the interval leading up to `next` a point in time is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Progress {
+    /// Clamped to `0.0..=1.0`.
+    pub fraction: f64,
+    pub overdue: bool,
+}
+impl Progress {
+    pub const BAR_WIDTH: usize = 20;
+
+    /// Computes `(now - (next - interval)) / interval`. A non-positive interval is treated as
+    /// either not started or complete, depending on whether `next` has passed.
+    pub fn of(next: DateTime<Utc>, interval: TimeDelta, now: DateTime<Utc>) -> Self {
+        let overdue = now > next;
+        let fraction = if interval <= TimeDelta::zero() {
+            if now >= next { 1.0 } else { 0.0 }
+        } else {
+            let elapsed = interval + now.signed_duration_since(next);
+            (elapsed.num_milliseconds() as f64 / interval.num_milliseconds() as f64).clamp(0.0, 1.0)
+        };
+        Self { fraction, overdue }
+    }
+    pub fn percent(&self) -> String {
+        if self.overdue {
+            ">100%".to_string()
+        } else {
+            format!("{:.0}%", self.fraction * 100.0)
+        }
+    }
+    pub fn bar(&self) -> String {
+        let filled = (self.fraction * Self::BAR_WIDTH as f64).round() as usize;
+        format!(
+            "[{}{}]",
+            "#".repeat(filled),
+            "-".repeat(Self::BAR_WIDTH - filled)
+        )
+    }
+}
+
+/// How datetimes and durations are printed, as `--precise`, `--duration-format`, and
+/// `--time-format` choose.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Options {
+    /// Print exact `FormattedInterval`s instead of `HumanDelta`s.
+    pub precise: bool,
+    /// Print intervals as ISO 8601 durations, in porcelain output too.
+    pub iso_durations: bool,
+    /// A validated strftime-style format for displayed datetimes.
+    pub time_format: Option<String>,
+}
+impl Options {
+    /// Formats a datetime for display, honouring `time_format`.
+    pub fn datetime(&self, date: &DateTime<Utc>) -> String {
+        match &self.time_format {
+            Some(format) => date.format(format).to_string(),
+            None => date.to_string(),
+        }
+    }
+
+    /// Formats a datetime as `show` prints it, in both UTC and `tz`, honouring `time_format` for
+    /// the side in `tz`.
+    pub fn dual_datetime<Tz: TimeZone>(&self, date: &DateTime<Utc>, tz: &Tz) -> String
+    where
+        Tz::Offset: Display,
+    {
+        dual(date, tz, self.time_format.as_deref())
+    }
+
+    /// Formats an exact interval for display, honouring `iso_durations`.
+    pub fn interval(&self, delta: TimeDelta) -> String {
+        match self.iso_durations {
+            true => IsoDuration(delta).to_string(),
+            false => FormattedInterval(delta).to_string(),
+        }
+    }
+
+    /// Formats a porcelain duration column in whole seconds, or as an ISO 8601 duration with
+    /// `iso_durations`. Columns named as seconds, like those of CSV and JSON, use
+    /// `porcelain::seconds` instead, so that they stay numbers.
+    pub fn porcelain_duration(&self, delta: &TimeDelta) -> String {
+        match self.iso_durations {
+            true => IsoDuration(*delta).to_string(),
+            false => porcelain::seconds(delta),
+        }
+    }
+
+    /// Formats the `next` porcelain column: RFC 3339 unless `time_format` is given.
+    pub fn porcelain_next(&self, date: &DateTime<Utc>) -> String {
+        match &self.time_format {
+            Some(format) => date.format(format).to_string(),
+            None => porcelain::timestamp(date),
+        }
+    }
+
+    /// Describes how long ago something `delta` before now happened, honouring `precise`.
+    pub fn ago(&self, delta: TimeDelta) -> String {
+        let delta = TimeDelta::seconds(delta.num_seconds());
+        match self.precise {
+            true => format!("{} ago", self.interval(delta)),
+            false => HumanAgo(delta).to_string(),
+        }
+    }
+
+    /// Describes how far away `delta` is, honouring `precise`. This is the only place relative
+    /// times are phrased, so every action words them the same way: `in ...` when `delta` is zero
+    /// or positive, and overdue otherwise.
+    pub fn relative(&self, delta: TimeDelta) -> String {
+        // measured from the current instant, so a fraction of a second is only noise
+        let delta = TimeDelta::seconds(delta.num_seconds());
+        match (self.precise, delta < TimeDelta::zero()) {
+            (true, false) => format!("in {}", self.interval(delta)),
+            (true, true) => format!("overdue by {}", self.interval(-delta)),
+            (false, _) => HumanDelta(delta).to_string(),
+        }
+    }
+}
+
+/// The default format of the local side of `dual`, like `18:00 +09:00 Sat`.
+pub const DUAL_LOCAL: &str = "%H:%M %:z %a";
+
+/// `date` in UTC followed by the same moment in `tz`, like `2025-03-01 09:00 UTC (18:00 +09:00
+/// Sat)`, with the part in `tz` in `format` if one is given.
+pub fn dual<Tz: TimeZone>(date: &DateTime<Utc>, tz: &Tz, format: Option<&str>) -> String
+where
+    Tz::Offset: Display,
+{
+    format!(
+        "{} ({})",
+        date.format("%Y-%m-%d %H:%M UTC"),
+        date.with_timezone(tz).format(format.unwrap_or(DUAL_LOCAL))
+    )
+}
+
+/// Checks a strftime-style format up front, since chrono panics when displaying a datetime with an
+/// invalid specifier.
+pub fn validate_time_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(HrtError::Usage(format!(
+            "`{format}` is not a valid time format"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn human(delta: TimeDelta) -> String {
+        HumanDelta(delta).to_string()
+    }
+
+    #[test]
+    fn under_a_minute_is_just_now() {
+        assert_eq!(human(TimeDelta::zero()), "just now");
+        assert_eq!(human(TimeDelta::seconds(59)), "just now");
+        assert_eq!(human(TimeDelta::seconds(-59)), "just now");
+        assert_eq!(human(TimeDelta::milliseconds(59_999)), "just now");
+    }
+
+    #[test]
+    fn minutes_start_at_one_and_round_down() {
+        assert_eq!(human(TimeDelta::minutes(1)), "in 1 minute");
+        assert_eq!(human(TimeDelta::seconds(119)), "in 1 minute");
+        assert_eq!(human(TimeDelta::minutes(2)), "in 2 minutes");
+        assert_eq!(human(TimeDelta::seconds(59 * 60 + 59)), "in 59 minutes");
+    }
+
+    #[test]
+    fn hours_carry_minutes_but_days_drop_them() {
+        assert_eq!(human(TimeDelta::hours(1)), "in 1 hour");
+        assert_eq!(human(TimeDelta::minutes(61)), "in 1 hour 1 minute");
+        assert_eq!(
+            human(TimeDelta::minutes(23 * 60 + 59)),
+            "in 23 hours 59 minutes"
+        );
+        assert_eq!(human(TimeDelta::hours(24)), "in 1 day");
+        assert_eq!(human(TimeDelta::minutes(24 * 60 + 59)), "in 1 day");
+        assert_eq!(human(TimeDelta::hours(25)), "in 1 day 1 hour");
+        assert_eq!(human(TimeDelta::hours(2 * 24 + 4)), "in 2 days 4 hours");
+        assert_eq!(human(TimeDelta::days(400)), "in 400 days");
+    }
+
+    #[test]
+    fn negative_deltas_are_overdue() {
+        assert_eq!(human(TimeDelta::minutes(-1)), "1 minute overdue");
+        assert_eq!(
+            human(TimeDelta::hours(-(2 * 24 + 4))),
+            "2 days 4 hours overdue"
+        );
+    }
+
+    #[test]
+    fn iso_durations_round_trip_through_the_parser() {
+        let hours = TimeDelta::hours;
+        let minutes = TimeDelta::minutes;
+        for (delta, iso) in [
+            (TimeDelta::zero(), "PT0S"),
+            (hours(6), "PT6H"),
+            (hours(1) + minutes(30), "PT1H30M"),
+            (TimeDelta::seconds(90), "PT1M30S"),
+            (TimeDelta::days(1), "P1D"),
+            (TimeDelta::days(14), "P14D"),
+            (hours(2 * 24 + 3), "P2DT3H"),
+            (TimeDelta::days(1) + minutes(30), "P1DT30M"),
+            (TimeDelta::milliseconds(500), "PT0.5S"),
+            (TimeDelta::milliseconds(1250), "PT1.25S"),
+            (TimeDelta::nanoseconds(1), "PT0.000000001S"),
+            (-(hours(1) + minutes(30)), "-PT1H30M"),
+            (-TimeDelta::days(1), "-P1D"),
+            (-TimeDelta::milliseconds(1500), "-PT1.5S"),
+        ] {
+            assert_eq!(IsoDuration(delta).to_string(), iso);
+            assert_eq!(crate::parse::parse_timedelta(iso).unwrap(), delta, "{iso}");
+        }
+    }
+
+    fn precise() -> Options {
+        Options {
+            precise: true,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn relative_times_are_overdue_only_below_zero() {
+        let options = precise();
+        assert_eq!(options.relative(TimeDelta::zero()), "in 00h00m00s");
+        assert_eq!(options.relative(TimeDelta::seconds(1)), "in 00h00m01s");
+        assert_eq!(
+            options.relative(TimeDelta::seconds(-1)),
+            "overdue by 00h00m01s"
+        );
+        assert_eq!(
+            options.relative(TimeDelta::minutes(-130)),
+            "overdue by 02h10m00s"
+        );
+    }
+
+    #[test]
+    fn relative_times_drop_fractions_of_a_second() {
+        let options = precise();
+        assert_eq!(
+            options.relative(TimeDelta::milliseconds(-500)),
+            "in 00h00m00s"
+        );
+        assert_eq!(
+            options.relative(TimeDelta::milliseconds(1500)),
+            "in 00h00m01s"
+        );
+    }
+
+    #[test]
+    fn relative_times_are_rounded_unless_precise() {
+        let options = Options::default();
+        assert_eq!(options.relative(TimeDelta::zero()), "just now");
+        assert_eq!(options.relative(TimeDelta::hours(3)), "in 3 hours");
+        assert_eq!(options.relative(TimeDelta::days(-2)), "2 days overdue");
+        assert_eq!(options.ago(TimeDelta::hours(26)), "1 day 2 hours ago");
+        assert_eq!(precise().ago(TimeDelta::minutes(90)), "01h30m00s ago");
+    }
+
+    #[test]
+    fn durations_follow_the_duration_format() {
+        let mut options = Options::default();
+        let delta = TimeDelta::minutes(-90);
+        assert_eq!(options.interval(-delta), "01h30m00s");
+        assert_eq!(options.porcelain_duration(&delta), "-5400");
+        options.iso_durations = true;
+        assert_eq!(options.interval(-delta), "PT1H30M");
+        assert_eq!(options.porcelain_duration(&delta), "-PT1H30M");
+        assert_eq!(options.relative(delta), "1 hour 30 minutes overdue");
+        options.precise = true;
+        assert_eq!(options.relative(delta), "overdue by PT1H30M");
+    }
+
+    #[test]
+    fn datetimes_follow_the_time_format() {
+        let date = Utc.with_ymd_and_hms(2025, 3, 1, 9, 30, 15).unwrap();
+        let mut options = Options::default();
+        assert_eq!(options.datetime(&date), "2025-03-01 09:30:15 UTC");
+        assert_eq!(options.porcelain_next(&date), "2025-03-01T09:30:15Z");
+        assert_eq!(
+            options.dual_datetime(&date, &Utc),
+            "2025-03-01 09:30 UTC (09:30 +00:00 Sat)"
+        );
+        options.time_format = Some("%a %H:%M".to_string());
+        assert_eq!(options.datetime(&date), "Sat 09:30");
+        assert_eq!(options.porcelain_next(&date), "Sat 09:30");
+        assert_eq!(
+            options.dual_datetime(&date, &Utc),
+            "2025-03-01 09:30 UTC (Sat 09:30)"
+        );
+    }
+}
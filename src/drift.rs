@@ -0,0 +1,344 @@
+//! How late steps are compared to the occurrences they satisfied, for `drift` and `streak`.
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// With fewer compared steps than this, `drift` says there isn't enough data instead of printing
+/// statistics that a single step would skew.
+pub const MIN_COMPARED: usize = 3;
+
+/// The value `p` (from 0 to 1) of the way through `sorted`, by the nearest-rank method, so that
+/// it's always one of the values. `None` if `sorted` is empty.
+pub fn percentile(sorted: &[TimeDelta], p: f64) -> Option<TimeDelta> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Pairs each step in `history` with the occurrence it satisfied in `satisfied`, oldest first.
+/// `satisfied` lines up with the end of `history`, so the older steps without an occurrence are
+/// left out.
+pub fn pairs<'a>(
+    history: &'a [DateTime<Utc>],
+    satisfied: &'a [DateTime<Utc>],
+) -> impl Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> + 'a {
+    let history = &history[history.len().saturating_sub(satisfied.len())..];
+    let satisfied = &satisfied[satisfied.len().saturating_sub(history.len())..];
+    history.iter().copied().zip(satisfied.iter().copied())
+}
+
+/// How late each step in `history` was for the occurrence it satisfied, in the same order, or
+/// `None` for the older steps that recorded no occurrence.
+pub fn lateness(history: &[DateTime<Utc>], satisfied: &[DateTime<Utc>]) -> Vec<Option<TimeDelta>> {
+    let untracked = history.len().saturating_sub(satisfied.len());
+    let mut lateness = vec![None; untracked];
+    lateness.extend(
+        pairs(history, satisfied).map(|(stepped, due)| Some(stepped.signed_duration_since(due))),
+    );
+    lateness
+}
+
+/// Lateness across a schedule's recorded steps.
+///
+/// Each step is paired with the occurrence that was due when it was made, as recorded by
+/// `RegularSchedule::step`, rather than with the nearest occurrence. Stepping twice in one interval
+/// therefore counts the second step as early for the following occurrence, and an occurrence that
+/// was never stepped (such as one skipped by `--to-future`) pairs with nothing instead of making
+/// the steps after it look later or earlier than they were.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Drift {
+    /// Steps that had a recorded occurrence to compare against.
+    pub compared: usize,
+    /// Steps made before their occurrence was due.
+    pub early: usize,
+    /// The mean, median, and largest lateness of the steps that weren't early, or `None` if every
+    /// step was.
+    pub mean: Option<TimeDelta>,
+    pub median: Option<TimeDelta>,
+    /// The lateness that 90% of the steps that weren't early were within.
+    pub p90: Option<TimeDelta>,
+    pub max: Option<TimeDelta>,
+}
+impl Drift {
+    /// Compares the steps in `history` against the occurrences in `satisfied`, which line up with
+    /// the end of `history` (steps older than `satisfied` are left out).
+    pub fn of(history: &[DateTime<Utc>], satisfied: &[DateTime<Utc>]) -> Self {
+        let mut late: Vec<TimeDelta> = Vec::new();
+        let mut drift = Self::default();
+        for (stepped, due) in pairs(history, satisfied) {
+            drift.compared += 1;
+            let lateness = stepped.signed_duration_since(due);
+            if lateness < TimeDelta::zero() {
+                drift.early += 1;
+            } else {
+                late.push(lateness);
+            }
+        }
+        late.sort();
+        if let (Some(max), Ok(count)) = (late.last(), i32::try_from(late.len())) {
+            let total = late
+                .iter()
+                .fold(TimeDelta::zero(), |total, delta| total + *delta);
+            drift.mean = Some(total / count);
+            drift.max = Some(*max);
+            let middle = late.len() / 2;
+            drift.median = Some(match late.len() % 2 {
+                0 => (late[middle - 1] + late[middle]) / 2,
+                _ => late[middle],
+            });
+            drift.p90 = percentile(&late, 0.9);
+        }
+        drift
+    }
+    /// The fraction of compared steps that were early, from 0 to 1.
+    pub fn early_fraction(&self) -> f64 {
+        match self.compared {
+            0 => 0.0,
+            compared => self.early as f64 / compared as f64,
+        }
+    }
+}
+
+/// A run of consecutive occurrences that were each stepped before the following one was due.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Streak {
+    pub length: usize,
+    /// The first and last occurrences in the run.
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+}
+impl Streak {
+    /// How many of the run's occurrences, which are `interval` apart, were due at or after
+    /// `since`.
+    pub fn since(&self, since: DateTime<Utc>, interval: TimeDelta) -> usize {
+        if self.last < since {
+            return 0;
+        }
+        if self.first >= since {
+            return self.length;
+        }
+        // both are positive here, since `first` is before `since`
+        let before = since.signed_duration_since(self.first).num_milliseconds() as u64;
+        let skipped = before.div_ceil(interval.num_milliseconds().max(1) as u64);
+        self.length.saturating_sub(skipped as usize)
+    }
+}
+
+/// The streaks in a schedule's recorded steps, as paired by `pairs`.
+///
+/// Because every step satisfies its own occurrence, stepping twice in one interval satisfies the
+/// following occurrence early rather than being ignored, and the first recorded occurrence always
+/// starts a streak if it was stepped in time. Occurrences that were skipped (by `--to-future`, or
+/// by editing `next`) end a streak.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Streaks {
+    /// The streak up to the latest step, unless the occurrence after it was missed by `now`.
+    pub current: Option<Streak>,
+    /// The earliest of the longest streaks.
+    pub longest: Option<Streak>,
+    /// The streak up to the latest step that was in time, even if it has been broken since.
+    pub latest: Option<Streak>,
+}
+impl Streaks {
+    /// Finds the streaks of a schedule repeating every `interval` and due next at `next`.
+    pub fn of(
+        history: &[DateTime<Utc>],
+        satisfied: &[DateTime<Utc>],
+        interval: TimeDelta,
+        next: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        // dates too late to add to are treated as never passing
+        let following = |date: DateTime<Utc>| date.checked_add_signed(interval);
+        let mut streaks = Self::default();
+        let mut run: Option<Streak> = None;
+        for (stepped, due) in pairs(history, satisfied) {
+            if following(due).is_some_and(|following| stepped >= following) {
+                run = None;
+                continue;
+            }
+            run = Some(match run {
+                Some(run) if following(run.last) == Some(due) => Streak {
+                    length: run.length + 1,
+                    last: due,
+                    ..run
+                },
+                _ => Streak {
+                    length: 1,
+                    first: due,
+                    last: due,
+                },
+            });
+            streaks.latest = run;
+            if streaks
+                .longest
+                .is_none_or(|longest| run.is_some_and(|run| run.length > longest.length))
+            {
+                streaks.longest = run;
+            }
+        }
+        streaks.current = run.filter(|run| {
+            following(run.last) == Some(next) && following(next).is_none_or(|missed| now < missed)
+        });
+        streaks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const DAY: TimeDelta = TimeDelta::hours(24);
+
+    /// `hours` after the start of 2025.
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + TimeDelta::hours(hours)
+    }
+
+    fn streak(length: usize, first: i64, last: i64) -> Option<Streak> {
+        Some(Streak {
+            length,
+            first: at(first),
+            last: at(last),
+        })
+    }
+
+    fn hours(hours: &[i64]) -> Vec<DateTime<Utc>> {
+        hours.iter().copied().map(at).collect()
+    }
+
+    #[test]
+    fn a_second_step_in_one_interval_satisfies_the_following_occurrence() {
+        let history = hours(&[1, 2, 30]);
+        let satisfied = hours(&[0, 24, 48]);
+        let streaks = Streaks::of(&history, &satisfied, DAY, at(72), at(80));
+        assert_eq!(streaks.current, streak(3, 0, 48));
+        assert_eq!(streaks.longest, streak(3, 0, 48));
+        assert_eq!(streaks.latest, streak(3, 0, 48));
+
+        // missing the occurrence at 72 by the time the one after it is due ends the streak
+        let streaks = Streaks::of(&history, &satisfied, DAY, at(72), at(96));
+        assert_eq!(streaks.current, None);
+        assert_eq!(streaks.longest, streak(3, 0, 48));
+    }
+
+    #[test]
+    fn the_first_recorded_occurrence_starts_a_streak() {
+        let streaks = Streaks::of(&hours(&[5]), &hours(&[0]), DAY, at(24), at(30));
+        assert_eq!(streaks.current, streak(1, 0, 0));
+        // older steps without an occurrence are left out
+        let streaks = Streaks::of(&hours(&[-50, 5]), &hours(&[0]), DAY, at(24), at(30));
+        assert_eq!(streaks.current, streak(1, 0, 0));
+        assert_eq!(streaks.longest, streak(1, 0, 0));
+
+        let late = Streaks::of(&hours(&[24]), &hours(&[0]), DAY, at(48), at(50));
+        assert_eq!(late, Streaks::default());
+        assert_eq!(Streaks::of(&[], &[], DAY, at(0), at(1)), Streaks::default());
+    }
+
+    #[test]
+    fn late_and_skipped_occurrences_end_streaks() {
+        let history = hours(&[1, 25, 75, 73, 97, 145]);
+        let satisfied = hours(&[0, 24, 48, 72, 96, 144]);
+        let streaks = Streaks::of(&history, &satisfied, DAY, at(168), at(170));
+        assert_eq!(streaks.current, streak(1, 144, 144));
+        // the run from 72 to 96 is as long, but the earlier one is kept
+        assert_eq!(streaks.longest, streak(2, 0, 24));
+        assert_eq!(streaks.latest, streak(1, 144, 144));
+
+        // `next` no longer following the latest step means an occurrence was skipped
+        let skipped = Streaks::of(&history, &satisfied, DAY, at(192), at(170));
+        assert_eq!(skipped.current, None);
+        assert_eq!(skipped.latest, streak(1, 144, 144));
+    }
+
+    #[test]
+    fn streaks_count_the_occurrences_since_a_date() {
+        let streak = streak(5, 0, 96).unwrap();
+        assert_eq!(streak.since(at(-1), DAY), 5);
+        assert_eq!(streak.since(at(0), DAY), 5);
+        assert_eq!(streak.since(at(1), DAY), 4);
+        assert_eq!(streak.since(at(24), DAY), 4);
+        assert_eq!(streak.since(at(25), DAY), 3);
+        assert_eq!(streak.since(at(96), DAY), 1);
+        assert_eq!(streak.since(at(97), DAY), 0);
+    }
+
+    /// The streaks in `pairs` of steps and their occurrences, found by splitting them into runs
+    /// first.
+    fn reference(
+        pairs: &[(DateTime<Utc>, DateTime<Utc>)],
+        interval: TimeDelta,
+        next: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Streaks {
+        let mut runs: Vec<Vec<DateTime<Utc>>> = Vec::new();
+        let mut open = false;
+        for &(stepped, due) in pairs {
+            if stepped >= due + interval {
+                open = false;
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if open && run[run.len() - 1] + interval == due => run.push(due),
+                _ => runs.push(vec![due]),
+            }
+            open = true;
+        }
+        let streak = |run: &Vec<DateTime<Utc>>| Streak {
+            length: run.len(),
+            first: run[0],
+            last: run[run.len() - 1],
+        };
+        // `max_by_key` keeps the last of equal runs, so look from the end to keep the first
+        let longest = runs.iter().rev().max_by_key(|run| run.len()).map(streak);
+        let latest = runs.last().map(streak);
+        let current =
+            latest.filter(|latest| open && latest.last + interval == next && now < next + interval);
+        Streaks {
+            current,
+            longest,
+            latest,
+        }
+    }
+
+    struct Random(u64);
+    impl Random {
+        fn below(&mut self, bound: i64) -> i64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as i64
+        }
+    }
+
+    #[test]
+    fn streaks_match_a_reference() {
+        let mut random = Random(0x5eed_cafe_f00d_d00d);
+        for _ in 0..2000 {
+            let interval = TimeDelta::hours(1 + random.below(3));
+            let mut due = at(0);
+            let mut history = hours(&vec![-1000; random.below(3) as usize]);
+            let mut satisfied = Vec::new();
+            for _ in 0..random.below(12) {
+                // mostly the next occurrence, but sometimes the same one again or one skipped
+                due += interval * [1, 1, 1, 0, 2][random.below(5) as usize];
+                let minutes = random.below(interval.num_minutes() * 3) - interval.num_minutes();
+                history.push(due + TimeDelta::minutes(minutes));
+                satisfied.push(due);
+            }
+            let next = due + interval * (1 + random.below(2) as i32);
+            let now = next + TimeDelta::minutes(random.below(interval.num_minutes() * 2));
+
+            let paired: Vec<_> = pairs(&history, &satisfied).collect();
+            assert_eq!(
+                Streaks::of(&history, &satisfied, interval, next, now),
+                reference(&paired, interval, next, now),
+                "{paired:?}, due next at {next} as of {now}"
+            );
+        }
+    }
+}
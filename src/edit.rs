@@ -0,0 +1,230 @@
+//! Editing a schedule as text in an editor, for `edit`.
+
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use crate::{
+    error::{HrtError, Result},
+    schedule::{RegularSchedule, decode_schedule},
+    store::Store,
+    text,
+};
+
+/// The editor to run: the first of `$VISUAL` and `$EDITOR` that `var` says is set and not empty,
+/// or `vi`.
+pub fn editor(var: impl Fn(&str) -> Option<String>) -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|name| var(name).filter(|editor| !editor.is_empty()))
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Writes `bytes` to a new file at `path` that only its owner can read. The temporary directory is
+/// shared, so a file or link already at `path` is never written through, and fails instead.
+pub fn create_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(bytes)
+}
+
+/// A schedule about to be edited, as its file was when it was opened.
+#[derive(Clone, Debug)]
+pub struct Edit {
+    pub name: String,
+    pub schedule: RegularSchedule,
+    before: Vec<u8>,
+}
+impl Edit {
+    /// Reads the schedule called `name` from `store`.
+    pub fn open(store: &impl Store, name: &str) -> Result<Self> {
+        let before = store.load(name)?;
+        let schedule = decode_schedule(&before).map_err(|source| HrtError::Decode {
+            path: PathBuf::from(name),
+            size: before.len() as u64,
+            source,
+        })?;
+        Ok(Self {
+            name: name.to_string(),
+            schedule,
+            before,
+        })
+    }
+
+    /// Opens the schedule in `editor` as `text::render` writes it, and once the editor exits,
+    /// calls `save` with the edited schedule while `store` is locked. Returns whether anything
+    /// changed. Nothing is saved if the editor fails, the text doesn't parse, or the schedule was
+    /// changed in `store` in the meantime; the error then names the temporary file, which is kept
+    /// so that the edits aren't lost.
+    pub fn run(
+        &self,
+        store: &impl Store,
+        editor: &str,
+        save: impl FnOnce(&RegularSchedule) -> Result<()>,
+    ) -> Result<bool> {
+        let name = &self.name;
+        let original = text::render(name, &self.schedule);
+        let path = env::temp_dir().join(format!("hrtracker-{}-{name}.txt", process::id()));
+        create_private(&path, original.as_bytes()).map_err(|error| HrtError::io(&path, error))?;
+        let kept = |reason: String| HrtError::Config {
+            path: path.clone(),
+            reason: format!("{reason} (the edited text is kept there)"),
+        };
+        // through `sh`, since editors like `code --wait` come with arguments
+        let status = Command::new("sh")
+            .args(["-c", &format!("{editor} \"$1\""), "sh"])
+            .arg(&path)
+            .status()
+            .map_err(|error| kept(format!("unable to start `{editor}`: {error}")))?;
+        if !status.success() {
+            return Err(kept(format!("`{editor}` exited with {status}")));
+        }
+        let edited = fs::read_to_string(&path).map_err(|error| HrtError::io(&path, error))?;
+        let changed = edited != original;
+        if changed {
+            let edited = text::parse(&edited, &self.schedule).map_err(kept)?;
+            let _lock = store.lock()?;
+            if store.load(name)? != self.before {
+                return Err(kept(format!(
+                    "`{name}` was changed while it was being edited, so it wasn't saved"
+                )));
+            }
+            save(&edited)?;
+        }
+        // the edits are safe, so a leftover temporary file only wastes a little space
+        let _ = fs::remove_file(&path);
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::store::{MemStore, open_named, save_named};
+
+    /// A store with a daily schedule called `name`.
+    fn store(name: &str) -> MemStore {
+        let store = MemStore::new();
+        let next = "2025-03-01T09:00:00Z".parse().unwrap();
+        save_named(
+            &store,
+            name,
+            &RegularSchedule::create(next, TimeDelta::days(1)),
+        )
+        .unwrap();
+        store
+    }
+
+    fn temporary(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("hrtracker-{}-{name}.txt", process::id()))
+    }
+
+    #[test]
+    fn editors_are_chosen_from_the_environment() {
+        let vars = |set: &'static [(&str, &str)]| {
+            move |name: &str| {
+                set.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            editor(vars(&[("EDITOR", "nano"), ("VISUAL", "code --wait")])),
+            "code --wait"
+        );
+        assert_eq!(editor(vars(&[("EDITOR", "nano"), ("VISUAL", "")])), "nano");
+        assert_eq!(editor(vars(&[])), "vi");
+    }
+
+    #[test]
+    fn private_files_are_never_written_through_what_is_there() {
+        let folder = env::temp_dir().join(format!("hrtracker-private-{}", process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir(&folder).unwrap();
+        let path = folder.join("water.txt");
+        create_private(&path, b"next = now").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"next = now");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            let target = folder.join("target");
+            fs::write(&target, "untouched").unwrap();
+            let link = folder.join("link.txt");
+            std::os::unix::fs::symlink(&target, &link).unwrap();
+            let error = create_private(&link, b"overwritten").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+            assert_eq!(fs::read_to_string(&target).unwrap(), "untouched");
+        }
+        let error = create_private(&path, b"again").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"next = now");
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn edits_are_saved_through_the_callback() {
+        let store = store("edit-saved");
+        let edit = Edit::open(&store, "edit-saved").unwrap();
+        let editor = "sed -i 's/^interval = .*/interval = 12:00:00/'";
+        let changed = edit
+            .run(&store, editor, |edited| {
+                save_named(&store, "edit-saved", edited)
+            })
+            .unwrap();
+        assert!(changed);
+        let saved: RegularSchedule = open_named(&store, "edit-saved").unwrap();
+        assert_eq!(saved.interval, TimeDelta::hours(12));
+        assert!(!temporary("edit-saved").exists());
+        // an editor that leaves the text alone saves nothing
+        let edit = Edit::open(&store, "edit-saved").unwrap();
+        assert!(!edit.run(&store, "true", |_| unreachable!()).unwrap());
+    }
+
+    #[test]
+    fn failed_edits_keep_the_text() {
+        let store = store("edit-failed");
+        let edit = Edit::open(&store, "edit-failed").unwrap();
+        let path = temporary("edit-failed");
+        let error = edit.run(&store, "false", |_| unreachable!()).unwrap_err();
+        assert!(error.to_string().contains("`false` exited with"));
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+        let error = edit
+            .run(&store, "echo 'next = not a date' >", |_| unreachable!())
+            .unwrap_err();
+        assert!(matches!(error, HrtError::Config { path: kept, .. } if kept == path));
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("next = not a date")
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn schedules_changed_meanwhile_are_not_overwritten() {
+        let store = store("edit-raced");
+        let edit = Edit::open(&store, "edit-raced").unwrap();
+        let next = "2030-01-01T00:00:00Z".parse().unwrap();
+        let changed = RegularSchedule::create(next, TimeDelta::days(1));
+        save_named(&store, "edit-raced", &changed).unwrap();
+        let editor = "sed -i 's/^priority = .*/priority = 5/'";
+        let error = edit.run(&store, editor, |_| unreachable!()).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("was changed while it was being edited")
+        );
+        fs::remove_file(temporary("edit-raced")).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+//! The errors hrtracker can fail with.
+
+use std::{fmt::Display, io, path::PathBuf};
+
+use crate::store::schedule_display_name;
+
+/// Everything that can go wrong, structured so that callers can match on the kind of failure
+/// instead of its message.
+#[derive(Debug)]
+pub enum HrtError {
+    /// No schedule called `name` exists.
+    NotFound { name: String },
+    /// `input` isn't a date (optionally with a time of day) that `parse_datetime` accepts.
+    ParseDate { input: String, reason: String },
+    /// `input` isn't an interval or time of day that `parse_timedelta` accepts.
+    ParseInterval { input: String, reason: String },
+    /// The schedule file at `path`, `size` bytes long, exists but couldn't be decoded.
+    Decode {
+        path: PathBuf,
+        size: u64,
+        source: io::Error,
+    },
+    /// The schedule file at `path` is `size` bytes long, more than the `limit` it may be read up
+    /// to, so it wasn't read.
+    TooLarge {
+        path: PathBuf,
+        size: u64,
+        limit: u64,
+    },
+    /// Reading or writing failed, at `path` if it is known.
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// `name` can't be used as a schedule name because it breaks `rule`.
+    InvalidName { name: String, rule: &'static str },
+    /// A schedule being built breaks `reason`.
+    InvalidSchedule { reason: &'static str },
+    /// Neither `HRTRACKER_DIR` nor `HOME` is set, so the data directory can't be found.
+    NoHome,
+    /// The config file at `path` is malformed.
+    Config { path: PathBuf, reason: String },
+    /// The hook `hook` failed to start, exited unsuccessfully, or timed out.
+    Hook { hook: String, reason: String },
+    /// An HTTP request to `url`, such as a webhook or a push notification, failed.
+    Webhook { url: String, reason: String },
+    /// The command line is otherwise malformed, such as a missing argument or a bad flag value.
+    Usage(String),
+}
+impl HrtError {
+    /// Attaches `path` to an IO error.
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Io {
+            path: Some(path.into()),
+            source,
+        }
+    }
+}
+impl Display for HrtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound { name } => write!(f, "schedule `{name}` does not exist"),
+            Self::ParseDate { input, reason } => {
+                write!(f, "`{input}` is not a valid date: {reason}")
+            }
+            Self::ParseInterval { input, reason } => {
+                write!(f, "`{input}` is not a valid time: {reason}")
+            }
+            Self::Decode { path, size, source } => write!(
+                f,
+                "unable to open schedule `{}` ({size} bytes): {source} ({})",
+                schedule_display_name(path),
+                source.kind()
+            ),
+            Self::TooLarge { path, size, limit } => write!(
+                f,
+                "unable to open schedule `{}`: it is {size} bytes, over the limit of {limit}",
+                schedule_display_name(path)
+            ),
+            Self::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "unable to access `{}`: {source}", path.display()),
+            Self::Io { path: None, source } => write!(f, "{source}"),
+            Self::InvalidName { name, rule } => write!(f, "invalid name `{name}`: {rule}"),
+            Self::InvalidSchedule { reason } => write!(f, "invalid schedule: {reason}"),
+            Self::NoHome => write!(
+                f,
+                "cannot find the data directory; set `HOME` or `HRTRACKER_DIR`"
+            ),
+            Self::Config { path, reason } => write!(f, "in {}: {reason}", path.display()),
+            Self::Hook { hook, reason } => write!(f, "hook `{hook}` failed: {reason}"),
+            Self::Webhook { url, reason } => write!(f, "request to `{url}` failed: {reason}"),
+            Self::Usage(message) => write!(f, "{message}"),
+        }
+    }
+}
+impl std::error::Error for HrtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode { source, .. } | Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for HrtError {
+    fn from(source: io::Error) -> Self {
+        Self::Io { path: None, source }
+    }
+}
+
+/// A `Result` that fails with `HrtError`.
+pub type Result<T, E = HrtError> = std::result::Result<T, E>;
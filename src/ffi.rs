@@ -0,0 +1,137 @@
+//! A C ABI over the schedule format, declared in `include/hrtracker.h`. Nothing here unwinds
+//! across the boundary: panics are caught and reported as `HRT_ERR_PANIC` (or a null or sentinel
+//! return value).
+
+use std::{
+    ffi::{CStr, c_char},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+};
+
+use chrono::Utc;
+
+use crate::{
+    error::HrtError,
+    schedule::{HISTORY_CAP, RegularSchedule, Schedule},
+};
+
+pub const HRT_OK: i32 = 0;
+/// A pointer argument was null.
+pub const HRT_ERR_NULL: i32 = -1;
+/// A path wasn't valid UTF-8.
+pub const HRT_ERR_PATH: i32 = -2;
+pub const HRT_ERR_NOT_FOUND: i32 = -3;
+pub const HRT_ERR_DECODE: i32 = -4;
+pub const HRT_ERR_IO: i32 = -5;
+pub const HRT_ERR_PANIC: i32 = -6;
+/// Anything else, such as an interval that overflows when stepping.
+pub const HRT_ERR_OTHER: i32 = -7;
+
+/// What the getters return when passed a null schedule.
+pub const HRT_INVALID: i64 = i64::MIN;
+
+/// An opened schedule, owned by the caller until it is passed to `hrt_free`.
+pub struct HrtSchedule(RegularSchedule);
+
+fn code(error: &HrtError) -> i32 {
+    match error {
+        HrtError::NotFound { .. } => HRT_ERR_NOT_FOUND,
+        HrtError::Decode { .. } => HRT_ERR_DECODE,
+        HrtError::Io { .. } => HRT_ERR_IO,
+        _ => HRT_ERR_OTHER,
+    }
+}
+
+/// # Safety
+/// `path` must be null or point to a NUL-terminated string.
+unsafe fn path<'a>(path: *const c_char) -> Result<&'a Path, i32> {
+    if path.is_null() {
+        return Err(HRT_ERR_NULL);
+    }
+    // SAFETY: checked for null above; the caller guarantees the string is terminated
+    let path = unsafe { CStr::from_ptr(path) };
+    path.to_str().map(Path::new).map_err(|_| HRT_ERR_PATH)
+}
+
+/// Opens the schedule file at `path`, returning null if it can't be read.
+///
+/// # Safety
+/// `path` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hrt_open(path: *const c_char) -> *mut HrtSchedule {
+    panic::catch_unwind(|| {
+        // SAFETY: forwarded from the caller
+        let path = unsafe { self::path(path) }.ok()?;
+        let schedule = RegularSchedule::open(path).ok()?;
+        Some(Box::into_raw(Box::new(HrtSchedule(schedule))))
+    })
+    .ok()
+    .flatten()
+    .unwrap_or(ptr::null_mut())
+}
+
+/// When `schedule` is next due, in seconds since the Unix epoch.
+///
+/// # Safety
+/// `schedule` must be null or a pointer returned by `hrt_open` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hrt_next_unix(schedule: *const HrtSchedule) -> i64 {
+    // SAFETY: the caller guarantees the pointer is null or valid
+    match unsafe { schedule.as_ref() } {
+        Some(HrtSchedule(schedule)) => schedule.next_at().timestamp(),
+        None => HRT_INVALID,
+    }
+}
+
+/// The interval of `schedule`, in whole seconds.
+///
+/// # Safety
+/// `schedule` must be null or a pointer returned by `hrt_open` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hrt_interval_seconds(schedule: *const HrtSchedule) -> i64 {
+    // SAFETY: the caller guarantees the pointer is null or valid
+    match unsafe { schedule.as_ref() } {
+        Some(HrtSchedule(schedule)) => schedule.interval.num_seconds(),
+        None => HRT_INVALID,
+    }
+}
+
+/// Advances `schedule` by its interval and saves it to `path`, returning `HRT_OK` or an error
+/// code. The history is pruned to the latest `HISTORY_CAP` steps, as `step` does by default, and
+/// the in-memory schedule is only changed if saving succeeds.
+///
+/// # Safety
+/// `schedule` must be null or a pointer returned by `hrt_open` that hasn't been freed, and `path`
+/// must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hrt_step(schedule: *mut HrtSchedule, path: *const c_char) -> i32 {
+    // SAFETY: the caller guarantees the pointer is null or valid
+    let Some(HrtSchedule(schedule)) = (unsafe { schedule.as_mut() }) else {
+        return HRT_ERR_NULL;
+    };
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        // SAFETY: forwarded from the caller
+        let path = unsafe { self::path(path) }?;
+        let mut stepped = schedule.clone();
+        stepped.step(Utc::now()).map_err(|error| code(&error))?;
+        stepped.prune_history(HISTORY_CAP, None);
+        stepped.save(path).map_err(|error| code(&error))?;
+        *schedule = stepped;
+        Ok(())
+    }))
+    .unwrap_or(Err(HRT_ERR_PANIC))
+    .map_or_else(|code| code, |()| HRT_OK)
+}
+
+/// Frees a schedule returned by `hrt_open`. Null is ignored.
+///
+/// # Safety
+/// `schedule` must be null or a pointer returned by `hrt_open` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hrt_free(schedule: *mut HrtSchedule) {
+    if !schedule.is_null() {
+        // SAFETY: the pointer came from `Box::into_raw` in `hrt_open`
+        drop(unsafe { Box::from_raw(schedule) });
+    }
+}
@@ -0,0 +1,429 @@
+//! Codecs for the chrono types and text stored in schedule files, for use with `#[encode_with]` and
+//! `#[decode_with]`, and the limits every decoded length is held to.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc};
+use decent::{Decodable, Encodable, PrimitiveRepr, Version};
+
+/// Encodes a datetime as its day since the epoch, second of the day, and nanosecond.
+pub fn encode_datetime(
+    date: &DateTime<Utc>,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    let naive = date.naive_utc();
+    let date = naive.date();
+    let time = naive.time();
+    date.to_epoch_days().encode(to, version, repr)?;
+    time.num_seconds_from_midnight().encode(to, version, repr)?;
+    time.nanosecond().encode(to, version, repr)?;
+    Ok(())
+}
+/// Decodes a datetime written by `encode_datetime`.
+pub fn decode_datetime(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<DateTime<Utc>> {
+    let epoch_day_count = i32::decode(from, version, repr)?;
+    let second_count = u32::decode(from, version, repr)?;
+    let nanosecond_count = u32::decode(from, version, repr)?;
+    let date = NaiveDate::from_epoch_days(epoch_day_count).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            "invalid date while decoding date and time",
+        )
+    })?;
+    let time = NaiveTime::from_num_seconds_from_midnight_opt(second_count, nanosecond_count)
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "invalid time while decoding date and time",
+            )
+        })?;
+    let naive = NaiveDateTime::new(date, time);
+    let date = DateTime::from_naive_utc_and_offset(naive, Utc);
+    if !in_range(&date) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "date is outside the years {} to {}",
+                limits::MIN_YEAR,
+                limits::MAX_YEAR
+            ),
+        ));
+    }
+    Ok(date)
+}
+
+/// Whether `date` is within the years in `limits`. Schedules are never advanced past them, so
+/// that a corrupt or extreme schedule fails cleanly instead of overflowing later.
+pub fn in_range(date: &DateTime<Utc>) -> bool {
+    (limits::MIN_YEAR..=limits::MAX_YEAR).contains(&date.year())
+}
+
+/// The version time deltas were first written as whole seconds and nanoseconds, rather than as
+/// one number of nanoseconds, which can only reach about 292 years.
+pub const SPLIT_TIMEDELTA: Version = Version(0, 0, 12);
+
+/// Encodes a time delta as its whole seconds and the nanoseconds beyond them, or in formats before
+/// `SPLIT_TIMEDELTA` as a number of nanoseconds, failing if that doesn't fit in an `i64`.
+pub fn encode_timedelta(
+    delta: &TimeDelta,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    if version >= SPLIT_TIMEDELTA {
+        delta.num_seconds().encode(to, version, repr)?;
+        return delta.subsec_nanos().encode(to, version, repr);
+    }
+    delta
+        .num_nanoseconds()
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "the time is too long for this format version, which only reaches about 292 years",
+            )
+        })?
+        .encode(to, version, repr)
+}
+/// Decodes a time delta written by `encode_timedelta` in `version`.
+pub fn decode_timedelta(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<TimeDelta> {
+    if version < SPLIT_TIMEDELTA {
+        return Ok(TimeDelta::nanoseconds(i64::decode(from, version, repr)?));
+    }
+    let seconds = i64::decode(from, version, repr)?;
+    let nanos = i32::decode(from, version, repr)?;
+    // `subsec_nanos` has the sign of the whole delta, and is always less than a second
+    let consistent = nanos.unsigned_abs() < 1_000_000_000
+        && (nanos == 0 || seconds == 0 || (nanos < 0) == (seconds < 0));
+    TimeDelta::try_seconds(seconds)
+        .filter(|_| consistent)
+        .and_then(|whole| whole.checked_add(&TimeDelta::nanoseconds(nanos.into())))
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid time of {seconds} seconds and {nanos} nanoseconds"),
+            )
+        })
+}
+
+/// The most any length read from a schedule file may be, so that a corrupt or malicious file can
+/// never make decoding allocate more than a few kilobytes per field, and the range dates must be
+/// in.
+pub mod limits {
+    /// Bytes in a whole schedule file, far more than any real schedule needs, unless a `DirStore`
+    /// is given another limit.
+    pub const FILE: u64 = 1024 * 1024;
+    /// Bytes in a schedule name.
+    pub const NAME: usize = 256;
+    /// Bytes in a note.
+    pub const NOTE: usize = 4 * 1024;
+    /// Entries in a history.
+    pub const HISTORY: usize = 4096;
+    /// Key-value pairs of metadata.
+    pub const METADATA: usize = 32;
+    /// Bytes in a URL, such as a webhook.
+    pub const URL: usize = 2048;
+    /// Tags on a schedule, each of which is limited to `NAME` bytes.
+    pub const TAGS: usize = 32;
+    /// The earliest and latest years a date may be in.
+    pub const MIN_YEAR: i32 = 1;
+    pub const MAX_YEAR: i32 = 9999;
+}
+
+/// Decodes a length for `field`, failing before anything is allocated if it is over `max`. Every
+/// variable-length field must be decoded through this.
+pub fn read_bounded_len(
+    from: &mut dyn Read,
+    field: &str,
+    max: usize,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<usize> {
+    let len = u64::decode(from, version, repr)?;
+    match usize::try_from(len) {
+        Ok(len) if len <= max => Ok(len),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{field} has length {len}, over the limit of {max}"),
+        )),
+    }
+}
+
+/// Encodes a list of datetimes as its length followed by each one, as in `encode_datetime`.
+pub fn encode_datetimes(
+    dates: &Vec<DateTime<Utc>>,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    (dates.len() as u64).encode(to, version, repr)?;
+    for date in dates {
+        encode_datetime(date, to, version, repr)?;
+    }
+    Ok(())
+}
+/// Decodes a list written by `encode_datetimes`, failing if it has more than `max` entries.
+pub fn decode_datetimes(
+    from: &mut dyn Read,
+    field: &str,
+    max: usize,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Vec<DateTime<Utc>>> {
+    let len = read_bounded_len(from, field, max, version, repr)?;
+    (0..len)
+        .map(|_| decode_datetime(from, version, repr))
+        .collect()
+}
+
+/// Encodes text as its length in bytes followed by the bytes themselves.
+pub fn encode_text(
+    text: &str,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    (text.len() as u64).encode(to, version, repr)?;
+    to.write_all(text.as_bytes())
+}
+/// Decodes text written by `encode_text`, failing if it is over `max` bytes or isn't UTF-8.
+pub fn decode_text(
+    from: &mut dyn Read,
+    field: &str,
+    max: usize,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<String> {
+    let len = read_bounded_len(from, field, max, version, repr)?;
+    let mut bytes = vec![0; len];
+    from.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{field} is not valid UTF-8"),
+        )
+    })
+}
+
+/// Encodes optional text as a presence byte, then the text as in `encode_text` if there is any.
+pub fn encode_optional_text(
+    text: &Option<String>,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    match text {
+        Some(text) => {
+            1u8.encode(to, version, repr)?;
+            encode_text(text, to, version, repr)
+        }
+        None => 0u8.encode(to, version, repr),
+    }
+}
+/// Encodes a flag as a byte, 1 for `true` and 0 for `false`.
+pub fn encode_bool(
+    flag: &bool,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    u8::from(*flag).encode(to, version, repr)
+}
+/// Decodes a flag written by `encode_bool`, failing on any byte other than 0 or 1.
+pub fn decode_bool(
+    from: &mut dyn Read,
+    field: &str,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<bool> {
+    match u8::decode(from, version, repr)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid byte {other} while decoding {field}"),
+        )),
+    }
+}
+/// Encodes a list of text as its length followed by each entry, as in `encode_text`.
+pub fn encode_texts(
+    texts: &Vec<String>,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    (texts.len() as u64).encode(to, version, repr)?;
+    for text in texts {
+        encode_text(text, to, version, repr)?;
+    }
+    Ok(())
+}
+/// Decodes a list written by `encode_texts`, failing if it has more than `max` entries or an
+/// entry over `max_len` bytes.
+pub fn decode_texts(
+    from: &mut dyn Read,
+    field: &str,
+    max: usize,
+    max_len: usize,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Vec<String>> {
+    let len = read_bounded_len(from, field, max, version, repr)?;
+    (0..len)
+        .map(|_| decode_text(from, field, max_len, version, repr))
+        .collect()
+}
+/// Decodes optional text written by `encode_optional_text`.
+pub fn decode_optional_text(
+    from: &mut dyn Read,
+    field: &str,
+    max: usize,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Option<String>> {
+    match u8::decode(from, version, repr)? {
+        0 => Ok(None),
+        1 => decode_text(from, field, max, version, repr).map(Some),
+        other => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid presence byte {other} while decoding {field}"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::LATEST;
+
+    const REPR: PrimitiveRepr = PrimitiveRepr::Varint;
+
+    fn length(len: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        len.encode(&mut bytes, LATEST, REPR).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn lengths_up_to_the_limit_are_read() {
+        let len = read_bounded_len(&mut &length(256)[..], "name", 256, LATEST, REPR).unwrap();
+        assert_eq!(len, 256);
+        let error = read_bounded_len(&mut &length(257)[..], "name", 256, LATEST, REPR).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "name has length 257, over the limit of 256"
+        );
+    }
+
+    #[test]
+    fn huge_lengths_fail_before_allocating() {
+        let mut bytes = length(u64::MAX);
+        bytes.extend([b'x'; 16]);
+        let error = decode_text(&mut &bytes[..], "note", limits::NOTE, LATEST, REPR).unwrap_err();
+        assert!(error.to_string().starts_with("note has length"));
+        let error = decode_datetimes(&mut &bytes[..], "history", limits::HISTORY, LATEST, REPR)
+            .unwrap_err();
+        assert!(error.to_string().contains("over the limit of 4096"));
+    }
+
+    #[test]
+    fn dates_outside_the_years_fail_to_decode() {
+        use chrono::TimeZone;
+
+        for year in [limits::MIN_YEAR, limits::MAX_YEAR] {
+            let date = Utc.with_ymd_and_hms(year, 6, 1, 0, 0, 0).unwrap();
+            let mut bytes = Vec::new();
+            encode_datetime(&date, &mut bytes, LATEST, REPR).unwrap();
+            assert_eq!(
+                decode_datetime(&mut &bytes[..], LATEST, REPR).unwrap(),
+                date
+            );
+        }
+        for year in [limits::MIN_YEAR - 1, limits::MAX_YEAR + 1] {
+            let date = Utc.with_ymd_and_hms(year, 6, 1, 0, 0, 0).unwrap();
+            let mut bytes = Vec::new();
+            encode_datetime(&date, &mut bytes, LATEST, REPR).unwrap();
+            let error = decode_datetime(&mut &bytes[..], LATEST, REPR).unwrap_err();
+            assert_eq!(error.to_string(), "date is outside the years 1 to 9999");
+        }
+    }
+
+    #[test]
+    fn timedeltas_round_trip_in_both_forms() {
+        let before = Version(SPLIT_TIMEDELTA.0, SPLIT_TIMEDELTA.1, SPLIT_TIMEDELTA.2 - 1);
+        let most_nanoseconds = TimeDelta::nanoseconds(i64::MAX);
+        let round_trip = |delta: TimeDelta, version| {
+            let mut bytes = Vec::new();
+            encode_timedelta(&delta, &mut bytes, version, REPR)?;
+            decode_timedelta(&mut &bytes[..], version, REPR)
+        };
+        for version in [before, SPLIT_TIMEDELTA, LATEST] {
+            for delta in [
+                TimeDelta::zero(),
+                TimeDelta::nanoseconds(1),
+                TimeDelta::nanoseconds(-1),
+                TimeDelta::milliseconds(1500),
+                TimeDelta::milliseconds(-1500),
+                TimeDelta::days(365),
+                most_nanoseconds,
+                -most_nanoseconds,
+            ] {
+                assert_eq!(
+                    round_trip(delta, version).unwrap(),
+                    delta,
+                    "{delta} in {version:?}"
+                );
+            }
+        }
+
+        // a nanosecond more than the old form can hold only fits in the new one
+        let too_long = most_nanoseconds + TimeDelta::nanoseconds(1);
+        for delta in [
+            too_long,
+            -too_long - TimeDelta::nanoseconds(1),
+            TimeDelta::MAX,
+            TimeDelta::MIN,
+        ] {
+            assert_eq!(round_trip(delta, SPLIT_TIMEDELTA).unwrap(), delta);
+            let error = round_trip(delta, before).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert!(error.to_string().contains("about 292 years"), "{error}");
+        }
+    }
+
+    #[test]
+    fn inconsistent_times_fail_to_decode() {
+        let decode = |seconds: i64, nanos: i32| {
+            let mut bytes = Vec::new();
+            seconds.encode(&mut bytes, LATEST, REPR).unwrap();
+            nanos.encode(&mut bytes, LATEST, REPR).unwrap();
+            decode_timedelta(&mut &bytes[..], LATEST, REPR).ok()
+        };
+        assert_eq!(
+            decode(-1, -500_000_000),
+            Some(TimeDelta::milliseconds(-1500))
+        );
+        assert_eq!(decode(0, 500_000_000), Some(TimeDelta::milliseconds(500)));
+        assert_eq!(decode(1, -500_000_000), None);
+        assert_eq!(decode(1, 1_000_000_000), None);
+        assert_eq!(decode(i64::MAX, 0), None);
+    }
+
+    #[test]
+    fn text_shorter_than_its_length_is_an_error() {
+        let mut bytes = length(4);
+        bytes.extend(b"ab");
+        let error = decode_text(&mut &bytes[..], "name", limits::NAME, LATEST, REPR).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,141 @@
+//! Counting steps by weekday or hour, for `heatmap`.
+
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc, Weekday};
+
+/// The longest bar `render` draws, for the largest count.
+pub const WIDTH: usize = 40;
+
+/// The levels of `sparkline`, from an empty bucket to the largest.
+pub const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// The same levels in ASCII, for terminals that can't show `BLOCKS`.
+pub const ASCII: [char; 9] = [' ', '.', ',', ':', '-', '=', '+', '*', '#'];
+
+/// How many of `times` fall on each weekday in `tz`, from Monday to Sunday.
+pub fn by_weekday<Tz: TimeZone>(times: &[DateTime<Utc>], tz: &Tz) -> [usize; 7] {
+    let mut counts = [0; 7];
+    for time in times {
+        counts[time.with_timezone(tz).weekday().num_days_from_monday() as usize] += 1;
+    }
+    counts
+}
+
+/// How many of `times` fall in each hour of the day in `tz`, from midnight onwards.
+pub fn by_hour<Tz: TimeZone>(times: &[DateTime<Utc>], tz: &Tz) -> [usize; 24] {
+    let mut counts = [0; 24];
+    for time in times {
+        counts[time.with_timezone(tz).hour() as usize] += 1;
+    }
+    counts
+}
+
+/// The weekday labels `render` is given for `by_weekday`.
+pub fn weekday_labels() -> Vec<String> {
+    let mut day = Weekday::Mon;
+    (0..7)
+        .map(|_| {
+            let label = day.to_string();
+            day = day.succ();
+            label
+        })
+        .collect()
+}
+
+/// The hour labels `render` is given for `by_hour`, like `09:00`.
+pub fn hour_labels() -> Vec<String> {
+    (0..24).map(|hour| format!("{hour:02}:00")).collect()
+}
+
+/// The index of the largest count, or the first of them if several tie. `None` if every count is
+/// zero.
+pub fn modal(counts: &[usize]) -> Option<usize> {
+    let max = counts.iter().copied().max().filter(|max| *max > 0)?;
+    counts.iter().position(|count| *count == max)
+}
+
+/// One character per count, from `levels`, scaled so that the largest count is the last level and
+/// any count above zero is at least the second.
+pub fn sparkline(counts: &[usize], levels: &[char; 9]) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    counts
+        .iter()
+        .map(|count| match (count, max) {
+            (0, _) => levels[0],
+            (count, max) => levels[(count * 8).div_ceil(max)],
+        })
+        .collect()
+}
+
+/// One line per count, with its label, a bar of `#` as long as the count is relative to the
+/// largest (at least one for any count above zero), and the count, marking the largest ones.
+pub fn render(labels: &[String], counts: &[usize]) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let mut text = String::new();
+    for (label, count) in labels.iter().zip(counts) {
+        let bar = match max {
+            0 => 0,
+            max => (count * WIDTH).div_ceil(max),
+        };
+        let most = if *count == max && max > 0 {
+            " (most)"
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "{label:label_width$}  {:WIDTH$}  {count}{most}\n",
+            "#".repeat(bar)
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    #[test]
+    fn steps_are_counted_in_the_timezone() {
+        // late on a Sunday in UTC, and just after midnight on Monday an hour east
+        let times = ["2025-03-02T23:30:00Z".parse().unwrap()];
+        let east = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(by_weekday(&times, &Utc), [0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(by_weekday(&times, &east), [1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(by_hour(&times, &Utc)[23], 1);
+        assert_eq!(by_hour(&times, &east)[0], 1);
+        assert_eq!(weekday_labels()[..2], ["Mon", "Tue"]);
+        assert_eq!(hour_labels()[9], "09:00");
+    }
+
+    #[test]
+    fn the_mode_is_the_first_of_the_largest() {
+        assert_eq!(modal(&[0, 3, 1, 3]), Some(1));
+        assert_eq!(modal(&[0, 0]), None);
+        assert_eq!(modal(&[]), None);
+    }
+
+    #[test]
+    fn sparklines_show_any_step_at_all() {
+        assert_eq!(sparkline(&[0, 1, 4, 8], &BLOCKS), " ▁▄█");
+        assert_eq!(sparkline(&[0, 1, 4, 8], &ASCII), " .-#");
+        assert_eq!(sparkline(&[1, 100], &ASCII), ".#");
+        assert_eq!(sparkline(&[0, 0], &ASCII), "  ");
+    }
+
+    #[test]
+    fn bars_are_scaled_to_the_largest_count() {
+        let labels = ["Mon", "Tue", "Wed"].map(String::from);
+        assert_eq!(
+            render(&labels, &[2, 0, 1]),
+            format!(
+                "Mon  {}  2 (most)\nTue  {}  0\nWed  {}{}  1\n",
+                "#".repeat(40),
+                " ".repeat(40),
+                "#".repeat(20),
+                " ".repeat(20)
+            )
+        );
+        assert!(!render(&labels, &[0, 0, 0]).contains("(most)"));
+    }
+}
@@ -0,0 +1,304 @@
+//! User commands run when a schedule is created, stepped, deleted, or becomes due.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+
+use crate::{
+    error::{HrtError, Result},
+    schedule::RegularSchedule,
+};
+
+/// How long a hook may run before it is killed.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+/// The directory of executable hooks, relative to the data directory.
+pub const DIR: &str = "hooks";
+
+/// Something that happened to a schedule.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Event {
+    Step,
+    Due,
+    New,
+    Delete,
+}
+impl Event {
+    pub const ALL: [Self; 4] = [Self::Step, Self::Due, Self::New, Self::Delete];
+
+    /// How the event is passed in `HRT_EVENT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Step => "step",
+            Self::Due => "due",
+            Self::New => "new",
+            Self::Delete => "delete",
+        }
+    }
+    /// The config key and the file in `DIR` for the event's hooks, like `on_step`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Step => "on_step",
+            Self::Due => "on_due",
+            Self::New => "on_new",
+            Self::Delete => "on_delete",
+        }
+    }
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.key() == key)
+    }
+}
+
+/// The schedule an event happened to, as it is after the event.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fired<'a> {
+    pub event: Event,
+    pub name: &'a str,
+    pub next: DateTime<Utc>,
+    pub interval: TimeDelta,
+}
+impl<'a> Fired<'a> {
+    /// `event` happening to `schedule`, called `name`.
+    pub fn of(event: Event, name: &'a str, schedule: &RegularSchedule) -> Self {
+        Self {
+            event,
+            name,
+            next: schedule.next,
+            interval: schedule.interval,
+        }
+    }
+}
+
+/// Reacts to events. The command line uses `Hooks`; `()` ignores every event, for tests and
+/// `--no-hooks`.
+pub trait HookRunner {
+    /// Runs every hook for `fired`, returning an error for each one that failed. Failures are
+    /// never fatal, so callers report them as warnings.
+    fn fire(&self, fired: &Fired) -> Vec<HrtError>;
+}
+impl HookRunner for () {
+    fn fire(&self, _: &Fired) -> Vec<HrtError> {
+        Vec::new()
+    }
+}
+
+/// A hook to run.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hook {
+    /// A command from the config file, run through `sh -c`.
+    Shell(String),
+    /// An executable in the hooks directory.
+    Executable(PathBuf),
+}
+
+/// The hooks for each event, run as child processes with the schedule in their environment.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hooks {
+    pub hooks: BTreeMap<Event, Vec<Hook>>,
+    pub timeout: Duration,
+}
+impl Hooks {
+    /// The hooks set by `on_*` keys in `config`, followed by those in `dir` (each named after the
+    /// key of its event). Other keys are ignored.
+    pub fn load(config: &[(String, String)], dir: &Path) -> Self {
+        let mut hooks: BTreeMap<Event, Vec<Hook>> = BTreeMap::new();
+        for (key, command) in config {
+            if let Some(event) = Event::from_key(key) {
+                hooks
+                    .entry(event)
+                    .or_default()
+                    .push(Hook::Shell(command.clone()));
+            }
+        }
+        for event in Event::ALL {
+            let path = dir.join(event.key());
+            if path.is_file() {
+                hooks.entry(event).or_default().push(Hook::Executable(path));
+            }
+        }
+        Self {
+            hooks,
+            timeout: TIMEOUT,
+        }
+    }
+}
+impl HookRunner for Hooks {
+    fn fire(&self, fired: &Fired) -> Vec<HrtError> {
+        let Some(hooks) = self.hooks.get(&fired.event) else {
+            return Vec::new();
+        };
+        hooks
+            .iter()
+            .filter_map(|hook| run(hook, fired, self.timeout).err())
+            .collect()
+    }
+}
+
+fn run(hook: &Hook, fired: &Fired, timeout: Duration) -> Result<()> {
+    let (mut command, shown) = match hook {
+        Hook::Shell(command) => {
+            let mut shell = Command::new("sh");
+            shell.args(["-c", command]);
+            (shell, command.clone())
+        }
+        Hook::Executable(path) => (Command::new(path), path.display().to_string()),
+    };
+    let failed = |reason: String| HrtError::Hook {
+        hook: shown.clone(),
+        reason,
+    };
+    command
+        .env("HRT_NAME", fired.name)
+        .env("HRT_EVENT", fired.event.name())
+        .env(
+            "HRT_NEXT",
+            fired.next.to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+        .env(
+            "HRT_INTERVAL_SECS",
+            fired.interval.num_seconds().to_string(),
+        )
+        .stdin(Stdio::null())
+        // keep stdout for hrtracker's own output, which may be porcelain
+        .stdout(io::stderr());
+    let mut child = command.spawn().map_err(|error| failed(error.to_string()))?;
+    match wait(&mut child, timeout).map_err(|error| failed(error.to_string()))? {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(failed(format!("it exited with {status}"))),
+        None => {
+            // it may have exited just now, in which case there is nothing left to kill
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(failed(format!(
+                "it was killed after running for {} seconds",
+                timeout.as_secs()
+            )))
+        }
+    }
+}
+
+/// Waits for `child` to exit, or returns `None` once `timeout` has passed.
+fn wait(child: &mut Child, timeout: Duration) -> io::Result<Option<std::process::ExitStatus>> {
+    const POLL: Duration = Duration::from_millis(20);
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= timeout {
+            return Ok(None);
+        }
+        thread::sleep(POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    fn fired(name: &str) -> Fired<'_> {
+        Fired {
+            event: Event::Step,
+            name,
+            next: "2025-03-02T09:00:00Z".parse().unwrap(),
+            interval: TimeDelta::hours(12),
+        }
+    }
+
+    fn shell(command: &str, timeout: Duration) -> Hooks {
+        Hooks {
+            hooks: BTreeMap::from([(Event::Step, vec![Hook::Shell(command.to_string())])]),
+            timeout,
+        }
+    }
+
+    #[test]
+    fn events_are_found_by_key() {
+        for event in Event::ALL {
+            assert_eq!(Event::from_key(event.key()), Some(event));
+        }
+        assert_eq!(Event::from_key("step"), None);
+    }
+
+    #[test]
+    fn fired_schedules_are_seen_after_the_event() {
+        let next = "2025-03-01T09:00:00Z".parse().unwrap();
+        let mut schedule = RegularSchedule::create(next, TimeDelta::hours(12));
+        schedule.step(next).unwrap();
+        let fired = Fired::of(Event::Step, "water", &schedule);
+        assert_eq!(
+            fired.next,
+            "2025-03-01T21:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(fired.interval, TimeDelta::hours(12));
+    }
+
+    #[test]
+    fn config_hooks_run_before_the_hooks_directory() {
+        let dir = env::temp_dir().join(format!("hrtracker-hooks-{}", process::id()));
+        fs::create_dir_all(dir.join("on_due")).unwrap();
+        fs::write(dir.join("on_step"), "").unwrap();
+        let config = [
+            ("on_step".to_string(), "echo stepped".to_string()),
+            ("color".to_string(), "never".to_string()),
+        ];
+        let hooks = Hooks::load(&config, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        // `on_due` is a directory, so it isn't a hook
+        assert_eq!(
+            hooks.hooks,
+            BTreeMap::from([(
+                Event::Step,
+                vec![
+                    Hook::Shell("echo stepped".to_string()),
+                    Hook::Executable(dir.join("on_step")),
+                ],
+            )])
+        );
+        assert_eq!(hooks.timeout, TIMEOUT);
+    }
+
+    #[test]
+    fn hooks_see_the_schedule_in_their_environment() {
+        let check = r#"test "$HRT_NAME $HRT_EVENT $HRT_NEXT $HRT_INTERVAL_SECS" = "water step 2025-03-02T09:00:00Z 43200""#;
+        assert!(shell(check, TIMEOUT).fire(&fired("water")).is_empty());
+        assert_eq!(shell(check, TIMEOUT).fire(&fired("pills")).len(), 1);
+        // other events don't run it
+        let due = Fired {
+            event: Event::Due,
+            ..fired("pills")
+        };
+        assert!(shell(check, TIMEOUT).fire(&due).is_empty());
+    }
+
+    #[test]
+    fn failing_and_hanging_hooks_are_reported() {
+        let errors = shell("exit 3", TIMEOUT).fire(&fired("water"));
+        assert_eq!(
+            errors[0].to_string(),
+            "hook `exit 3` failed: it exited with exit status: 3"
+        );
+        let errors = shell("sleep 5", Duration::from_millis(100)).fire(&fired("water"));
+        assert!(
+            errors[0]
+                .to_string()
+                .starts_with("hook `sleep 5` failed: it was killed after running for")
+        );
+        let missing = Hooks {
+            hooks: BTreeMap::from([(
+                Event::Step,
+                vec![Hook::Executable(PathBuf::from("/nonexistent/on_step"))],
+            )]),
+            timeout: TIMEOUT,
+        };
+        assert_eq!(missing.fire(&fired("water")).len(), 1);
+    }
+}
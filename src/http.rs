@@ -0,0 +1,155 @@
+//! Just enough of an HTTP/1.1 server for `serve`: one request per connection, no bodies read.
+
+use std::io::{self, Read, Write};
+
+/// The most a request line and its headers may take up.
+pub const MAX_HEAD: usize = 8192;
+
+/// The parts of a request `serve` looks at.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Request {
+    pub method: String,
+    /// The path, without the query string, and still percent-encoded.
+    pub path: String,
+}
+
+/// Reads the request line and headers from `stream`. Anything that isn't an HTTP/1.x request,
+/// or doesn't fit in `MAX_HEAD`, is an `InvalidData` error; any body is left unread.
+pub fn read_request(stream: &mut impl Read) -> io::Result<Request> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason.to_string());
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_HEAD {
+            return Err(invalid("the request head is too long"));
+        }
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            return Err(invalid("the connection closed before the request ended"));
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let line = head.lines().next().unwrap_or_default();
+    let mut parts = line.split(' ');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version), None)
+            if !method.is_empty() && target.starts_with('/') && version.starts_with("HTTP/1.") =>
+        {
+            let path = target.split(['?', '#']).next().unwrap_or(target);
+            Ok(Request {
+                method: method.to_string(),
+                path: path.to_string(),
+            })
+        }
+        _ => Err(invalid("malformed request line")),
+    }
+}
+
+/// Decodes `%XX` escapes in a path segment, or `None` if an escape is malformed or the result
+/// isn't UTF-8.
+pub fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The reason phrase for the statuses `serve` responds with.
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Writes a complete response with a JSON `body`, after which the connection is closed.
+pub fn write_response(stream: &mut impl Write, status: u16, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        reason(status),
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(bytes: &[u8]) -> io::Result<Request> {
+        read_request(&mut &bytes[..])
+    }
+
+    #[test]
+    fn requests_keep_only_the_method_and_path() {
+        let request = read(b"GET /schedules/water?x=1 HTTP/1.1\r\nHost: here\r\n\r\nbody").unwrap();
+        assert_eq!(
+            request,
+            Request {
+                method: "GET".to_string(),
+                path: "/schedules/water".to_string(),
+            }
+        );
+        assert_eq!(read(b"POST /a#b HTTP/1.0\r\n\r\n").unwrap().path, "/a");
+    }
+
+    #[test]
+    fn anything_else_is_invalid_data() {
+        let long = [b'a'; MAX_HEAD + 2048];
+        for bytes in [
+            &b"GET schedules HTTP/1.1\r\n\r\n"[..],
+            b"GET / HTTP/2\r\n\r\n",
+            b"GET  / HTTP/1.1\r\n\r\n",
+            b"GET / HTTP/1.1\r\n",
+            &long,
+        ] {
+            let error = read(bytes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn escapes_are_decoded_to_utf8() {
+        assert_eq!(percent_decode("water%20bottle").unwrap(), "water bottle");
+        assert_eq!(percent_decode("%2F%c3%A9é").unwrap(), "/éé");
+        for bad in ["%zz", "%2", "%ff"] {
+            assert_eq!(percent_decode(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn responses_are_closed_after_the_body() {
+        let mut response = Vec::new();
+        write_response(&mut response, 404, "{}").unwrap();
+        assert_eq!(
+            String::from_utf8(response).unwrap(),
+            "HTTP/1.1 404 Not Found\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: 2\r\n\
+             Connection: close\r\n\
+             \r\n\
+             {}"
+        );
+        assert_eq!(reason(503), "Internal Server Error");
+    }
+}
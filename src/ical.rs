@@ -0,0 +1,449 @@
+//! iCalendar (RFC 5545) export and import of schedules.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+
+use crate::{
+    format::limits,
+    parse::validate_name,
+    schedule::{RegularSchedule, Schedule},
+};
+
+/// How many occurrences are written as separate events when an interval has no `RRULE`.
+pub const FALLBACK_OCCURRENCES: usize = 10;
+
+/// A hash of `text` that never changes between runs or Rust versions (64-bit FNV-1a), so that
+/// UIDs stay the same from one export to the next.
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The `RRULE` value for `interval`, if it is a whole number of weeks, days, or hours.
+pub fn rrule(interval: TimeDelta) -> Option<String> {
+    if interval <= TimeDelta::zero() || interval.subsec_nanos() != 0 {
+        return None;
+    }
+    let seconds = interval.num_seconds();
+    [
+        ("WEEKLY", 7 * 24 * 3600),
+        ("DAILY", 24 * 3600),
+        ("HOURLY", 3600),
+    ]
+    .into_iter()
+    .find(|(_, unit)| seconds % unit == 0)
+    .map(|(frequency, unit)| format!("FREQ={frequency};INTERVAL={}", seconds / unit))
+}
+
+/// A UTC date-time in the basic format, like `20250102T030405Z`.
+pub fn timestamp(date: &DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes the characters that are special in `TEXT` values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(char);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+/// Folds `line` into lines of at most 75 octets, each continuation starting with a space, and
+/// ends every line with CRLF. Characters are never split.
+pub fn fold(line: &str) -> String {
+    const LIMIT: usize = 75;
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut width = 0;
+    for char in line.chars() {
+        if width + char.len_utf8() > LIMIT {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(char);
+        width += char.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Writes `schedules` as a calendar. Each schedule whose interval maps to an `RRULE` becomes one
+/// recurring event; each other schedule becomes `FALLBACK_OCCURRENCES` separate events. `now` is
+/// used as the `DTSTAMP`.
+pub fn export<'a>(
+    schedules: impl IntoIterator<Item = &'a (String, RegularSchedule)>,
+    now: DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//hrtracker//hrtracker//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for (name, schedule) in schedules {
+        let uid = format!("{:016x}", stable_hash(name));
+        let mut event = |uid: String, start: DateTime<Utc>, rrule: Option<String>| {
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{uid}@hrtracker"));
+            lines.push(format!("DTSTAMP:{}", timestamp(&now)));
+            lines.push(format!("DTSTART:{}", timestamp(&start)));
+            lines.push(format!("SUMMARY:{}", escape(name)));
+            if let Some(rrule) = rrule {
+                lines.push(format!("RRULE:{rrule}"));
+            }
+            lines.push("END:VEVENT".to_string());
+        };
+        match rrule(schedule.interval) {
+            Some(rule) => event(uid, schedule.next_at(), Some(rule)),
+            None => {
+                for occurrence in schedule.occurrences().take(FALLBACK_OCCURRENCES) {
+                    // keyed by the occurrence itself, so that stepping doesn't renumber events
+                    event(
+                        format!("{uid}-{}", occurrence.timestamp()),
+                        occurrence,
+                        None,
+                    );
+                }
+            }
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Joins folded lines back together, accepting both CRLF and bare LF line endings.
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// One content line: `NAME;PARAM=value:VALUE`. Names and parameter names are uppercased.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+impl Property {
+    /// Parses a content line, or `None` if it has no `:`.
+    pub fn parse(line: &str) -> Option<Self> {
+        // parameter values may be quoted, and quoted values may contain `:` and `;`
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(index, char)| match char {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(index),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .map(|param| {
+                let (key, value) = param.split_once('=').unwrap_or((param, ""));
+                (
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                )
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Undoes `escape`.
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Parses a `DTSTART`. UTC (`...Z`) and floating times are supported, the latter in the local
+/// timezone, as are whole dates, which start at midnight UTC like `today` does.
+pub fn parse_start(property: &Property) -> Result<DateTime<Utc>, String> {
+    let value = property.value.trim();
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map_err(|error| format!("`{value}` is not a date ({error})"))?;
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
+    }
+    if let Some(zone) = property.param("TZID") {
+        return Err(format!("times in the `{zone}` timezone are not supported"));
+    }
+    let (naive, utc) = match value.strip_suffix('Z') {
+        Some(naive) => (naive, true),
+        None => (value, false),
+    };
+    let naive = NaiveDateTime::parse_from_str(naive, "%Y%m%dT%H%M%S")
+        .map_err(|error| format!("`{value}` is not a date and time ({error})"))?;
+    if utc {
+        Ok(naive.and_utc())
+    } else {
+        Local
+            .from_local_datetime(&naive)
+            .single()
+            .map(|local| local.with_timezone(&Utc))
+            .ok_or_else(|| format!("`{value}` is skipped or repeated by a clock change"))
+    }
+}
+
+/// Maps an `RRULE` of `FREQ=WEEKLY`, `DAILY`, or `HOURLY` (with an optional `INTERVAL`) to an
+/// interval. Anything more complex can't be represented by a `RegularSchedule`.
+pub fn parse_rrule(rule: &str) -> Result<TimeDelta, String> {
+    let mut unit = None;
+    let mut count: i64 = 1;
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=').unwrap_or((part, ""));
+        match (
+            key.to_ascii_uppercase().as_str(),
+            value.to_ascii_uppercase().as_str(),
+        ) {
+            ("FREQ", "WEEKLY") => unit = Some(TimeDelta::weeks(1)),
+            ("FREQ", "DAILY") => unit = Some(TimeDelta::days(1)),
+            ("FREQ", "HOURLY") => unit = Some(TimeDelta::hours(1)),
+            ("FREQ", other) => return Err(format!("`FREQ={other}` is not supported")),
+            ("INTERVAL", _) => {
+                count = value
+                    .parse()
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| format!("`INTERVAL={value}` is not a positive number"))?;
+            }
+            // other parts limit or reshape the recurrence, which a regular schedule can't do
+            _ => return Err(format!("`{part}` in the recurrence rule is not supported")),
+        }
+    }
+    let unit = unit.ok_or("the recurrence rule has no `FREQ`")?;
+    unit.checked_mul(count as i32)
+        .filter(|_| i32::try_from(count).is_ok())
+        .ok_or_else(|| format!("`INTERVAL={count}` is too large"))
+}
+
+/// Turns a `SUMMARY` into a valid schedule name: `/` becomes `-`, leading dots and surrounding
+/// whitespace are dropped, and overlong names are cut at a character boundary.
+pub fn sanitize_name(summary: &str) -> Result<String, String> {
+    let mut name = summary
+        .trim()
+        .trim_start_matches('.')
+        .replace('/', "-")
+        .trim()
+        .to_string();
+    if name.len() > limits::NAME {
+        let mut end = limits::NAME;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+    validate_name(&name).map_err(|error| error.to_string())?;
+    Ok(name)
+}
+
+/// An event that `import` couldn't turn into a schedule.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Skipped {
+    /// The event's `SUMMARY`, if it had one.
+    pub summary: Option<String>,
+    pub reason: String,
+}
+
+/// Turns each `VEVENT` in `text` into a named schedule, or the reason it couldn't be. Only
+/// `SUMMARY`, `DTSTART`, and `RRULE` are read; other properties and components are ignored.
+pub fn import(text: &str) -> Vec<Result<(String, RegularSchedule), Skipped>> {
+    let mut events = Vec::new();
+    let mut event: Option<Vec<Property>> = None;
+    for line in unfold(text) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+        match (property.name.as_str(), property.value.trim()) {
+            ("BEGIN", value) if value.eq_ignore_ascii_case("VEVENT") => event = Some(Vec::new()),
+            ("END", value) if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(properties) = event.take() {
+                    events.push(import_event(&properties));
+                }
+            }
+            _ => {
+                if let Some(properties) = &mut event {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+    events
+}
+
+fn import_event(properties: &[Property]) -> Result<(String, RegularSchedule), Skipped> {
+    let find = |name: &str| properties.iter().find(|property| property.name == name);
+    let summary = find("SUMMARY").map(|property| unescape(&property.value));
+    let skip = |reason: String| Skipped {
+        summary: summary.clone(),
+        reason,
+    };
+    let name = sanitize_name(
+        summary
+            .as_deref()
+            .ok_or_else(|| skip("it has no `SUMMARY`".into()))?,
+    )
+    .map_err(skip)?;
+    let start = parse_start(find("DTSTART").ok_or_else(|| skip("it has no `DTSTART`".into()))?)
+        .map_err(skip)?;
+    let interval = parse_rrule(
+        &find("RRULE")
+            .ok_or_else(|| skip("it doesn't repeat".into()))?
+            .value,
+    )
+    .map_err(skip)?;
+    let schedule = RegularSchedule::builder()
+        .start(start)
+        .interval(interval)
+        .build()
+        .map_err(|error| skip(error.to_string()))?;
+    Ok((name, schedule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(repr)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn export_matches_the_expected_calendar() {
+        let water = RegularSchedule::create(date("2020-01-01T09:00:00Z"), TimeDelta::days(1));
+        let pills = RegularSchedule::builder()
+            .start(date("2020-01-01T08:00:00Z"))
+            .interval(TimeDelta::minutes(90))
+            .count(3)
+            .build()
+            .unwrap();
+        let schedules = [("water".to_string(), water), ("pills".to_string(), pills)];
+        assert_eq!(
+            export(&schedules, date("2019-12-31T00:00:00Z")),
+            include_str!("../tests/golden/export.ics")
+        );
+    }
+
+    #[test]
+    fn uids_stay_the_same() {
+        assert_eq!(stable_hash(""), 0xcbf29ce484222325);
+        assert_eq!(stable_hash("water"), 0xd3cacd4c82e5be70);
+    }
+
+    #[test]
+    fn intervals_use_the_largest_unit_that_fits() {
+        assert_eq!(
+            rrule(TimeDelta::weeks(2)).as_deref(),
+            Some("FREQ=WEEKLY;INTERVAL=2")
+        );
+        assert_eq!(
+            rrule(TimeDelta::days(3)).as_deref(),
+            Some("FREQ=DAILY;INTERVAL=3")
+        );
+        assert_eq!(
+            rrule(TimeDelta::hours(36)).as_deref(),
+            Some("FREQ=HOURLY;INTERVAL=36")
+        );
+        assert_eq!(rrule(TimeDelta::minutes(90)), None);
+        assert_eq!(rrule(TimeDelta::hours(1) + TimeDelta::nanoseconds(1)), None);
+        assert_eq!(rrule(TimeDelta::zero()), None);
+        assert_eq!(rrule(TimeDelta::days(-1)), None);
+    }
+
+    #[test]
+    fn rrules_map_back_to_intervals() {
+        assert_eq!(
+            parse_rrule("FREQ=WEEKLY;INTERVAL=2"),
+            Ok(TimeDelta::weeks(2))
+        );
+        assert_eq!(parse_rrule("freq=daily"), Ok(TimeDelta::days(1)));
+        assert!(parse_rrule("FREQ=MONTHLY").is_err());
+        assert!(parse_rrule("FREQ=DAILY;INTERVAL=0").is_err());
+        assert!(parse_rrule("FREQ=DAILY;COUNT=3").is_err());
+        assert!(parse_rrule("INTERVAL=2").is_err());
+    }
+
+    #[test]
+    fn lines_fold_at_75_octets_without_splitting_characters() {
+        let line = "x".repeat(75);
+        assert_eq!(fold(&line), format!("{line}\r\n"));
+        let line = "x".repeat(76);
+        assert_eq!(fold(&line), format!("{}\r\n x\r\n", "x".repeat(75)));
+        let line = format!("{}é", "x".repeat(74));
+        assert_eq!(fold(&line), format!("{}\r\n é\r\n", "x".repeat(74)));
+        assert_eq!(unfold(&fold(&"x".repeat(200)))[0], "x".repeat(200));
+    }
+
+    #[test]
+    fn text_round_trips_through_escaping() {
+        let text = "water; the plants, then\nthe \\ garden";
+        assert_eq!(escape(text), r"water\; the plants\, then\nthe \\ garden");
+        assert_eq!(unescape(&escape(text)), text);
+    }
+
+    #[test]
+    fn exported_schedules_import_back() {
+        let water = RegularSchedule::create(date("2020-01-01T09:00:00Z"), TimeDelta::days(1));
+        let schedules = [("water/plants".to_string(), water.clone())];
+        let imported = import(&export(&schedules, date("2019-12-31T00:00:00Z")));
+        let [Ok((name, schedule))] = &imported[..] else {
+            panic!("expected one schedule, got {imported:?}");
+        };
+        assert_eq!(name, "water-plants");
+        assert_eq!(schedule.next, water.next);
+        assert_eq!(schedule.interval, water.interval);
+    }
+
+    #[test]
+    fn events_that_cant_be_schedules_are_skipped() {
+        let calendar = "BEGIN:VEVENT\nSUMMARY:once\nDTSTART:20200101T090000Z\nEND:VEVENT\n\
+                        BEGIN:VEVENT\nDTSTART;VALUE=DATE:20200101\nRRULE:FREQ=DAILY\nEND:VEVENT\n";
+        let imported = import(calendar);
+        assert_eq!(imported.len(), 2);
+        assert!(
+            matches!(&imported[0], Err(Skipped { summary: Some(summary), .. }) if summary == "once")
+        );
+        assert!(matches!(&imported[1], Err(Skipped { summary: None, .. })));
+    }
+}
@@ -0,0 +1,320 @@
+//! A cache of what `list`, `due` and `count` need from each schedule, kept in the data directory
+//! so that they don't decode every file each time. Each entry is only trusted while the size and
+//! modification time of its file are the ones it was made from; anything stale is decoded again,
+//! and an index that is missing or can't be read is rebuilt from scratch.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind, Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use decent::{Decodable, Encodable, PrimitiveRepr, Version};
+
+use crate::{
+    error::Result,
+    format::{
+        decode_bool, decode_datetime, decode_text, decode_texts, decode_timedelta, encode_bool,
+        encode_datetime, encode_text, encode_texts, encode_timedelta, limits,
+    },
+    schedule::{Bound, LATEST, RegularSchedule, Schedule, ScheduleID, ScheduleInfo},
+    store::{Loaded, Stamp, Store, open_all},
+};
+
+/// The file the index is kept in, relative to the data directory.
+pub const FILE: &str = ".index";
+
+/// The version of the index's own layout, written before the schedule format version its entries
+/// were decoded with. Either differing makes the index be rebuilt.
+pub const FORMAT: Version = Version(1, 0, 0);
+
+/// How long after a file was last modified before its entry is cached. Within this long, the file
+/// could still change without its modification time changing on a filesystem with coarse
+/// timestamps, which keep them to as little as 2 seconds.
+pub const SETTLE: Duration = Duration::from_secs(2);
+
+/// What the index keeps of one schedule.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Entry {
+    pub info: ScheduleInfo,
+    pub tags: Vec<String>,
+    /// How many unexpected bytes followed the schedule in its file, so that they are still
+    /// warned about.
+    pub trailing: usize,
+}
+impl Entry {
+    pub fn of(schedule: &RegularSchedule, trailing: usize) -> Self {
+        Self {
+            info: schedule.describe(),
+            tags: schedule.tags().to_vec(),
+            trailing,
+        }
+    }
+}
+
+/// Every cached entry by the name the store knows its schedule by, with the stamp it was made
+/// from.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Index(pub BTreeMap<String, (Stamp, Entry)>);
+impl Index {
+    /// The index kept in `store`, or an empty one if there is none or it can't be read. One
+    /// written by another version of hrtracker is also ignored, since its entries may lack
+    /// something this one shows.
+    pub fn load(store: &impl Store) -> Self {
+        let Ok(bytes) = store.load(FILE) else {
+            return Self::default();
+        };
+        Self::decode(&mut &bytes[..]).unwrap_or_default()
+    }
+    fn decode(from: &mut dyn Read) -> io::Result<Self> {
+        let repr = PrimitiveRepr::Varint;
+        let format = Version::decode(from, Version::ZERO, repr)?;
+        // an index from before `FORMAT` starts with the schedule format version instead
+        let version = match format == FORMAT {
+            true => Version::decode(from, Version::ZERO, repr)?,
+            false => format,
+        };
+        if format != FORMAT || version != LATEST {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "the index is from another version",
+            ));
+        }
+        let mut entries = BTreeMap::new();
+        // entries are read one at a time rather than allocated up front, so a corrupt count
+        // only runs into the end of the file
+        for _ in 0..u64::decode(from, version, repr)? {
+            let name = decode_text(from, "the name", limits::NAME, version, repr)?;
+            let stamp = Stamp {
+                size: u64::decode(from, version, repr)?,
+                modified: Duration::new(
+                    u64::decode(from, version, repr)?,
+                    u32::decode(from, version, repr)?,
+                ),
+            };
+            let next = decode_datetime(from, version, repr)?;
+            let interval = decode_timedelta(from, version, repr)?;
+            let protected = decode_bool(from, "the protected flag", version, repr)?;
+            let bound = match decode_bool(from, "the bound", version, repr)? {
+                true => Some(Bound {
+                    remaining: u32::decode(from, version, repr)?,
+                    total: u32::decode(from, version, repr)?,
+                }),
+                false => None,
+            };
+            if bound.is_some_and(|bound| bound.total == 0 || bound.remaining > bound.total) {
+                return Err(io::Error::new(ErrorKind::InvalidData, "invalid bound"));
+            }
+            let last_stepped = match decode_bool(from, "the last step", version, repr)? {
+                true => Some(decode_datetime(from, version, repr)?),
+                false => None,
+            };
+            let tags = decode_texts(from, "the tags", limits::TAGS, limits::NAME, version, repr)?;
+            let trailing = u64::decode(from, version, repr)? as usize;
+            let info = ScheduleInfo {
+                type_name: RegularSchedule::NAME,
+                next,
+                interval,
+                protected,
+                bound,
+                last_stepped,
+            };
+            let entry = Entry {
+                info,
+                tags,
+                trailing,
+            };
+            entries.insert(name, (stamp, entry));
+        }
+        Ok(Self(entries))
+    }
+    fn encode(&self, to: &mut dyn Write) -> io::Result<()> {
+        let (version, repr) = (LATEST, PrimitiveRepr::Varint);
+        FORMAT.encode(to, version, repr)?;
+        version.encode(to, version, repr)?;
+        (self.0.len() as u64).encode(to, version, repr)?;
+        for (name, (stamp, entry)) in &self.0 {
+            encode_text(name, to, version, repr)?;
+            stamp.size.encode(to, version, repr)?;
+            stamp.modified.as_secs().encode(to, version, repr)?;
+            stamp.modified.subsec_nanos().encode(to, version, repr)?;
+            encode_datetime(&entry.info.next, to, version, repr)?;
+            encode_timedelta(&entry.info.interval, to, version, repr)?;
+            encode_bool(&entry.info.protected, to, version, repr)?;
+            encode_bool(&entry.info.bound.is_some(), to, version, repr)?;
+            if let Some(bound) = entry.info.bound {
+                bound.remaining.encode(to, version, repr)?;
+                bound.total.encode(to, version, repr)?;
+            }
+            encode_bool(&entry.info.last_stepped.is_some(), to, version, repr)?;
+            if let Some(last_stepped) = &entry.info.last_stepped {
+                encode_datetime(last_stepped, to, version, repr)?;
+            }
+            encode_texts(&entry.tags, to, version, repr)?;
+            (entry.trailing as u64).encode(to, version, repr)?;
+        }
+        Ok(())
+    }
+}
+
+/// Like `store::load_all`, but giving the entries of the index instead of whole schedules, and
+/// only decoding the schedules whose entries are missing or stale, with `store::open_all`. The
+/// index is saved again if that changed it; failing to is harmless, since it is only a cache.
+pub fn load_all(store: &(impl Store + Sync)) -> Result<Loaded<Entry>> {
+    let before = Index::load(store);
+    let mut fresh = Index::default();
+    let mut loaded = Loaded {
+        schedules: Vec::new(),
+        unreadable: Vec::new(),
+        trailing: Vec::new(),
+    };
+    let settled = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|now| now.checked_sub(SETTLE));
+    let mut names = store.list()?;
+    names.sort();
+    // stamped before reading, so a change made meanwhile leaves the entry stale
+    let stamps = names
+        .iter()
+        .map(|name| store.stamp(name))
+        .collect::<Result<Vec<_>>>()?;
+    let cached: Vec<_> = names
+        .iter()
+        .zip(&stamps)
+        .map(|(name, stamp)| {
+            let (cached, entry) = before.0.get(name)?;
+            (Some(*cached) == *stamp).then_some(entry)
+        })
+        .collect();
+    let stale: Vec<_> = names
+        .iter()
+        .zip(&cached)
+        .filter(|(_, cached)| cached.is_none())
+        .map(|(name, _)| name.clone())
+        .collect();
+    let mut opened = open_all::<RegularSchedule>(store, &stale).into_iter();
+    for ((name, stamp), cached) in names.into_iter().zip(stamps).zip(cached) {
+        let entry = match cached {
+            Some(entry) => entry.clone(),
+            // there is one result for every entry that wasn't cached, in the same order
+            None => match opened.next().expect("a result for each stale entry") {
+                Ok((schedule, trailing)) => Entry::of(&schedule, trailing),
+                Err(error) => {
+                    loaded.unreadable.push(error);
+                    continue;
+                }
+            },
+        };
+        if let Some(stamp) = stamp.filter(|stamp| settled.is_some_and(|at| stamp.modified < at)) {
+            fresh.0.insert(name.clone(), (stamp, entry.clone()));
+        }
+        if entry.trailing > 0 {
+            loaded.trailing.push((name.clone(), entry.trailing));
+        }
+        loaded.schedules.push((name, entry));
+    }
+    if fresh != before {
+        let mut bytes = Vec::new();
+        if fresh.encode(&mut bytes).is_ok() {
+            let _ = store.save(FILE, &bytes);
+        }
+    }
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use crate::{
+        error::HrtError,
+        store::{MemStore, save_named},
+    };
+
+    fn schedule() -> RegularSchedule {
+        let next: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        let mut schedule = RegularSchedule::create(next, TimeDelta::days(1));
+        schedule.set_tags(vec!["health".to_string()]).unwrap();
+        schedule
+    }
+
+    /// A `MemStore` whose schedules all look like they were last saved long ago.
+    #[derive(Default)]
+    struct Stamped(MemStore);
+    impl Store for Stamped {
+        fn list(&self) -> Result<Vec<String>> {
+            self.0.list()
+        }
+        fn load(&self, name: &str) -> Result<Vec<u8>> {
+            self.0.load(name)
+        }
+        fn save(&self, name: &str, bytes: &[u8]) -> Result<()> {
+            self.0.save(name, bytes)
+        }
+        fn delete(&self, name: &str) -> Result<()> {
+            self.0.delete(name)
+        }
+        fn stamp(&self, _name: &str) -> Result<Option<Stamp>> {
+            Ok(Some(Stamp {
+                size: 1,
+                modified: Duration::from_secs(1),
+            }))
+        }
+    }
+
+    #[test]
+    fn indexes_decode_as_they_were_encoded() {
+        let stamp = Stamp {
+            size: 40,
+            modified: Duration::new(1_700_000_000, 5),
+        };
+        let mut index = Index::default();
+        index
+            .0
+            .insert("water".to_string(), (stamp, Entry::of(&schedule(), 2)));
+        let mut bytes = Vec::new();
+        index.encode(&mut bytes).unwrap();
+        assert_eq!(Index::decode(&mut &bytes[..]).unwrap(), index);
+    }
+
+    #[test]
+    fn unreadable_indexes_are_ignored() {
+        let store = MemStore::new();
+        assert_eq!(Index::load(&store), Index::default());
+        store.save(FILE, b"\xff\xff").unwrap();
+        assert_eq!(Index::load(&store), Index::default());
+    }
+
+    #[test]
+    fn schedules_without_stamps_are_never_cached() {
+        let store = MemStore::new();
+        save_named(&store, "water", &schedule()).unwrap();
+        let loaded = load_all(&store).unwrap();
+        assert_eq!(
+            loaded.schedules,
+            [("water".to_string(), Entry::of(&schedule(), 0))]
+        );
+        assert!(matches!(store.load(FILE), Err(HrtError::NotFound { .. })));
+    }
+
+    #[test]
+    fn settled_entries_are_cached_and_trusted() {
+        let store = Stamped::default();
+        save_named(&store, "water", &schedule()).unwrap();
+        store.save("broken", b"\xff").unwrap();
+        let loaded = load_all(&store).unwrap();
+        assert_eq!(loaded.unreadable.len(), 1);
+        assert!(store.load(FILE).is_ok());
+
+        // the stamp is the same, so the entry is used without decoding the file again
+        store.save("water", b"\xff").unwrap();
+        let loaded = load_all(&store).unwrap();
+        assert_eq!(
+            loaded.schedules,
+            [("water".to_string(), Entry::of(&schedule(), 0))]
+        );
+        assert_eq!(loaded.unreadable.len(), 1);
+    }
+}
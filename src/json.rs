@@ -0,0 +1,190 @@
+//! Just enough JSON output for webhooks and `serve`, without pulling in serde.
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+
+use crate::{
+    adherence::{Adherence, Report, Summary},
+    schedule::{RegularSchedule, Schedule, Step},
+};
+
+/// `text` as a JSON string literal.
+pub fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char if char.is_control() => quoted.push_str(&format!("\\u{:04x}", char as u32)),
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `date` as a JSON string in RFC 3339, in UTC.
+pub fn timestamp(date: DateTime<Utc>) -> String {
+    format!("\"{}\"", date.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// One row of `report` as a JSON object, with the percentage `null` when nothing was expected.
+pub fn adherence(adherence: &Adherence) -> String {
+    let percent = match adherence.percent() {
+        Some(percent) => format!("{percent:.1}"),
+        None => "null".to_string(),
+    };
+    format!(
+        r#"{{"name":{},"steps":{},"expected":{},"adherence_percent":{percent}}}"#,
+        string(&adherence.name),
+        adherence.steps,
+        adherence.expected,
+    )
+}
+
+/// `summary` as `summary --json` prints it.
+pub fn summary(summary: &Summary) -> String {
+    let rows = |rows: &[Adherence]| rows.iter().map(adherence).collect::<Vec<_>>().join(",");
+    let streaks: Vec<_> = summary
+        .streaks
+        .iter()
+        .map(|(name, change)| {
+            format!(
+                r#"{{"name":{},"gained":{},"length":{},"broken":{}}}"#,
+                string(name),
+                change.gained,
+                change.length,
+                change.broken
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"since":{},"until":{},"stepped":[{}],"missed":[{}],"overdue_seconds":{},"streaks":[{}]}}"#,
+        timestamp(summary.since),
+        timestamp(summary.until),
+        rows(&summary.stepped),
+        rows(&summary.missed),
+        summary.overdue.num_seconds(),
+        streaks.join(",")
+    )
+}
+
+/// `report` as `report --json` prints it.
+pub fn report(report: &Report) -> String {
+    let rows: Vec<_> = report.rows.iter().map(adherence).collect();
+    format!(
+        r#"{{"since":{},"until":{},"schedules":[{}],"total":{},"neglected":{}}}"#,
+        timestamp(report.since),
+        timestamp(report.until),
+        rows.join(","),
+        adherence(&report.total),
+        report.neglected
+    )
+}
+
+/// One step of the schedule called `name`, as `history --export json` writes it.
+pub fn step(name: &str, step: &Step, lateness: Option<TimeDelta>) -> String {
+    let or_null = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+    format!(
+        r#"{{"schedule":{},"timestamp":{},"note":{},"lateness_seconds":{}}}"#,
+        string(name),
+        timestamp(step.at),
+        or_null(step.note.as_deref().map(string)),
+        or_null(lateness.map(|lateness| lateness.num_seconds().to_string())),
+    )
+}
+
+/// The schedule called `name` as a JSON object. Its webhook is left out, since the URL may
+/// contain a secret.
+pub fn schedule(name: &str, schedule: &RegularSchedule, now: DateTime<Utc>) -> String {
+    let next = schedule.next_at();
+    let priority = match schedule.priority() {
+        Some(priority) => priority.to_string(),
+        None => "null".to_string(),
+    };
+    format!(
+        r#"{{"name":{},"next":{},"seconds_until_next":{},"interval_seconds":{},"overdue":{},"priority":{priority}}}"#,
+        string(name),
+        timestamp(next),
+        next.signed_duration_since(now).num_seconds(),
+        schedule.interval.num_seconds(),
+        next < now,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adherence::StreakChange;
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    fn row(name: &str, steps: u64, expected: u64) -> Adherence {
+        Adherence {
+            name: name.to_string(),
+            steps,
+            expected,
+        }
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(string("a \"b\"\\\n\u{7}"), r#""a \"b\"\\\n\u0007""#);
+    }
+
+    #[test]
+    fn adherence_is_null_when_nothing_was_expected() {
+        assert_eq!(
+            adherence(&row("water", 1, 3)),
+            r#"{"name":"water","steps":1,"expected":3,"adherence_percent":33.3}"#
+        );
+        assert_eq!(
+            adherence(&row("rest", 0, 0)),
+            r#"{"name":"rest","steps":0,"expected":0,"adherence_percent":null}"#
+        );
+    }
+
+    #[test]
+    fn summaries_list_each_section() {
+        let summary = Summary {
+            since: date("2025-03-01T00:00:00Z"),
+            until: date("2025-03-08T00:00:00Z"),
+            stepped: vec![row("water", 3, 6)],
+            missed: vec![],
+            streaks: vec![(
+                "water".to_string(),
+                StreakChange {
+                    gained: 3,
+                    length: 3,
+                    broken: true,
+                },
+            )],
+            overdue: TimeDelta::hours(1),
+        };
+        assert_eq!(
+            super::summary(&summary),
+            r#"{"since":"2025-03-01T00:00:00Z","until":"2025-03-08T00:00:00Z","stepped":[{"name":"water","steps":3,"expected":6,"adherence_percent":50.0}],"missed":[],"overdue_seconds":3600,"streaks":[{"name":"water","gained":3,"length":3,"broken":true}]}"#
+        );
+    }
+
+    #[test]
+    fn reports_list_the_rows_and_the_total() {
+        let report = Report {
+            since: date("2025-03-01T00:00:00Z"),
+            until: date("2025-03-08T00:00:00Z"),
+            rows: vec![row("pills", 0, 2)],
+            total: row("total", 0, 2),
+            neglected: 1,
+        };
+        assert_eq!(
+            super::report(&report),
+            r#"{"since":"2025-03-01T00:00:00Z","until":"2025-03-08T00:00:00Z","schedules":[{"name":"pills","steps":0,"expected":2,"adherence_percent":0.0}],"total":{"name":"total","steps":0,"expected":2,"adherence_percent":0.0},"neglected":1}"#
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Tracking of recurring schedules, stored as small binary files in `~/.hrtracker/`.
+//!
+//! The `hrtracker` binary is a thin command line over these modules; other tools can use them to
+//! read and write the same schedule files.
+
+pub mod adherence;
+pub mod alias;
+pub mod at;
+pub mod batch;
+pub mod calendar;
+pub mod clock;
+pub mod cron;
+pub mod csv;
+pub mod diff;
+pub mod display;
+pub mod drift;
+#[cfg(feature = "std-fs")]
+pub mod edit;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod heatmap;
+#[cfg(feature = "std-fs")]
+pub mod hooks;
+pub mod http;
+pub mod ical;
+pub mod index;
+pub mod json;
+pub mod listing;
+pub mod metrics;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod parse;
+pub mod porcelain;
+pub mod reconcile;
+pub mod schedule;
+pub mod serve;
+pub mod store;
+pub mod style;
+pub mod suggest;
+pub mod systemd;
+pub mod table;
+pub mod text;
+pub mod tracker;
+#[cfg(feature = "std-fs")]
+pub mod tui;
+pub mod verify;
+pub mod watch;
+pub mod webhook;
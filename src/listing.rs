@@ -0,0 +1,332 @@
+//! Ordering and summarizing many schedules at once.
+
+use std::{cmp::Ordering, collections::BTreeMap, iter::Peekable};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    display::plural,
+    error::{HrtError, Result},
+    schedule::{Schedule, ScheduleInfo},
+};
+
+/// The order `list` prints schedules in. Ties are always broken by name.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortKey {
+    /// Soonest first.
+    #[default]
+    Next,
+    Name,
+    /// Shortest interval first.
+    Interval,
+    /// Most overdue first, measured in intervals so that a schedule an hour late on a 2 hour
+    /// interval ranks above one a day late on a weekly interval.
+    Overdue,
+    /// Longest since last stepped first, starting with the schedules never stepped.
+    Since,
+}
+impl SortKey {
+    pub fn parse(repr: &str) -> Result<Self> {
+        match repr {
+            "next" => Ok(Self::Next),
+            "name" => Ok(Self::Name),
+            "interval" => Ok(Self::Interval),
+            "overdue" => Ok(Self::Overdue),
+            "since" => Ok(Self::Since),
+            unknown => Err(HrtError::Usage(format!(
+                "`{unknown}` is not a valid sort key (expected `next`, `name`, `interval`, `overdue`, or `since`)"
+            ))),
+        }
+    }
+    /// Compares two schedules by this key alone; `Name` compares nothing, since names are the
+    /// universal tie-break.
+    pub fn compare(&self, a: &ScheduleInfo, b: &ScheduleInfo, now: DateTime<Utc>) -> Ordering {
+        match self {
+            Self::Next => a.next.cmp(&b.next),
+            Self::Name => Ordering::Equal,
+            Self::Interval => a.interval.cmp(&b.interval),
+            Self::Overdue => {
+                let lateness = |schedule: &ScheduleInfo| {
+                    now.signed_duration_since(schedule.next).num_milliseconds() as f64
+                        / schedule.interval.num_milliseconds().max(1) as f64
+                };
+                lateness(b).total_cmp(&lateness(a))
+            }
+            // `None` sorts first, so never stepped comes before anything stepped
+            Self::Since => a.last_stepped.cmp(&b.last_stepped),
+        }
+    }
+    /// The total order every listing uses: by this key, then by name, byte by byte, so that the
+    /// order never depends on the order the store lists schedules in.
+    pub fn order(
+        &self,
+        (a_name, a): (&str, &ScheduleInfo),
+        (b_name, b): (&str, &ScheduleInfo),
+        now: DateTime<Utc>,
+    ) -> Ordering {
+        self.compare(a, b, now).then_with(|| a_name.cmp(b_name))
+    }
+}
+
+/// Sorts `schedules` by `key`, as in `SortKey::order`.
+pub fn sort<S: Schedule>(schedules: &mut [(String, S)], key: SortKey, now: DateTime<Utc>) {
+    schedules.sort_by(|(a_name, a), (b_name, b)| {
+        key.order((a_name, &a.describe()), (b_name, &b.describe()), now)
+    });
+}
+
+/// Orders `schedules` the way `list` shows them: by `key`, as in `SortKey::order`, and then
+/// backwards if `reverse` is set.
+pub fn order(
+    schedules: &mut [(String, ScheduleInfo)],
+    key: SortKey,
+    reverse: bool,
+    now: DateTime<Utc>,
+) {
+    schedules.sort_by(|(a_name, a), (b_name, b)| key.order((a_name, a), (b_name, b), now));
+    if reverse {
+        schedules.reverse();
+    }
+}
+
+/// Which schedules `list` and `count` keep, by when they are due.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Window {
+    /// Keep only the schedules due within this long from now, if given.
+    pub within: Option<TimeDelta>,
+    /// Leave out the schedules that are already overdue.
+    pub exclude_overdue: bool,
+    /// Keep only the schedules that have never been stepped.
+    pub never_stepped: bool,
+}
+impl Window {
+    pub fn contains(&self, schedule: &ScheduleInfo, now: DateTime<Utc>) -> bool {
+        if self.never_stepped && !schedule.never_stepped() {
+            return false;
+        }
+        if schedule.next < now {
+            return !self.exclude_overdue;
+        }
+        // a window reaching past the representable dates holds everything after now
+        self.within
+            .and_then(|within| now.checked_add_signed(within))
+            .is_none_or(|end| schedule.next <= end)
+    }
+}
+
+/// The occurrences of several schedules as one stream, soonest first, from `merge`.
+#[derive(Clone, Debug)]
+pub struct Merge<'a, I: Iterator<Item = DateTime<Utc>>> {
+    streams: Vec<(&'a str, Peekable<I>)>,
+}
+impl<'a, I: Iterator<Item = DateTime<Utc>>> Iterator for Merge<'a, I> {
+    type Item = (&'a str, DateTime<Utc>);
+    fn next(&mut self) -> Option<Self::Item> {
+        // `min_by_key` keeps the first of equal keys, so ties go to the earlier stream
+        let (index, _) = self
+            .streams
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, (_, stream))| stream.peek().map(|next| (index, *next)))
+            .min_by_key(|(_, next)| *next)?;
+        let (name, stream) = &mut self.streams[index];
+        Some((*name, stream.next()?))
+    }
+}
+
+/// Merges the occurrences of each named schedule into one chronological stream, with ties in the
+/// order the schedules are given. Only one occurrence of each is looked at ahead of time, so the
+/// streams may be endless.
+pub fn merge<'a, I: Iterator<Item = DateTime<Utc>>>(
+    streams: impl IntoIterator<Item = (&'a str, I)>,
+) -> Merge<'a, I> {
+    Merge {
+        streams: streams
+            .into_iter()
+            .map(|(name, stream)| (name, stream.peekable()))
+            .collect(),
+    }
+}
+
+/// Aggregate counts over a set of schedules, as printed after `list` and by `count`.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Summary {
+    pub total: usize,
+    pub overdue: usize,
+    pub unreadable: usize,
+    /// The name and time of the soonest schedule that isn't overdue yet.
+    pub soonest: Option<(String, DateTime<Utc>)>,
+}
+impl Summary {
+    /// Summarizes `schedules`, plus `unreadable` entries that couldn't be decoded.
+    pub fn of<'a, S: Schedule + 'a>(
+        schedules: impl IntoIterator<Item = &'a (String, S)>,
+        unreadable: usize,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let infos: Vec<_> = schedules
+            .into_iter()
+            .map(|(name, schedule)| (name, schedule.describe()))
+            .collect();
+        Self::of_infos(
+            infos.iter().map(|(name, info)| (&name[..], info)),
+            unreadable,
+            now,
+        )
+    }
+    /// Like `of`, from what display code knows about each schedule.
+    pub fn of_infos<'a>(
+        schedules: impl IntoIterator<Item = (&'a str, &'a ScheduleInfo)>,
+        unreadable: usize,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut summary = Self {
+            unreadable,
+            ..Self::default()
+        };
+        for (name, schedule) in schedules {
+            summary.add(name, schedule, now);
+        }
+        summary
+    }
+    /// Counts one more schedule.
+    pub fn add(&mut self, name: &str, schedule: &ScheduleInfo, now: DateTime<Utc>) {
+        let next = schedule.next;
+        self.total += 1;
+        if next < now {
+            self.overdue += 1;
+        } else if self
+            .soonest
+            .as_ref()
+            .is_none_or(|(soonest_name, soonest)| (next, name) < (*soonest, soonest_name.as_str()))
+        {
+            self.soonest = Some((name.to_string(), next));
+        }
+    }
+    /// Describes the summary in one line, phrasing the soonest schedule with `relative`.
+    pub fn describe(&self, relative: impl Fn(TimeDelta) -> String, now: DateTime<Utc>) -> String {
+        let mut parts = vec![
+            plural(self.total as i64, "schedule"),
+            format!("{} overdue", self.overdue),
+        ];
+        if self.unreadable > 0 {
+            parts.push(format!("{} unreadable", self.unreadable));
+        }
+        if let Some((name, next)) = &self.soonest {
+            parts.push(format!(
+                "next due {} ({name})",
+                relative(next.signed_duration_since(now))
+            ));
+        }
+        parts.join(", ")
+    }
+}
+
+/// The schedules `list --group-by tag` shows under one heading.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Group {
+    /// `None` for the schedules without any tags.
+    pub tag: Option<String>,
+    pub schedules: Vec<(String, ScheduleInfo)>,
+}
+impl Group {
+    /// How many of the group's schedules were due before `now`.
+    pub fn overdue(&self, now: DateTime<Utc>) -> usize {
+        self.schedules
+            .iter()
+            .filter(|(_, schedule)| schedule.next < now)
+            .count()
+    }
+}
+
+/// Splits `schedules` into a group per tag, ordered by tag and followed by the untagged ones,
+/// keeping the order of `schedules` within each group. `tags` gives the tags of each schedule by
+/// name, and a schedule with several is in the group of each.
+pub fn group_by_tag<'a>(
+    schedules: &[(String, ScheduleInfo)],
+    tags: impl Fn(&str) -> &'a [String],
+) -> Vec<Group> {
+    let mut groups: BTreeMap<Option<&str>, Vec<(String, ScheduleInfo)>> = BTreeMap::new();
+    for (name, schedule) in schedules {
+        let tags = tags(name);
+        if tags.is_empty() {
+            groups
+                .entry(None)
+                .or_default()
+                .push((name.clone(), *schedule));
+        }
+        for tag in tags {
+            groups
+                .entry(Some(tag))
+                .or_default()
+                .push((name.clone(), *schedule));
+        }
+    }
+    // `None` sorts first, but reads better last
+    let untagged = groups.remove(&None);
+    groups
+        .into_iter()
+        .map(|(tag, schedules)| Group {
+            tag: tag.map(str::to_string),
+            schedules,
+        })
+        .chain(untagged.map(|schedules| Group {
+            tag: None,
+            schedules,
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::schedule::RegularSchedule;
+
+    #[test]
+    fn windows_can_keep_only_the_never_stepped() {
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        let now = start + TimeDelta::days(10);
+        let fresh = RegularSchedule::create(start, TimeDelta::days(1));
+        let mut stepped = fresh.clone();
+        stepped.step(start).unwrap();
+
+        let window = Window {
+            never_stepped: true,
+            ..Window::default()
+        };
+        assert!(window.contains(&fresh.describe(), now));
+        assert!(!window.contains(&stepped.describe(), now));
+        assert!(Window::default().contains(&stepped.describe(), now));
+        // and the other limits still apply to what is left
+        let upcoming = Window {
+            exclude_overdue: true,
+            ..window
+        };
+        assert!(!upcoming.contains(&fresh.describe(), now));
+    }
+
+    #[test]
+    fn orders_can_be_reversed() {
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        let mut schedules: Vec<_> = [("b", 1), ("a", 1), ("c", 2)]
+            .into_iter()
+            .map(|(name, days)| {
+                let schedule = RegularSchedule::create(start, TimeDelta::days(days));
+                (name.to_string(), schedule.describe())
+            })
+            .collect();
+        let names = |schedules: &[(String, ScheduleInfo)]| {
+            schedules
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        };
+        order(&mut schedules, SortKey::Interval, false, start);
+        assert_eq!(names(&schedules), ["a", "b", "c"]);
+        // the tie-break by name is reversed too
+        order(&mut schedules, SortKey::Interval, true, start);
+        assert_eq!(names(&schedules), ["c", "b", "a"]);
+    }
+}
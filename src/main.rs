@@ -1,349 +1,4609 @@
 use std::{
+    collections::{BTreeMap, HashSet},
     env,
-    error::Error,
     fmt::Display,
-    fs::{self, File},
-    io::{self, ErrorKind, Read, Write},
-    marker::PhantomData,
-    path::Path,
+    fs,
+    io::{self, IsTerminal, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process, thread,
 };
 
-use anyhow::{Result, anyhow, ensure};
-use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc};
-use decent::{Decodable, Encodable, PrimitiveRepr, Version};
-use decent_macros::Binary;
-
-pub const LATEST: Version = Version(0, 0, 2);
-
-pub fn encode_datetime(
-    date: &DateTime<Utc>,
-    to: &mut dyn Write,
-    version: Version,
-    repr: PrimitiveRepr,
-) -> io::Result<()> {
-    let naive = date.naive_utc();
-    let date = naive.date();
-    let time = naive.time();
-    date.to_epoch_days().encode(to, version, repr)?;
-    time.num_seconds_from_midnight().encode(to, version, repr)?;
-    time.nanosecond().encode(to, version, repr)?;
-    Ok(())
-}
-pub fn decode_datetime(
-    from: &mut dyn Read,
-    version: Version,
-    repr: PrimitiveRepr,
-) -> io::Result<DateTime<Utc>> {
-    let epoch_day_count = i32::decode(from, version, repr)?;
-    let second_count = u32::decode(from, version, repr)?;
-    let nanosecond_count = u32::decode(from, version, repr)?;
-    let date = NaiveDate::from_epoch_days(epoch_day_count).ok_or_else(|| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            "invalid date while decoding date and time",
-        )
-    })?;
-    let time = NaiveTime::from_num_seconds_from_midnight_opt(second_count, nanosecond_count)
-        .ok_or_else(|| {
-            io::Error::new(
-                ErrorKind::InvalidData,
-                "invalid time while decoding date and time",
-            )
-        })?;
-    let naive = NaiveDateTime::new(date, time);
-    Ok(DateTime::from_naive_utc_and_offset(naive, Utc))
-}
-
-pub fn encode_timedelta(
-    delta: &TimeDelta,
-    to: &mut dyn Write,
-    version: Version,
-    repr: PrimitiveRepr,
-) -> io::Result<()> {
-    delta
-        .num_nanoseconds()
-        .ok_or_else(|| {
-            io::Error::new(ErrorKind::InvalidData, "number of nanoseconds is too large")
-        })?
-        .encode(to, version, repr)
-}
-pub fn decode_timedelta(
-    from: &mut dyn Read,
-    version: Version,
-    repr: PrimitiveRepr,
-) -> io::Result<TimeDelta> {
-    Ok(TimeDelta::nanoseconds(i64::decode(from, version, repr)?))
-}
-
-pub fn today() -> DateTime<Utc> {
-    DateTime::from_naive_utc_and_offset(
-        NaiveDateTime::new(
-            Utc::now().date_naive(),
-            NaiveTime::from_num_seconds_from_midnight_opt(0, 0).unwrap(),
-        ),
-        Utc,
-    )
-}
-
-pub fn try_split_once<'a>(all: &'a str, delimiter: &str) -> (&'a str, Option<&'a str>) {
-    match all.split_once(delimiter) {
-        Some((first, rest)) => (first, Some(rest)),
-        None => (all, None),
-    }
-}
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use decent::Version;
+use hrtracker::{
+    adherence,
+    alias::{Aliased, Aliases},
+    at, batch,
+    calendar::Calendar,
+    clock::{self, Jump},
+    cron, csv, diff,
+    display::{self, Progress, neglect_count, plural, validate_time_format},
+    drift::{self, Drift, Streak, Streaks},
+    edit,
+    error::{HrtError, Result},
+    format::limits,
+    heatmap,
+    hooks::{self, HookRunner, Hooks},
+    ical, index, json,
+    listing::{self, SortKey, Summary, Window},
+    metrics,
+    parse::{parse_datetime, parse_timedelta, split_words, try_split_once, validate_name},
+    porcelain,
+    reconcile::{self, Change, Wanted},
+    schedule::{
+        HISTORY_CAP, LATEST, MIN_SUPPORTED, RegularSchedule, Schedule, ScheduleInfo, dotted,
+        parse_version, peek_version,
+    },
+    serve,
+    store::{self, DirStore, Loaded, Store, load_all, open_named, open_named_trailing, read_named},
+    style, suggest, systemd, table, tui,
+    verify::Verification,
+    watch, webhook,
+};
 
-// #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-// pub struct StringError(pub String);
-// pub fn error_str<T>(str: String) -> Result<T> {
-//     return Err(anyhow::Error::new(StringError(str)));
-// }
-// impl Display for StringError {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-//         write!(f, "{}", self.0)
-//     }
-// }
-// impl Error for StringError {}
+mod get {
+    use super::*;
 
-pub fn parse_timedelta(hhmmss: &str) -> Result<TimeDelta> {
-    let mut delta = TimeDelta::zero();
-    let (hh, maybe_mmss) = try_split_once(hhmmss, ":");
-    ensure!(hh.len() == 2, "expected 2 hour digits, got {}", hh.len());
-    match hh.parse::<u8>()? {
-        hours @ 0..=23 => {
-            delta += TimeDelta::hours(hours as i64);
+    pub fn name(args: &mut impl Iterator<Item = String>) -> Result<String> {
+        let path = args
+            .next()
+            .ok_or_else(|| HrtError::Usage("an event category must be specified".to_string()))?;
+        validate_name(&path)?;
+        Ok(path)
+    }
+    pub fn optional_name(args: &mut impl Iterator<Item = String>) -> Result<Option<String>> {
+        let mut args = args.peekable();
+        if args.peek().is_none() {
+            return Ok(None);
         }
-        oob => return Err(anyhow!("`{oob}` out of bounds (expected 0 to 23 hours)")),
+        name(&mut args).map(Some)
     }
-
-    let Some(mmss) = maybe_mmss else {
-        return Ok(delta);
-    };
-    let (mm, maybe_ss) = try_split_once(mmss, ":");
-    ensure!(mm.len() == 2, "expected 2 minute digits, got {}", mm.len());
-    match mm.parse::<u8>()? {
-        minutes @ 0..=59 => {
-            delta += TimeDelta::minutes(minutes as i64);
+    pub fn optional_count(
+        args: &mut impl Iterator<Item = String>,
+        default: usize,
+    ) -> Result<usize> {
+        match args.next() {
+            Some(count) => count
+                .parse()
+                .map_err(|_| HrtError::Usage(format!("`{count}` is not a valid count"))),
+            None => Ok(default),
         }
-        oob => return Err(anyhow!("`{oob}` out of bounds (expected 0 to 59 minutes)")),
     }
-
-    let Some(ss) = maybe_ss else {
-        return Ok(delta);
-    };
-    ensure!(ss.len() == 2, "expected 2 minute digits, got {}", ss.len());
-    match ss.parse::<u8>()? {
-        seconds @ 0..=59 => {
-            delta += TimeDelta::seconds(seconds as i64);
+    /// Takes the format flag out of the rest of `action`'s arguments.
+    pub fn format(
+        action: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(ExportFormat, Vec<String>)> {
+        let mut format = None;
+        let mut rest = Vec::new();
+        for arg in args.by_ref() {
+            match ExportFormat::parse(&arg) {
+                Some(parsed) => format = Some(parsed),
+                None => rest.push(arg),
+            }
         }
-        oob => return Err(anyhow!("`{oob}` out of bounds (expected 0 to 59 seconds)")),
+        let format = format.ok_or_else(|| {
+            HrtError::Usage(format!("`{action}` needs a format, such as `--ical`"))
+        })?;
+        Ok((format, rest))
     }
-
-    return Ok(delta);
-}
-
-pub fn parse_date(repr: &str) -> Result<DateTime<Utc>> {
-    let date = match repr {
-        "now" => Utc::now(),
-        "today" => today(),
-        "tomorrow" | "tmrw" => today() + Days::new(1),
-        unknown => {
-            return Err(anyhow!("`{unknown}` is not a valid date"));
+    /// The rest of `action`'s arguments, failing on any flag it doesn't take. A `--` ends the
+    /// flags and is left out, so that what follows it, like a schedule called `--weird`, is taken
+    /// as it is.
+    pub fn checked(action: &str, args: &mut impl Iterator<Item = String>) -> Result<Vec<String>> {
+        let accepted = Action::flags(action);
+        let mut checked = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                checked.extend(args.by_ref());
+                break;
+            }
+            if arg.starts_with("--") {
+                let (flag, inline) = try_split_once(&arg, "=");
+                if !accepted.contains(&flag) {
+                    let takes: Vec<_> = accepted.iter().map(|flag| format!("`{flag}`")).collect();
+                    return Err(HrtError::Usage(match takes.is_empty() {
+                        true => format!("`{action}` doesn't take `{flag}`"),
+                        false => format!(
+                            "`{action}` doesn't take `{flag}`; it takes {}",
+                            takes.join(", ")
+                        ),
+                    }));
+                }
+                if inline.is_none() && Action::VALUED.contains(&flag) {
+                    checked.push(arg);
+                    checked.extend(args.next());
+                    continue;
+                }
+            }
+            checked.push(arg);
         }
-    };
-    return Ok(date);
+        Ok(checked)
+    }
+    /// Takes the switch `flag` out of the rest of the arguments, returning whether it was there.
+    pub fn switch(flag: &str, args: &mut impl Iterator<Item = String>) -> (bool, Vec<String>) {
+        let (present, rest): (Vec<_>, Vec<_>) = args.partition(|arg| arg == flag);
+        (!present.is_empty(), rest)
+    }
+    /// Takes `<flag> <value>` (or `<flag>=<value>`) out of the rest of the arguments.
+    pub fn valued(
+        flag: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(Option<String>, Vec<String>)> {
+        let mut value = None;
+        let mut rest = Vec::new();
+        while let Some(arg) = args.next() {
+            match try_split_once(&arg, "=") {
+                (name, Some(inline)) if name == flag => value = Some(inline.to_string()),
+                (name, None) if name == flag => {
+                    value = Some(
+                        args.next()
+                            .ok_or_else(|| HrtError::Usage(format!("`{flag}` requires a value")))?,
+                    )
+                }
+                _ => rest.push(arg),
+            }
+        }
+        Ok((value, rest))
+    }
+    /// Like `valued`, but failing if `flag` is given more than once.
+    pub fn valued_once(
+        flag: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(Option<String>, Vec<String>)> {
+        let args: Vec<_> = args.collect();
+        let given = args
+            .iter()
+            .filter(|arg| try_split_once(arg, "=").0 == flag)
+            .count();
+        if given > 1 {
+            return Err(HrtError::Usage(format!("`{flag}` is given more than once")));
+        }
+        valued(flag, &mut args.into_iter())
+    }
+    /// The start of a new schedule. `in 20 minutes` may also be given unquoted, as three words.
+    pub fn start(args: &mut impl Iterator<Item = String>) -> Result<Start> {
+        let mut start = args
+            .next()
+            .ok_or_else(|| HrtError::Usage("a date must be specified".to_string()))?;
+        if start == "in" {
+            for word in args.take(2) {
+                start.push(' ');
+                start.push_str(&word);
+            }
+        }
+        Start::parse(&start)
+    }
+    pub fn interval(args: &mut impl Iterator<Item = String>) -> Result<TimeDelta> {
+        parse_timedelta(
+            &args
+                .next()
+                .ok_or_else(|| HrtError::Usage("an interval must be specified".to_string()))?,
+        )
+    }
 }
 
-pub fn parse_datetime(repr: &str) -> Result<DateTime<Utc>> {
-    let (date_repr, maybe_hhmmss) = try_split_once(repr, "+");
-    let mut date = parse_date(date_repr)?;
-    let Some(hhmmss) = maybe_hhmmss else {
-        return Ok(date);
-    };
-    date += parse_timedelta(hhmmss)?;
-    Ok(date)
-}
+pub const HELP: &str = "\
+usage: hrtracker [flags] [action] [arguments]
 
-pub trait ScheduleID {
-    const BYTES: [u8; 8];
-    const NAME: &'static str;
-}
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ID<T: ScheduleID>(pub PhantomData<T>);
-impl<T: ScheduleID> Encodable for ID<T> {
-    fn encode(&self, to: &mut dyn Write, _: Version, _: PrimitiveRepr) -> io::Result<()> {
-        to.write_all(&T::BYTES)
-    }
+actions:
+    list                        list all schedules (the default); also `ls`
+    new <name> [date] [interval] [--start <date>] [--every <interval>] [--count <n>] [--force]
+                                add a schedule starting at <date> (by default now), repeating
+                                every <interval>, either of which may be given by its flag
+                                instead (only <n> times with --count); <date> may also be `+` for
+                                one interval from now, or `after:<other>` for the next occurrence
+                                of <other>; refuses a <name> that only differs by case from another
+                                schedule, unless --force; also `add` or `create`
+    next [name]                 show when the next event of <name> is scheduled, or which
+                                schedules are next across all of them; also `n`
+    step <name> [--at <date>] [--allow-future] [--force] [--note <text>]
+                                add the interval of <name> to its date, recording the step as
+                                of <date> (which must be past, unless --allow-future is given)
+                                with <text> as a note; refuses if <name> seems to have just been
+                                stepped, unless --force; also `s`
+    delete <name> [--force]     delete the schedule <name>, even if it is protected with --force;
+                                also `rm` or `del`
+    rename <name> <new> [--force]
+                                rename the schedule <name> to <new>, even if it is protected or
+                                <new> only differs by case from another schedule with --force
+    verify [name]               check that every schedule (or just <name>) decodes and, encoded
+                                again, gives back the same bytes, and whether its `.bak` backup
+                                matches it, exiting with code 4 if any fail
+    recover <name> [--next <date>] [--interval <time>]
+                                read what survives of the damaged schedule <name>, listing the
+                                fields that were recovered and lost, and save it as
+                                `<name>.recovered` (leaving <name> alone), asking for <date> and
+                                <time> if they were lost and not given
+    doctor                      look for schedules whose names only differ by case, printing the
+                                `rename` commands that fix them, and exit with code 1 if any exist
+    protect <name>              make `new`, `delete`, `edit`, `apply`, `tag`, `set-priority`, and
+                                `set-webhook` refuse to change <name> unless given --force, while
+                                still allowing `step`
+    unprotect <name>            stop protecting <name>
+    alias [<short> <name>]      let <short> stand for <name> wherever a schedule is named, or
+                                list every alias
+    unalias <short>             remove the alias <short>
+    due [name]                  list the schedules (or just <name>) that are due
+    set-priority <name> [1-5] [--force]
+                                set how urgent <name> is for `--push`, or reset it to 3
+    tag <name> [tag...] [--force]
+                                set the tags of <name> that `list --group-by tag` groups it by,
+                                or clear them if none are given
+    set-webhook <name> [url] [--force]
+                                send a JSON request to <url> whenever <name> is stepped or `watch`
+                                sees it become due, or stop sending them if <url> is left out
+    show <name>                 show the details of <name>, including its progress
+    diff <name> <other>|--bak   list the fields that differ between <name> and <other>, or the
+                                backup `<name>.bak` with --bak
+    edit <name> [--force]       open <name> as text in $VISUAL or $EDITOR (or vi), saving it once
+                                the editor exits if it is still valid and unchanged elsewhere
+    forecast <name> [count]     list the next <count> (default 5) occurrences of <name>
+    forecast --all [--within <time>] [--max-per-schedule <count>]
+                                list the occurrences of every schedule within <time> from now
+                                (default a day), in order, with at most <count> (default 5) from
+                                each schedule
+    history <name> [--csv]      list when <name> was stepped and the notes of the steps, as CSV
+                                with --csv
+    history [name] --export csv|json
+                                write every step of <name>, or of every schedule, with its note
+                                and how late it was, as CSV or a JSON array
+    history <name> --prune [--keep <count>] [--before <date>]
+                                remove the steps of <name> made before <date>, and then all but
+                                the latest <count> (by default the --history-cap)
+    drift <name>                how late the steps of <name> were compared to the occurrences
+                                they satisfied: mean, median, 90th percentile, and most, and how
+                                many were early
+    streak <name>               the current and longest runs of occurrences of <name> that were
+                                each stepped before the following one was due
+    heatmap <name> [--by-hour] [--ascii]
+                                chart how many steps of <name> were made on each weekday, or in
+                                each hour of the day, in the local timezone, along with a row of
+                                the hours (in ASCII with --ascii) and the scheduled time of day
+    summary [--week|--month|--since <date>] [--until <date>] [--json]
+                                what happened to every schedule this week (the default), this
+                                month, or between <date>s: the steps made, the schedules that
+                                were due but not stepped, how their streaks changed, and the
+                                time spent overdue
+    report --since <date> [--json]
+                                how many steps each schedule had since <date> against how many
+                                its interval expected, worst kept first, with a total
+    count                       count all schedules, and how many are overdue
+    cal [name] [--months <count>]
+                                show this month (and the next <count> - 1) as a calendar, marking
+                                the days all schedules (or just <name>) occur on
+    watch                       keep running, announcing each schedule once when it becomes due
+    serve [--addr <address>] [--allow-step]
+                                serve the schedules as JSON over HTTP on <address> (by default
+                                127.0.0.1:7878), letting clients step them with --allow-step
+    tui                         browse the schedules interactively, stepping (s) or deleting (d)
+                                the selected one, showing its next time (n), or quitting (q)
+    export --ical|--csv [name]  print all schedules (or just <name>) as an iCalendar file, or as
+                                CSV with one row per schedule
+    import --ical <file> [--overwrite]
+                                add a schedule for each repeating event in an iCalendar file,
+                                replacing existing schedules of the same name with --overwrite
+    apply <file> [--reset-changed] [--prune] [--dry-run] [--force]
+                                make the schedules match <file>, with one `<name> <date>
+                                <interval> [priority=<1-5>]` line each: create missing ones,
+                                update the interval and priority of others (restarting them at
+                                <date> with --reset-changed), and delete unlisted ones with
+                                --prune; --dry-run only prints what would change, and protected
+                                schedules are only changed with --force
+    cron <name> [--command <command>]
+                                print a crontab line that runs <command> (by default
+                                `hrtracker due <name>`) whenever <name> is due
+    remind <name> [--before <time>] [--command <command>] [--schedule]
+                                print the `at` command line that runs <command> (by default
+                                `hrtracker --notify due <name>`) <time> before the next occurrence
+                                of <name>, or run it with --schedule
+    systemd <name> [--command <command>] [--stdout] [--user]
+                                install a systemd timer and service running <command> (by default
+                                `hrtracker --notify due <name>`) whenever <name> is due, as user
+                                units with --user, or print them instead with --stdout
+    batch [--stop-on-error]     run one action per line of stdin, with its own flags, skipping
+                                blank lines and `#` comments; --stop-on-error stops at the first
+                                failure instead of running the rest
+    metrics [--output <path>]   print Prometheus metrics for every schedule, or write them to
+                                <path> (atomically, for node_exporter's textfile collector)
+    help [action]               show this message, or only the entries of <action>
+
+actions can be given in any case, like `LIST` or `Step`.
+
+flags:
+    --precise                   print exact intervals instead of rounded ones (to the millisecond)
+    --color=always|never|auto   control colored output (auto respects NO_COLOR)
+    --soon=<time>               how close a schedule must be to count as upcoming (default 01)
+    --no-header                 omit the header row of `list` and of CSV output
+    --long                      print `list` as sentences instead of a table
+    --sort=next|name|interval|overdue|since
+                                order `list` by soonest (the default), name, shortest
+                                interval, most overdue relative to the interval, or longest
+                                since last stepped
+    --reverse                   reverse the order of `list`; schedules that tie on the sort key
+                                are always ordered by name, byte by byte, in `list`, `due`,
+                                `watch`, and `serve`, whatever order they are stored in
+    --due-within <time>         make `list` and `count` only include the schedules due within
+                                <time> from now, and those already overdue
+    --exclude-overdue           make `list` and `count` leave out overdue schedules
+    --never-stepped             make `list` and `count` only include schedules never stepped
+    --limit <count>             make `list` print at most <count> schedules, after sorting and
+                                filtering, and say how many more there are
+    --offset <count>            make `list` skip its first <count> schedules, for paging
+    --group-by=tag|none         split `list` into a section per tag, with subtotals
+    --progress                  add a progress bar to `list`
+    --streaks                   add the current streak of each schedule to `list`
+    --no-since                  leave out how long ago each schedule was stepped from `list`
+    --with-since                add when each schedule was last stepped to porcelain `list` output
+    --time-format <format>      display datetimes with a strftime-style format, like `%a %H:%M`
+    --duration-format=clock|iso print intervals like `01h30m00s` (the default) or as ISO 8601
+                                durations like `PT1H30M`, in porcelain output too
+    --no-summary                omit the summary line after `list`
+    --strict                    make `list` exit with code 4 if any schedule can't be read, or
+                                has unexpected bytes after it
+    --fail-if-overdue           make `count` exit with code 5 if anything is overdue
+    --countdown                 keep `next` running, redrawing the time left every second
+    --until-due                 stop a `--countdown` once the schedule is due
+    --notify                    make `watch` and `due` send desktop notifications as well
+    --notify-command <command>  make `watch` and `due` notify by running <command> through
+                                `sh -c`, with the title and message as $1 and $2 and the name in
+                                $HRTRACKER_SCHEDULE
+    --no-hooks                  don't run any hooks
+    --to-future                 make `step` also skip every occurrence that has already passed
+    --step-guard <fraction>     how many intervals ahead a schedule may already be before `step`
+                                refuses to step it again without --force (default 1)
+    --push <url>                make `watch` also send each notification to <url> with a PUT,
+                                as ntfy.sh expects, using the schedule's priority
+    --clock-jump <time>         how far the clock may move forwards between runs before hrtracker
+                                warns that it seems to have jumped, as it does when it moves
+                                backwards, and refuses to change anything without --force
+                                (default 90d+00)
+    --force                     confirm that the clock is right after it seems to have jumped,
+                                for any action, besides what the actions that take it use it for
+    --durable                   sync each saved schedule to the disk before going on, and its
+                                directory too on Unix, so that no change is lost to a power cut
+    --write-version <version>   save schedules in an older format, like 0.0.9, so that older
+                                versions of hrtracker can still read them, refusing to save one
+                                with something that format can't hold
+    --default-start <date>      the <date> `new` starts at when it is left out, also set by
+                                `$HRTRACKER_DEFAULT_START`
+    --default-interval <time>   the <interval> `new` repeats every when it is left out, also set
+                                by `$HRTRACKER_DEFAULT_INTERVAL`
+    --max-file-size <bytes>     the largest schedule file hrtracker will read, treating larger
+                                ones as unreadable (default 1048576)
+    --history-cap <count>       how many steps a schedule's history keeps, dropping the oldest
+                                when it is stepped (default 1024)
+    --porcelain                 print stable, tab-separated output for scripts
+
+schedules are kept in `$HRTRACKER_DIR`, or `~/.hrtracker` if that isn't set.
+
+flags that take a value accept both `--flag=value` and `--flag value`. an action refuses flags it
+doesn't take, and `--` ends the flags, so that a name starting with `--` can follow it. their
+defaults can be set in `.config` in the data directory with `flag = value` lines (without the
+leading `--`), such as `time-format = %a %H:%M` or `precise = true`.
+
+hooks are commands run when a schedule is created, stepped, deleted, or seen becoming due by
+`watch`. set them in `.config` with `on_new`, `on_step`, `on_delete`, or `on_due` keys, or add
+executables with those names to `hooks/` in the data directory. they get $HRT_NAME, $HRT_EVENT,
+$HRT_NEXT, and $HRT_INTERVAL_SECS, and are killed after 10 seconds.
+
+dates are `today`, `tomorrow`/`tmrw`, `yesterday`, or `now`, optionally followed by `+<time>` or
+`-<time>`, in UTC unless followed by `@local` or an offset like `@+01:00`. dates may also be
+`in <number> <unit>` with a unit of seconds, minutes, hours, days, or weeks, or RFC 3339.
+times and intervals are `hh`, `hh:mm`, or `hh:mm:ss`, with an optional fraction of a second like
+`00:00:01.5`, and intervals may start with a number of days and `d+`, like `2d+03:00`.
+
+porcelain output:
+    each record is one line of tab-separated fields. timestamps are RFC 3339 in UTC (or the
+    `--time-format`, which only affects the <next> column) and durations are whole seconds,
+    negative when overdue. warnings go to stderr. this format will not change without a major
+    version bump.
+
+    list    <name> <next> <seconds until next> <interval seconds>, then the <current streak>
+            with --streaks, after the <tag> (empty if untagged) with --group-by tag, once per tag
+    next    <next> <seconds until next>, or <name> <next> <seconds until next> for each
+            soonest schedule without a name
+    step    <new next> <seconds until new next>
+    alias   <short> <name>, for each alias
+    due     <name> <next> <seconds until next>
+    edit    saved|unchanged
+    diff    <field> <value in name> <value in other>, for each field that differs
+    show    <name> <next> <seconds until next> <interval seconds> <percent through interval>
+    forecast <occurrence> <seconds until occurrence>, or <name> <occurrence> <seconds until
+            occurrence> with --all
+    cal     <date> <name>, once per schedule for each day it occurs on
+    history <stepped at>, or <steps removed> with --prune
+    drift   <steps compared> <early steps> <mean> <median> <max>, with the last three in
+            seconds late (empty if every step was early)
+    streak  <current> <first> <last> <longest> <first> <last>, where the lengths are counts of
+            occurrences and the dates are empty without a streak
+    heatmap <weekday or hour> <steps>, from Mon or 00:00
+    report  <name> <steps> <expected> <percent>, worst first and then `total`, with an empty
+            percent when nothing was expected
+    count   <schedules> <overdue> <unreadable>
+    verify  <name> pass|fail none|same|differs <problem>, where the third field is the backup and
+            the problem is empty for files that pass
+    recover <field> recovered|missing, for each field in the file
+    doctor  <kept name> <name differing by case> <suggested name>, for each schedule to rename
+    watch   <name> <next> <seconds until next>, once per schedule as it becomes due
+    import  <name> imported|skipped <reason>, with an empty reason for imported events
+    apply   <name> create|update|keep|extra|prune, where extra schedules aren't in the file
+    remind  <reminder time> <at command line>
+    systemd <path>, for each unit written
+    serve   <address>, once listening
+    batch   <line number> ok|failed <error>, after each line's own output
+
+exit codes:
+    0   success
+    1   generic error
+    2   the command line could not be parsed
+    3   the named schedule does not exist
+    4   a schedule file could not be decoded, or `list --strict` or `verify` found one
+    5   `due` found something that is due, or `count --fail-if-overdue` found something overdue
+    6   a schedule would be invalid, such as one with an empty interval or past the last date
+";
+
+/// The formats `export` can write and `import` can read.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExportFormat {
+    ICal,
+    Csv,
 }
-impl<T: ScheduleID> Decodable for ID<T> {
-    fn decode(from: &mut dyn Read, _: Version, _: PrimitiveRepr) -> io::Result<Self> {
-        let mut bytes = vec![0u8; T::BYTES.len()];
-        from.read_exact(&mut bytes)?;
-        if bytes != T::BYTES {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("incorrect identifier for {}", T::NAME),
-            ));
+impl ExportFormat {
+    /// Recognizes a format flag like `--ical`.
+    pub fn parse(flag: &str) -> Option<Self> {
+        match flag {
+            "--ical" => Some(Self::ICal),
+            "--csv" => Some(Self::Csv),
+            _ => None,
         }
-        return Ok(Self(PhantomData));
     }
 }
 
-#[derive(Binary, Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-struct RegularSchedule {
-    #[version]
-    version: Version,
-    #[since(0, 0, 2)]
-    id: ID<Self>,
-    #[encode_with(encode_datetime)]
-    #[decode_with(decode_datetime)]
-    next: DateTime<Utc>,
-    #[encode_with(encode_timedelta)]
-    #[decode_with(decode_timedelta)]
-    interval: TimeDelta,
+/// When a new schedule starts, which may depend on its interval or on another schedule and so is
+/// only resolved once the action runs.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Start {
+    At(DateTime<Utc>),
+    /// One interval from now, written `+`.
+    OneInterval,
+    /// At the `next` of another schedule, written `after:<name>`.
+    After(String),
 }
-impl RegularSchedule {
-    pub fn create(start: DateTime<Utc>, every: TimeDelta) -> Self {
-        Self {
-            version: LATEST,
-            id: ID(PhantomData),
-            next: start,
-            interval: every,
+impl Start {
+    pub fn parse(repr: &str) -> Result<Self> {
+        if repr == "+" {
+            return Ok(Self::OneInterval);
         }
+        if let Some(name) = repr.strip_prefix("after:") {
+            validate_name(name)?;
+            return Ok(Self::After(name.to_string()));
+        }
+        parse_datetime(repr).map(Self::At)
     }
-    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        Ok(Self::decode(
-            &mut File::open(&path)?,
-            Version::ZERO,
-            PrimitiveRepr::Varint,
-        )?)
-    }
-    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
-        self.version = LATEST;
-        Ok(self.encode(&mut File::create(&path)?, LATEST, PrimitiveRepr::Varint)?)
+    /// When the schedule `name`, repeating every `every`, starts.
+    pub fn resolve(
+        &self,
+        store: &impl Store,
+        name: &str,
+        every: TimeDelta,
+    ) -> Result<DateTime<Utc>> {
+        match self {
+            Self::At(start) => Ok(*start),
+            Self::OneInterval => Utc::now().checked_add_signed(every).ok_or_else(|| {
+                HrtError::Usage("the interval is too long to start after".to_string())
+            }),
+            Self::After(other) if other == name => Err(HrtError::Usage(format!(
+                "`{name}` can't start after itself"
+            ))),
+            Self::After(other) => Ok(open_named::<RegularSchedule>(store, other)?.next),
+        }
     }
 }
-impl ScheduleID for RegularSchedule {
-    const BYTES: [u8; 8] = *b"regular ";
-    const NAME: &'static str = "regular schedule";
-}
-
-mod get {
-    use super::*;
 
-    pub fn name(args: &mut impl Iterator<Item = String>) -> Result<String> {
-        let path = args
-            .next()
-            .ok_or_else(|| anyhow!("an event category must be specified"))?;
-        ensure!(!path.contains('/'), "path must not contain `/`");
-        Ok(path)
-    }
-    pub fn datetime(args: &mut impl Iterator<Item = String>) -> Result<DateTime<Utc>> {
-        parse_datetime(
-            &args
-                .next()
-                .ok_or_else(|| anyhow!("a date must be specified"))?,
-        )
-    }
-    pub fn interval(args: &mut impl Iterator<Item = String>) -> Result<TimeDelta> {
-        parse_timedelta(
-            &args
-                .next()
-                .ok_or_else(|| anyhow!("an interval must be specified"))?,
-        )
-    }
+/// Which steps `history --prune` removes.
+pub struct Prune {
+    /// How many of the latest steps to keep, or `--history-cap` if not given.
+    pub keep: Option<usize>,
+    /// Also remove every step made before this.
+    pub before: Option<DateTime<Utc>>,
 }
 
 pub enum Action {
     List,
     New {
         name: String,
-        start: DateTime<Utc>,
-        every: TimeDelta,
+        /// `None` if it was left out, for `--default-start` to fill in.
+        start: Option<Start>,
+        /// `None` if it was left out, for `--default-interval` to fill in.
+        every: Option<TimeDelta>,
+        /// How many steps the schedule has before it is finished, if not forever.
+        count: Option<u32>,
+        /// Create it even if another schedule's name only differs by case.
+        force: bool,
+    },
+    Step {
+        name: String,
+        /// When the step happened, if not now.
+        at: Option<DateTime<Utc>>,
+        allow_future: bool,
+        /// Step even if it looks like the schedule was just stepped.
+        force: bool,
+        /// Kept with the step in the history.
+        note: Option<String>,
+    },
+    Delete {
+        name: String,
+        /// Delete it even if it is protected.
+        force: bool,
+    },
+    Rename {
+        name: String,
+        new: String,
+        /// Rename it even if it is protected, or another schedule's name only differs by case.
+        force: bool,
+    },
+    /// Checks the data directory for problems, like names that only differ by case.
+    Doctor,
+    /// Checks that `name`, or every schedule, decodes and encodes back to the same bytes.
+    Verify(Option<String>),
+    /// Reads what it can of the damaged schedule `name`, saving it as `<name>.recovered` with
+    /// `next` and `interval` replaced if given.
+    Recover {
+        name: String,
+        next: Option<DateTime<Utc>>,
+        interval: Option<TimeDelta>,
+    },
+    /// Protects `name` against changes other than stepping, or stops protecting it.
+    Protect {
+        name: String,
+        protected: bool,
+    },
+    /// Makes `short` stand for `target`, or lists every alias without them.
+    Alias(Option<(String, String)>),
+    Unalias(String),
+    Next(Option<String>),
+    Due(Option<String>),
+    Show(String),
+    Edit {
+        name: String,
+        force: bool,
+    },
+    Diff {
+        old: String,
+        new: String,
+    },
+    Forecast {
+        name: String,
+        count: usize,
+    },
+    /// The occurrences of every schedule within `within` from now, merged in order.
+    ForecastAll {
+        within: TimeDelta,
+        max_per_schedule: usize,
+    },
+    History {
+        name: String,
+        csv: bool,
+        /// Trim the history instead of listing it.
+        prune: Option<Prune>,
+    },
+    /// Every step of `name`, or of every schedule, as CSV or a JSON array.
+    ExportHistory {
+        name: Option<String>,
+        json: bool,
+    },
+    Drift(String),
+    Streak(String),
+    /// How the steps of `name` are spread over the week, or over the day with `by_hour`.
+    Heatmap {
+        name: String,
+        by_hour: bool,
+        /// Draw the hours with ASCII instead of block characters.
+        ascii: bool,
+    },
+    /// What happened to every schedule between `since` and `until` (or now), where `since` is
+    /// the start of the week or month if not given.
+    Summary {
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        month: bool,
+        json: bool,
+    },
+    /// How well every schedule was kept to since `since`.
+    Report {
+        since: DateTime<Utc>,
+        json: bool,
+    },
+    Count,
+    Cal {
+        name: Option<String>,
+        months: usize,
+    },
+    Watch,
+    Tui,
+    Serve {
+        addr: String,
+        allow_step: bool,
+    },
+    Export {
+        format: ExportFormat,
+        name: Option<String>,
+    },
+    Import {
+        format: ExportFormat,
+        path: String,
+        overwrite: bool,
+    },
+    Apply {
+        path: String,
+        /// Also move `next` of updated schedules back to their start.
+        reset_changed: bool,
+        prune: bool,
+        dry_run: bool,
+        /// Change protected schedules too.
+        force: bool,
+    },
+    Cron {
+        name: String,
+        command: Option<String>,
+    },
+    Remind {
+        name: String,
+        before: TimeDelta,
+        command: Option<String>,
+        schedule: bool,
+    },
+    Batch {
+        stop_on_error: bool,
+    },
+    Metrics {
+        output: Option<String>,
+    },
+    SetPriority {
+        name: String,
+        priority: Option<u8>,
+        force: bool,
+    },
+    SetWebhook {
+        name: String,
+        url: Option<String>,
+        force: bool,
+    },
+    /// Replaces the tags of `name`, clearing them if there are none.
+    Tag {
+        name: String,
+        tags: Vec<String>,
+        force: bool,
     },
-    Step(String),
-    Next(String),
+    Systemd {
+        name: String,
+        command: Option<String>,
+        stdout: bool,
+        user: bool,
+    },
+    /// With an action, only its entries.
+    Help(Option<String>),
+    /// Left out of `HELP`, for completion and other tools.
+    Introspect,
+    Names,
 }
 impl Action {
+    /// Steps `name` as of now, like `step <name>`.
+    pub fn step(name: String) -> Self {
+        Self::Step {
+            name,
+            at: None,
+            allow_future: false,
+            force: false,
+            note: None,
+        }
+    }
+    /// Whether the action can change schedules, aliases, or anything else in the data directory.
+    /// `batch` might, depending on its input.
+    pub fn changes(&self) -> bool {
+        match self {
+            Self::New { .. }
+            | Self::Step { .. }
+            | Self::Delete { .. }
+            | Self::Rename { .. }
+            | Self::Recover { .. }
+            | Self::Protect { .. }
+            | Self::Alias(Some(_))
+            | Self::Unalias(_)
+            | Self::Edit { .. }
+            | Self::History { prune: Some(_), .. }
+            | Self::Tui
+            | Self::Import { .. }
+            | Self::Batch { .. }
+            | Self::SetPriority { .. }
+            | Self::SetWebhook { .. }
+            | Self::Tag { .. } => true,
+            Self::Serve { allow_step, .. } => *allow_step,
+            Self::Apply { dry_run, .. } => !dry_run,
+            _ => false,
+        }
+    }
+    /// The flags of its own that the action called `action` takes, besides the ones in `Flags`.
+    /// Any other flag given to it is refused before it is parsed.
+    pub fn flags(action: &str) -> &'static [&'static str] {
+        match action {
+            "new" => &["--start", "--every", "--count", "--force"],
+            "step" => &["--at", "--note", "--allow-future", "--force"],
+            "delete" | "rename" | "edit" | "set-priority" | "set-webhook" | "tag" => &["--force"],
+            "recover" => &["--next", "--interval"],
+            "diff" => &["--bak"],
+            "forecast" => &["--all", "--within", "--max-per-schedule"],
+            "history" => &["--keep", "--before", "--prune", "--csv", "--export"],
+            "heatmap" => &["--by-hour", "--ascii"],
+            "summary" => &["--week", "--month", "--json", "--since", "--until"],
+            "report" => &["--since", "--json"],
+            "cal" => &["--months"],
+            "serve" => &["--addr", "--allow-step"],
+            "export" => &["--ical", "--csv"],
+            "import" => &["--ical", "--csv", "--overwrite"],
+            "apply" => &["--reset-changed", "--prune", "--dry-run", "--force"],
+            "batch" => &["--stop-on-error"],
+            "metrics" => &["--output"],
+            "cron" => &["--command"],
+            "remind" => &["--before", "--command", "--schedule"],
+            "systemd" => &["--command", "--stdout", "--user"],
+            _ => &[],
+        }
+    }
+    /// The flags in `flags` that are followed by a value, which is never taken for a flag itself.
+    const VALUED: &[&str] = &[
+        "--start",
+        "--every",
+        "--count",
+        "--at",
+        "--note",
+        "--next",
+        "--interval",
+        "--within",
+        "--max-per-schedule",
+        "--keep",
+        "--before",
+        "--export",
+        "--since",
+        "--until",
+        "--months",
+        "--addr",
+        "--output",
+        "--command",
+    ];
+
+    /// Shorter words for some actions, which stand for them after being lowercased like the
+    /// action itself. Only these are accepted, rather than any prefix, so that adding an action
+    /// can never change what an existing word means.
+    pub const ALIASES: &[(&str, &str)] = &[
+        ("ls", "list"),
+        ("add", "new"),
+        ("create", "new"),
+        ("n", "next"),
+        ("s", "step"),
+        ("rm", "delete"),
+        ("del", "delete"),
+    ];
+
     pub fn get(args: &mut impl Iterator<Item = String>) -> Result<Self> {
-        let Some(action) = args.next() else {
+        let Some(given) = args.next() else {
             return Ok(Action::List);
         };
-        let action = match &action[..] {
+        if given.starts_with("--") {
+            return Err(HrtError::Usage(format!("unknown flag `{given}`")));
+        }
+        let action = Self::canonical(&given);
+        Self::parse(&action, &given, args).map_err(|error| match error {
+            HrtError::Usage(message) => HrtError::Usage(Self::with_usage(&action, message)),
+            error => error,
+        })
+    }
+    /// The action `given` stands for, lowercased and with any alias resolved.
+    pub fn canonical(given: &str) -> String {
+        let action = given.to_ascii_lowercase();
+        match Self::ALIASES.iter().find(|(alias, _)| *alias == action) {
+            Some((_, action)) => action.to_string(),
+            None => action,
+        }
+    }
+    /// The entries of `HELP` for `action`: the line of each form it takes, and their descriptions.
+    pub fn help(action: &str) -> Vec<&'static str> {
+        let mut lines = Vec::new();
+        let mut within = false;
+        for line in Self::help_entries() {
+            // descriptions are indented further than the forms they describe
+            if !line.starts_with("     ") {
+                within = line.split_whitespace().next() == Some(action);
+            }
+            if within {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+    /// The forms `action` takes, as listed in `HELP` without their descriptions.
+    pub fn usage(action: &str) -> Vec<&'static str> {
+        Self::help(action)
+            .into_iter()
+            .filter(|line| !line.starts_with("     "))
+            .map(|line| line.trim().split("  ").next().unwrap_or_default())
+            .collect()
+    }
+    /// Every action listed in `HELP`, followed by the aliases in `ALIASES`.
+    pub fn names() -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for line in Self::help_entries().filter(|line| !line.starts_with("     ")) {
+            let name = line.split_whitespace().next().unwrap_or_default();
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+        names.extend(Self::ALIASES.iter().map(|(alias, _)| alias.to_string()));
+        names
+    }
+    /// The positional arguments of `form`, one of the forms in `usage`, leaving out flags and
+    /// their values: the name of each, its kind, whether it can be left out, and whether it can
+    /// be repeated.
+    pub fn arguments(form: &str) -> Vec<(&str, &'static str, bool, bool)> {
+        let mut arguments = Vec::new();
+        let (mut depth, mut valued) = (0, false);
+        for word in form.split_whitespace().skip(1) {
+            let opened = depth + word.matches('[').count();
+            depth = opened.saturating_sub(word.matches(']').count());
+            let inner = word.trim_matches(['[', ']']);
+            if std::mem::take(&mut valued) {
+                continue;
+            }
+            if inner.starts_with("--") {
+                // in a choice like `--week|--since <date>`, the value belongs to the last flag
+                valued = inner
+                    .rsplit('|')
+                    .next()
+                    .is_some_and(|flag| Self::VALUED.contains(&flag));
+                continue;
+            }
+            let repeated = inner.ends_with("...");
+            let name = inner
+                .trim_end_matches("...")
+                .split('|')
+                .next()
+                .unwrap_or_default();
+            let name = name.trim_matches(['<', '>']);
+            let kind = match name {
+                "name" | "other" | "new" | "short" => "name",
+                "date" => "datetime",
+                "interval" | "time" => "interval",
+                "count" | "n" | "1-5" => "number",
+                "action" => "action",
+                _ => "text",
+            };
+            arguments.push((name, kind, opened > 0, repeated));
+        }
+        arguments
+    }
+    /// Every action with its aliases, forms, and flags, and then the flags in `Flags`, as JSON
+    /// for tools to read, all from the same tables as `HELP` and the parser.
+    pub fn introspect() -> String {
+        let strings = |values: &mut dyn Iterator<Item = &str>| {
+            values.map(json::string).collect::<Vec<_>>().join(",")
+        };
+        let flag = |(name, valued): (&str, bool)| {
+            format!(r#"{{"name":{},"value":{valued}}}"#, json::string(name))
+        };
+        let actions: Vec<_> = Self::names()
+            .iter()
+            .filter(|name| !Self::ALIASES.iter().any(|(alias, _)| alias == name))
+            .map(|name| {
+                let forms: Vec<_> = Self::usage(name)
+                    .into_iter()
+                    .map(|form| {
+                        let arguments: Vec<_> = Self::arguments(form)
+                            .into_iter()
+                            .map(|(name, kind, optional, repeated)| {
+                                format!(
+                                    r#"{{"name":{},"type":"{kind}","optional":{optional},"repeated":{repeated}}}"#,
+                                    json::string(name),
+                                )
+                            })
+                            .collect();
+                        format!(
+                            r#"{{"usage":{},"arguments":[{}]}}"#,
+                            json::string(form),
+                            arguments.join(",")
+                        )
+                    })
+                    .collect();
+                let flags: Vec<_> = Self::flags(name)
+                    .iter()
+                    .map(|name| flag((name, Self::VALUED.contains(name))))
+                    .collect();
+                let aliases = &mut Self::ALIASES
+                    .iter()
+                    .filter(|(_, action)| action == name)
+                    .map(|(alias, _)| *alias);
+                format!(
+                    r#"{{"name":{},"aliases":[{}],"forms":[{}],"flags":[{}]}}"#,
+                    json::string(name),
+                    strings(aliases),
+                    forms.join(","),
+                    flags.join(",")
+                )
+            })
+            .collect();
+        let global: Vec<_> = HELP
+            .lines()
+            .skip_while(|line| *line != "flags:")
+            .filter_map(|line| line.strip_prefix("    --"))
+            .map(|line| {
+                let mut words = line.split_whitespace();
+                let first = words.next().unwrap_or_default();
+                let (name, inline) = try_split_once(first, "=");
+                let valued =
+                    inline.is_some() || words.next().is_some_and(|word| word.starts_with('<'));
+                flag((&format!("--{name}"), valued))
+            })
+            .collect();
+        format!(
+            r#"{{"actions":[{}],"flags":[{}]}}"#,
+            actions.join(","),
+            global.join(",")
+        )
+    }
+    /// The error for `given` not being an action, suggesting the closest ones.
+    fn unknown(given: &str) -> HrtError {
+        let hint = suggest::hint(&given.to_ascii_lowercase(), &Self::names(), "actions");
+        HrtError::Usage(format!("unknown action `{given}`; {hint}"))
+    }
+    /// The lines of the actions listed in `HELP`.
+    fn help_entries() -> impl Iterator<Item = &'static str> {
+        HELP.lines()
+            .skip_while(|line| *line != "actions:")
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+    }
+    /// `message`, followed by the forms `action` takes, if it is an action.
+    fn with_usage(action: &str, mut message: String) -> String {
+        let usage = Self::usage(action);
+        if usage.is_empty() {
+            return message;
+        }
+        for form in usage {
+            message.push_str(&format!("\nusage: hrtracker {form}"));
+        }
+        message.push_str(&format!("\n(see `hrtracker help {action}`)"));
+        message
+    }
+    fn parse(action: &str, given: &str, args: &mut impl Iterator<Item = String>) -> Result<Self> {
+        let args = &mut get::checked(action, args)?.into_iter();
+        let action = match action {
             "list" => Self::List,
-            "new" => Self::New {
+            "new" => {
+                let (count, rest) = get::valued("--count", args)?;
+                let (force, rest) = get::switch("--force", &mut rest.into_iter());
+                let (named_start, rest) = get::valued_once("--start", &mut rest.into_iter())?;
+                let (named_every, rest) = get::valued_once("--every", &mut rest.into_iter())?;
+                let name = get::name(&mut rest.first().cloned().into_iter())?;
+                let positional = &rest[1..];
+                // whatever isn't given by its flag is taken from the positional arguments, in order
+                let date_words = match positional.first() {
+                    Some(first) if first == "in" => 3,
+                    _ => 1,
+                };
+                let wanted =
+                    named_start.is_none() as usize * date_words + named_every.is_none() as usize;
+                let named = match (&named_start, &named_every) {
+                    (Some(_), Some(_)) => Some("the date and interval are"),
+                    (Some(_), None) => Some("the date is"),
+                    (None, Some(_)) => Some("the interval is"),
+                    (None, None) => None,
+                };
+                if let Some(named) = named.filter(|_| positional.len() > wanted) {
+                    return Err(HrtError::Usage(format!(
+                        "{named} already given by flag, so `{}` can't be one of them",
+                        positional[wanted]
+                    )));
+                }
+                let rest = &mut positional.iter().cloned().peekable();
+                let start = match &named_start {
+                    Some(start) => Some(Start::parse(start)?),
+                    None => rest
+                        .peek()
+                        .is_some()
+                        .then(|| get::start(rest))
+                        .transpose()?,
+                };
+                let every = match &named_every {
+                    Some(every) => Some(parse_timedelta(every)?),
+                    None => rest
+                        .peek()
+                        .is_some()
+                        .then(|| get::interval(rest))
+                        .transpose()?,
+                };
+                Self::New {
+                    force,
+                    name,
+                    start,
+                    every,
+                    count: count
+                        .map(|count| {
+                            count.parse().map_err(|_| {
+                                HrtError::Usage(format!("`{count}` is not a number of steps"))
+                            })
+                        })
+                        .transpose()?,
+                }
+            }
+            "step" => {
+                let (at, rest) = get::valued("--at", args)?;
+                let (note, rest) = get::valued("--note", &mut rest.into_iter())?;
+                let switch = |name: &str| rest.iter().any(|arg| arg == name);
+                let (allow_future, force) = (switch("--allow-future"), switch("--force"));
+                let mut rest = rest
+                    .into_iter()
+                    .filter(|arg| arg != "--allow-future" && arg != "--force");
+                Self::Step {
+                    name: get::name(&mut rest)?,
+                    at: at.as_deref().map(parse_datetime).transpose()?,
+                    allow_future,
+                    force,
+                    note,
+                }
+            }
+            "delete" => {
+                let (force, rest) = get::switch("--force", args);
+                Self::Delete {
+                    name: get::name(&mut rest.into_iter())?,
+                    force,
+                }
+            }
+            "rename" => {
+                let (force, rest) = get::switch("--force", args);
+                let rest = &mut rest.into_iter();
+                Self::Rename {
+                    name: get::name(rest)?,
+                    new: get::name(rest)?,
+                    force,
+                }
+            }
+            "doctor" => Self::Doctor,
+            "verify" => Self::Verify(get::optional_name(args)?),
+            "recover" => {
+                let (next, rest) = get::valued("--next", args)?;
+                let (interval, rest) = get::valued("--interval", &mut rest.into_iter())?;
+                Self::Recover {
+                    name: get::name(&mut rest.into_iter())?,
+                    next: next.as_deref().map(parse_datetime).transpose()?,
+                    interval: interval.as_deref().map(parse_interval).transpose()?,
+                }
+            }
+            "protect" | "unprotect" => Self::Protect {
                 name: get::name(args)?,
-                start: get::datetime(args)?,
-                every: get::interval(args)?,
+                protected: action == "protect",
+            },
+            "alias" => match get::optional_name(args)? {
+                Some(short) => Self::Alias(Some((short, get::name(args)?))),
+                None => Self::Alias(None),
             },
-            "step" => Self::Step(get::name(args)?),
-            "next" => Self::Next(get::name(args)?),
-            unknown => return Err(anyhow!("unknown action `{unknown}`"))?,
+            "unalias" => Self::Unalias(get::name(args)?),
+            "next" => Self::Next(get::optional_name(args)?),
+            "due" => Self::Due(get::optional_name(args)?),
+            "show" => Self::Show(get::name(args)?),
+            "edit" => {
+                let (force, rest) = get::switch("--force", args);
+                Self::Edit {
+                    name: get::name(&mut rest.into_iter())?,
+                    force,
+                }
+            }
+            "diff" => {
+                let old = get::name(args)?;
+                let new = match args.next() {
+                    Some(flag) if flag == "--bak" => format!("{old}.bak"),
+                    Some(new) => get::name(&mut Some(new).into_iter())?,
+                    None => {
+                        return Err(HrtError::Usage(
+                            "`diff` needs a second schedule, or `--bak`".to_string(),
+                        ));
+                    }
+                };
+                Self::Diff { old, new }
+            }
+            "forecast" => {
+                let (all, rest) = get::switch("--all", args);
+                let (within, rest) = get::valued("--within", &mut rest.into_iter())?;
+                let (max, rest) = get::valued("--max-per-schedule", &mut rest.into_iter())?;
+                let rest = &mut rest.into_iter();
+                if !all {
+                    if within.is_some() || max.is_some() {
+                        return Err(HrtError::Usage(
+                            "`--within` and `--max-per-schedule` only apply to `forecast --all`"
+                                .to_string(),
+                        ));
+                    }
+                    return Ok(Self::Forecast {
+                        name: get::name(rest)?,
+                        count: get::optional_count(rest, 5)?,
+                    });
+                }
+                if let Some(extra) = rest.next() {
+                    return Err(HrtError::Usage(format!(
+                        "`forecast --all` takes no schedule name, but got `{extra}`"
+                    )));
+                }
+                Self::ForecastAll {
+                    within: match within {
+                        Some(within) => parse_timedelta(&within)?,
+                        None => TimeDelta::days(1),
+                    },
+                    max_per_schedule: match max.as_deref().map(str::parse) {
+                        None => 5,
+                        Some(Ok(max @ 1..)) => max,
+                        Some(_) => {
+                            return Err(HrtError::Usage(
+                                "`--max-per-schedule` must be a number of at least 1".to_string(),
+                            ));
+                        }
+                    },
+                }
+            }
+            "history" => {
+                let (keep, rest) = get::valued("--keep", args)?;
+                let (before, rest) = get::valued("--before", &mut rest.into_iter())?;
+                let (prune, rest) = get::switch("--prune", &mut rest.into_iter());
+                let (csv, rest) = get::switch("--csv", &mut rest.into_iter());
+                let (export, rest) = get::valued("--export", &mut rest.into_iter())?;
+                if let Some(format) = export {
+                    if prune || csv {
+                        return Err(HrtError::Usage(
+                            "`--export` can't be combined with `--csv` or `--prune`".to_string(),
+                        ));
+                    }
+                    let json = match &format[..] {
+                        "csv" => false,
+                        "json" => true,
+                        other => {
+                            return Err(HrtError::Usage(format!(
+                                "`{other}` is not an export format (expected `csv` or `json`)"
+                            )));
+                        }
+                    };
+                    return Ok(Self::ExportHistory {
+                        name: get::optional_name(&mut rest.into_iter())?,
+                        json,
+                    });
+                }
+                if !prune && (keep.is_some() || before.is_some()) {
+                    return Err(HrtError::Usage(
+                        "`--keep` and `--before` only apply to `history --prune`".to_string(),
+                    ));
+                }
+                Self::History {
+                    name: get::name(&mut rest.into_iter())?,
+                    csv,
+                    prune: prune.then_some(Prune {
+                        keep: keep
+                            .map(|keep| get::optional_count(&mut Some(keep).into_iter(), 0))
+                            .transpose()?,
+                        before: before.as_deref().map(parse_datetime).transpose()?,
+                    }),
+                }
+            }
+            "drift" => Self::Drift(get::name(args)?),
+            "streak" => Self::Streak(get::name(args)?),
+            "heatmap" => {
+                let (by_hour, rest) = get::switch("--by-hour", args);
+                let (ascii, rest) = get::switch("--ascii", &mut rest.into_iter());
+                Self::Heatmap {
+                    name: get::name(&mut rest.into_iter())?,
+                    by_hour,
+                    ascii,
+                }
+            }
+            "summary" => {
+                let (week, rest) = get::switch("--week", args);
+                let (month, rest) = get::switch("--month", &mut rest.into_iter());
+                let (json, rest) = get::switch("--json", &mut rest.into_iter());
+                let (since, rest) = get::valued("--since", &mut rest.into_iter())?;
+                let (until, rest) = get::valued("--until", &mut rest.into_iter())?;
+                if let Some(extra) = rest.first() {
+                    return Err(HrtError::Usage(format!(
+                        "unexpected argument `{extra}` for `summary`"
+                    )));
+                }
+                if [week, month, since.is_some()]
+                    .iter()
+                    .filter(|given| **given)
+                    .count()
+                    > 1
+                {
+                    return Err(HrtError::Usage(
+                        "`summary` takes only one of `--week`, `--month`, and `--since`"
+                            .to_string(),
+                    ));
+                }
+                Self::Summary {
+                    since: since.as_deref().map(parse_datetime).transpose()?,
+                    until: until.as_deref().map(parse_datetime).transpose()?,
+                    month,
+                    json,
+                }
+            }
+            "report" => {
+                let (since, rest) = get::valued("--since", args)?;
+                let (json, rest) = get::switch("--json", &mut rest.into_iter());
+                if let Some(extra) = rest.first() {
+                    return Err(HrtError::Usage(format!(
+                        "unexpected argument `{extra}` for `report`"
+                    )));
+                }
+                let Some(since) = since else {
+                    return Err(HrtError::Usage(
+                        "`report` needs `--since <date>`".to_string(),
+                    ));
+                };
+                Self::Report {
+                    since: parse_datetime(&since)?,
+                    json,
+                }
+            }
+            "count" => Self::Count,
+            "cal" => {
+                let (months, rest) = get::valued("--months", args)?;
+                let months = match months {
+                    Some(months) => get::optional_count(&mut Some(months).into_iter(), 1)?,
+                    None => 1,
+                };
+                Self::Cal {
+                    name: get::optional_name(&mut rest.into_iter())?,
+                    months: months.max(1),
+                }
+            }
+            "watch" => Self::Watch,
+            "tui" => Self::Tui,
+            "serve" => {
+                let (addr, rest) = get::valued("--addr", args)?;
+                let mut allow_step = false;
+                for arg in rest {
+                    match &arg[..] {
+                        "--allow-step" => allow_step = true,
+                        other => {
+                            return Err(HrtError::Usage(format!(
+                                "unexpected argument `{other}` to `serve`"
+                            )));
+                        }
+                    }
+                }
+                Self::Serve {
+                    addr: addr.unwrap_or_else(|| "127.0.0.1:7878".to_string()),
+                    allow_step,
+                }
+            }
+            "export" => {
+                let (format, rest) = get::format("export", args)?;
+                Self::Export {
+                    format,
+                    name: get::optional_name(&mut rest.into_iter())?,
+                }
+            }
+            "import" => {
+                let (format, rest) = get::format("import", args)?;
+                let overwrite = rest.iter().any(|arg| arg == "--overwrite");
+                let mut rest = rest.into_iter().filter(|arg| arg != "--overwrite");
+                Self::Import {
+                    format,
+                    path: rest.next().ok_or_else(|| {
+                        HrtError::Usage("`import` needs a file to read".to_string())
+                    })?,
+                    overwrite,
+                }
+            }
+            "apply" => {
+                let (mut path, mut reset_changed, mut prune, mut dry_run, mut force) =
+                    (None, false, false, false, false);
+                for arg in args.by_ref() {
+                    match &arg[..] {
+                        "--reset-changed" => reset_changed = true,
+                        "--prune" => prune = true,
+                        "--dry-run" => dry_run = true,
+                        "--force" => force = true,
+                        _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+                        other => {
+                            return Err(HrtError::Usage(format!(
+                                "unexpected argument `{other}` to `apply`"
+                            )));
+                        }
+                    }
+                }
+                Self::Apply {
+                    path: path.ok_or_else(|| {
+                        HrtError::Usage("`apply` needs a file to read".to_string())
+                    })?,
+                    reset_changed,
+                    prune,
+                    dry_run,
+                    force,
+                }
+            }
+            "batch" => {
+                let mut stop_on_error = false;
+                for arg in args.by_ref() {
+                    match &arg[..] {
+                        "--stop-on-error" => stop_on_error = true,
+                        other => {
+                            return Err(HrtError::Usage(format!(
+                                "unexpected argument `{other}` to `batch`"
+                            )));
+                        }
+                    }
+                }
+                Self::Batch { stop_on_error }
+            }
+            "metrics" => {
+                let (output, rest) = get::valued("--output", args)?;
+                if let Some(other) = rest.first() {
+                    return Err(HrtError::Usage(format!(
+                        "unexpected argument `{other}` to `metrics`"
+                    )));
+                }
+                Self::Metrics { output }
+            }
+            "set-priority" => {
+                let (force, rest) = get::switch("--force", args);
+                let args = &mut rest.into_iter();
+                Self::SetPriority {
+                    name: get::name(args)?,
+                    priority: match args.next() {
+                        Some(priority) => Some(priority.parse().map_err(|_| {
+                            HrtError::Usage(format!("`{priority}` is not a priority from 1 to 5"))
+                        })?),
+                        None => None,
+                    },
+                    force,
+                }
+            }
+            "set-webhook" => {
+                let (force, rest) = get::switch("--force", args);
+                let args = &mut rest.into_iter();
+                Self::SetWebhook {
+                    name: get::name(args)?,
+                    url: args.next(),
+                    force,
+                }
+            }
+            "tag" => {
+                let (force, rest) = get::switch("--force", args);
+                let args = &mut rest.into_iter();
+                Self::Tag {
+                    name: get::name(args)?,
+                    tags: args.collect(),
+                    force,
+                }
+            }
+            "cron" => {
+                let (command, rest) = get::valued("--command", args)?;
+                Self::Cron {
+                    name: get::name(&mut rest.into_iter())?,
+                    command,
+                }
+            }
+            "remind" => {
+                let (before, rest) = get::valued("--before", args)?;
+                let (command, rest) = get::valued("--command", &mut rest.into_iter())?;
+                let schedule = rest.iter().any(|arg| arg == "--schedule");
+                Self::Remind {
+                    name: get::name(&mut rest.into_iter().filter(|arg| arg != "--schedule"))?,
+                    before: match before {
+                        Some(before) => parse_timedelta(&before)?,
+                        None => TimeDelta::zero(),
+                    },
+                    command,
+                    schedule,
+                }
+            }
+            "systemd" => {
+                let (command, rest) = get::valued("--command", args)?;
+                let switch = |name: &str| rest.iter().any(|arg| arg == name);
+                let (stdout, user) = (switch("--stdout"), switch("--user"));
+                let mut rest = rest
+                    .into_iter()
+                    .filter(|arg| arg != "--stdout" && arg != "--user");
+                Self::Systemd {
+                    name: get::name(&mut rest)?,
+                    command,
+                    stdout,
+                    user,
+                }
+            }
+            "help" => Self::Help(args.next().map(|action| Self::canonical(&action))),
+            "_introspect" => Self::Introspect,
+            "_names" => Self::Names,
+            _ => return Err(Self::unknown(given)),
         };
         return Ok(action);
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct FormattedInterval(pub TimeDelta);
-impl Display for FormattedInterval {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}{:02}h{:02}m{:02}s",
-            if self.0 < TimeDelta::zero() { "-" } else { "" },
-            self.0.num_hours().abs(),
-            self.0.num_minutes().abs() % 60,
-            self.0.num_seconds().abs() % 60,
-        )
+/// Flags that may appear anywhere on the command line; they are removed before the action is
+/// parsed. Their defaults can be changed in the config file.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Flags {
+    /// How datetimes and durations are printed, with `--precise`, `--duration-format`, and
+    /// `--time-format`.
+    pub display: display::Options,
+    pub color: style::ColorChoice,
+    /// Schedules due within this window are highlighted as upcoming.
+    pub soon: TimeDelta,
+    /// Omit the header row of `list`.
+    pub no_header: bool,
+    /// Print `list` as one sentence per schedule instead of a table.
+    pub long: bool,
+    /// Print the stable, tab-separated format documented in `HELP`.
+    pub porcelain: bool,
+    pub sort: SortKey,
+    pub reverse: bool,
+    /// Add a progress bar to `list`.
+    pub progress: bool,
+    /// Omit the summary line after `list`.
+    pub no_summary: bool,
+    /// Make `count` exit with `ExitCode::Due` when anything is overdue.
+    pub fail_if_overdue: bool,
+    /// Keep `next` running as a live countdown.
+    pub countdown: bool,
+    /// Stop a countdown once the schedule is due.
+    pub until_due: bool,
+    /// Make `watch` and `due` send desktop notifications.
+    pub notify: bool,
+    /// Make `watch` and `due` notify through a shell command instead.
+    pub notify_command: Option<String>,
+    /// Skip the `hooks` for this run.
+    pub no_hooks: bool,
+    /// Make `watch` send push notifications to this URL, such as an ntfy.sh topic.
+    pub push: Option<String>,
+    /// Make `list` exit with `ExitCode::Corrupt` when a schedule can't be read.
+    pub strict: bool,
+    /// Make `step` skip occurrences that have already passed.
+    pub to_future: bool,
+    /// How far ahead `next` may already be, in thousandths of the interval, before `step` refuses
+    /// to step again.
+    pub step_guard: u32,
+    /// Print `list` in sections by tag.
+    pub group_by_tag: bool,
+    /// The most steps a schedule keeps in its history, dropping the oldest when stepped.
+    pub history_cap: usize,
+    /// Which schedules `list` and `count` include.
+    pub window: Window,
+    /// The most rows `list` prints, after skipping `offset` of them.
+    pub limit: Option<usize>,
+    pub offset: usize,
+    /// Add each schedule's current streak to `list`.
+    pub streaks: bool,
+    /// Leave out how long ago each schedule was stepped from `list`.
+    pub no_since: bool,
+    /// Add when each schedule was last stepped to porcelain `list` output.
+    pub with_since: bool,
+    /// How far the clock may move forwards between runs before it seems to have jumped.
+    pub clock_jump: TimeDelta,
+    /// Whether `--force` was given, which confirms the clock is right after it seems to have
+    /// jumped, whatever else the action takes it to mean. Never read from the config.
+    pub confirm_clock: bool,
+    /// The most bytes a schedule file may be before it is refused as unreadable.
+    pub max_file_size: u64,
+    /// The format schedules are saved in, if not `LATEST`.
+    pub write_version: Option<Version>,
+    /// Wait for every saved schedule to reach the disk.
+    pub durable: bool,
+    /// What `new` starts at when no date is given.
+    pub default_start: Option<Start>,
+    /// What `new` repeats every when no interval is given.
+    pub default_interval: Option<TimeDelta>,
+}
+impl Default for Flags {
+    fn default() -> Self {
+        Self {
+            display: display::Options::default(),
+            color: style::ColorChoice::Auto,
+            soon: TimeDelta::hours(1),
+            no_header: false,
+            long: false,
+            porcelain: false,
+            sort: SortKey::Next,
+            reverse: false,
+            progress: false,
+            no_summary: false,
+            fail_if_overdue: false,
+            countdown: false,
+            until_due: false,
+            notify: false,
+            notify_command: None,
+            no_hooks: false,
+            push: None,
+            strict: false,
+            to_future: false,
+            step_guard: 1000,
+            group_by_tag: false,
+            history_cap: HISTORY_CAP,
+            window: Window::default(),
+            limit: None,
+            offset: 0,
+            streaks: false,
+            no_since: false,
+            with_since: false,
+            clock_jump: clock::THRESHOLD,
+            confirm_clock: false,
+            max_file_size: limits::FILE,
+            write_version: None,
+            durable: false,
+            default_start: None,
+            default_interval: None,
+        }
     }
 }
+impl Flags {
+    /// Flags that take a value, given either as `--flag=value` or as `--flag value`.
+    pub const VALUED: &[&str] = &[
+        "--color",
+        "--soon",
+        "--sort",
+        "--time-format",
+        "--notify-command",
+        "--push",
+        "--step-guard",
+        "--group-by",
+        "--history-cap",
+        "--due-within",
+        "--limit",
+        "--offset",
+        "--clock-jump",
+        "--max-file-size",
+        "--write-version",
+        "--default-start",
+        "--default-interval",
+        "--duration-format",
+    ];
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let folder =
-        env::var("HOME").expect("`HOME` must exist; cannot access tracker file") + "/.hrtracker/";
-    if !fs::exists(&folder)? {
-        fs::create_dir(&folder)?;
+    /// Sets `flag` from its `value`, returning whether the flag is known. Switches accept no value,
+    /// `true`, or `false`.
+    pub fn set(&mut self, flag: &str, value: Option<&str>) -> Result<bool> {
+        let switch = || match value {
+            None | Some("true") => Ok(true),
+            Some("false") => Ok(false),
+            Some(other) => Err(HrtError::Usage(format!(
+                "`{flag}` takes `true` or `false`, not `{other}`"
+            ))),
+        };
+        let required =
+            || value.ok_or_else(|| HrtError::Usage(format!("`{flag}` requires a value")));
+        match flag {
+            "--precise" => self.display.precise = switch()?,
+            "--no-header" => self.no_header = switch()?,
+            "--long" => self.long = switch()?,
+            "--porcelain" => self.porcelain = switch()?,
+            "--reverse" => self.reverse = switch()?,
+            "--progress" => self.progress = switch()?,
+            "--streaks" => self.streaks = switch()?,
+            "--no-since" => self.no_since = switch()?,
+            "--with-since" => self.with_since = switch()?,
+            "--durable" => self.durable = switch()?,
+            "--no-summary" => self.no_summary = switch()?,
+            "--fail-if-overdue" => self.fail_if_overdue = switch()?,
+            "--countdown" => self.countdown = switch()?,
+            "--until-due" => self.until_due = switch()?,
+            "--notify" => self.notify = switch()?,
+            "--no-hooks" => self.no_hooks = switch()?,
+            "--to-future" => self.to_future = switch()?,
+            "--strict" => self.strict = switch()?,
+            "--exclude-overdue" => self.window.exclude_overdue = switch()?,
+            "--never-stepped" => self.window.never_stepped = switch()?,
+            "--notify-command" => self.notify_command = Some(required()?.to_string()),
+            "--push" => {
+                let url = required()?;
+                webhook::Url::parse(url).map_err(|error| HrtError::Usage(error.to_string()))?;
+                self.push = Some(url.to_string());
+            }
+            "--step-guard" => {
+                let value = required()?;
+                self.step_guard = match value.parse::<f64>() {
+                    Ok(fraction) if fraction.is_finite() && (0.0..=1000.0).contains(&fraction) => {
+                        (fraction * 1000.0).round() as u32
+                    }
+                    _ => {
+                        return Err(HrtError::Usage(format!(
+                            "`{value}` is not a fraction of the interval, like `1` or `1.5`"
+                        )));
+                    }
+                }
+            }
+            "--sort" => self.sort = SortKey::parse(required()?)?,
+            "--history-cap" => {
+                let value = required()?;
+                self.history_cap = match value.parse() {
+                    Ok(cap @ 1..=limits::HISTORY) => cap,
+                    _ => {
+                        return Err(HrtError::Usage(format!(
+                            "`{value}` is not a history cap from 1 to {}",
+                            limits::HISTORY
+                        )));
+                    }
+                }
+            }
+            "--duration-format" => {
+                self.display.iso_durations = match required()? {
+                    "iso" => true,
+                    "clock" => false,
+                    other => {
+                        return Err(HrtError::Usage(format!(
+                            "`{other}` is not a duration format (expected `clock` or `iso`)"
+                        )));
+                    }
+                }
+            }
+            "--group-by" => {
+                self.group_by_tag = match required()? {
+                    "tag" => true,
+                    "none" => false,
+                    other => {
+                        return Err(HrtError::Usage(format!(
+                            "`{other}` is not something to group by (expected `tag` or `none`)"
+                        )));
+                    }
+                }
+            }
+            "--color" => self.color = style::ColorChoice::parse(required()?)?,
+            "--soon" => self.soon = parse_timedelta(required()?)?,
+            "--limit" => {
+                let value = required()?;
+                self.limit = match value.parse() {
+                    Ok(0) => {
+                        return Err(HrtError::Usage("`--limit` must be at least 1".to_string()));
+                    }
+                    Ok(limit) => Some(limit),
+                    Err(_) => {
+                        return Err(HrtError::Usage(format!(
+                            "`{value}` is not a number of rows"
+                        )));
+                    }
+                }
+            }
+            "--offset" => {
+                let value = required()?;
+                self.offset = value
+                    .parse()
+                    .map_err(|_| HrtError::Usage(format!("`{value}` is not a number of rows")))?;
+            }
+            "--due-within" => {
+                let within = parse_timedelta(required()?)?;
+                if within < TimeDelta::zero() {
+                    return Err(HrtError::Usage(
+                        "`--due-within` can't be negative".to_string(),
+                    ));
+                }
+                self.window.within = Some(within);
+            }
+            "--default-start" => self.default_start = Some(Start::parse(required()?)?),
+            "--default-interval" => self.default_interval = Some(parse_interval(required()?)?),
+            "--write-version" => {
+                let value = required()?;
+                self.write_version = match parse_version(value) {
+                    Some(version) if (MIN_SUPPORTED..=LATEST).contains(&version) => Some(version),
+                    _ => {
+                        return Err(HrtError::Usage(format!(
+                            "`{value}` is not a format version from {} to {}",
+                            dotted(MIN_SUPPORTED),
+                            dotted(LATEST)
+                        )));
+                    }
+                }
+            }
+            "--max-file-size" => {
+                let value = required()?;
+                self.max_file_size = match value.parse() {
+                    Ok(0) | Err(_) => {
+                        return Err(HrtError::Usage(format!(
+                            "`{value}` is not a number of bytes above zero"
+                        )));
+                    }
+                    Ok(size) => size,
+                }
+            }
+            "--clock-jump" => {
+                let threshold = parse_timedelta(required()?)?;
+                if threshold <= TimeDelta::zero() {
+                    return Err(HrtError::Usage(
+                        "`--clock-jump` must be longer than zero".to_string(),
+                    ));
+                }
+                self.clock_jump = threshold;
+            }
+            "--time-format" => {
+                let format = required()?;
+                validate_time_format(format)?;
+                self.display.time_format = Some(format.to_string());
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
     }
-    let mut argv = env::args().skip(1);
-    let action = Action::get(&mut argv)?;
-    match action {
-        Action::List => {
-            for entry in fs::read_dir(folder)? {
-                let Ok(entry) = entry else {
-                    println!("directory entry error");
-                    continue;
-                };
-                let Ok(schedule) = RegularSchedule::open(entry.path()) else {
-                    println!("unable to open {}", entry.path().display());
-                    continue;
-                };
-                let now = Utc::now();
-                println!(
-                    "schedule `{}`: next at {} (in {}) with interval {}",
-                    entry.path().file_name().unwrap().display(),
-                    schedule.next,
-                    FormattedInterval(schedule.next.signed_duration_since(now)),
-                    FormattedInterval(schedule.interval)
-                );
+
+    /// The environment variables that set flags, each with the flag it sets. `run` applies them
+    /// after the config file and before the command line, so they override the one and are
+    /// overridden by the other.
+    pub const ENVIRONMENT: &[(&str, &str)] = &[
+        ("HRTRACKER_DEFAULT_START", "--default-start"),
+        ("HRTRACKER_DEFAULT_INTERVAL", "--default-interval"),
+    ];
+
+    /// Applies the `ENVIRONMENT` variables that are set and not empty, failing with the name of
+    /// one that is invalid.
+    pub fn environment(&mut self) -> Result<()> {
+        for (variable, flag) in Self::ENVIRONMENT {
+            let Some(value) = env::var(variable).ok().filter(|value| !value.is_empty()) else {
+                continue;
+            };
+            self.set(flag, Some(&value))
+                .map_err(|error| HrtError::Usage(format!("in `${variable}`: {error}")))?;
+        }
+        Ok(())
+    }
+
+    /// Applies the `key = value` pairs of a config file, where each key is a flag name without
+    /// its leading `--`, failing on any key that isn't one.
+    pub fn configure(&mut self, config: &[(String, String)]) -> Result<()> {
+        for (key, value) in config {
+            let flag = format!("--{key}");
+            if !self.set(&flag, Some(value))? {
+                return Err(HrtError::Usage(format!("unknown config key `{key}`")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes and applies every recognized flag in `args`, leaving only the positionals. Every
+    /// action takes `--force` to confirm the clock, which sets `confirm_clock`, and it is only
+    /// left in `args` for the actions that also take it themselves.
+    pub fn take(&mut self, args: &mut Vec<String>) -> Result<()> {
+        let mut positionals = Vec::with_capacity(args.len());
+        let mut forced = Vec::new();
+        let mut rest = std::mem::take(args).into_iter();
+        while let Some(arg) = rest.next() {
+            // the action reads its own arguments after `--`, along with the `--` itself
+            if arg == "--" {
+                positionals.push(arg);
+                positionals.extend(rest.by_ref());
+                break;
+            }
+            if arg == "--force" {
+                self.confirm_clock = true;
+                forced.push(positionals.len());
+                positionals.push(arg);
+                continue;
+            }
+            let (flag, inline) = try_split_once(&arg, "=");
+            let value = match inline {
+                Some(value) => Some(value.to_string()),
+                None if Self::VALUED.contains(&flag) => rest.next(),
+                None => None,
+            };
+            if !self.set(flag, value.as_deref())? {
+                positionals.push(arg);
             }
         }
-        Action::New { name, start, every } => {
-            RegularSchedule::create(start, every).save(folder + &name)?;
+        // the action is the first positional that isn't a `--force` given before it
+        let leading = forced
+            .iter()
+            .enumerate()
+            .take_while(|(order, index)| order == *index)
+            .count();
+        let takes_force = positionals
+            .get(leading)
+            .is_some_and(|action| Action::flags(&Action::canonical(action)).contains(&"--force"));
+        if takes_force {
+            if leading > 0 {
+                positionals.drain(..leading);
+                positionals.insert(1, "--force".to_string());
+            }
+        } else {
+            for index in forced.into_iter().rev() {
+                positionals.remove(index);
+            }
         }
-        Action::Step(name) => {
-            let mut schedule = RegularSchedule::open(folder.clone() + &name)?;
-            schedule.next += schedule.interval;
-            println!(
-                "now in {}",
-                FormattedInterval(schedule.next.signed_duration_since(Utc::now()))
-            );
-            schedule.save(folder + &name)?;
+        *args = positionals;
+        Ok(())
+    }
+
+    /// Orders `schedules` the way `list` shows them, honouring `--sort` and `--reverse`.
+    pub fn order(&self, schedules: &mut [(String, ScheduleInfo)], now: DateTime<Utc>) {
+        listing::order(schedules, self.sort, self.reverse, now);
+    }
+
+    /// Cuts `rows` down to the page `--offset` and `--limit` select, returning how many rows
+    /// came after it.
+    pub fn page<T>(&self, rows: &mut Vec<T>) -> usize {
+        rows.drain(..self.offset.min(rows.len()));
+        let limit = self.limit.unwrap_or(rows.len()).min(rows.len());
+        rows.drain(limit..).count()
+    }
+
+    /// Prints a message meant for people, which goes to stderr under `--porcelain` so that stdout
+    /// stays machine-readable.
+    pub fn notice(&self, message: impl Display) {
+        if self.porcelain {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
         }
-        Action::Next(name) => {
-            let schedule = RegularSchedule::open(folder + &name)?;
-            let delta = schedule.next.signed_duration_since(Utc::now());
-            println!("{} (in {})", schedule.next, FormattedInterval(delta));
+    }
+}
+
+/// The process exit codes hrtracker uses, as documented in `HELP`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExitCode {
+    Success = 0,
+    Generic = 1,
+    /// The command line could not be parsed.
+    Usage = 2,
+    /// A named schedule does not exist.
+    NotFound = 3,
+    /// A schedule file exists but could not be decoded.
+    Corrupt = 4,
+    /// `due` found at least one schedule that is due, or `count --fail-if-overdue` found one
+    /// that is overdue.
+    Due = 5,
+    /// A schedule would break a rule of its data, such as stepping it past the representable
+    /// dates.
+    Invalid = 6,
+}
+
+impl ExitCode {
+    /// The exit code `error` terminates the process with.
+    pub fn of(error: &HrtError) -> Self {
+        match error {
+            HrtError::NotFound { .. } => Self::NotFound,
+            HrtError::Decode { .. } | HrtError::TooLarge { .. } => Self::Corrupt,
+            HrtError::ParseDate { .. }
+            | HrtError::ParseInterval { .. }
+            | HrtError::InvalidName { .. }
+            | HrtError::Usage(_) => Self::Usage,
+            HrtError::InvalidSchedule { .. } => Self::Invalid,
+            HrtError::Io { .. }
+            | HrtError::NoHome
+            | HrtError::Config { .. }
+            | HrtError::Hook { .. }
+            | HrtError::Webhook { .. } => Self::Generic,
         }
     }
-    Ok(())
+}
+
+mod config {
+    use std::{fs, io::ErrorKind, path::Path};
+
+    use hrtracker::error::{HrtError, Result};
+
+    /// The config file, relative to the data directory.
+    pub const FILE: &str = ".config";
+
+    /// Reads `key = value` lines, skipping blank lines and `#` comments. A missing file is empty.
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<(String, String)>> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(HrtError::io(path.as_ref(), error)),
+        };
+        let mut entries = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| HrtError::Config {
+                path: path.as_ref().to_path_buf(),
+                reason: format!("line {} is not a `key = value` pair", index + 1),
+            })?;
+            entries.push((key.trim().to_string(), value.trim().to_string()));
+        }
+        Ok(entries)
+    }
+}
+
+/// Redraws a single line every second with the time left until `name` is due, formatted as
+/// `Flags::interval` formats it, until interrupted or, with `--until-due`, until it is due. The
+/// schedule is re-read every few seconds so that an external `step` is noticed; the line is cut
+/// to the terminal width (from `COLUMNS`) so it never wraps.
+pub fn countdown(store: &impl Store, flags: &Flags, name: &str) -> Result<()> {
+    const RELOAD_EVERY: u32 = 5;
+    let width = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .unwrap_or(80)
+        .saturating_sub(1);
+    let mut schedule: RegularSchedule = open_named(store, name)?;
+    let mut stdout = io::stdout();
+    let mut tick: u32 = 0;
+    loop {
+        if tick > 0 && tick.is_multiple_of(RELOAD_EVERY) {
+            // a concurrent `step` may be midway through rewriting the file, so keep the last good
+            // copy rather than giving up
+            if let Ok(reloaded) = open_named(store, name) {
+                schedule = reloaded;
+            }
+        }
+        let delta = TimeDelta::seconds(
+            schedule
+                .next
+                .signed_duration_since(Utc::now())
+                .num_seconds(),
+        );
+        let status = if delta < TimeDelta::zero() {
+            format!("`{name}`: OVERDUE for {}", flags.display.interval(-delta))
+        } else {
+            format!("`{name}`: due in {}", flags.display.interval(delta))
+        };
+        let line: String = status.chars().take(width).collect();
+        write!(stdout, "\r{line:<width$}")?;
+        stdout.flush()?;
+        if flags.until_due && delta <= TimeDelta::zero() {
+            writeln!(stdout)?;
+            return Ok(());
+        }
+        thread::sleep(std::time::Duration::from_secs(1));
+        tick = tick.wrapping_add(1);
+    }
+}
+
+/// Saves `schedule` as `name` in the format `--write-version` pins, or else `LATEST`. Without
+/// `--write-version`, it notes when that upgrades the file, since older versions of hrtracker may
+/// not be able to read it any more; each file is only upgraded once, so the note doesn't repeat.
+fn save(store: &impl Store, flags: &Flags, name: &str, schedule: &RegularSchedule) -> Result<()> {
+    let version = flags.write_version.unwrap_or(LATEST);
+    let bytes = schedule.encode_as(version)?;
+    let old = match flags.write_version {
+        Some(_) => None,
+        None => store
+            .load(name)
+            .ok()
+            .and_then(|bytes| peek_version(&bytes).ok()),
+    };
+    store.save(name, &bytes)?;
+    if let Some(old) = old.filter(|old| *old < version) {
+        eprintln!(
+            "note: `{name}` was upgraded from format {} to {}, which older versions of hrtracker \
+             may not read; set `write-version = {}` in the config to keep the older format",
+            dotted(old),
+            dotted(version),
+            dotted(old)
+        );
+    }
+    Ok(())
+}
+
+/// An interval that must be longer than zero, as a schedule's is.
+fn parse_interval(text: &str) -> Result<TimeDelta> {
+    let interval = parse_timedelta(text)?;
+    if interval <= TimeDelta::zero() {
+        return Err(HrtError::InvalidSchedule {
+            reason: "the interval must be longer than zero",
+        });
+    }
+    Ok(interval)
+}
+
+/// Asks on the terminal for the `field` of a schedule until `parse` accepts the answer. Without a
+/// terminal, or with `--porcelain`, it fails instead, naming the `flag` to give it with.
+fn ask<T>(flags: &Flags, field: &str, flag: &str, parse: impl Fn(&str) -> Result<T>) -> Result<T> {
+    let missing = || HrtError::Usage(format!("the {field} was lost; give it with `{flag}`"));
+    if flags.porcelain || !io::stdin().is_terminal() {
+        return Err(missing());
+    }
+    loop {
+        print!("{field}: ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Err(missing());
+        }
+        match parse(line.trim()) {
+            Ok(value) => return Ok(value),
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+}
+
+/// Where `rename` keeps a schedule while only the case of its name changes. It starts with `.`, so
+/// it is never listed, and it is only used under the store's lock, so one name is enough.
+const RENAMING: &str = ".renaming";
+
+/// Fails if another schedule's name differs from `name` only by case, unless `force` is set,
+/// since the two would be the same file wherever the data directory is on a filesystem that
+/// ignores case. `renaming` is left out, as it is about to become `name`.
+fn check_case(store: &impl Store, name: &str, renaming: Option<&str>, force: bool) -> Result<()> {
+    let names: Vec<String> = store
+        .list()?
+        .into_iter()
+        .filter(|other| Some(&other[..]) != renaming)
+        .collect();
+    match suggest::case_variant(name, &names) {
+        Some(other) if !force => Err(HrtError::Usage(format!(
+            "`{name}` only differs by case from `{other}`, which is the same file where case is \
+             ignored; pass --force to use it anyway"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Fails if `name` is an alias, since saving a schedule under it would replace what it stands for.
+fn check_not_alias(aliases: &Aliases, name: &str) -> Result<()> {
+    match aliases.0.get(name) {
+        Some(target) => Err(HrtError::Usage(format!(
+            "`{name}` is an alias of `{target}`; `unalias {name}` first to use it as a name"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The schedule `name` stands for: itself if there is a schedule by that name, or else what it
+/// resolves to through aliases.
+fn real_name(store: &impl Store, aliases: &Aliases, name: &str) -> Result<String> {
+    match store.list()?.iter().any(|other| other == name) {
+        true => Ok(name.to_string()),
+        false => Ok(aliases.resolve(name)?.to_string()),
+    }
+}
+
+/// Fails unless `schedule` is unprotected or `force` is set. Every action that changes a schedule
+/// in a way other than stepping it, or replaces or deletes it, checks this first.
+pub fn check_unprotected(name: &str, schedule: &RegularSchedule, force: bool) -> Result<()> {
+    if schedule.protected() && !force {
+        return Err(HrtError::Usage(format!(
+            "`{name}` is protected; pass --force to change it anyway, or `unprotect` it first"
+        )));
+    }
+    Ok(())
+}
+
+/// Opens `name` in `$VISUAL` or `$EDITOR` (or `vi`) as `Edit::run` does, saving the result
+/// unless it doesn't parse or the schedule was changed in the meantime.
+pub fn edit(store: &impl Store, name: &str, force: bool, flags: &Flags) -> Result<()> {
+    let opened = edit::Edit::open(store, name)?;
+    check_unprotected(name, &opened.schedule, force)?;
+    let editor = edit::editor(|var| env::var(var).ok());
+    let saved = opened.run(store, &editor, |edited| save(store, flags, name, edited))?;
+    match (flags.porcelain, saved) {
+        (true, saved) => println!("{}", if saved { "saved" } else { "unchanged" }),
+        (false, true) => println!("saved `{name}`"),
+        (false, false) => println!("`{name}` is unchanged"),
+    }
+    Ok(())
+}
+
+mod notify {
+    use std::process::Command;
+
+    use hrtracker::error::{HrtError, Result};
+
+    /// Shows a desktop notification through `org.freedesktop.Notifications`.
+    #[cfg(feature = "notify")]
+    pub fn desktop(title: &str, message: &str) -> Result<()> {
+        notify_rust::Notification::new()
+            .appname("hrtracker")
+            .summary(title)
+            .body(message)
+            .show()
+            .map(|_| ())
+            .map_err(|error| HrtError::Usage(format!("unable to send a notification: {error}")))
+    }
+    /// Without the `notify` feature, `notify-send` makes the same D-Bus call.
+    #[cfg(not(feature = "notify"))]
+    pub fn desktop(title: &str, message: &str) -> Result<()> {
+        run(Command::new("notify-send").args(["--app-name=hrtracker", title, message]))
+    }
+
+    /// Runs `command` through `sh -c`, with `title` and `message` as its arguments and the schedule
+    /// name in `HRTRACKER_SCHEDULE`.
+    pub fn command(command: &str, name: &str, title: &str, message: &str) -> Result<()> {
+        run(Command::new("sh")
+            .args(["-c", command, "hrtracker", title, message])
+            .env("HRTRACKER_SCHEDULE", name))
+    }
+
+    /// The title and message of the notification that `name` is due.
+    pub fn text(name: &str) -> (String, String) {
+        (
+            format!("{name} is due"),
+            format!("run `hrtracker step {name}` once it's done"),
+        )
+    }
+
+    /// Announces that `name` is due in the way `--notify` or `--notify-command` asks for, if
+    /// either was given. Failures are printed rather than returned.
+    pub fn due(flags: &super::Flags, name: &str) {
+        let (title, message) = text(name);
+        let sent = match &flags.notify_command {
+            Some(command) => self::command(command, name, &title, &message),
+            None if flags.notify => desktop(&title, &message),
+            None => Ok(()),
+        };
+        if let Err(error) = sent {
+            flags.notice(error);
+        }
+    }
+
+    fn run(command: &mut Command) -> Result<()> {
+        let status = command.status()?;
+        if !status.success() {
+            return Err(HrtError::Usage(format!(
+                "the notification command failed ({status})"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Prints `months` months from the current one, marking the days each schedule occurs on in the
+/// local timezone, as `Calendar::render` draws them.
+pub fn cal(schedules: &[(String, RegularSchedule)], months: usize, flags: &Flags) {
+    let Some(calendar) = Calendar::of(schedules, Local::now().date_naive(), months, &Local) else {
+        return;
+    };
+    match flags.porcelain {
+        true => print!("{}", calendar.porcelain()),
+        false => print!("{}", calendar.render(flags.color.enabled())),
+    }
+}
+
+/// The interactive `tui` action, as `tui::interact` runs it. Every change is made through
+/// `execute`, so that it behaves exactly like the matching action on the command line.
+pub fn tui(
+    store: &(impl Store + Sync),
+    hooks: &(impl HookRunner + Sync),
+    flags: &Flags,
+) -> Result<()> {
+    if flags.porcelain {
+        return Err(HrtError::Usage(
+            "`tui` is interactive, so it has no porcelain output".to_string(),
+        ));
+    }
+    let view = tui::View {
+        display: flags.display.clone(),
+        sort: flags.sort,
+        reverse: flags.reverse,
+        soon: flags.soon,
+        color: flags.color.enabled(),
+    };
+    tui::session(|terminal| {
+        tui::interact(store, &view, terminal, |command, name| {
+            let name = name.to_string();
+            let action = match command {
+                tui::Command::Step => Action::step(name),
+                tui::Command::Delete => Action::Delete { name, force: false },
+            };
+            execute(store, hooks, action, flags).map(|_| ())
+        })
+    })
+}
+
+/// Announces each schedule as `watch::run` finds it due, in print and in whichever ways `flags`
+/// ask for.
+pub fn watch(store: &(impl Store + Sync), hooks: &impl HookRunner, flags: &Flags) -> Result<()> {
+    watch::run(store, |name, schedule, now| {
+        let next = schedule.next_at();
+        let delta = next.signed_duration_since(now);
+        if flags.porcelain {
+            println!(
+                "{}",
+                porcelain::line(&[
+                    name.to_string(),
+                    flags.display.porcelain_next(&next),
+                    flags.display.porcelain_duration(&delta),
+                ])
+            );
+        } else {
+            println!("`{name}` is due ({})", flags.display.relative(delta));
+        }
+        notify::due(flags, name);
+        push(flags, name, schedule);
+        call_webhook(flags, name, schedule, webhook::Event::Due);
+        fire(hooks, flags, hooks::Event::Due, name, schedule);
+    })
+}
+
+/// Serves the schedules as JSON on `addr` until interrupted, as `serve::respond` answers. Every
+/// request reads `store` again, so changes made elsewhere show up straight away.
+pub fn serve(
+    store: &(impl Store + Sync),
+    hooks: &(impl HookRunner + Sync),
+    flags: &Flags,
+    addr: &str,
+    allow_step: bool,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    if flags.porcelain {
+        println!("{}", porcelain::line(&[local.to_string()]));
+    } else {
+        println!("listening on http://{local}");
+    }
+    let step =
+        |name: &str| execute(store, hooks, Action::step(name.to_string()), flags).map(|_| ());
+    serve::run(&listener, |request| {
+        let step: serve::Stepper = &step;
+        let response = serve::respond(store, request, allow_step.then_some(step), Utc::now());
+        for error in &response.unreadable {
+            eprintln!("{error}");
+        }
+        response
+    });
+    Ok(())
+}
+
+/// Sends `event` to the webhook of `schedule`, if it has one. Failures are printed rather than
+/// returned, so that they never undo or block whatever triggered the event.
+fn call_webhook(flags: &Flags, name: &str, schedule: &RegularSchedule, event: webhook::Event) {
+    if let Err(error) = webhook::call(name, schedule, event, Utc::now()) {
+        flags.notice(error);
+    }
+}
+
+/// Runs each line of stdin as its own command line, with its flags applied on top of `flags`, as
+/// `batch::run` does. The exit code is `ExitCode::Generic` if any line failed.
+pub fn batch(
+    store: &(impl Store + Sync),
+    hooks: &(impl HookRunner + Sync),
+    flags: &Flags,
+    stop_on_error: bool,
+) -> Result<ExitCode> {
+    let run_line = |line: &str| {
+        let mut words = split_words(line)?;
+        let mut flags = flags.clone();
+        flags.take(&mut words)?;
+        match Action::get(&mut words.into_iter())? {
+            Action::Batch { .. } => Err(HrtError::Usage("`batch` can't be nested".to_string())),
+            Action::Tui => Err(HrtError::Usage(
+                "`tui` reads the keyboard, so it can't run from `batch`".to_string(),
+            )),
+            Action::Edit { .. } => Err(HrtError::Usage(
+                "`edit` opens an editor, so it can't run from `batch`".to_string(),
+            )),
+            action => execute(store, hooks, action, &flags).map(|_| ()),
+        }
+    };
+    let worked = batch::run(
+        io::stdin().lock(),
+        stop_on_error,
+        run_line,
+        |outcome| match flags.porcelain {
+            true => println!("{}", outcome.porcelain()),
+            false => println!("{}", outcome.render()),
+        },
+    )?;
+    Ok(match worked {
+        true => ExitCode::Success,
+        false => ExitCode::Generic,
+    })
+}
+
+/// Sends the notification that `name` is due to `--push`, if it was given, retrying once. Failures
+/// go to stderr, so that they never stop `watch`.
+fn push(flags: &Flags, name: &str, schedule: &RegularSchedule) {
+    let Some(url) = &flags.push else {
+        return;
+    };
+    let (title, message) = notify::text(name);
+    if let Err(error) = webhook::push(url, &title, &message, schedule.priority()) {
+        eprintln!("{error}");
+    }
+}
+
+/// Prints `schedules` the way `list` does, in order, with `prefix` before the fields of each
+/// porcelain line. `streaks` has the current streak of each schedule, in the same order, with
+/// `--streaks`.
+fn print_schedules(
+    schedules: Vec<(String, ScheduleInfo)>,
+    streaks: Option<Vec<usize>>,
+    prefix: &[String],
+    flags: &Flags,
+    now: DateTime<Utc>,
+) {
+    let color = flags.color.enabled() && !flags.porcelain;
+    let mut rows = Vec::new();
+    for (index, (name, schedule)) in schedules.into_iter().enumerate() {
+        let delta = schedule.next.signed_duration_since(now);
+        let streak = streaks.as_ref().map(|streaks| streaks[index]);
+        if flags.porcelain {
+            let mut fields = prefix.to_vec();
+            fields.extend([
+                name,
+                flags.display.porcelain_next(&schedule.next),
+                flags.display.porcelain_duration(&delta),
+                flags.display.porcelain_duration(&schedule.interval),
+            ]);
+            fields.extend(streak.map(|streak| streak.to_string()));
+            if flags.with_since {
+                fields.push(
+                    schedule
+                        .last_stepped
+                        .map_or_else(String::new, |last| porcelain::timestamp(&last)),
+                );
+            }
+            println!("{}", porcelain::line(&fields));
+            continue;
+        }
+        let since = match schedule.last_stepped {
+            Some(last) => flags.display.ago(now.signed_duration_since(last)),
+            None => "never".to_string(),
+        };
+        let name = match schedule.protected {
+            true => format!("{name} (protected)"),
+            false => name,
+        };
+        let name = match schedule.bound {
+            Some(bound) => format!("{name} [{bound}]"),
+            None => name,
+        };
+        let name = match (schedule.never_stepped(), schedule.neglected(now)) {
+            (true, true) => format!("{name} (never stepped)"),
+            (true, false) => format!("{name} (new)"),
+            (false, _) => name,
+        };
+        let urgency = if delta < TimeDelta::zero() {
+            style::RED
+        } else if delta <= flags.soon {
+            style::YELLOW
+        } else {
+            ""
+        };
+        let progress = Progress::of(schedule.next, schedule.interval, now);
+        if flags.long {
+            println!(
+                "schedule `{}`: {} with interval {}{}{}{}",
+                style::paint(name, style::BOLD, color),
+                style::paint(
+                    format!(
+                        "next at {} ({})",
+                        flags.display.datetime(&schedule.next),
+                        flags.display.relative(delta)
+                    ),
+                    urgency,
+                    color
+                ),
+                flags.display.interval(schedule.interval),
+                match (flags.no_since, schedule.last_stepped) {
+                    (true, _) => String::new(),
+                    (false, Some(_)) => format!(", last stepped {since}"),
+                    (false, None) => ", never stepped".to_string(),
+                },
+                if flags.progress {
+                    format!(" {}", style::paint(progress.bar(), urgency, color))
+                } else {
+                    String::new()
+                },
+                streak.map_or_else(String::new, |streak| format!(
+                    ", on a streak of {}",
+                    plural(streak as i64, "occurrence")
+                ))
+            );
+            continue;
+        }
+        let mut row = vec![
+            table::Cell::styled(name, style::BOLD),
+            table::Cell::styled(flags.display.datetime(&schedule.next), urgency),
+            table::Cell::styled(flags.display.relative(delta), urgency),
+            table::Cell::plain(flags.display.interval(schedule.interval)),
+        ];
+        if !flags.no_since {
+            row.push(table::Cell::plain(since));
+        }
+        if flags.progress {
+            row.push(table::Cell::styled(progress.bar(), urgency));
+        }
+        if let Some(streak) = streak {
+            row.push(table::Cell::plain(streak));
+        }
+        rows.push(row);
+    }
+    if !flags.long && !flags.porcelain {
+        let mut header = vec!["NAME", "NEXT", "IN", "INTERVAL"];
+        if !flags.no_since {
+            header.push("SINCE");
+        }
+        if flags.progress {
+            header.push("PROGRESS");
+        }
+        if streaks.is_some() {
+            header.push("STREAK");
+        }
+        let header = &header[..];
+        print!(
+            "{}",
+            table::render((!flags.no_header).then_some(header), &rows, color)
+        );
+    }
+}
+
+/// The streaks of `schedule` as of `now`.
+fn streaks(schedule: &RegularSchedule, now: DateTime<Utc>) -> Streaks {
+    Streaks::of(
+        &schedule.stepped_at(),
+        schedule.satisfied(),
+        schedule.interval,
+        schedule.next,
+        now,
+    )
+}
+
+/// The length of the current streak of `schedule`, or 0 if it has none.
+fn current_streak(schedule: &RegularSchedule, now: DateTime<Utc>) -> usize {
+    streaks(schedule, now)
+        .current
+        .map_or(0, |streak| streak.length)
+}
+
+/// Reads the routines `apply` makes the schedules match: one `<name> <start> <interval>` line per
+/// schedule, split into words like `batch` lines and followed by an optional `priority=<1-5>`.
+/// Blank lines and `#` comments are skipped.
+fn read_routines(path: &str) -> Result<Vec<(Wanted, Start)>> {
+    let text = fs::read_to_string(path).map_err(|error| HrtError::io(path, error))?;
+    let mut routines: Vec<(Wanted, Start)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |reason: String| HrtError::Config {
+            path: PathBuf::from(path),
+            reason: format!("line {}: {reason}", index + 1),
+        };
+        let parse = || {
+            let mut words = split_words(line)?.into_iter();
+            let name = get::name(&mut words)?;
+            let start = get::start(&mut words)?;
+            let interval = get::interval(&mut words)?;
+            if interval <= TimeDelta::zero() {
+                return Err(HrtError::InvalidSchedule {
+                    reason: "the interval must be longer than zero",
+                });
+            }
+            let mut priority = None;
+            for option in words {
+                match option.strip_prefix("priority=").map(str::parse) {
+                    Some(Ok(value @ 1..=5)) => priority = Some(value),
+                    _ => {
+                        return Err(HrtError::Usage(format!(
+                            "`{option}` is not an option; the only one is `priority=<1-5>`"
+                        )));
+                    }
+                }
+            }
+            let wanted = Wanted {
+                name,
+                interval,
+                priority,
+            };
+            Ok((wanted, start))
+        };
+        let (wanted, start) = parse().map_err(|error| invalid(error.to_string()))?;
+        if routines.iter().any(|(other, _)| other.name == wanted.name) {
+            return Err(invalid(format!(
+                "`{}` is listed more than once",
+                wanted.name
+            )));
+        }
+        routines.push((wanted, start));
+    }
+    Ok(routines)
+}
+
+/// Runs the hooks for `event` on `schedule`, printing their failures as warnings.
+fn fire(
+    hooks: &impl HookRunner,
+    flags: &Flags,
+    event: hooks::Event,
+    name: &str,
+    schedule: &RegularSchedule,
+) {
+    for error in hooks.fire(&hooks::Fired::of(event, name, schedule)) {
+        flags.notice(error);
+    }
+}
+
+/// Whether a schedule whose `next` is `ahead` of now is further ahead than `guard` thousandths of
+/// its `interval`, which suggests it has already been stepped for the current occurrence.
+fn stepped_recently(ahead: TimeDelta, interval: TimeDelta, guard: u32) -> bool {
+    let nanos = |delta: TimeDelta| {
+        delta.num_seconds() as i128 * 1_000_000_000 + delta.subsec_nanos() as i128
+    };
+    nanos(ahead) * 1000 > nanos(interval) * guard as i128
+}
+
+/// How generated cron jobs and units run hrtracker: the running executable, quoted for `sh`,
+/// since their `PATH` may not include it.
+fn program() -> String {
+    let program = env::current_exe()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "hrtracker".to_string());
+    cron::shell_quote(&program)
+}
+
+/// Loads every schedule in `store`, printing a notice for each one that can't be read. Also returns
+/// the number of those.
+pub fn load_reporting(
+    store: &(impl Store + Sync),
+    flags: &Flags,
+) -> Result<(Vec<(String, RegularSchedule)>, usize)> {
+    Ok(report(load_all(store)?, flags))
+}
+
+/// Like `load_reporting`, but loading only what the index keeps of each schedule, which is all that
+/// `list`, `due` and `count` need.
+pub fn load_indexed(
+    store: &(impl Store + Sync),
+    flags: &Flags,
+) -> Result<(Vec<(String, index::Entry)>, usize)> {
+    Ok(report(index::load_all(store)?, flags))
+}
+
+fn report<S>(loaded: Loaded<S>, flags: &Flags) -> (Vec<(String, S)>, usize) {
+    let Loaded {
+        mut schedules,
+        unreadable,
+        trailing,
+    } = loaded;
+    for error in &unreadable {
+        flags.notice(error);
+    }
+    for (name, bytes) in &trailing {
+        flags.notice(format!(
+            "schedule `{name}` is followed by {} in its file, so it may be corrupt",
+            plural(*bytes as i64, "unexpected trailing byte")
+        ));
+    }
+    // with `--strict`, they count as unreadable
+    if flags.strict && !trailing.is_empty() {
+        schedules.retain(|(name, _)| !trailing.iter().any(|(trailing, _)| trailing == name));
+        return (schedules, unreadable.len() + trailing.len());
+    }
+    (schedules, unreadable.len())
+}
+
+fn main() {
+    match run() {
+        Ok(code) => process::exit(code as i32),
+        Err(error) => {
+            eprintln!("error: {error}");
+            if let HrtError::NotFound { name } = &error
+                && let Some(hint) = not_found_hint(name)
+            {
+                eprintln!("{hint}");
+            }
+            process::exit(ExitCode::of(&error) as i32);
+        }
+    }
+}
+
+/// Suggests the closest existing names to the missing schedule `name`, or lists every name if
+/// none are close. `None` if there are no schedules at all.
+fn not_found_hint(name: &str) -> Option<String> {
+    let names = DirStore::new(store::data_dir().ok()?).list().ok()?;
+    if names.is_empty() {
+        return None;
+    }
+    Some(suggest::hint(name, &names, "schedules"))
+}
+
+fn run() -> Result<ExitCode> {
+    let folder = store::data_dir()?;
+    let mut argv: Vec<String> = env::args().skip(1).collect();
+    let mut flags = Flags::default();
+    let config_path = folder.join(config::FILE);
+    let (hook_config, flag_config): (Vec<_>, Vec<_>) = config::load(&config_path)?
+        .into_iter()
+        .partition(|(key, _)| hooks::Event::from_key(key).is_some());
+    flags
+        .configure(&flag_config)
+        .map_err(|error| HrtError::Config {
+            path: config_path,
+            reason: error.to_string(),
+        })?;
+    flags.environment()?;
+    flags.take(&mut argv)?;
+    let action = Action::get(&mut argv.into_iter())?;
+    check_clock(&folder.join(clock::FILE), &action, &flags)?;
+    let store = Aliased::new(
+        DirStore::new(&folder)
+            .with_max_size(flags.max_file_size)
+            .with_durable(flags.durable),
+    );
+    if flags.no_hooks {
+        return execute(&store, &(), action, &flags);
+    }
+    let hooks = Hooks::load(&hook_config, &folder.join(hooks::DIR));
+    execute(&store, &hooks, action, &flags)
+}
+
+/// Warns if the clock seems to have jumped since hrtracker last ran, which would make everything
+/// look far overdue (or not due at all), and refuses to let `action` change anything then unless
+/// `--force` confirmed the clock. The time is recorded as the last seen unless a jump is left
+/// unconfirmed, so that the warning keeps coming until it is.
+fn check_clock(path: &Path, action: &Action, flags: &Flags) -> Result<()> {
+    let now = Utc::now();
+    let jump = clock::load(path)?.and_then(|seen| Jump::between(seen, now, flags.clock_jump));
+    if let Some(jump) = jump {
+        eprintln!("warning: {jump}, so the system clock may be wrong");
+        if !flags.confirm_clock {
+            if action.changes() {
+                return Err(HrtError::Usage(format!(
+                    "not changing anything, since {jump}; pass --force if the clock is right"
+                )));
+            }
+            return Ok(());
+        }
+    }
+    // the marker is only a safeguard, so failing to write it shouldn't stop the action
+    let _ = clock::save(path, now);
+    Ok(())
+}
+
+/// Carries out `action` against the schedules in `store`, running `hooks` for the events it
+/// causes. Both are `Sync` because `serve` shares them between its threads.
+pub fn execute(
+    store: &(impl Store + Sync),
+    hooks: &(impl HookRunner + Sync),
+    action: Action,
+    flags: &Flags,
+) -> Result<ExitCode> {
+    match action {
+        Action::List => {
+            let color = flags.color.enabled() && !flags.porcelain;
+            let now = Utc::now();
+            // streaks need whole histories, which the index doesn't keep
+            let (mut loaded, unreadable, current) = if flags.streaks {
+                let (schedules, unreadable) = load_reporting(store, flags)?;
+                let current: BTreeMap<String, usize> = schedules
+                    .iter()
+                    .map(|(name, schedule)| (name.clone(), current_streak(schedule, now)))
+                    .collect();
+                let entries = schedules
+                    .iter()
+                    .map(|(name, schedule)| (name.clone(), index::Entry::of(schedule, 0)))
+                    .collect();
+                (entries, unreadable, current)
+            } else {
+                let (entries, unreadable) = load_indexed(store, flags)?;
+                (entries, unreadable, BTreeMap::new())
+            };
+            loaded.retain(|(_, entry)| flags.window.contains(&entry.info, now));
+            let summary = Summary::of_infos(
+                loaded.iter().map(|(name, entry)| (&name[..], &entry.info)),
+                unreadable,
+                now,
+            );
+            let mut schedules: Vec<_> = loaded
+                .iter()
+                .map(|(name, entry)| (name.clone(), entry.info))
+                .collect();
+            flags.order(&mut schedules, now);
+            let more = flags.page(&mut schedules);
+            let streak_column = |schedules: &[(String, ScheduleInfo)]| {
+                flags.streaks.then(|| {
+                    schedules
+                        .iter()
+                        .map(|(name, _)| current[&name[..]])
+                        .collect()
+                })
+            };
+            if !flags.group_by_tag {
+                let streaks = streak_column(&schedules);
+                print_schedules(schedules, streaks, &[], flags, now);
+            } else {
+                let tags: BTreeMap<&str, &[String]> = loaded
+                    .iter()
+                    .map(|(name, entry)| (&name[..], &entry.tags[..]))
+                    .collect();
+                let groups = listing::group_by_tag(&schedules, |name| {
+                    tags.get(name).copied().unwrap_or_default()
+                });
+                for (index, group) in groups.into_iter().enumerate() {
+                    let overdue = group.overdue(now);
+                    let tag = group.tag.clone().unwrap_or_default();
+                    let mut schedules = group.schedules;
+                    let streaks = streak_column(&schedules);
+                    if flags.porcelain {
+                        print_schedules(schedules, streaks, &[tag], flags, now);
+                        continue;
+                    }
+                    for (name, _) in &mut schedules {
+                        let others: Vec<_> = tags[&name[..]]
+                            .iter()
+                            .filter(|other| **other != tag)
+                            .map(|other| format!("#{other}"))
+                            .collect();
+                        if !others.is_empty() {
+                            name.push_str(&format!(" (also {})", others.join(", ")));
+                        }
+                    }
+                    if index > 0 {
+                        println!();
+                    }
+                    let heading = match &group.tag {
+                        Some(tag) => format!("#{tag}"),
+                        None => "(untagged)".to_string(),
+                    };
+                    println!("{}", style::paint(heading, style::BOLD, color));
+                    let total = schedules.len();
+                    print_schedules(schedules, streaks, &[], flags, now);
+                    if !flags.no_summary {
+                        println!("{}, {overdue} overdue", plural(total as i64, "schedule"));
+                    }
+                }
+                if !flags.porcelain && !flags.no_summary {
+                    println!();
+                }
+            }
+            if more > 0 && !flags.porcelain {
+                println!("… and {more} more");
+            }
+            if !flags.no_summary && !flags.porcelain {
+                println!(
+                    "{}",
+                    summary.describe(|delta| flags.display.relative(delta), now)
+                );
+            }
+            if flags.strict && unreadable > 0 {
+                return Ok(ExitCode::Corrupt);
+            }
+        }
+        Action::New {
+            name,
+            start,
+            every,
+            count,
+            force,
+        } => {
+            let start = start
+                .or_else(|| flags.default_start.clone())
+                .unwrap_or_else(|| Start::At(Utc::now()));
+            let every = every.or(flags.default_interval).ok_or_else(|| {
+                HrtError::Usage(Action::with_usage(
+                    "new",
+                    "an interval must be specified".to_string(),
+                ))
+            })?;
+            let _lock = store.lock()?;
+            check_not_alias(&Aliases::read(store)?, &name)?;
+            check_case(store, &name, None, force)?;
+            // a schedule that can't be read isn't protected, so replacing it is left as it was
+            if let Ok(existing) = open_named::<RegularSchedule>(store, &name) {
+                check_unprotected(&name, &existing, force)?;
+            }
+            let mut builder = RegularSchedule::builder()
+                .start(start.resolve(store, &name, every)?)
+                .interval(every);
+            if let Some(count) = count {
+                builder = builder.count(count);
+            }
+            let schedule = builder.build()?;
+            save(store, flags, &name, &schedule)?;
+            fire(hooks, flags, hooks::Event::New, &name, &schedule);
+        }
+        Action::Step {
+            name,
+            at,
+            allow_future,
+            force,
+            note,
+        } => {
+            let now = Utc::now();
+            if at.is_some_and(|at| at > now) && !allow_future {
+                return Err(HrtError::Usage(
+                    "`--at` is in the future; pass --allow-future to step as of then anyway"
+                        .to_string(),
+                ));
+            }
+            let _lock = store.lock()?;
+            let mut schedule: RegularSchedule = open_named(store, &name)?;
+            let ahead = schedule.next.signed_duration_since(now);
+            if !force && stepped_recently(ahead, schedule.interval, flags.step_guard) {
+                return Err(HrtError::Usage(format!(
+                    "this schedule was stepped recently (next is already {} ahead); pass --force \
+                     to step anyway",
+                    flags
+                        .display
+                        .interval(TimeDelta::seconds(ahead.num_seconds()))
+                )));
+            }
+            let reference = at.unwrap_or(now);
+            schedule.step_noted(reference, note)?;
+            if at.is_some() {
+                flags.notice(format!(
+                    "stepped as of {} rather than now",
+                    flags.display.datetime(&reference)
+                ));
+            }
+            let skipped = match flags.to_future {
+                true => schedule.roll_forward(reference)?,
+                false => 0,
+            };
+            if skipped > 0 {
+                flags.notice(format!(
+                    "skipped {skipped} occurrence{} that had already passed",
+                    if skipped == 1 { "" } else { "s" }
+                ));
+            }
+            let next = schedule.next_at();
+            let delta = next.signed_duration_since(Utc::now());
+            if flags.porcelain {
+                println!(
+                    "{}",
+                    porcelain::line(&[
+                        flags.display.porcelain_next(&next),
+                        flags.display.porcelain_duration(&delta)
+                    ])
+                );
+            } else {
+                println!("next occurrence {}", flags.display.relative(delta));
+            }
+            schedule.prune_history(flags.history_cap, None);
+            save(store, flags, &name, &schedule)?;
+            call_webhook(flags, &name, &schedule, webhook::Event::Step);
+            fire(hooks, flags, hooks::Event::Step, &name, &schedule);
+        }
+        Action::Delete { name, force } => {
+            let _lock = store.lock()?;
+            // read first, so that the hook can be told what was deleted
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            check_unprotected(&name, &schedule, force)?;
+            let mut aliases = Aliases::read(store)?;
+            let deleted = real_name(store, &aliases, &name)?;
+            store.delete(&name)?;
+            // aliases of the schedule would otherwise be left pointing at nothing
+            let removed = aliases.remove_resolving_to(&deleted);
+            if !removed.is_empty() {
+                aliases.write(store)?;
+                let quoted: Vec<_> = removed.iter().map(|short| format!("`{short}`")).collect();
+                flags.notice(format_args!("removed the aliases {}", quoted.join(", ")));
+            }
+            fire(hooks, flags, hooks::Event::Delete, &name, &schedule);
+        }
+        Action::Rename { name, new, force } => {
+            let _lock = store.lock()?;
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let bytes = store.load(&name)?;
+            check_unprotected(&name, &schedule, force)?;
+            let mut aliases = Aliases::read(store)?;
+            check_not_alias(&aliases, &new)?;
+            let renamed = real_name(store, &aliases, &name)?;
+            let case_only = suggest::fold(&name) == suggest::fold(&new);
+            if !case_only && store.load(&new).is_ok() {
+                return Err(HrtError::Usage(format!(
+                    "there is already a schedule called `{new}`"
+                )));
+            }
+            check_case(store, &new, Some(&name), force)?;
+            // on a filesystem that ignores case, both names are the same file, so saving the new
+            // one first and then deleting the old one would delete both. it is moved aside first
+            // instead, so that it isn't lost if saving the new name fails
+            if case_only {
+                store.save(RENAMING, &bytes)?;
+                store.delete(&name)?;
+                if let Err(error) = store.save(&new, &bytes) {
+                    store.save(&name, &bytes)?;
+                    store.delete(RENAMING)?;
+                    return Err(error);
+                }
+                store.delete(RENAMING)?;
+            } else {
+                store.save(&new, &bytes)?;
+                store.delete(&name)?;
+            }
+            // aliases follow the schedule to its new name
+            if aliases.retarget(&renamed, &new) {
+                aliases.write(store)?;
+            }
+        }
+        Action::Doctor => {
+            let mut names = store.list()?;
+            names.sort();
+            let collisions = suggest::case_collisions(&names);
+            let mut taken: HashSet<String> = names.iter().map(|name| suggest::fold(name)).collect();
+            for group in &collisions {
+                let Some((kept, others)) = group.split_first() else {
+                    continue;
+                };
+                if !flags.porcelain {
+                    let quoted: Vec<_> = group.iter().map(|name| format!("`{name}`")).collect();
+                    println!(
+                        "{} only differ by case, so they are the same file where case is ignored",
+                        quoted.join(" and ")
+                    );
+                }
+                for other in others {
+                    // the first free name like `water-2`, which no other schedule folds to
+                    let mut suffix = 2;
+                    let renamed = loop {
+                        let renamed = format!("{other}-{suffix}");
+                        if !taken.contains(&suggest::fold(&renamed)) {
+                            break renamed;
+                        }
+                        suffix += 1;
+                    };
+                    taken.insert(suggest::fold(&renamed));
+                    if flags.porcelain {
+                        println!(
+                            "{}",
+                            porcelain::line(&[
+                                kept.to_string(),
+                                other.to_string(),
+                                renamed.clone()
+                            ])
+                        );
+                    } else {
+                        println!(
+                            "  fix with: {} rename {} {}",
+                            program(),
+                            cron::shell_quote(other),
+                            cron::shell_quote(&renamed)
+                        );
+                    }
+                }
+            }
+            let neglected: Vec<_> = index::load_all(store)?
+                .schedules
+                .into_iter()
+                .filter(|(_, entry)| entry.info.neglected(Utc::now()))
+                .map(|(name, _)| format!("`{name}`"))
+                .collect();
+            if !neglected.is_empty() {
+                flags.notice(format!(
+                    "note: {} never been stepped, though first due over an interval ago: {}",
+                    neglect_count(neglected.len()),
+                    neglected.join(", ")
+                ));
+            }
+            if collisions.is_empty() {
+                flags.notice("no problems found");
+            } else {
+                return Ok(ExitCode::Generic);
+            }
+        }
+        Action::Verify(name) => {
+            let names = match name {
+                Some(name) => vec![name],
+                None => {
+                    let mut names = store.list()?;
+                    names.sort();
+                    // backups are compared against their schedules rather than checked alone
+                    let backup_of = |name: &str| {
+                        name.strip_suffix(".bak")
+                            .is_some_and(|of| names.iter().any(|other| other == of))
+                    };
+                    names
+                        .iter()
+                        .filter(|name| !backup_of(name))
+                        .cloned()
+                        .collect()
+                }
+            };
+            let verifications = names
+                .iter()
+                .map(|name| Verification::of(store, name))
+                .collect::<Result<Vec<_>>>()?;
+            let backup = |verification: &Verification| match verification.backup {
+                None => "none",
+                Some(true) => "same",
+                Some(false) => "differs",
+            };
+            if flags.porcelain {
+                for verification in &verifications {
+                    let result = if verification.passed() {
+                        "pass"
+                    } else {
+                        "fail"
+                    };
+                    println!(
+                        "{}",
+                        porcelain::line(&[
+                            verification.name.clone(),
+                            result.into(),
+                            backup(verification).into(),
+                            verification.failure.clone().unwrap_or_default(),
+                        ])
+                    );
+                }
+            } else {
+                let rows: Vec<_> = verifications
+                    .iter()
+                    .map(|verification| {
+                        let result = match verification.passed() {
+                            true => table::Cell::styled("PASS", style::GREEN),
+                            false => table::Cell::styled("FAIL", style::RED),
+                        };
+                        vec![
+                            table::Cell::plain(&verification.name),
+                            result,
+                            table::Cell::plain(backup(verification)),
+                            table::Cell::plain(verification.failure.as_deref().unwrap_or("")),
+                        ]
+                    })
+                    .collect();
+                let header: &[&str] = &["NAME", "RESULT", "BACKUP", "PROBLEM"];
+                let header = (!flags.no_header).then_some(header);
+                print!("{}", table::render(header, &rows, flags.color.enabled()));
+            }
+            let failed = verifications
+                .iter()
+                .filter(|verification| !verification.passed())
+                .count();
+            if !flags.no_summary && !flags.porcelain {
+                println!("{} passed, {failed} failed", verifications.len() - failed);
+            }
+            if failed > 0 {
+                return Ok(ExitCode::Corrupt);
+            }
+        }
+        Action::Recover {
+            name,
+            next,
+            interval,
+        } => {
+            let target = format!("{name}.recovered");
+            validate_name(&target)?;
+            let bytes = store.load(&name)?;
+            let mut recovery =
+                RegularSchedule::recover(&bytes).map_err(|source| HrtError::Decode {
+                    path: PathBuf::from(&name),
+                    size: bytes.len() as u64,
+                    source,
+                })?;
+            if flags.porcelain {
+                for field in &recovery.recovered {
+                    println!(
+                        "{}",
+                        porcelain::line(&[field.to_string(), "recovered".into()])
+                    );
+                }
+                for field in &recovery.missing {
+                    println!(
+                        "{}",
+                        porcelain::line(&[field.to_string(), "missing".into()])
+                    );
+                }
+            } else {
+                println!("recovered: {}", recovery.recovered.join(", "));
+                match &recovery.error {
+                    Some(error) => println!(
+                        "missing: {} (reading stopped there: {error})",
+                        recovery.missing.join(", ")
+                    ),
+                    None => println!("nothing is missing"),
+                }
+            }
+            let lost_next = recovery.lost("next");
+            let lost_interval = recovery.lost("interval");
+            let schedule = &mut recovery.schedule;
+            match next {
+                Some(next) => schedule.next = next,
+                None if lost_next => schedule.next = ask(flags, "next", "--next", parse_datetime)?,
+                None => {}
+            }
+            match interval {
+                Some(interval) => schedule.interval = interval,
+                None if lost_interval => {
+                    schedule.interval = ask(flags, "interval", "--interval", parse_interval)?
+                }
+                None => {}
+            }
+            // written beside the original, which is left alone in case more can be read from it
+            save(store, flags, &target, schedule)?;
+            flags.notice(format!(
+                "saved it as `{target}`; check it with `{} show {}`, then move it over `{name}` \
+                 in the data directory",
+                program(),
+                cron::shell_quote(&target)
+            ));
+        }
+        Action::Protect { name, protected } => {
+            let _lock = store.lock()?;
+            let mut schedule: RegularSchedule = open_named(store, &name)?;
+            schedule.set_protected(protected);
+            save(store, flags, &name, &schedule)?;
+        }
+        Action::Alias(None) => {
+            let aliases = Aliases::read(store)?;
+            if aliases.0.is_empty() {
+                flags.notice("no aliases");
+            }
+            for (short, target) in aliases.0 {
+                match flags.porcelain {
+                    true => println!("{}", porcelain::line(&[short, target])),
+                    false => println!("{short} -> {target}"),
+                }
+            }
+        }
+        Action::Alias(Some((short, target))) => {
+            let _lock = store.lock()?;
+            if store.list()?.contains(&short) {
+                return Err(HrtError::Usage(format!(
+                    "`{short}` is already a schedule, so it can't be an alias"
+                )));
+            }
+            let mut aliases = Aliases::read(store)?;
+            aliases.0.insert(short.clone(), target);
+            let resolved = aliases.resolve(&short)?.to_string();
+            open_named::<RegularSchedule>(store, &resolved)?;
+            aliases.write(store)?;
+        }
+        Action::Unalias(short) => {
+            let _lock = store.lock()?;
+            let mut aliases = Aliases::read(store)?;
+            if aliases.0.remove(&short).is_none() {
+                return Err(HrtError::Usage(format!("there is no alias `{short}`")));
+            }
+            aliases.write(store)?;
+        }
+        Action::SetWebhook { name, url, force } => {
+            if let Some(url) = &url {
+                webhook::Url::parse(url).map_err(|error| HrtError::Usage(error.to_string()))?;
+            }
+            let _lock = store.lock()?;
+            let mut schedule: RegularSchedule = open_named(store, &name)?;
+            check_unprotected(&name, &schedule, force)?;
+            schedule.set_webhook(url);
+            save(store, flags, &name, &schedule)?;
+        }
+        Action::Tag { name, tags, force } => {
+            let _lock = store.lock()?;
+            let mut schedule: RegularSchedule = open_named(store, &name)?;
+            check_unprotected(&name, &schedule, force)?;
+            schedule.set_tags(tags)?;
+            save(store, flags, &name, &schedule)?;
+        }
+        Action::SetPriority {
+            name,
+            priority,
+            force,
+        } => {
+            let _lock = store.lock()?;
+            let mut schedule: RegularSchedule = open_named(store, &name)?;
+            check_unprotected(&name, &schedule, force)?;
+            schedule.set_priority(priority)?;
+            save(store, flags, &name, &schedule)?;
+        }
+        Action::Next(None) if flags.countdown => {
+            return Err(HrtError::Usage(
+                "`--countdown` needs the name of a schedule".to_string(),
+            ));
+        }
+        Action::Next(Some(name)) if flags.countdown => countdown(store, flags, &name)?,
+        Action::Next(None) => {
+            let now = Utc::now();
+            let (schedules, _) = load_indexed(store, flags)?;
+            let Some(soonest) = schedules.iter().map(|(_, entry)| entry.info.next).min() else {
+                flags.notice("nothing scheduled");
+                return Ok(ExitCode::Success);
+            };
+            let mut tied: Vec<_> = schedules
+                .iter()
+                .filter(|(_, entry)| entry.info.next == soonest)
+                .map(|(name, _)| name.as_str())
+                .collect();
+            tied.sort();
+            let delta = soonest.signed_duration_since(now);
+            for name in tied {
+                if flags.porcelain {
+                    println!(
+                        "{}",
+                        porcelain::line(&[
+                            name.to_string(),
+                            flags.display.porcelain_next(&soonest),
+                            flags.display.porcelain_duration(&delta),
+                        ])
+                    );
+                } else {
+                    println!(
+                        "`{name}` at {} ({})",
+                        flags.display.datetime(&soonest),
+                        flags.display.relative(delta)
+                    );
+                }
+            }
+        }
+        Action::Next(Some(name)) => {
+            let next = read_named(store, &name, |reader| reader.read_next())?;
+            let delta = next.signed_duration_since(Utc::now());
+            if flags.porcelain {
+                println!(
+                    "{}",
+                    porcelain::line(&[
+                        flags.display.porcelain_next(&next),
+                        flags.display.porcelain_duration(&delta)
+                    ])
+                );
+            } else {
+                println!(
+                    "{} ({})",
+                    flags.display.datetime(&next),
+                    flags.display.relative(delta)
+                );
+            }
+        }
+        Action::Due(name) => {
+            let now = Utc::now();
+            let schedules: Vec<(String, DateTime<Utc>)> = match name {
+                Some(name) => vec![(
+                    name.clone(),
+                    read_named(store, &name, |reader| reader.read_next())?,
+                )],
+                None => load_indexed(store, flags)?
+                    .0
+                    .into_iter()
+                    .map(|(name, entry)| (name, entry.info.next))
+                    .collect(),
+            };
+            let mut due: Vec<_> = schedules
+                .into_iter()
+                .filter(|(_, next)| *next <= now)
+                .collect();
+            // soonest first, as `SortKey::Next` orders them
+            due.sort_by(|(a_name, a), (b_name, b)| (a, a_name).cmp(&(b, b_name)));
+            for (name, next) in &due {
+                let delta = next.signed_duration_since(now);
+                if flags.porcelain {
+                    println!(
+                        "{}",
+                        porcelain::line(&[
+                            name.clone(),
+                            flags.display.porcelain_next(next),
+                            flags.display.porcelain_duration(&delta),
+                        ])
+                    );
+                } else {
+                    println!("`{name}` is due ({})", flags.display.relative(delta));
+                }
+                notify::due(flags, name);
+            }
+            if !due.is_empty() {
+                return Ok(ExitCode::Due);
+            }
+        }
+        Action::Forecast { name, count } => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let now = Utc::now();
+            for occurrence in schedule.occurrences().take(count) {
+                let delta = occurrence.signed_duration_since(now);
+                if flags.porcelain {
+                    println!(
+                        "{}",
+                        porcelain::line(&[
+                            flags.display.porcelain_next(&occurrence),
+                            flags.display.porcelain_duration(&delta)
+                        ])
+                    );
+                } else {
+                    println!(
+                        "{} ({})",
+                        flags.display.datetime(&occurrence),
+                        flags.display.relative(delta)
+                    );
+                }
+            }
+        }
+        Action::ForecastAll {
+            within,
+            max_per_schedule,
+        } => {
+            let now = Utc::now();
+            let end = now
+                .checked_add_signed(within)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC);
+            let (schedules, _) = load_reporting(store, flags)?;
+            let streams = schedules.iter().map(|(name, schedule)| {
+                let upcoming = schedule
+                    .occurrences()
+                    .skip_while(move |occurrence| *occurrence < now)
+                    .take_while(move |occurrence| *occurrence <= end)
+                    .take(max_per_schedule);
+                (&name[..], upcoming)
+            });
+            let mut any = false;
+            for (name, occurrence) in listing::merge(streams) {
+                any = true;
+                let delta = occurrence.signed_duration_since(now);
+                if flags.porcelain {
+                    println!(
+                        "{}",
+                        porcelain::line(&[
+                            name.to_string(),
+                            flags.display.porcelain_next(&occurrence),
+                            flags.display.porcelain_duration(&delta),
+                        ])
+                    );
+                } else {
+                    println!(
+                        "`{name}` at {} ({})",
+                        flags.display.datetime(&occurrence),
+                        flags.display.relative(delta)
+                    );
+                }
+            }
+            if !any {
+                flags.notice(format!(
+                    "nothing occurs within {}",
+                    flags.display.interval(within)
+                ));
+            }
+        }
+        Action::Show(name) => {
+            let (schedule, trailing): (RegularSchedule, _) = open_named_trailing(store, &name)?;
+            if trailing > 0 {
+                flags.notice(format!(
+                    "schedule `{name}` is followed by {} in its file, so it may be corrupt",
+                    plural(trailing as i64, "unexpected trailing byte")
+                ));
+            }
+            let now = Utc::now();
+            let delta = schedule.next.signed_duration_since(now);
+            let progress = Progress::of(schedule.next, schedule.interval, now);
+            if flags.porcelain {
+                println!(
+                    "{}",
+                    porcelain::line(&[
+                        name,
+                        flags.display.porcelain_next(&schedule.next),
+                        flags.display.porcelain_duration(&delta),
+                        flags.display.porcelain_duration(&schedule.interval),
+                        format!("{:.0}", progress.fraction * 100.0),
+                    ])
+                );
+            } else {
+                println!("schedule `{name}`");
+                println!(
+                    "next:     {}, {}",
+                    flags.display.dual_datetime(&schedule.next, &Local),
+                    flags.display.relative(delta)
+                );
+                println!("interval: {}", flags.display.interval(schedule.interval));
+                println!("progress: {} {}", progress.percent(), progress.bar());
+                if let Some(bound) = schedule.bound() {
+                    match schedule.finishes_at() {
+                        Some(finish) => println!(
+                            "count:    {bound}, finishing {} if kept to the interval",
+                            flags.display.dual_datetime(&finish, &Local)
+                        ),
+                        None => println!("count:    {bound}, finished"),
+                    }
+                }
+                if let Some(step) = schedule.history().last() {
+                    println!(
+                        "stepped:  {}",
+                        flags.display.dual_datetime(&step.at, &Local)
+                    );
+                }
+                if schedule.total_steps() > 0 {
+                    println!(
+                        "steps:    {} in total, {} in the history",
+                        schedule.total_steps(),
+                        schedule.history().len()
+                    );
+                }
+                if let Some(priority) = schedule.priority() {
+                    println!("priority: {priority}");
+                }
+                if let Some(url) = schedule.webhook() {
+                    println!("webhook:  {url}");
+                }
+                if !schedule.tags().is_empty() {
+                    println!("tags:     {}", schedule.tags().join(", "));
+                }
+                if let Some(note) = schedule.note() {
+                    println!("note:     {note}");
+                }
+                if schedule.protected() {
+                    println!("protected, so only `step` changes it without --force");
+                }
+            }
+        }
+        Action::Diff { old, new } => {
+            let (a, b): (RegularSchedule, RegularSchedule) =
+                (open_named(store, &old)?, open_named(store, &new)?);
+            let differences = diff::diff(&a, &b);
+            let color = flags.color.enabled() && !flags.porcelain;
+            if !flags.porcelain {
+                if differences.is_empty() {
+                    println!("`{old}` and `{new}` are identical");
+                } else {
+                    println!("{}", style::paint(format!("--- {old}"), style::BOLD, color));
+                    println!("{}", style::paint(format!("+++ {new}"), style::BOLD, color));
+                }
+            }
+            for difference in differences {
+                if flags.porcelain {
+                    println!(
+                        "{}",
+                        porcelain::line(&[
+                            difference.field.to_string(),
+                            difference.old,
+                            difference.new
+                        ])
+                    );
+                    continue;
+                }
+                let note = match difference.field {
+                    "next" => {
+                        let delta = b.next.signed_duration_since(a.next);
+                        let direction = if delta < TimeDelta::zero() {
+                            "earlier"
+                        } else {
+                            "later"
+                        };
+                        format!(" ({} {direction})", flags.display.interval(delta.abs()))
+                    }
+                    _ => String::new(),
+                };
+                let field = difference.field;
+                let old = format!("-{field}: {}", difference.old);
+                let new = format!("+{field}: {}{note}", difference.new);
+                println!("{}", style::paint(old, style::RED, color));
+                println!("{}", style::paint(new, style::GREEN, color));
+            }
+        }
+        Action::Count => {
+            let now = Utc::now();
+            let (mut schedules, unreadable) = load_indexed(store, flags)?;
+            schedules.retain(|(_, entry)| flags.window.contains(&entry.info, now));
+            let summary = Summary::of_infos(
+                schedules
+                    .iter()
+                    .map(|(name, entry)| (&name[..], &entry.info)),
+                unreadable,
+                now,
+            );
+            if flags.porcelain {
+                println!(
+                    "{}",
+                    porcelain::line(&[
+                        summary.total.to_string(),
+                        summary.overdue.to_string(),
+                        summary.unreadable.to_string(),
+                    ])
+                );
+            } else {
+                println!(
+                    "{}",
+                    summary.describe(|delta| flags.display.relative(delta), now)
+                );
+            }
+            if flags.fail_if_overdue && summary.overdue > 0 {
+                return Ok(ExitCode::Due);
+            }
+        }
+        Action::History {
+            name,
+            csv: _,
+            prune: Some(prune),
+        } => {
+            let _lock = store.lock()?;
+            let mut schedule: RegularSchedule = open_named(store, &name)?;
+            let keep = prune.keep.unwrap_or(flags.history_cap);
+            let removed = schedule.prune_history(keep, prune.before);
+            if removed > 0 {
+                save(store, flags, &name, &schedule)?;
+            }
+            if flags.porcelain {
+                println!("{removed}");
+            } else if removed == 0 {
+                println!("nothing to prune from `{name}`");
+            } else {
+                println!(
+                    "removed {} from the history of `{name}`, keeping {} of {} in total",
+                    plural(removed as i64, "step"),
+                    schedule.history().len(),
+                    schedule.total_steps()
+                );
+            }
+        }
+        Action::History {
+            name,
+            csv,
+            prune: None,
+        } => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let now = Utc::now();
+            if csv && !flags.no_header {
+                print!("{}", csv::row(&["name", "stepped_rfc3339", "note"]));
+            }
+            if schedule.history().is_empty() && !csv {
+                flags.notice(format!("`{name}` has no recorded steps"));
+            }
+            for step in schedule.history() {
+                let note = step.note.as_deref().unwrap_or_default();
+                if csv {
+                    print!(
+                        "{}",
+                        csv::row(&[&name, &porcelain::timestamp(&step.at), note])
+                    );
+                } else if flags.porcelain {
+                    // notes are left out, to keep the porcelain format stable; the CSV has them
+                    println!("{}", porcelain::timestamp(&step.at));
+                } else {
+                    let delta = step.at.signed_duration_since(now);
+                    print!(
+                        "{} ({})",
+                        flags.display.datetime(&step.at),
+                        flags.display.relative(delta)
+                    );
+                    match &step.note {
+                        Some(note) => println!(": {note}"),
+                        None => println!(),
+                    }
+                }
+            }
+        }
+        Action::ExportHistory { name, json } => {
+            let schedules = match name {
+                Some(name) => vec![(name.clone(), open_named(store, &name)?)],
+                None => load_reporting(store, flags)?.0,
+            };
+            if json {
+                println!("[");
+            } else if !flags.no_header {
+                print!("{}", csv::row(&csv::STEP_HEADER));
+            }
+            // each row is written as soon as it's made, so that nothing holds the whole export
+            let mut first = true;
+            for (name, schedule) in &schedules {
+                let lateness = drift::lateness(&schedule.stepped_at(), schedule.satisfied());
+                for (step, lateness) in schedule.history().iter().zip(lateness) {
+                    if json {
+                        let separator = if first { "" } else { "," };
+                        println!("{separator}{}", json::step(name, step, lateness));
+                    } else {
+                        print!("{}", csv::step(name, step, lateness));
+                    }
+                    first = false;
+                }
+            }
+            if json {
+                println!("]");
+            }
+        }
+        Action::Drift(name) => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let drift = Drift::of(&schedule.stepped_at(), schedule.satisfied());
+            let untracked = schedule.history().len().saturating_sub(drift.compared);
+            if untracked > 0 {
+                flags.notice(format!(
+                    "{untracked} older step(s) of `{name}` have no recorded occurrence and are left out"
+                ));
+            }
+            if flags.porcelain {
+                let seconds = |delta: Option<TimeDelta>| {
+                    delta
+                        .map(|delta| flags.display.porcelain_duration(&delta))
+                        .unwrap_or_default()
+                };
+                println!(
+                    "{}",
+                    porcelain::line(&[
+                        drift.compared.to_string(),
+                        drift.early.to_string(),
+                        seconds(drift.mean),
+                        seconds(drift.median),
+                        seconds(drift.max),
+                    ])
+                );
+            } else if drift.compared == 0 {
+                println!("`{name}` has no steps to compare");
+            } else if drift.compared < drift::MIN_COMPARED {
+                println!(
+                    "`{name}`: only {} compared, which is not enough data",
+                    plural(drift.compared as i64, "step")
+                );
+            } else {
+                println!(
+                    "`{name}`: {} compared, {} of them early ({:.0}%)",
+                    plural(drift.compared as i64, "step"),
+                    drift.early,
+                    drift.early_fraction() * 100.0
+                );
+                if let (Some(mean), Some(median), Some(p90), Some(max)) =
+                    (drift.mean, drift.median, drift.p90, drift.max)
+                {
+                    println!(
+                        "late by {} on average, {} at the median, {} at the 90th percentile, and \
+                         {} at most",
+                        flags.display.interval(mean),
+                        flags.display.interval(median),
+                        flags.display.interval(p90),
+                        flags.display.interval(max)
+                    );
+                }
+            }
+        }
+        Action::Streak(name) => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let Streaks {
+                current, longest, ..
+            } = streaks(&schedule, Utc::now());
+            if flags.porcelain {
+                let fields = |streak: Option<Streak>| match streak {
+                    Some(streak) => [
+                        streak.length.to_string(),
+                        porcelain::timestamp(&streak.first),
+                        porcelain::timestamp(&streak.last),
+                    ],
+                    None => ["0".to_string(), String::new(), String::new()],
+                };
+                let mut line = fields(current).to_vec();
+                line.extend(fields(longest));
+                println!("{}", porcelain::line(&line));
+            } else {
+                let describe = |streak: Option<Streak>| match streak {
+                    Some(streak) => format!(
+                        "{}, from {} to {}",
+                        plural(streak.length as i64, "occurrence"),
+                        flags.display.datetime(&streak.first),
+                        flags.display.datetime(&streak.last)
+                    ),
+                    None => "none".to_string(),
+                };
+                println!("current: {}", describe(current));
+                println!("longest: {}", describe(longest));
+            }
+        }
+        Action::Heatmap {
+            name,
+            by_hour,
+            ascii,
+        } => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let stepped = schedule.stepped_at();
+            if stepped.is_empty() {
+                flags.notice(format!("`{name}` has no recorded steps to map yet"));
+                return Ok(ExitCode::Success);
+            }
+            let (labels, counts) = match by_hour {
+                true => (
+                    heatmap::hour_labels(),
+                    heatmap::by_hour(&stepped, &Local).to_vec(),
+                ),
+                false => (
+                    heatmap::weekday_labels(),
+                    heatmap::by_weekday(&stepped, &Local).to_vec(),
+                ),
+            };
+            if flags.porcelain {
+                for (label, count) in labels.into_iter().zip(counts) {
+                    println!("{}", porcelain::line(&[label, count.to_string()]));
+                }
+            } else {
+                print!("{}", heatmap::render(&labels, &counts));
+                let hours = heatmap::by_hour(&stepped, &Local);
+                if !by_hour {
+                    let levels = if ascii {
+                        &heatmap::ASCII
+                    } else {
+                        &heatmap::BLOCKS
+                    };
+                    let color = flags.color.enabled();
+                    let modal = heatmap::modal(&hours);
+                    let line: String = heatmap::sparkline(&hours, levels)
+                        .chars()
+                        .enumerate()
+                        .map(|(hour, level)| match Some(hour) == modal {
+                            true => style::paint(level, style::YELLOW, color),
+                            false => level.to_string(),
+                        })
+                        .collect();
+                    println!();
+                    println!("by hour  |{line}|");
+                    println!("          0     6     12    18");
+                    if let Some(modal) = modal {
+                        println!("most steps are made at {modal:02}:00 to {modal:02}:59");
+                    }
+                }
+                let scheduled = schedule.next.with_timezone(&Local).format("%H:%M");
+                let whole_days = schedule.interval.num_milliseconds()
+                    % TimeDelta::days(1).num_milliseconds()
+                    == 0;
+                match whole_days {
+                    true => println!("scheduled at {scheduled}"),
+                    // the time of day moves with every occurrence
+                    false => println!("next scheduled at {scheduled}"),
+                }
+            }
+        }
+        Action::Summary {
+            since,
+            until,
+            month,
+            json,
+        } => {
+            let now = Utc::now();
+            let until = until.unwrap_or(now);
+            let since = match since {
+                Some(since) => since,
+                None => {
+                    let local = until.with_timezone(&Local);
+                    match month {
+                        true => adherence::month_start(&local),
+                        false => adherence::week_start(&local),
+                    }
+                    .ok_or_else(|| {
+                        HrtError::Usage("the start of that period can't be represented".to_string())
+                    })?
+                }
+            };
+            if since >= until {
+                return Err(HrtError::Usage(
+                    "the summary has to start before it ends".to_string(),
+                ));
+            }
+            let (schedules, _) = load_reporting(store, flags)?;
+            let summary = adherence::Summary::of(&schedules, since, until);
+            if json {
+                println!("{}", json::summary(&summary));
+                return Ok(ExitCode::Success);
+            }
+            if flags.porcelain {
+                return Err(HrtError::Usage(
+                    "`summary` has no porcelain output; use `--json` instead".to_string(),
+                ));
+            }
+            print!("{}", summary.render(&flags.display, flags.color.enabled()));
+        }
+        Action::Report { since, json } => {
+            let now = Utc::now();
+            if since > now {
+                return Err(HrtError::Usage(
+                    "`--since` is in the future, so there is nothing to report".to_string(),
+                ));
+            }
+            let (schedules, _) = load_reporting(store, flags)?;
+            let report = adherence::Report::of(&schedules, since, now);
+            if json {
+                println!("{}", json::report(&report));
+            } else if flags.porcelain {
+                print!("{}", report.porcelain());
+            } else {
+                print!("{}", report.render(!flags.no_header, flags.color.enabled()));
+            }
+        }
+        Action::Metrics { output } => {
+            let (schedules, unreadable) = load_reporting(store, flags)?;
+            let text = metrics::render(&schedules, unreadable, Utc::now());
+            match output {
+                Some(path) => store::write_atomic(Path::new(&path), text.as_bytes())?,
+                None => print!("{text}"),
+            }
+        }
+        Action::Cal { name, months } => {
+            let schedules = match name {
+                Some(name) => vec![(name.clone(), open_named(store, &name)?)],
+                None => {
+                    let mut schedules = load_reporting(store, flags)?.0;
+                    schedules.sort_by(|(a, _), (b, _)| a.cmp(b));
+                    schedules
+                }
+            };
+            cal(&schedules, months, flags);
+        }
+        Action::Watch => watch(store, hooks, flags)?,
+        Action::Tui => tui(store, hooks, flags)?,
+        Action::Edit { name, force } => edit(store, &name, force, flags)?,
+        Action::Serve { addr, allow_step } => serve(store, hooks, flags, &addr, allow_step)?,
+        Action::Export { format, name } => {
+            let mut schedules = match name {
+                Some(name) => vec![(name.clone(), open_named(store, &name)?)],
+                None => load_reporting(store, flags)?.0,
+            };
+            schedules.sort_by(|(a, _), (b, _)| a.cmp(b));
+            match format {
+                ExportFormat::ICal => print!("{}", ical::export(&schedules, Utc::now())),
+                ExportFormat::Csv => {
+                    let now = Utc::now();
+                    if !flags.no_header {
+                        print!("{}", csv::row(&csv::SCHEDULE_HEADER));
+                    }
+                    for (name, schedule) in &schedules {
+                        print!("{}", csv::schedule(name, schedule, now));
+                    }
+                }
+            }
+        }
+        Action::Import {
+            format,
+            path,
+            overwrite,
+        } => {
+            let text = fs::read_to_string(&path).map_err(|error| HrtError::io(&path, error))?;
+            let events = match format {
+                ExportFormat::ICal => ical::import(&text),
+                ExportFormat::Csv => {
+                    return Err(HrtError::Usage(
+                        "`import` only reads iCalendar files, with `--ical`".to_string(),
+                    ));
+                }
+            };
+            let _lock = store.lock()?;
+            let mut taken: HashSet<String> = store.list()?.into_iter().collect();
+            for event in events {
+                let (name, result) = match event {
+                    Ok((name, _)) if taken.contains(&name) && !overwrite => (
+                        name,
+                        Err("a schedule with this name already exists".to_string()),
+                    ),
+                    // import has no --force, so protected schedules are never replaced
+                    Ok((name, _))
+                        if taken.contains(&name)
+                            && open_named(store, &name)
+                                .is_ok_and(|existing: RegularSchedule| existing.protected()) =>
+                    {
+                        (
+                            name,
+                            Err("the schedule with this name is protected".to_string()),
+                        )
+                    }
+                    Ok((name, schedule)) => {
+                        save(store, flags, &name, &schedule)?;
+                        fire(hooks, flags, hooks::Event::New, &name, &schedule);
+                        taken.insert(name.clone());
+                        (name, Ok(()))
+                    }
+                    Err(ical::Skipped { summary, reason }) => (
+                        summary.unwrap_or_else(|| "(untitled)".to_string()),
+                        Err(reason),
+                    ),
+                };
+                match (flags.porcelain, result) {
+                    (true, Ok(())) => println!(
+                        "{}",
+                        porcelain::line(&[name, "imported".into(), String::new()])
+                    ),
+                    (true, Err(reason)) => {
+                        println!("{}", porcelain::line(&[name, "skipped".into(), reason]))
+                    }
+                    (false, Ok(())) => println!("imported `{name}`"),
+                    (false, Err(reason)) => flags.notice(format!("skipped `{name}`: {reason}")),
+                }
+            }
+        }
+        Action::Apply {
+            path,
+            reset_changed,
+            prune,
+            dry_run,
+            force,
+        } => {
+            let routines = read_routines(&path)?;
+            let _lock = store.lock()?;
+            let (existing, _) = load_reporting(store, flags)?;
+            let names = store.list()?;
+            // a schedule that can't be read would otherwise look missing and be replaced
+            if let Some((wanted, _)) = routines.iter().find(|(wanted, _)| {
+                !existing.iter().any(|(name, _)| *name == wanted.name)
+                    && names.contains(&wanted.name)
+            }) {
+                return Err(HrtError::Usage(format!(
+                    "`{}` exists but can't be read, so it can't be applied",
+                    wanted.name
+                )));
+            }
+            let changes = reconcile::plan(routines.iter().map(|(wanted, _)| wanted), &existing);
+            for (name, change) in &changes {
+                let changing = match change {
+                    Change::Update => true,
+                    Change::Extra => prune,
+                    Change::Create | Change::Keep => false,
+                };
+                if let Some((_, schedule)) = existing.iter().find(|(other, _)| other == name)
+                    && changing
+                {
+                    check_unprotected(name, schedule, force)?;
+                }
+            }
+            let would = if dry_run { "would " } else { "" };
+            for (name, change) in changes {
+                if flags.porcelain {
+                    let change = match change {
+                        Change::Extra if prune => "prune",
+                        change => change.name(),
+                    };
+                    println!(
+                        "{}",
+                        porcelain::line(&[name.to_string(), change.to_string()])
+                    );
+                }
+                let routine = routines.iter().find(|(wanted, _)| wanted.name == name);
+                match (change, routine) {
+                    (Change::Keep, _) => {}
+                    (Change::Create, Some((wanted, start))) => {
+                        if !flags.porcelain {
+                            println!(
+                                "{would}create `{name}`, every {}",
+                                flags.display.interval(wanted.interval)
+                            );
+                        }
+                        if dry_run {
+                            continue;
+                        }
+                        let mut builder = RegularSchedule::builder()
+                            .start(start.resolve(store, name, wanted.interval)?)
+                            .interval(wanted.interval);
+                        if let Some(priority) = wanted.priority {
+                            builder = builder.priority(priority);
+                        }
+                        let schedule = builder.build()?;
+                        save(store, flags, name, &schedule)?;
+                        fire(hooks, flags, hooks::Event::New, name, &schedule);
+                    }
+                    (Change::Update, Some((wanted, start))) => {
+                        let mut schedule: RegularSchedule = open_named(store, name)?;
+                        if !flags.porcelain {
+                            let mut changed = Vec::new();
+                            if wanted.interval != schedule.interval {
+                                changed.push(format!(
+                                    "every {} instead of {}",
+                                    flags.display.interval(wanted.interval),
+                                    flags.display.interval(schedule.interval)
+                                ));
+                            }
+                            if let Some(priority) = wanted.priority
+                                && schedule.priority() != Some(priority)
+                            {
+                                changed.push(format!("priority {priority}"));
+                            }
+                            if reset_changed {
+                                changed.push("starting over".to_string());
+                            }
+                            println!("{would}update `{name}`: {}", changed.join(", "));
+                        }
+                        if dry_run {
+                            continue;
+                        }
+                        if reset_changed {
+                            schedule.next = start.resolve(store, name, wanted.interval)?;
+                        }
+                        schedule.interval = wanted.interval;
+                        schedule.set_priority(wanted.priority.or(schedule.priority()))?;
+                        save(store, flags, name, &schedule)?;
+                    }
+                    (Change::Create | Change::Update, None) => {}
+                    (Change::Extra, _) if !prune => {
+                        if !flags.porcelain {
+                            println!("`{name}` isn't in `{path}`; pass --prune to delete it");
+                        }
+                    }
+                    (Change::Extra, _) => {
+                        if !flags.porcelain {
+                            println!("{would}delete `{name}`");
+                        }
+                        if dry_run {
+                            continue;
+                        }
+                        let schedule: RegularSchedule = open_named(store, name)?;
+                        store.delete(name)?;
+                        fire(hooks, flags, hooks::Event::Delete, name, &schedule);
+                    }
+                }
+            }
+        }
+        Action::Cron { name, command } => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            // cron reads its fields in the local timezone
+            let next = schedule.next.with_timezone(&Local).naive_local();
+            let expression = cron::expression(next, schedule.interval).ok_or_else(|| {
+                HrtError::Usage(format!(
+                    "an interval of {} can't be expressed in cron, which only repeats within an \
+                     hour, a day, or a week; use `hrtracker watch` instead",
+                    flags.display.interval(schedule.interval)
+                ))
+            })?;
+            let command = command
+                .unwrap_or_else(|| format!("{} due {}", program(), cron::shell_quote(&name)));
+            println!("{}", cron::line(&expression, &command));
+        }
+        Action::Remind {
+            name,
+            before,
+            command,
+            schedule: run_at,
+        } => {
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            let at = schedule
+                .next_at()
+                .checked_sub_signed(before)
+                .ok_or_else(|| HrtError::Usage("the lead time is too long".to_string()))?;
+            if at <= Utc::now() {
+                return Err(HrtError::Usage(format!(
+                    "the reminder for `{name}` would be at {}, which has already passed",
+                    flags.display.datetime(&at)
+                )));
+            }
+            // `at` reads times in the local timezone
+            let time = at::time(at.with_timezone(&Local).naive_local());
+            let command = command.unwrap_or_else(|| {
+                format!("{} --notify due {}", program(), cron::shell_quote(&name))
+            });
+            if run_at {
+                let mut child = process::Command::new("at")
+                    .args(["-t", &time])
+                    .stdin(process::Stdio::piped())
+                    .spawn()?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    writeln!(stdin, "{command}")?;
+                }
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(io::Error::other(format!("`at` exited with {status}")).into());
+                }
+            }
+            if flags.porcelain {
+                println!(
+                    "{}",
+                    porcelain::line(&[porcelain::timestamp(&at), at::invocation(&command, &time)])
+                );
+            } else if run_at {
+                println!(
+                    "reminding about `{name}` at {}",
+                    flags.display.datetime(&at)
+                );
+            } else {
+                println!("{}", at::invocation(&command, &time));
+            }
+        }
+        Action::Systemd {
+            name,
+            command,
+            stdout,
+            user,
+        } => {
+            let unit = systemd::unit_name(&name)?;
+            let schedule: RegularSchedule = open_named(store, &name)?;
+            // `OnCalendar=` is in the local timezone unless it says otherwise
+            let next = schedule.next.with_timezone(&Local).naive_local();
+            let command = command.unwrap_or_else(|| {
+                format!("{} --notify due {}", program(), cron::shell_quote(&name))
+            });
+            // the service won't inherit the shell's environment, so the data directory is passed on
+            let dir = env::var("HRTRACKER_DIR").unwrap_or_default();
+            let environment: &[(&str, &str)] = match &dir[..] {
+                "" => &[],
+                dir => &[("HRTRACKER_DIR", dir)],
+            };
+            let units = [
+                (
+                    format!("{unit}.timer"),
+                    systemd::timer(&name, next, schedule.interval),
+                ),
+                (
+                    format!("{unit}.service"),
+                    systemd::service(&name, &command, environment),
+                ),
+            ];
+            if stdout {
+                for (index, (file, text)) in units.iter().enumerate() {
+                    if index > 0 {
+                        println!();
+                    }
+                    print!("# {file}\n{text}");
+                }
+                return Ok(ExitCode::Success);
+            }
+            let folder = match user {
+                true => match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+                    Some(config) => PathBuf::from(config).join("systemd/user"),
+                    None => PathBuf::from(env::var_os("HOME").ok_or(HrtError::NoHome)?)
+                        .join(".config/systemd/user"),
+                },
+                false => PathBuf::from("/etc/systemd/system"),
+            };
+            fs::create_dir_all(&folder).map_err(|error| HrtError::io(&folder, error))?;
+            for (file, text) in &units {
+                let path = folder.join(file);
+                fs::write(&path, text).map_err(|error| HrtError::io(&path, error))?;
+                if flags.porcelain {
+                    println!("{}", path.display());
+                } else {
+                    println!("wrote {}", path.display());
+                }
+            }
+            let systemctl = if user {
+                "systemctl --user"
+            } else {
+                "systemctl"
+            };
+            flags.notice(format!(
+                "enable it with `{systemctl} daemon-reload && {systemctl} enable --now {unit}.timer`"
+            ));
+        }
+        Action::Batch { stop_on_error } => return batch(store, hooks, flags, stop_on_error),
+        Action::Help(None) => print!("{HELP}"),
+        Action::Introspect => println!("{}", Action::introspect()),
+        Action::Names => {
+            let mut names = store.list()?;
+            names.sort();
+            for name in names {
+                println!("{name}");
+            }
+        }
+        Action::Help(Some(action)) => {
+            let help = Action::help(&action);
+            if help.is_empty() {
+                return Err(Action::unknown(&action));
+            }
+            for line in help {
+                println!("{}", line.strip_prefix("    ").unwrap_or(line));
+            }
+        }
+    }
+    Ok(ExitCode::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use hrtracker::store::MemStore;
+
+    use super::*;
+
+    /// Runs the command line `line` against `store`, as `run` would after taking the flags.
+    fn execute_line(store: &(impl Store + Sync), line: &str) -> Result<ExitCode> {
+        let action = Action::get(&mut split_words(line)?.into_iter())?;
+        execute(store, &(), action, &Flags::default())
+    }
+
+    fn ok(store: &(impl Store + Sync), line: &str) {
+        if let Err(error) = execute_line(store, line) {
+            panic!("`{line}` failed: {error}");
+        }
+    }
+
+    fn open(store: &impl Store, name: &str) -> RegularSchedule {
+        open_named(store, name).unwrap()
+    }
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        parse_datetime(repr).unwrap()
+    }
+
+    /// A store with `water`, overdue since the start of 2020 and repeating daily.
+    fn watered() -> MemStore {
+        let store = MemStore::new();
+        ok(&store, "new water 2020-01-01T00:00:00Z 1d+00");
+        store
+    }
+
+    #[test]
+    fn new_creates_a_schedule() {
+        let store = watered();
+        let water = open(&store, "water");
+        assert_eq!(water.next, date("2020-01-01T00:00:00Z"));
+        assert_eq!(water.interval, TimeDelta::days(1));
+        assert_eq!(water.total_steps(), 0);
+        assert!(matches!(
+            execute_line(&store, "new pills now"),
+            Err(HrtError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn step_advances_and_records_the_step() {
+        let store = watered();
+        ok(
+            &store,
+            "step water --at 2020-01-01T10:00:00Z --note watered",
+        );
+        let water = open(&store, "water");
+        assert_eq!(water.next, date("2020-01-02T00:00:00Z"));
+        assert_eq!(water.stepped_at(), [date("2020-01-01T10:00:00Z")]);
+        assert_eq!(water.history()[0].note.as_deref(), Some("watered"));
+        assert_eq!(water.satisfied(), [date("2020-01-01T00:00:00Z")]);
+        assert!(matches!(
+            execute_line(&store, "step water --at 9999-01-01T00:00:00Z"),
+            Err(HrtError::Usage(_))
+        ));
+        assert!(matches!(
+            execute_line(&store, "step missing"),
+            Err(HrtError::NotFound { name }) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn steps_past_the_count_are_invalid() {
+        let store = MemStore::new();
+        ok(&store, "new once 2020-01-01T00:00:00Z 1d+00 --count 1");
+        ok(&store, "step once");
+        assert!(matches!(
+            execute_line(&store, "step once"),
+            Err(HrtError::InvalidSchedule { .. })
+        ));
+    }
+
+    #[test]
+    fn protected_schedules_need_force() {
+        let store = watered();
+        ok(&store, "protect water");
+        assert!(open(&store, "water").protected());
+        for line in [
+            "delete water",
+            "rename water plants",
+            "tag water daily",
+            "set-priority water 5",
+            "set-webhook water http://example.com/hook",
+            "new water now 1d+00",
+        ] {
+            assert!(
+                matches!(execute_line(&store, line), Err(HrtError::Usage(_))),
+                "`{line}` changed a protected schedule"
+            );
+        }
+        ok(&store, "step water");
+        ok(&store, "tag water daily --force");
+        assert_eq!(open(&store, "water").tags(), ["daily"]);
+        ok(&store, "unprotect water");
+        ok(&store, "delete water");
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rename_moves_the_schedule() {
+        let store = watered();
+        let water = open(&store, "water");
+        ok(&store, "rename water plants");
+        assert_eq!(store.list().unwrap(), ["plants"]);
+        assert_eq!(open(&store, "plants"), water);
+
+        ok(&store, "new water now 1d+00");
+        assert!(matches!(
+            execute_line(&store, "rename water plants"),
+            Err(HrtError::Usage(_))
+        ));
+        ok(&store, "delete water");
+        ok(&store, "rename plants Plants");
+        assert_eq!(store.list().unwrap(), ["Plants"]);
+        assert!(store.load(RENAMING).is_err());
+    }
+
+    #[test]
+    fn settings_are_saved() {
+        let store = watered();
+        ok(&store, "tag water outside daily daily");
+        ok(&store, "set-priority water 5");
+        ok(&store, "set-webhook water http://example.com/hook");
+        let water = open(&store, "water");
+        assert_eq!(water.tags(), ["daily", "outside"]);
+        assert_eq!(water.priority(), Some(5));
+        assert_eq!(water.webhook(), Some("http://example.com/hook"));
+
+        ok(&store, "tag water");
+        ok(&store, "set-priority water");
+        ok(&store, "set-webhook water");
+        let water = open(&store, "water");
+        assert!(water.tags().is_empty());
+        assert_eq!(water.priority(), None);
+        assert_eq!(water.webhook(), None);
+        assert!(execute_line(&store, "set-priority water 6").is_err());
+    }
+
+    #[test]
+    fn history_prune_keeps_the_latest_steps() {
+        let store = watered();
+        for day in 1..=3 {
+            ok(&store, &format!("step water --at 2020-01-0{day}T10:00:00Z"));
+        }
+        ok(&store, "history water --prune --keep 1");
+        let water = open(&store, "water");
+        assert_eq!(water.stepped_at(), [date("2020-01-03T10:00:00Z")]);
+        assert_eq!(water.total_steps(), 3);
+    }
+
+    #[test]
+    fn exit_codes_report_what_was_found() {
+        let store = watered();
+        assert_eq!(execute_line(&store, "due").unwrap(), ExitCode::Due);
+        assert_eq!(execute_line(&store, "list").unwrap(), ExitCode::Success);
+        assert_eq!(execute_line(&store, "verify").unwrap(), ExitCode::Success);
+        assert_eq!(execute_line(&store, "doctor").unwrap(), ExitCode::Success);
+
+        ok(&store, "new Water now 1d+00 --force");
+        assert_eq!(execute_line(&store, "doctor").unwrap(), ExitCode::Generic);
+
+        store.save("broken", b"not a schedule").unwrap();
+        assert_eq!(execute_line(&store, "verify").unwrap(), ExitCode::Corrupt);
+        assert!(matches!(
+            execute_line(&store, "show broken"),
+            Err(HrtError::Decode { .. })
+        ));
+    }
+
+    /// `line` split into words, with the flags in it taken out and applied to `flags`.
+    fn take(flags: &mut Flags, line: &str) -> Result<Vec<String>> {
+        let mut args = split_words(line)?;
+        flags.take(&mut args)?;
+        Ok(args)
+    }
+
+    /// The message of the usage error `checked` gives for `action` with `line`.
+    fn refused(action: &str, line: &str) -> String {
+        match get::checked(action, &mut split_words(line).unwrap().into_iter()) {
+            Err(HrtError::Usage(message)) => message,
+            other => panic!("expected `{action} {line}` to be refused, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn flags_are_taken_before_and_after_the_action() {
+        let mut flags = Flags::default();
+        let args = take(
+            &mut flags,
+            "--porcelain list --precise --soon 02:00 --limit=3",
+        )
+        .unwrap();
+        assert_eq!(args, ["list"]);
+        assert!(flags.porcelain && flags.display.precise);
+        assert_eq!(flags.soon, TimeDelta::hours(2));
+        assert_eq!(flags.limit, Some(3));
+
+        let mut flags = Flags::default();
+        let args = take(&mut flags, "--porcelain=false --precise=true next").unwrap();
+        assert_eq!(args, ["next"]);
+        assert!(!flags.porcelain && flags.display.precise);
+    }
+
+    #[test]
+    fn flags_after_a_double_dash_are_left_alone() {
+        let mut flags = Flags::default();
+        let args = take(&mut flags, "step -- --weird --porcelain").unwrap();
+        assert_eq!(args, ["step", "--", "--weird", "--porcelain"]);
+        assert!(!flags.porcelain);
+
+        let store = MemStore::new();
+        ok(&store, "new -- --weird 2020-01-01T00:00:00Z 1d+00");
+        assert_eq!(open(&store, "--weird").interval, TimeDelta::days(1));
+        ok(&store, "step -- --weird");
+        assert_eq!(open(&store, "--weird").total_steps(), 1);
+    }
+
+    #[test]
+    fn force_is_left_only_for_actions_that_take_it() {
+        for (line, left) in [
+            ("step water --force", &["step", "water", "--force"][..]),
+            ("s water --force", &["s", "water", "--force"]),
+            ("--force delete water", &["delete", "--force", "water"]),
+            ("--force --force rm water", &["rm", "--force", "water"]),
+            ("list --force", &["list"]),
+            ("--force next water", &["next", "water"]),
+        ] {
+            let mut flags = Flags::default();
+            assert_eq!(take(&mut flags, line).unwrap(), left, "{line}");
+            assert!(flags.confirm_clock, "{line}");
+        }
+    }
+
+    #[test]
+    fn flags_that_are_not_global_are_left_for_the_action() {
+        let mut flags = Flags::default();
+        assert_eq!(
+            take(&mut flags, "list --note x").unwrap(),
+            ["list", "--note", "x"]
+        );
+        assert!(refused("list", "--note x").starts_with("`list` doesn't take `--note`"));
+        assert!(matches!(
+            Action::get(&mut split_words("list --note x").unwrap().into_iter()),
+            Err(HrtError::Usage(message)) if message.starts_with("`list` doesn't take `--note`")
+        ));
+
+        let mut flags = Flags::default();
+        assert!(matches!(
+            take(&mut flags, "list --limit 0"),
+            Err(HrtError::Usage(_))
+        ));
+        assert!(matches!(
+            take(&mut flags, "list --limit"),
+            Err(HrtError::Usage(message)) if message == "`--limit` requires a value"
+        ));
+        assert!(matches!(
+            take(&mut flags, "list --precise=maybe"),
+            Err(HrtError::Usage(message)) if message == "`--precise` takes `true` or `false`, not `maybe`"
+        ));
+    }
+
+    #[test]
+    fn actions_check_their_own_flags() {
+        let checked = |action: &str, line: &str| {
+            get::checked(action, &mut split_words(line).unwrap().into_iter()).unwrap()
+        };
+        // a flag's value is never taken for a flag itself
+        assert_eq!(
+            checked("new", "water --start --weird --force"),
+            ["water", "--start", "--weird", "--force"]
+        );
+        assert_eq!(checked("step", "water --at=now"), ["water", "--at=now"]);
+        assert_eq!(checked("step", "-- --weird --bak"), ["--weird", "--bak"]);
+        assert_eq!(
+            refused("step", "water --bak"),
+            "`step` doesn't take `--bak`; it takes `--at`, `--note`, `--allow-future`, `--force`"
+        );
+        assert_eq!(
+            refused("step", "water --bak=1"),
+            refused("step", "water --bak")
+        );
+        assert_eq!(refused("list", "--x"), "`list` doesn't take `--x`");
+    }
+
+    #[test]
+    fn aliases_stand_for_their_actions() {
+        for (alias, action) in [
+            ("ls", "list"),
+            ("add", "new"),
+            ("create", "new"),
+            ("n", "next"),
+            ("s", "step"),
+            ("rm", "delete"),
+            ("del", "delete"),
+        ] {
+            assert_eq!(Action::canonical(alias), action);
+            assert_eq!(Action::canonical(&alias.to_ascii_uppercase()), action);
+            // and `help` lists each next to its action
+            let help = Action::help(action).join("\n");
+            assert!(help.contains(&format!("`{alias}`")), "{help}");
+        }
+        assert_eq!(Action::ALIASES.len(), 7);
+    }
+
+    #[test]
+    fn actions_ignore_case_but_not_prefixes() {
+        let get = |line: &str| Action::get(&mut split_words(line).unwrap().into_iter());
+        assert!(matches!(get("LIST"), Ok(Action::List)));
+        assert!(matches!(get("Ls"), Ok(Action::List)));
+        assert_eq!(Action::canonical("ReName"), "rename");
+        assert!(
+            matches!(get("DeL water"), Ok(Action::Delete { name, force: false }) if name == "water")
+        );
+        // names keep their case even when the action doesn't
+        assert!(matches!(get("RM Water"), Ok(Action::Delete { name, .. }) if name == "Water"));
+        for prefix in ["li", "ste", "d", "lis"] {
+            assert_eq!(Action::canonical(prefix), prefix);
+            assert!(matches!(get(prefix), Err(HrtError::Usage(_))), "{prefix}");
+        }
+    }
+
+    #[test]
+    fn mistyped_actions_suggest_the_closest() {
+        let message = |line: &str| match Action::get(&mut split_words(line).unwrap().into_iter()) {
+            Err(HrtError::Usage(message)) => message,
+            Err(error) => panic!("`{line}` failed with {error}"),
+            Ok(_) => panic!("`{line}` was taken for an action"),
+        };
+        assert_eq!(
+            message("lst"),
+            "unknown action `lst`; did you mean `list` or `ls`?"
+        );
+        assert_eq!(
+            message("LST"),
+            "unknown action `LST`; did you mean `list` or `ls`?"
+        );
+        assert_eq!(
+            message("stepp water"),
+            "unknown action `stepp`; did you mean `step`?"
+        );
+        // with nothing close, every action and alias is listed
+        let garbage = message("xyzzyq");
+        assert!(garbage.starts_with("unknown action `xyzzyq`; the actions are "));
+        for name in Action::names() {
+            assert!(garbage.contains(&format!("`{name}`")), "{name} missing");
+        }
+        // an exact alias needs no suggestion
+        assert!(matches!(
+            Action::get(&mut ["ls".to_string()].into_iter()),
+            Ok(Action::List)
+        ));
+    }
+
+    #[test]
+    fn arguments_leave_out_flags_and_their_values() {
+        assert_eq!(
+            Action::arguments("new <name> [date] [--count <n>] [--force]"),
+            [
+                ("name", "name", false, false),
+                ("date", "datetime", true, false)
+            ]
+        );
+        assert_eq!(
+            Action::arguments("summary [--week|--month|--since <date>] [--json]"),
+            []
+        );
+        assert_eq!(
+            Action::arguments("tag <name> [tag...]"),
+            [("name", "name", false, false), ("tag", "text", true, true)]
+        );
+        assert_eq!(
+            Action::arguments("diff <name> <other>|--bak"),
+            [
+                ("name", "name", false, false),
+                ("other", "name", false, false)
+            ]
+        );
+    }
+
+    /// `watered`, with `w` as an alias of `water`, read through aliases as `run` does.
+    fn aliased() -> Aliased<MemStore> {
+        let store = Aliased::new(watered());
+        ok(&store, "alias w water");
+        store
+    }
+
+    #[test]
+    fn aliases_are_kept_in_the_store() {
+        let store = aliased();
+        assert_eq!(
+            Aliases::read(&store.store).unwrap().0,
+            BTreeMap::from([("w".to_string(), "water".to_string())])
+        );
+        ok(&store, "step w --at 2020-01-01T10:00:00Z");
+        assert_eq!(open(&store, "water").total_steps(), 1);
+        ok(&store, "unalias w");
+        assert!(Aliases::read(&store.store).unwrap().0.is_empty());
+        assert!(matches!(
+            execute_line(&store, "step w"),
+            Err(HrtError::NotFound { name }) if name == "w"
+        ));
+    }
+
+    #[test]
+    fn new_refuses_alias_names() {
+        let store = aliased();
+        assert!(matches!(
+            execute_line(&store, "new w 2030-01-01T00:00:00Z 12"),
+            Err(HrtError::Usage(_))
+        ));
+        let water = open(&store, "water");
+        assert_eq!(water.next, date("2020-01-01T00:00:00Z"));
+        assert_eq!(water.interval, TimeDelta::days(1));
+        assert!(store.store.load("w").is_err());
+    }
+
+    #[test]
+    fn renaming_retargets_aliases() {
+        let store = aliased();
+        ok(&store, "alias ww w");
+        ok(&store, "new pills 2020-01-01T00:00:00Z 12");
+        assert!(matches!(
+            execute_line(&store, "rename pills w"),
+            Err(HrtError::Usage(_))
+        ));
+        ok(&store, "rename water plants");
+        assert_eq!(
+            Aliases::read(&store.store).unwrap().0,
+            BTreeMap::from([
+                ("w".to_string(), "plants".to_string()),
+                ("ww".to_string(), "w".to_string()),
+            ])
+        );
+        assert_eq!(open(&store, "ww").interval, TimeDelta::days(1));
+        // renaming through an alias moves what it stands for
+        ok(&store, "rename w water");
+        assert_eq!(
+            Aliases::read(&store.store).unwrap().0["w"],
+            "water".to_string()
+        );
+        assert!(store.store.load("plants").is_err());
+        assert_eq!(open(&store, "w").interval, TimeDelta::days(1));
+    }
+
+    #[test]
+    fn deleting_removes_aliases() {
+        let store = aliased();
+        ok(&store, "alias ww w");
+        ok(&store, "new pills 2020-01-01T00:00:00Z 12");
+        ok(&store, "alias p pills");
+        ok(&store, "delete ww");
+        assert!(store.store.load("water").is_err());
+        assert_eq!(
+            Aliases::read(&store.store).unwrap().0,
+            BTreeMap::from([("p".to_string(), "pills".to_string())])
+        );
+        ok(&store, "delete pills");
+        assert!(Aliases::read(&store.store).unwrap().0.is_empty());
+    }
 }
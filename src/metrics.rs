@@ -0,0 +1,134 @@
+//! Prometheus text exposition format, for node_exporter's textfile collector.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+
+use crate::schedule::{RegularSchedule, Schedule};
+
+/// `value` escaped for use inside a quoted label value.
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+/// The metrics for `schedules`, plus a count of the `unreadable` schedule files.
+pub fn render(
+    schedules: &[(String, RegularSchedule)],
+    unreadable: usize,
+    now: DateTime<Utc>,
+) -> String {
+    let mut text = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: &[(Option<&str>, String)]| {
+        // writing to a `String` never fails
+        let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} {kind}");
+        for (label, value) in samples {
+            let _ = match label {
+                Some(schedule) => {
+                    writeln!(
+                        text,
+                        "{name}{{name=\"{}\"}} {value}",
+                        escape_label(schedule)
+                    )
+                }
+                None => writeln!(text, "{name} {value}"),
+            };
+        }
+    };
+    let each = |value: &dyn Fn(&RegularSchedule) -> String| -> Vec<(Option<&str>, String)> {
+        schedules
+            .iter()
+            .map(|(name, schedule)| (Some(name.as_str()), value(schedule)))
+            .collect()
+    };
+    let overdue = |schedule: &RegularSchedule| schedule.next_at() < now;
+    family(
+        "hrtracker_schedule_next_timestamp_seconds",
+        "gauge",
+        "When the schedule is next due, in seconds since the Unix epoch.",
+        &each(&|schedule| schedule.next.timestamp().to_string()),
+    );
+    family(
+        "hrtracker_schedule_interval_seconds",
+        "gauge",
+        "How far apart the schedule's occurrences are.",
+        &each(&|schedule| schedule.interval.num_seconds().to_string()),
+    );
+    family(
+        "hrtracker_schedule_overdue",
+        "gauge",
+        "1 if the schedule is overdue, 0 otherwise.",
+        &each(&|schedule| u8::from(overdue(schedule)).to_string()),
+    );
+    family(
+        "hrtracker_schedules_total",
+        "gauge",
+        "How many schedules could be read.",
+        &[(None, schedules.len().to_string())],
+    );
+    let overdue_count = schedules
+        .iter()
+        .filter(|(_, schedule)| overdue(schedule))
+        .count();
+    family(
+        "hrtracker_schedules_overdue",
+        "gauge",
+        "How many schedules are overdue.",
+        &[(None, overdue_count.to_string())],
+    );
+    family(
+        "hrtracker_schedule_errors_total",
+        "gauge",
+        "How many schedule files couldn't be read.",
+        &[(None, unreadable.to_string())],
+    );
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(escape_label("a\\b\"c\nd"), r#"a\\b\"c\nd"#);
+    }
+
+    #[test]
+    fn every_schedule_gets_a_sample() {
+        let next = "2025-03-01T09:00:00Z".parse().unwrap();
+        let schedules = [
+            (
+                "wa\"ter".to_string(),
+                RegularSchedule::create(next, TimeDelta::days(1)),
+            ),
+            (
+                "pills".to_string(),
+                RegularSchedule::create(next + TimeDelta::days(1), TimeDelta::hours(12)),
+            ),
+        ];
+        let text = render(&schedules, 2, "2025-03-01T10:00:00Z".parse().unwrap());
+        assert!(text.starts_with(
+            "# HELP hrtracker_schedule_next_timestamp_seconds When the schedule is next due, \
+             in seconds since the Unix epoch.\n\
+             # TYPE hrtracker_schedule_next_timestamp_seconds gauge\n\
+             hrtracker_schedule_next_timestamp_seconds{name=\"wa\\\"ter\"} 1740819600\n\
+             hrtracker_schedule_next_timestamp_seconds{name=\"pills\"} 1740906000\n"
+        ));
+        for line in [
+            "hrtracker_schedule_interval_seconds{name=\"pills\"} 43200",
+            "hrtracker_schedule_overdue{name=\"wa\\\"ter\"} 1",
+            "hrtracker_schedule_overdue{name=\"pills\"} 0",
+            "hrtracker_schedules_total 2",
+            "hrtracker_schedules_overdue 1",
+            "hrtracker_schedule_errors_total 2",
+        ] {
+            assert!(text.lines().any(|sample| sample == line), "{line}");
+        }
+        assert_eq!(text.matches("# TYPE ").count(), 6);
+    }
+}
@@ -0,0 +1,120 @@
+//! Async counterparts of `store`, for use on a tokio runtime. Only the IO is async; encoding and
+//! decoding still happen synchronously over in-memory buffers.
+
+use std::{future::Future, io::ErrorKind, path::PathBuf};
+
+use tokio::io::AsyncReadExt;
+
+use decent::{Decodable, Encodable};
+
+use crate::{
+    error::{HrtError, Result},
+    schedule::{Schedule, decode_schedule, encode_schedule},
+    store::{DirStore, MemStore, Store},
+};
+
+/// `Store`, with every operation returning a future.
+pub trait AsyncStore {
+    fn list(&self) -> impl Future<Output = Result<Vec<String>>> + Send;
+    fn load(&self, name: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+    fn save(&self, name: &str, bytes: &[u8]) -> impl Future<Output = Result<()>> + Send;
+    fn delete(&self, name: &str) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl AsyncStore for DirStore {
+    async fn list(&self) -> Result<Vec<String>> {
+        let io = |error| HrtError::io(&self.folder, error);
+        let mut entries = tokio::fs::read_dir(&self.folder).await.map_err(io)?;
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(io)? {
+            let is_dir = entry.file_type().await.is_ok_and(|kind| kind.is_dir());
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with('.') && !is_dir {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+    /// Like `store::read_capped`, refusing files over `max_size` bytes.
+    async fn load(&self, name: &str) -> Result<Vec<u8>> {
+        let path = self.path(name);
+        let failed = |error: std::io::Error| match error.kind() {
+            ErrorKind::NotFound => HrtError::NotFound {
+                name: name.to_string(),
+            },
+            _ => HrtError::io(&path, error),
+        };
+        let too_large = |size| HrtError::TooLarge {
+            path: path.clone(),
+            size,
+            limit: self.max_size,
+        };
+        let file = tokio::fs::File::open(&path).await.map_err(failed)?;
+        let size = file.metadata().await.map_err(failed)?.len();
+        if size > self.max_size {
+            return Err(too_large(size));
+        }
+        let mut bytes = Vec::new();
+        file.take(self.max_size.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(failed)?;
+        if bytes.len() as u64 > self.max_size {
+            return Err(too_large(bytes.len() as u64));
+        }
+        Ok(bytes)
+    }
+    async fn save(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path(name);
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|error| HrtError::io(path, error))
+    }
+    async fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path(name);
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|error| match error.kind() {
+                ErrorKind::NotFound => HrtError::NotFound {
+                    name: name.to_string(),
+                },
+                _ => HrtError::io(path, error),
+            })
+    }
+}
+
+/// Memory never blocks, so these just call the `Store` methods.
+impl AsyncStore for MemStore {
+    async fn list(&self) -> Result<Vec<String>> {
+        Store::list(self)
+    }
+    async fn load(&self, name: &str) -> Result<Vec<u8>> {
+        Store::load(self, name)
+    }
+    async fn save(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        Store::save(self, name, bytes)
+    }
+    async fn delete(&self, name: &str) -> Result<()> {
+        Store::delete(self, name)
+    }
+}
+
+/// Opens the schedule called `name` in `store`, like `store::open_named`.
+pub async fn open_async<S: Schedule + Decodable>(store: &impl AsyncStore, name: &str) -> Result<S> {
+    let bytes = store.load(name).await?;
+    decode_schedule(&bytes).map_err(|source| HrtError::Decode {
+        path: PathBuf::from(name),
+        size: bytes.len() as u64,
+        source,
+    })
+}
+
+/// Saves `schedule` as `name` in `store`, like `store::save_named`.
+pub async fn save_async<S: Schedule + Encodable>(
+    store: &impl AsyncStore,
+    name: &str,
+    schedule: &S,
+) -> Result<()> {
+    let bytes = encode_schedule(schedule).map_err(|error| HrtError::io(name, error))?;
+    store.save(name, &bytes).await
+}
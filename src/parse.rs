@@ -0,0 +1,607 @@
+//! Parsers for the dates, times, and intervals accepted on the command line.
+
+use chrono::{
+    DateTime, Days, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc,
+};
+
+use crate::{
+    error::{HrtError, Result},
+    format::{in_range, limits},
+};
+
+/// Midnight (UTC) at the start of the current day.
+pub fn today() -> DateTime<Utc> {
+    Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Splits `all` at the first `delimiter`, returning all of it if the delimiter doesn't occur.
+pub fn try_split_once<'a>(all: &'a str, delimiter: &str) -> (&'a str, Option<&'a str>) {
+    match all.split_once(delimiter) {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (all, None),
+    }
+}
+
+/// Splits `line` into words like a shell would, without any expansion: words are separated by
+/// whitespace, `'...'` is taken literally, `"..."` allows `\"` and `\\` escapes, and a `\` outside
+/// quotes escapes the character after it.
+pub fn split_words(line: &str) -> Result<Vec<String>> {
+    let unterminated = |what: &str| HrtError::Usage(format!("unterminated {what} in `{line}`"));
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            char if char.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(char) => word.push(char),
+                        None => return Err(unterminated("`'`")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => word.push(escaped),
+                            Some(other) => word.extend(['\\', other]),
+                            None => return Err(unterminated("`\"`")),
+                        },
+                        Some(char) => word.push(char),
+                        None => return Err(unterminated("`\"`")),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars.next().ok_or_else(|| unterminated("escape"))?;
+                word.get_or_insert_default().push(escaped);
+            }
+            char => word.get_or_insert_default().push(char),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+// pub struct StringError(pub String);
+// pub fn error_str<T>(str: String) -> Result<T> {
+//     return Err(anyhow::Error::new(StringError(str)));
+// }
+// impl Display for StringError {
+//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//         write!(f, "{}", self.0)
+//     }
+// }
+// impl Error for StringError {}
+
+/// Parses `hh`, `hh:mm`, or `hh:mm:ss`, where the seconds may have a fraction of up to nine
+/// digits, like `00:00:01.250`. Any of these may follow a number of days and `d+`, like
+/// `2d+03:00` for 51 hours. An ISO 8601 duration like `PT6H` or `P1DT30M` is also accepted, as
+/// `IsoDuration` prints them.
+pub fn parse_timedelta(hhmmss: &str) -> Result<TimeDelta> {
+    let fail = |reason: String| HrtError::ParseInterval {
+        input: hhmmss.to_string(),
+        reason,
+    };
+    if hhmmss.starts_with('P') || hhmmss.starts_with("-P") {
+        return parse_iso_duration(hhmmss).map_err(fail);
+    }
+    if let Some((days, clock)) = hhmmss.split_once("d+") {
+        if days.is_empty() || !days.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(fail("expected a number of days before `d+`".to_string()));
+        }
+        let days = days
+            .parse()
+            .ok()
+            .and_then(TimeDelta::try_days)
+            .ok_or_else(|| fail(format!("`{days}` days is too long")))?;
+        // hours are already limited to 23, so `1d+24` can't be mistaken for two days
+        let clock = parse_timedelta(clock).map_err(|error| match error {
+            HrtError::ParseInterval { reason, .. } => fail(reason),
+            error => error,
+        })?;
+        return days
+            .checked_add(&clock)
+            .ok_or_else(|| fail("the interval is too long".to_string()));
+    }
+    let field = |digits: &str, unit: &str, max: u8| {
+        if digits.len() != 2 {
+            return Err(fail(format!(
+                "expected 2 {unit} digits, got {}",
+                digits.len()
+            )));
+        }
+        match digits.parse::<u8>() {
+            Ok(value) if value <= max => Ok(value as i64),
+            Ok(oob) => Err(fail(format!(
+                "`{oob}` out of bounds (expected 0 to {max} {unit}s)"
+            ))),
+            Err(error) => Err(fail(format!("`{digits}` is not a number ({error})"))),
+        }
+    };
+    let (hh, maybe_mmss) = try_split_once(hhmmss, ":");
+    let mut delta = TimeDelta::hours(field(hh, "hour", 23)?);
+
+    let Some(mmss) = maybe_mmss else {
+        return Ok(delta);
+    };
+    let (mm, maybe_ss) = try_split_once(mmss, ":");
+    delta += TimeDelta::minutes(field(mm, "minute", 59)?);
+
+    let Some(ss) = maybe_ss else {
+        return Ok(delta);
+    };
+    let (ss, maybe_fraction) = try_split_once(ss, ".");
+    delta += TimeDelta::seconds(field(ss, "second", 59)?);
+
+    let Some(fraction) = maybe_fraction else {
+        return Ok(delta);
+    };
+    if fraction.is_empty()
+        || fraction.len() > 9
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return Err(fail(format!(
+            "expected 1 to 9 digits after the decimal point, got `{fraction}`"
+        )));
+    }
+    // pad to nanoseconds, so `.25` is 250000000
+    let nanoseconds: i64 = format!("{fraction:0<9}")
+        .parse()
+        .map_err(|error| fail(format!("`{fraction}` is not a number ({error})")))?;
+    delta += TimeDelta::nanoseconds(nanoseconds);
+
+    return Ok(delta);
+}
+
+/// Parses an ISO 8601 duration of weeks, days, hours, minutes, and seconds, where only the seconds
+/// may have a fraction. Years and months are refused, since they have no fixed length.
+fn parse_iso_duration(iso: &str) -> std::result::Result<TimeDelta, String> {
+    let (negative, rest) = match iso.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, iso),
+    };
+    let rest = rest.strip_prefix('P').unwrap_or(rest);
+    let (date, time) = try_split_once(rest, "T");
+    if rest.is_empty() || time == Some("") {
+        return Err("expected at least one number after `P` or `T`".to_string());
+    }
+    let too_long = || "the interval is too long".to_string();
+    let mut delta = TimeDelta::zero();
+    for (mut part, units, in_date) in [(date, "WD", true), (time.unwrap_or_default(), "HMS", false)]
+    {
+        let mut units = units.chars();
+        while !part.is_empty() {
+            let end = part
+                .find(|char: char| !char.is_ascii_digit() && char != '.')
+                .ok_or_else(|| format!("expected a unit after `{part}`"))?;
+            let (number, rest) = part.split_at(end);
+            let unit = rest.chars().next().unwrap_or_default();
+            part = &rest[unit.len_utf8()..];
+            // each unit may only come once, in order
+            if !units.by_ref().any(|expected| expected == unit) {
+                return Err(match unit {
+                    'Y' | 'M' if in_date => "years and months have no fixed length".to_string(),
+                    unit => format!("unexpected `{unit}`"),
+                });
+            }
+            let (whole, fraction) = try_split_once(number, ".");
+            if whole.is_empty() {
+                return Err(format!("expected a number before `{unit}`"));
+            }
+            if fraction.is_some() && unit != 'S' {
+                return Err(format!(
+                    "only seconds may have a fraction, not `{number}{unit}`"
+                ));
+            }
+            let whole: i64 = whole.parse().map_err(|_| too_long())?;
+            let whole = match unit {
+                'W' => TimeDelta::try_weeks(whole),
+                'D' => TimeDelta::try_days(whole),
+                'H' => TimeDelta::try_hours(whole),
+                'M' => TimeDelta::try_minutes(whole),
+                _ => TimeDelta::try_seconds(whole),
+            }
+            .ok_or_else(too_long)?;
+            delta = delta.checked_add(&whole).ok_or_else(too_long)?;
+            if let Some(fraction) = fraction {
+                if fraction.is_empty() || fraction.len() > 9 || fraction.contains('.') {
+                    return Err(format!(
+                        "expected 1 to 9 digits after the decimal point, got `{fraction}`"
+                    ));
+                }
+                let nanoseconds: i64 = format!("{fraction:0<9}").parse().map_err(|_| too_long())?;
+                delta = delta
+                    .checked_add(&TimeDelta::nanoseconds(nanoseconds))
+                    .ok_or_else(too_long)?;
+            }
+        }
+    }
+    Ok(if negative { -delta } else { delta })
+}
+
+/// Parses `today`, `tomorrow`/`tmrw`, `yesterday`, or `now`, in UTC.
+pub fn parse_date(repr: &str) -> Result<DateTime<Utc>> {
+    parse_wall_date(repr, Utc::now().naive_utc()).map(|date| date.and_utc())
+}
+
+/// Parses `today`, `tomorrow`/`tmrw`, `yesterday`, or `now` as a wall time in a timezone where it
+/// is currently `now`.
+fn parse_wall_date(repr: &str, now: NaiveDateTime) -> Result<NaiveDateTime> {
+    let today = now.date().and_time(NaiveTime::MIN);
+    let date = match repr {
+        "now" => now,
+        "today" => today,
+        "tomorrow" | "tmrw" => today + Days::new(1),
+        "yesterday" => today - Days::new(1),
+        unknown => {
+            return Err(HrtError::ParseDate {
+                input: unknown.to_string(),
+                reason: "expected `today`, `tomorrow`, `tmrw`, `yesterday`, or `now`".to_string(),
+            });
+        }
+    };
+    Ok(date)
+}
+
+/// The timezone a date is read in, from the qualifier after its `@`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Zone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+impl Zone {
+    /// Parses `UTC`, `local`, or an offset like `+01:00` or `-05:30`.
+    pub fn parse(repr: &str) -> Result<Self> {
+        let fail = || HrtError::ParseDate {
+            input: format!("@{repr}"),
+            reason: "expected `@UTC`, `@local`, or an offset like `@+01:00`".to_string(),
+        };
+        match repr {
+            "UTC" | "utc" | "Z" => return Ok(Self::Utc),
+            "local" => return Ok(Self::Local),
+            _ => {}
+        }
+        let (sign, rest) = match repr.as_bytes().first() {
+            Some(b'+') => (1, &repr[1..]),
+            Some(b'-') => (-1, &repr[1..]),
+            _ => return Err(fail()),
+        };
+        let (hours, minutes) = rest.split_once(':').ok_or_else(fail)?;
+        let field = |digits: &str, max: i32| match digits.parse::<i32>() {
+            Ok(value) if digits.len() == 2 && value <= max => Ok(value),
+            _ => Err(fail()),
+        };
+        let seconds = sign * (field(hours, 23)? * 3600 + field(minutes, 59)? * 60);
+        FixedOffset::east_opt(seconds)
+            .map(Self::Fixed)
+            .ok_or_else(fail)
+    }
+    /// The current wall time in the zone.
+    fn now(self) -> NaiveDateTime {
+        let now = Utc::now();
+        match self {
+            Self::Utc => now.naive_utc(),
+            Self::Local => now.with_timezone(&Local).naive_local(),
+            Self::Fixed(offset) => now.with_timezone(&offset).naive_local(),
+        }
+    }
+    /// The instant `wall` is in the zone. A local time skipped by a daylight saving change is
+    /// an error, and one that happens twice is the earlier of the two.
+    fn to_utc(self, wall: NaiveDateTime, input: &str) -> Result<DateTime<Utc>> {
+        let date = match self {
+            Self::Utc => Some(wall.and_utc()),
+            Self::Local => Local
+                .from_local_datetime(&wall)
+                .earliest()
+                .map(|date| date.with_timezone(&Utc)),
+            Self::Fixed(offset) => offset
+                .from_local_datetime(&wall)
+                .single()
+                .map(|date| date.with_timezone(&Utc)),
+        };
+        date.ok_or_else(|| HrtError::ParseDate {
+            input: input.to_string(),
+            reason: "that time doesn't exist in the local timezone".to_string(),
+        })
+    }
+}
+
+/// The units of time accepted in `in <number> <unit>`, each with its length in seconds. Plurals
+/// (with a trailing `s`) are accepted too.
+pub const UNITS: &[(&str, i64)] = &[
+    ("second", 1),
+    ("minute", 60),
+    ("hour", 60 * 60),
+    ("day", 24 * 60 * 60),
+    ("week", 7 * 24 * 60 * 60),
+];
+
+/// Parses `in <number> <unit>`, like `in 20 minutes`, as that long from now. `None` if `repr`
+/// doesn't start with `in`.
+fn parse_in(repr: &str) -> Option<Result<DateTime<Utc>>> {
+    let rest = repr.strip_prefix("in ")?;
+    let fail = |reason: String| HrtError::ParseDate {
+        input: repr.to_string(),
+        reason,
+    };
+    let mut words = rest.split_whitespace();
+    let (Some(count), Some(unit), None) = (words.next(), words.next(), words.next()) else {
+        return Some(Err(fail("expected `in <number> <unit>`".to_string())));
+    };
+    let Ok(count) = count.parse::<u32>().map(i64::from) else {
+        return Some(Err(fail(format!("`{count}` is not a whole number"))));
+    };
+    let singular = unit.strip_suffix('s').unwrap_or(unit);
+    let Some((_, seconds)) = UNITS.iter().find(|(name, _)| *name == singular) else {
+        let names: Vec<_> = UNITS.iter().map(|(name, _)| *name).collect();
+        return Some(Err(fail(format!(
+            "unknown unit `{unit}`; expected one of {} (or their plurals)",
+            names.join(", ")
+        ))));
+    };
+    let date = count
+        .checked_mul(*seconds)
+        .and_then(TimeDelta::try_seconds)
+        .and_then(|delta| Utc::now().checked_add_signed(delta));
+    Some(date.ok_or_else(|| fail("that is too far away".to_string())))
+}
+
+/// Parses a date optionally followed by `+` or `-` and a time to add or subtract, such as
+/// `tomorrow+09:30` or `now-02:00`, and then optionally by `@` and the `Zone` to read it in (UTC
+/// by default), like `today+18:00@+01:00`. Also accepts `in <number> <unit>`, and RFC 3339 dates
+/// like `2025-02-01T18:00:00+01:00`. Dates outside the years `format::in_range` allows are
+/// refused, since no schedule could be saved with them.
+pub fn parse_datetime(repr: &str) -> Result<DateTime<Utc>> {
+    let date = parse_any_datetime(repr)?;
+    if !in_range(&date) {
+        return Err(HrtError::ParseDate {
+            input: repr.to_string(),
+            reason: format!(
+                "the date must be within the years {} to {}",
+                limits::MIN_YEAR,
+                limits::MAX_YEAR
+            ),
+        });
+    }
+    Ok(date)
+}
+
+fn parse_any_datetime(repr: &str) -> Result<DateTime<Utc>> {
+    if let Some(date) = parse_in(repr) {
+        return date;
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(repr) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    let (wall_repr, zone) = match repr.rsplit_once('@') {
+        Some((wall_repr, zone)) => (wall_repr, Zone::parse(zone)?),
+        None => (repr, Zone::Utc),
+    };
+    let now = zone.now();
+    let wall = match wall_repr.find(['+', '-']) {
+        None => parse_wall_date(wall_repr, now)?,
+        Some(index) => {
+            let date = parse_wall_date(&wall_repr[..index], now)?;
+            let delta = parse_timedelta(&wall_repr[index + 1..])?;
+            let moved = match wall_repr.as_bytes()[index] {
+                b'+' => date.checked_add_signed(delta),
+                _ => date.checked_sub_signed(delta),
+            };
+            moved.ok_or_else(|| HrtError::ParseDate {
+                input: repr.to_string(),
+                reason: "that is too far away".to_string(),
+            })?
+        }
+    };
+    zone.to_utc(wall, repr)
+}
+
+/// Checks that `name` can be used as a schedule name, which is also its file name.
+pub fn validate_name(name: &str) -> Result<()> {
+    let rule = if name.is_empty() {
+        "path must not be empty"
+    } else if name.len() > limits::NAME {
+        "path must be at most 256 bytes long"
+    } else if name.contains('/') {
+        "path must not contain `/`"
+    } else if name.starts_with('.') {
+        "path must not start with `.`, which is reserved for hrtracker's own files"
+    } else {
+        return Ok(());
+    };
+    Err(HrtError::InvalidName {
+        name: name.to_string(),
+        rule,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reason `repr` isn't a valid interval, checking that the whole input is reported.
+    fn interval_error(repr: &str) -> String {
+        match parse_timedelta(repr) {
+            Err(HrtError::ParseInterval { input, reason }) => {
+                assert_eq!(input, repr);
+                reason
+            }
+            other => panic!("expected `{repr}` to be refused, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn days_come_before_the_clock() {
+        assert_eq!(parse_timedelta("2d+03").unwrap(), TimeDelta::hours(51));
+        assert_eq!(
+            parse_timedelta("2d+03:30").unwrap(),
+            TimeDelta::hours(51) + TimeDelta::minutes(30)
+        );
+        assert_eq!(parse_timedelta("0d+00:30").unwrap(), TimeDelta::minutes(30));
+        assert_eq!(parse_timedelta("0d+00").unwrap(), TimeDelta::zero());
+        assert_eq!(
+            parse_timedelta("1d+00:00:00.5").unwrap(),
+            TimeDelta::days(1) + TimeDelta::milliseconds(500)
+        );
+    }
+
+    #[test]
+    fn day_counts_are_limited_to_what_an_interval_holds() {
+        let most = 106_751_991_167;
+        assert_eq!(
+            parse_timedelta(&format!("{most}d+00")).unwrap(),
+            TimeDelta::days(most)
+        );
+        assert_eq!(
+            interval_error(&format!("{}d+00", most + 1)),
+            format!("`{}` days is too long", most + 1)
+        );
+        assert_eq!(
+            interval_error(&format!("{most}d+23")),
+            "the interval is too long"
+        );
+        assert_eq!(
+            interval_error("99999999999999999999d+00"),
+            "`99999999999999999999` days is too long"
+        );
+    }
+
+    #[test]
+    fn day_prefixes_need_a_number_and_a_short_clock() {
+        for repr in ["d+03", "xd+03", "-1d+03", "1.5d+03", " 1d+03"] {
+            assert_eq!(
+                interval_error(repr),
+                "expected a number of days before `d+`"
+            );
+        }
+        assert_eq!(
+            interval_error("1d+24"),
+            "`24` out of bounds (expected 0 to 23 hours)"
+        );
+        assert_eq!(interval_error("1d+"), "expected 2 hour digits, got 0");
+    }
+
+    #[test]
+    fn iso_durations_take_any_units_in_order() {
+        assert_eq!(parse_timedelta("P1W").unwrap(), TimeDelta::days(7));
+        assert_eq!(parse_timedelta("PT36H").unwrap(), TimeDelta::hours(36));
+        assert_eq!(parse_timedelta("PT90M").unwrap(), TimeDelta::minutes(90));
+        assert_eq!(
+            parse_timedelta("P1W2DT3H4M5.5S").unwrap(),
+            TimeDelta::days(9)
+                + TimeDelta::hours(3)
+                + TimeDelta::minutes(4)
+                + TimeDelta::milliseconds(5500)
+        );
+        assert_eq!(parse_timedelta("-PT6H").unwrap(), TimeDelta::hours(-6));
+    }
+
+    #[test]
+    fn iso_durations_refuse_what_they_cant_mean() {
+        for (repr, reason) in [
+            ("P", "expected at least one number after `P` or `T`"),
+            ("PT", "expected at least one number after `P` or `T`"),
+            ("P1", "expected a unit after `1`"),
+            ("PTH", "expected a number before `H`"),
+            ("P1Y", "years and months have no fixed length"),
+            ("P1M", "years and months have no fixed length"),
+            ("PT1.5M", "only seconds may have a fraction, not `1.5M`"),
+            ("PT1H1H", "unexpected `H`"),
+            ("PT1S1M", "unexpected `M`"),
+            ("P1D1W", "unexpected `W`"),
+            (
+                "PT1.S",
+                "expected 1 to 9 digits after the decimal point, got ``",
+            ),
+            ("P99999999999999999999D", "the interval is too long"),
+        ] {
+            assert_eq!(interval_error(repr), reason, "{repr}");
+        }
+    }
+
+    #[test]
+    fn zones_parse_from_their_qualifiers() {
+        let east = |seconds| Zone::Fixed(FixedOffset::east_opt(seconds).unwrap());
+        assert_eq!(Zone::parse("UTC").unwrap(), Zone::Utc);
+        assert_eq!(Zone::parse("Z").unwrap(), Zone::Utc);
+        assert_eq!(Zone::parse("local").unwrap(), Zone::Local);
+        assert_eq!(Zone::parse("+01:00").unwrap(), east(3600));
+        assert_eq!(Zone::parse("-05:30").unwrap(), east(-5 * 3600 - 30 * 60));
+        assert_eq!(Zone::parse("+13:00").unwrap(), east(13 * 3600));
+        for repr in [
+            "", "+1:00", "+01", "01:00", "+24:00", "+01:60", "+0a:00", "Local",
+        ] {
+            match Zone::parse(repr) {
+                Err(HrtError::ParseDate { input, .. }) => assert_eq!(input, format!("@{repr}")),
+                other => panic!("expected `@{repr}` to be refused, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_move_wall_times_across_days() {
+        let utc = |month, day, hour| Utc.with_ymd_and_hms(2025, month, day, hour, 0, 0).unwrap();
+        let convert = |zone, hour| {
+            let wall = utc(2, 1, hour).naive_utc();
+            Zone::parse(zone).unwrap().to_utc(wall, "").unwrap()
+        };
+        assert_eq!(convert("UTC", 18), utc(2, 1, 18));
+        assert_eq!(convert("+01:00", 18), utc(2, 1, 17));
+        // 06:00 at +13:00 is still the day before in UTC, and 22:00 at -05:00 the day after
+        assert_eq!(convert("+13:00", 6), utc(1, 31, 17));
+        assert_eq!(convert("-05:00", 22), utc(2, 2, 3));
+    }
+
+    #[test]
+    fn rfc_3339_offsets_are_converted_to_utc() {
+        for (repr, month, day, hour) in [
+            ("2025-02-01T18:00:00+01:00", 2, 1, 17),
+            ("2025-02-01T06:00:00+13:00", 1, 31, 17),
+            ("2025-02-01T22:00:00-05:00", 2, 2, 3),
+            ("2025-02-01T18:00:00Z", 2, 1, 18),
+        ] {
+            let expected = Utc.with_ymd_and_hms(2025, month, day, hour, 0, 0).unwrap();
+            assert_eq!(parse_datetime(repr).unwrap(), expected, "{repr}");
+        }
+    }
+
+    #[test]
+    fn keyword_dates_are_read_in_their_zone() {
+        for (repr, offset, hour, minute) in [
+            ("today+18:00@+01:00", 3600, 18, 0),
+            ("today+18:00@+13:00", 13 * 3600, 18, 0),
+            ("tomorrow+06:30@-05:30", -5 * 3600 - 30 * 60, 6, 30),
+            ("today+18:00@UTC", 0, 18, 0),
+        ] {
+            // the day depends on when the test runs, but the wall time in the zone doesn't
+            let offset = FixedOffset::east_opt(offset).unwrap();
+            let date = parse_datetime(repr).unwrap().with_timezone(&offset);
+            let expected = NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+            assert_eq!(date.time(), expected, "{repr}");
+        }
+        assert!(matches!(
+            parse_datetime("today+18:00@+99:00"),
+            Err(HrtError::ParseDate { input, .. }) if input == "@+99:00"
+        ));
+    }
+
+    #[test]
+    fn far_away_day_counts_are_a_date_error() {
+        match parse_datetime("now+99999999d+00") {
+            Err(HrtError::ParseDate { input, reason }) => {
+                assert_eq!(input, "now+99999999d+00");
+                assert_eq!(reason, "that is too far away");
+            }
+            other => panic!("expected a date error, got {other:?}"),
+        }
+    }
+}
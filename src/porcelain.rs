@@ -0,0 +1,16 @@
+//! The fields of the stable, tab-separated `--porcelain` format.
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+
+/// `date` in RFC 3339, in UTC and to the second.
+pub fn timestamp(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+/// `delta` in whole seconds, negative when it is.
+pub fn seconds(delta: &TimeDelta) -> String {
+    delta.num_seconds().to_string()
+}
+/// One record, with its fields separated by tabs.
+pub fn line(fields: &[String]) -> String {
+    fields.join("\t")
+}
@@ -0,0 +1,144 @@
+//! Working out what `apply` has to change to make the schedules in a store match a list of wanted
+//! ones.
+
+use chrono::TimeDelta;
+
+use crate::schedule::RegularSchedule;
+
+/// A schedule as a file of routines describes it. Its start is only needed to create it, so it's
+/// left to the caller.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Wanted {
+    pub name: String,
+    pub interval: TimeDelta,
+    /// `None` leaves the priority of an existing schedule alone.
+    pub priority: Option<u8>,
+}
+
+/// What has to happen to one schedule.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    /// It's wanted, but there is no schedule by that name.
+    Create,
+    /// It exists, but with a different interval or priority.
+    Update,
+    /// It exists just as it's wanted.
+    Keep,
+    /// It exists, but isn't wanted.
+    Extra,
+}
+impl Change {
+    /// How the change is written in porcelain output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Keep => "keep",
+            Self::Extra => "extra",
+        }
+    }
+}
+
+/// Compares `wanted` against `existing`, giving the change for each wanted schedule in order,
+/// followed by every unwanted one by name.
+pub fn plan<'a>(
+    wanted: impl IntoIterator<Item = &'a Wanted>,
+    existing: &'a [(String, RegularSchedule)],
+) -> Vec<(&'a str, Change)> {
+    let find = |name: &str| existing.iter().find(|(existing, _)| existing == name);
+    let mut seen = Vec::new();
+    let mut changes = Vec::new();
+    for wanted in wanted {
+        seen.push(&wanted.name[..]);
+        let change = match find(&wanted.name) {
+            None => Change::Create,
+            Some((_, schedule))
+                if schedule.interval != wanted.interval
+                    || wanted
+                        .priority
+                        .is_some_and(|priority| schedule.priority() != Some(priority)) =>
+            {
+                Change::Update
+            }
+            Some(_) => Change::Keep,
+        };
+        changes.push((&wanted.name[..], change));
+    }
+    let mut extra: Vec<_> = existing
+        .iter()
+        .map(|(name, _)| &name[..])
+        .filter(|name| !seen.contains(name))
+        .collect();
+    extra.sort();
+    changes.extend(extra.into_iter().map(|name| (name, Change::Extra)));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn wanted(name: &str, hours: i64, priority: Option<u8>) -> Wanted {
+        Wanted {
+            name: name.to_string(),
+            interval: TimeDelta::hours(hours),
+            priority,
+        }
+    }
+
+    fn existing(name: &str, hours: i64, priority: Option<u8>) -> (String, RegularSchedule) {
+        let next: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        let mut schedule = RegularSchedule::create(next, TimeDelta::hours(hours));
+        schedule.set_priority(priority).unwrap();
+        (name.to_string(), schedule)
+    }
+
+    #[test]
+    fn wanted_schedules_come_in_order_then_extras_by_name() {
+        let existing = [
+            existing("zzz", 24, None),
+            existing("water", 24, None),
+            existing("old", 24, None),
+            existing("pills", 12, Some(2)),
+        ];
+        let wanted = [
+            wanted("water", 24, None),
+            wanted("walk", 24, None),
+            wanted("pills", 12, Some(3)),
+        ];
+        assert_eq!(
+            plan(&wanted, &existing),
+            [
+                ("water", Change::Keep),
+                ("walk", Change::Create),
+                ("pills", Change::Update),
+                ("old", Change::Extra),
+                ("zzz", Change::Extra),
+            ]
+        );
+    }
+
+    #[test]
+    fn intervals_must_match_but_priorities_only_if_given() {
+        let existing = [existing("pills", 12, Some(2))];
+        assert_eq!(
+            plan(&[wanted("pills", 12, None)], &existing),
+            [("pills", Change::Keep)]
+        );
+        assert_eq!(
+            plan(&[wanted("pills", 12, Some(2))], &existing),
+            [("pills", Change::Keep)]
+        );
+        assert_eq!(
+            plan(&[wanted("pills", 24, None)], &existing),
+            [("pills", Change::Update)]
+        );
+    }
+
+    #[test]
+    fn changes_have_porcelain_names() {
+        let names = [Change::Create, Change::Update, Change::Keep, Change::Extra].map(Change::name);
+        assert_eq!(names, ["create", "update", "keep", "extra"]);
+    }
+}
@@ -0,0 +1,1714 @@
+//! The schedule types stored in the data directory.
+
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::{
+    fmt::{self, Display},
+    io::{self, ErrorKind, Read, Write},
+    marker::PhantomData,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use decent::{Decodable, Encodable, PrimitiveRepr, Version};
+
+#[cfg(feature = "std-fs")]
+use crate::store::{read_capped, schedule_display_name, write_atomic};
+use crate::{
+    error::{HrtError, Result},
+    format::{
+        decode_bool, decode_datetime, decode_datetimes, decode_optional_text, decode_texts,
+        decode_timedelta, encode_bool, encode_datetime, encode_datetimes, encode_optional_text,
+        encode_texts, encode_timedelta, in_range, limits, read_bounded_len,
+    },
+};
+
+/// The format version new schedule files are written with.
+pub const LATEST: Version = Version(0, 0, 13);
+
+/// How many steps a history is pruned to after each step unless told otherwise, as by
+/// `--history-cap`. Until a history is pruned, it is only held to `limits::HISTORY` when read.
+pub const HISTORY_CAP: usize = 1024;
+
+/// One entry of a schedule's history.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Step {
+    /// When the schedule was stepped.
+    pub at: DateTime<Utc>,
+    /// Given with `step --note`, and at most `limits::NOTE` bytes.
+    pub note: Option<String>,
+}
+
+/// What display code needs to know about a schedule, independent of its type.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScheduleInfo {
+    /// The schedule's `Schedule::type_name`.
+    pub type_name: &'static str,
+    /// When the schedule is next due.
+    pub next: DateTime<Utc>,
+    /// How long the period leading up to `next` is.
+    pub interval: TimeDelta,
+    /// Whether the schedule is protected against changes other than stepping.
+    pub protected: bool,
+    pub bound: Option<Bound>,
+    /// When the schedule was last stepped, if its history has any steps.
+    pub last_stepped: Option<DateTime<Utc>>,
+}
+impl ScheduleInfo {
+    pub fn never_stepped(&self) -> bool {
+        self.last_stepped.is_none()
+    }
+    /// Whether the schedule has never been stepped, even though its first occurrence was more
+    /// than an interval ago. Schedules don't record when they were created, so the interval after
+    /// the first occurrence is the grace period a new schedule gets before it counts as neglected.
+    pub fn neglected(&self, now: DateTime<Utc>) -> bool {
+        self.never_stepped()
+            && self
+                .next
+                .checked_add_signed(self.interval)
+                .is_some_and(|grace| grace < now)
+    }
+}
+
+/// How far along a schedule created with a set number of steps is.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bound {
+    /// Steps left before the schedule is finished.
+    pub remaining: u32,
+    /// The steps it was created with, at least 1 and never less than `remaining`.
+    pub total: u32,
+}
+impl Bound {
+    pub fn done(&self) -> u32 {
+        self.total - self.remaining
+    }
+    /// `done` as a percentage of `total`, rounded to the nearest whole number.
+    pub fn percent(&self) -> u32 {
+        let total = u64::from(self.total);
+        ((u64::from(self.done()) * 100 + total / 2) / total) as u32
+    }
+    pub fn finished(&self) -> bool {
+        self.remaining == 0
+    }
+}
+impl Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} done ({}%)",
+            self.done(),
+            self.total,
+            self.percent()
+        )
+    }
+}
+
+/// A kind of recurring schedule. Actions only go through this trait, so that adding a new kind
+/// doesn't mean touching each of them.
+pub trait Schedule: Sized {
+    /// When the schedule is next due.
+    fn next_at(&self) -> DateTime<Utc>;
+    /// Moves the schedule on to its following occurrence.
+    fn advance(&mut self) -> Result<()>;
+    /// The details shown by `list`, `next`, and `step`.
+    fn describe(&self) -> ScheduleInfo;
+    /// A short, human-readable name for the kind of schedule.
+    fn type_name(&self) -> &'static str;
+}
+
+/// The oldest format version schedule files can be read in. Every version so far still can, but
+/// raising this makes older files fail with a clear error instead of being misread.
+pub const MIN_SUPPORTED: Version = Version::ZERO;
+
+/// The format version a schedule file was written in, read from the start of its contents
+/// without decoding the rest.
+pub fn peek_version(mut bytes: &[u8]) -> io::Result<Version> {
+    Version::decode(&mut bytes, Version::ZERO, PrimitiveRepr::Varint)
+}
+
+/// A version as `major.minor.patch`, like `0.0.12`.
+pub fn dotted(Version(major, minor, patch): Version) -> String {
+    format!("{major}.{minor}.{patch}")
+}
+
+/// Reads a version written like `dotted` writes it.
+pub fn parse_version(text: &str) -> Option<Version> {
+    let mut parts = text.split('.').map(str::parse);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => {
+            Some(Version(major, minor, patch))
+        }
+        _ => None,
+    }
+}
+
+/// Fails with advice if schedules written in `version` can't be read, because it is newer than
+/// `LATEST` or older than `MIN_SUPPORTED`.
+pub fn check_version(version: Version) -> io::Result<()> {
+    let unsupported = if version > LATEST {
+        format!(
+            "this file was written by a newer hrtracker ({} > {}); please upgrade",
+            dotted(version),
+            dotted(LATEST)
+        )
+    } else if version < MIN_SUPPORTED {
+        format!(
+            "this file is in format {}, which is older than the oldest this hrtracker reads ({})",
+            dotted(version),
+            dotted(MIN_SUPPORTED)
+        )
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(ErrorKind::InvalidData, unsupported))
+}
+
+/// Decodes a schedule of type `S` from the contents of its file, checking its version first so
+/// that a file from a newer hrtracker is never misread.
+pub fn decode_schedule<S: Schedule + Decodable>(bytes: &[u8]) -> io::Result<S> {
+    decode_schedule_trailing(bytes).map(|(schedule, _)| schedule)
+}
+
+/// Like `decode_schedule`, also giving how many bytes were left over after the last field. A
+/// sound file has none, so any are a sign of corruption or of two writes run together.
+pub fn decode_schedule_trailing<S: Schedule + Decodable>(
+    mut bytes: &[u8],
+) -> io::Result<(S, usize)> {
+    check_version(peek_version(bytes)?)?;
+    let schedule = S::decode(&mut bytes, Version::ZERO, PrimitiveRepr::Varint)?;
+    Ok((schedule, bytes.len()))
+}
+
+/// Encodes `schedule` in the `LATEST` format.
+pub fn encode_schedule<S: Schedule + Encodable>(schedule: &S) -> io::Result<Vec<u8>> {
+    encode_schedule_as(schedule, LATEST)
+}
+
+/// Encodes `schedule` in the format of `version`, which should be the one it was read with; a
+/// schedule decoded from a file encodes back to the same bytes this way.
+pub fn encode_schedule_as<S: Schedule + Encodable>(
+    schedule: &S,
+    version: Version,
+) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    schedule.encode(&mut bytes, version, PrimitiveRepr::Varint)?;
+    Ok(bytes)
+}
+
+/// Reads a schedule of type `S` from `path`.
+#[cfg(feature = "std-fs")]
+pub fn open_schedule<S: Schedule + Decodable>(path: impl AsRef<Path>) -> Result<S> {
+    let path = path.as_ref();
+    let bytes = read_capped(path, &schedule_display_name(path), limits::FILE)?;
+    decode_schedule(&bytes).map_err(|source| HrtError::Decode {
+        path: path.to_path_buf(),
+        size: bytes.len() as u64,
+        source,
+    })
+}
+
+/// Writes `schedule` to `path` in the `LATEST` format, through `write_atomic`, so that a failed
+/// save leaves the old file as it was.
+#[cfg(feature = "std-fs")]
+pub fn save_schedule<S: Schedule + Encodable>(schedule: &S, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let bytes = encode_schedule(schedule).map_err(|error| HrtError::io(path, error))?;
+    write_atomic(path, &bytes)
+}
+
+/// A kind of schedule, identified in its file by a fixed tag.
+pub trait ScheduleID {
+    /// The tag written at the start of the file (after the version).
+    const BYTES: [u8; 8];
+    /// What the schedule is called in errors.
+    const NAME: &'static str;
+}
+/// Encodes as the tag of `T`, and fails to decode unless that tag is present.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ID<T: ScheduleID>(pub PhantomData<T>);
+impl<T: ScheduleID> Encodable for ID<T> {
+    fn encode(&self, to: &mut dyn Write, _: Version, _: PrimitiveRepr) -> io::Result<()> {
+        to.write_all(&T::BYTES)
+    }
+}
+impl<T: ScheduleID> Decodable for ID<T> {
+    fn decode(from: &mut dyn Read, _: Version, _: PrimitiveRepr) -> io::Result<Self> {
+        let mut bytes = vec![0u8; T::BYTES.len()];
+        from.read_exact(&mut bytes)?;
+        if bytes != T::BYTES {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("incorrect identifier for {}", T::NAME),
+            ));
+        }
+        return Ok(Self(PhantomData));
+    }
+}
+
+/// A schedule that recurs every `interval`, starting from `next`. Files hold its version and
+/// `id`, then the rest of its fields in the order of `FIELDS`.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RegularSchedule {
+    version: Version,
+    id: ID<Self>,
+    /// When the schedule is next due.
+    pub next: DateTime<Utc>,
+    /// How far apart occurrences are.
+    pub interval: TimeDelta,
+    webhook: Option<String>,
+    history: Vec<Step>,
+    /// From 1 to 5, or 0 if it was never set.
+    priority: u8,
+    /// The occurrence each of the last entries of `history` was due at.
+    satisfied: Vec<DateTime<Utc>>,
+    /// Sorted, without duplicates.
+    tags: Vec<String>,
+    protected: bool,
+    /// How many times the schedule was ever stepped, including steps pruned from `history`. Older
+    /// files have 0 here, so read it through `total_steps`.
+    steps: u64,
+    /// `None` for schedules that repeat forever.
+    bound: Option<Bound>,
+    /// At most `limits::NOTE` bytes, about the schedule rather than any one step.
+    note: Option<String>,
+}
+fn encode_bound(
+    bound: &Option<Bound>,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    match bound {
+        Some(bound) => {
+            1u8.encode(to, version, repr)?;
+            bound.remaining.encode(to, version, repr)?;
+            bound.total.encode(to, version, repr)
+        }
+        None => 0u8.encode(to, version, repr),
+    }
+}
+fn decode_bound(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Option<Bound>> {
+    let invalid = |reason: String| io::Error::new(ErrorKind::InvalidData, reason);
+    match u8::decode(from, version, repr)? {
+        0 => Ok(None),
+        1 => {
+            let remaining = u32::decode(from, version, repr)?;
+            let total = u32::decode(from, version, repr)?;
+            if total == 0 || remaining > total {
+                return Err(invalid(format!(
+                    "the bound has {remaining} of {total} steps remaining"
+                )));
+            }
+            Ok(Some(Bound { remaining, total }))
+        }
+        other => Err(invalid(format!(
+            "invalid presence byte {other} while decoding the bound"
+        ))),
+    }
+}
+fn decode_protected(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<bool> {
+    decode_bool(from, "protected", version, repr)
+}
+fn decode_tags(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Vec<String>> {
+    decode_texts(from, "tags", limits::TAGS, limits::NAME, version, repr)
+}
+fn decode_satisfied(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Vec<DateTime<Utc>>> {
+    decode_datetimes(
+        from,
+        "satisfied occurrences",
+        limits::HISTORY,
+        version,
+        repr,
+    )
+}
+fn encode_steps(
+    steps: &Vec<Step>,
+    to: &mut dyn Write,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<()> {
+    // as `decode_history` expects, older formats only have the times
+    if version < Version(0, 0, 10) {
+        let dates = steps.iter().map(|step| step.at).collect();
+        return encode_datetimes(&dates, to, version, repr);
+    }
+    (steps.len() as u64).encode(to, version, repr)?;
+    for step in steps {
+        encode_datetime(&step.at, to, version, repr)?;
+        encode_optional_text(&step.note, to, version, repr)?;
+    }
+    Ok(())
+}
+fn decode_history(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Vec<Step>> {
+    // notes were added in 0.0.10, and before that only the times were kept
+    if version < Version(0, 0, 10) {
+        let dates = decode_datetimes(from, "history", limits::HISTORY, version, repr)?;
+        return Ok(dates
+            .into_iter()
+            .map(|at| Step { at, note: None })
+            .collect());
+    }
+    let len = read_bounded_len(from, "history", limits::HISTORY, version, repr)?;
+    (0..len)
+        .map(|_| {
+            Ok(Step {
+                at: decode_datetime(from, version, repr)?,
+                note: decode_optional_text(from, "step note", limits::NOTE, version, repr)?,
+            })
+        })
+        .collect()
+}
+fn decode_webhook(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Option<String>> {
+    decode_optional_text(from, "webhook", limits::URL, version, repr)
+}
+fn decode_note(
+    from: &mut dyn Read,
+    version: Version,
+    repr: PrimitiveRepr,
+) -> io::Result<Option<String>> {
+    decode_optional_text(from, "note", limits::NOTE, version, repr)
+}
+impl RegularSchedule {
+    /// A new schedule first due at `start`.
+    pub fn create(start: DateTime<Utc>, every: TimeDelta) -> Self {
+        Self {
+            version: LATEST,
+            id: ID(PhantomData),
+            next: start,
+            interval: every,
+            webhook: None,
+            history: Vec::new(),
+            priority: 0,
+            satisfied: Vec::new(),
+            tags: Vec::new(),
+            protected: false,
+            steps: 0,
+            bound: None,
+            note: None,
+        }
+    }
+    /// How many of its steps the schedule has left, if it was created with a set number.
+    pub fn bound(&self) -> Option<Bound> {
+        self.bound
+    }
+    /// When the last remaining step is due, if the schedule has a bound that isn't finished and
+    /// keeps to its interval from `next`.
+    pub fn finishes_at(&self) -> Option<DateTime<Utc>> {
+        let bound = self.bound.filter(|bound| !bound.finished())?;
+        let left = self
+            .interval
+            .checked_mul(i32::try_from(bound.remaining - 1).ok()?)?;
+        self.next.checked_add_signed(left)
+    }
+    /// How urgent the schedule is, from 1 (least) to 5 (most), like ntfy's priorities. `None`
+    /// means the default, 3.
+    pub fn priority(&self) -> Option<u8> {
+        (self.priority != 0).then_some(self.priority)
+    }
+    /// Sets or clears the priority, which must be from 1 to 5.
+    pub fn set_priority(&mut self, priority: Option<u8>) -> Result<()> {
+        if priority.is_some_and(|priority| !(1..=5).contains(&priority)) {
+            return Err(HrtError::InvalidSchedule {
+                reason: "the priority must be from 1 to 5",
+            });
+        }
+        self.version = LATEST;
+        self.priority = priority.unwrap_or(0);
+        Ok(())
+    }
+    /// The format version the schedule was read with, or `LATEST` for new or changed schedules.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+    /// When the schedule was stepped, oldest first. Steps are only dropped by `prune_history`, and
+    /// schedules from before version 0.0.4 start out with none.
+    pub fn history(&self) -> &[Step] {
+        &self.history
+    }
+    /// When each step in `history` was made.
+    pub fn stepped_at(&self) -> Vec<DateTime<Utc>> {
+        self.history.iter().map(|step| step.at).collect()
+    }
+    /// The occurrence each step was due at, for the last steps in `history`. Steps recorded before
+    /// version 0.0.6 have none, so this may be shorter than `history`.
+    pub fn satisfied(&self) -> &[DateTime<Utc>] {
+        &self.satisfied
+    }
+    /// Advances the schedule and records that it was stepped `at`, for the occurrence that was
+    /// due. The history grows by one each time, so follow this with `prune_history`, as the
+    /// command line does with its `--history-cap`, before saving a schedule stepped many times.
+    pub fn step(&mut self, at: DateTime<Utc>) -> Result<()> {
+        self.step_noted(at, None)
+    }
+    /// Like `step`, also recording `note` with the step.
+    pub fn step_noted(&mut self, at: DateTime<Utc>, note: Option<String>) -> Result<()> {
+        if self.bound.is_some_and(|bound| bound.finished()) {
+            return Err(HrtError::InvalidSchedule {
+                reason: "every step of the schedule is already done",
+            });
+        }
+        if note.as_ref().is_some_and(|note| note.len() > limits::NOTE) {
+            return Err(HrtError::InvalidSchedule {
+                reason: "a step note must be at most 4096 bytes",
+            });
+        }
+        let due = self.next;
+        self.advance()?;
+        self.version = LATEST;
+        self.steps = self.total_steps() + 1;
+        self.history.push(Step { at, note });
+        self.satisfied.push(due);
+        if let Some(bound) = &mut self.bound {
+            bound.remaining -= 1;
+        }
+        Ok(())
+    }
+    /// How many times the schedule was ever stepped, even if some of those steps were pruned from
+    /// `history` since.
+    pub fn total_steps(&self) -> u64 {
+        self.steps.max(self.history.len() as u64)
+    }
+    /// Removes the steps from `history` that were made before `before`, if given, and then the
+    /// oldest ones until at most `keep` are left, returning how many were removed. `total_steps`
+    /// still counts them. Nothing changes (not even the format version) if nothing is removed.
+    pub fn prune_history(&mut self, keep: usize, before: Option<DateTime<Utc>>) -> usize {
+        // `satisfied` lines up with the end of `history`
+        let offset = self.history.len() - self.satisfied.len().min(self.history.len());
+        let mut entries: Vec<_> = self
+            .history
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                (
+                    step.clone(),
+                    index.checked_sub(offset).map(|index| self.satisfied[index]),
+                )
+            })
+            .collect();
+        let before_prune = entries.len();
+        if let Some(before) = before {
+            entries.retain(|(step, _)| step.at >= before);
+        }
+        entries.drain(..entries.len().saturating_sub(keep));
+        let removed = before_prune - entries.len();
+        if removed == 0 {
+            return 0;
+        }
+        self.steps = self.total_steps();
+        self.version = LATEST;
+        // steps without an occurrence are the oldest, so the rest still line up with the end
+        self.satisfied = entries.iter().filter_map(|(_, due)| *due).collect();
+        self.history = entries.into_iter().map(|(step, _)| step).collect();
+        removed
+    }
+    /// Adds as many intervals as it takes for `next` to be after `now`, returning how many that
+    /// was. Unlike `step`, this records nothing in the history. The skipped occurrences still use
+    /// up a bounded schedule's remaining steps, so it stops early once none are left.
+    pub fn roll_forward(&mut self, now: DateTime<Utc>) -> Result<u64> {
+        if self.interval <= TimeDelta::zero() {
+            return Err(HrtError::InvalidSchedule {
+                reason: "the interval must be longer than zero",
+            });
+        }
+        if self.next > now || self.bound.is_some_and(|bound| bound.finished()) {
+            return Ok(0);
+        }
+        // computed in nanoseconds rather than by looping, since tiny intervals far behind would
+        // take forever
+        let nanos = |delta: TimeDelta| {
+            delta.num_seconds() as i128 * 1_000_000_000 + delta.subsec_nanos() as i128
+        };
+        let mut intervals = nanos(now - self.next) / nanos(self.interval) + 1;
+        if let Some(bound) = &self.bound {
+            intervals = intervals.min(i128::from(bound.remaining));
+        }
+        let total = nanos(self.interval) * intervals;
+        let delta = i64::try_from(total.div_euclid(1_000_000_000))
+            .ok()
+            .and_then(|seconds| TimeDelta::new(seconds, total.rem_euclid(1_000_000_000) as u32));
+        self.next = checked_next(delta.and_then(|delta| self.next.checked_add_signed(delta)))?;
+        if let Some(bound) = &mut self.bound {
+            bound.remaining -= intervals as u32;
+        }
+        // nanosecond intervals thousands of years behind are more than a `u64` can count
+        Ok(u64::try_from(intervals).unwrap_or(u64::MAX))
+    }
+    /// The tags the schedule is grouped by, in order.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+    /// Replaces the tags, which must be non-empty, at most `limits::NAME` bytes, and free of
+    /// whitespace and commas.
+    pub fn set_tags(&mut self, mut tags: Vec<String>) -> Result<()> {
+        tags.sort();
+        tags.dedup();
+        if tags.len() > limits::TAGS {
+            return Err(HrtError::InvalidSchedule {
+                reason: "a schedule can have at most 32 tags",
+            });
+        }
+        let invalid = |tag: &String| {
+            tag.is_empty()
+                || tag.len() > limits::NAME
+                || tag.contains(|char: char| char.is_whitespace() || char == ',')
+        };
+        if let Some(tag) = tags.iter().find(|tag| invalid(tag)) {
+            return Err(HrtError::InvalidName {
+                name: tag.clone(),
+                rule: "tags must be non-empty, at most 256 bytes, and free of whitespace and commas",
+            });
+        }
+        self.version = LATEST;
+        self.tags = tags;
+        Ok(())
+    }
+    /// Whether `protect` was used on the schedule, so that it can't be changed (except by
+    /// stepping) without `--force`.
+    pub fn protected(&self) -> bool {
+        self.protected
+    }
+    /// Protects or unprotects the schedule.
+    pub fn set_protected(&mut self, protected: bool) {
+        self.version = LATEST;
+        self.protected = protected;
+    }
+    /// The URL that is sent a request when the schedule is stepped or becomes due.
+    pub fn webhook(&self) -> Option<&str> {
+        self.webhook.as_deref()
+    }
+    /// Sets or clears the webhook.
+    pub fn set_webhook(&mut self, url: Option<String>) {
+        self.version = LATEST;
+        self.webhook = url;
+    }
+    /// What the schedule is for, if a note was given.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+    /// Sets or clears the note, which must be at most `limits::NOTE` bytes.
+    pub fn set_note(&mut self, note: Option<String>) -> Result<()> {
+        if note.as_ref().is_some_and(|note| note.len() > limits::NOTE) {
+            return Err(HrtError::InvalidSchedule {
+                reason: "a schedule note must be at most 4096 bytes",
+            });
+        }
+        self.version = LATEST;
+        self.note = note;
+        Ok(())
+    }
+    /// Every occurrence from `next` onwards, up to the last remaining step if the schedule is
+    /// bounded.
+    pub fn occurrences(&self) -> Occurrences {
+        Occurrences {
+            next: Some(self.next),
+            interval: self.interval,
+            remaining: self.bound.map(|bound| bound.remaining),
+        }
+    }
+    /// Starts building a schedule, validating it once `ScheduleBuilder::build` is called.
+    pub fn builder() -> ScheduleBuilder {
+        ScheduleBuilder::default()
+    }
+    /// The oldest format version that can hold everything the schedule has, along with what
+    /// needs it.
+    ///
+    /// Whatever changes a schedule (each setter, `step`, and `prune_history` when it removes
+    /// anything) also upgrades its `version` to `LATEST`, since an older format
+    /// may have nowhere to keep the change. `encode_as` still writes any version from this one on.
+    pub fn minimum_version(&self) -> (Version, &'static str) {
+        let needs = [
+            (self.note.is_some(), Version(0, 0, 13), "a schedule note"),
+            (
+                self.interval.num_nanoseconds().is_none(),
+                Version(0, 0, 12),
+                "an interval of over 292 years",
+            ),
+            (
+                self.bound.is_some(),
+                Version(0, 0, 11),
+                "a set number of steps",
+            ),
+            (
+                self.history.iter().any(|step| step.note.is_some()),
+                Version(0, 0, 10),
+                "step notes",
+            ),
+            (
+                self.steps > self.history.len() as u64,
+                Version(0, 0, 9),
+                "a count of pruned steps",
+            ),
+            (self.protected, Version(0, 0, 8), "protection"),
+            (!self.tags.is_empty(), Version(0, 0, 7), "tags"),
+            (
+                !self.satisfied.is_empty(),
+                Version(0, 0, 6),
+                "the occurrences steps satisfied",
+            ),
+            (self.priority != 0, Version(0, 0, 5), "a priority"),
+            (!self.history.is_empty(), Version(0, 0, 4), "a history"),
+            (self.webhook.is_some(), Version(0, 0, 3), "a webhook"),
+        ];
+        needs
+            .into_iter()
+            .find(|(needed, _, _)| *needed)
+            .map_or((MIN_SUPPORTED, "anything"), |(_, version, what)| {
+                (version, what)
+            })
+    }
+    /// Encodes the schedule in the format of `version`, failing if that is newer than `LATEST`
+    /// or can't hold something the schedule has.
+    pub fn encode_as(&self, version: Version) -> Result<Vec<u8>> {
+        if version > LATEST {
+            return Err(HrtError::Usage(format!(
+                "format {} is newer than this hrtracker writes ({})",
+                dotted(version),
+                dotted(LATEST)
+            )));
+        }
+        let (needed, what) = self.minimum_version();
+        if version < needed {
+            return Err(HrtError::Usage(format!(
+                "format {} can't hold {what}, which needs at least {}",
+                dotted(version),
+                dotted(needed)
+            )));
+        }
+        let schedule = Self {
+            version,
+            ..self.clone()
+        };
+        encode_schedule_as(&schedule, version).map_err(HrtError::from)
+    }
+    /// Reads a schedule file of any supported version.
+    #[cfg(feature = "std-fs")]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        open_schedule(path)
+    }
+    /// Writes the schedule to `path` in the `LATEST` format.
+    #[cfg(feature = "std-fs")]
+    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.version = LATEST;
+        save_schedule(self, path)
+    }
+}
+/// Reads one field of a `RegularSchedule` from its file into the schedule.
+type ReadField = fn(&mut RegularSchedule, &mut dyn Read, Version, PrimitiveRepr) -> io::Result<()>;
+/// Writes one field of a `RegularSchedule` to its file.
+type WriteField = fn(&RegularSchedule, &mut dyn Write, Version, PrimitiveRepr) -> io::Result<()>;
+
+/// One field of `RegularSchedule` as it is written in files.
+#[derive(Clone, Copy, Debug)]
+struct Field {
+    name: &'static str,
+    /// The version the field was added in; older files don't have it.
+    since: Version,
+    read: ReadField,
+    write: WriteField,
+}
+
+/// The fields of `RegularSchedule` after its identifier, in the order they are written, which is
+/// the one place that order is kept: encoding, decoding, `ScheduleReader`, and
+/// `RegularSchedule::recover` all go through it. A field added to `RegularSchedule` needs an
+/// entry at the end.
+const FIELDS: &[Field] = &[
+    Field {
+        name: "next",
+        since: Version(0, 0, 0),
+        read: |schedule, from, version, repr| {
+            schedule.next = decode_datetime(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| encode_datetime(&schedule.next, to, version, repr),
+    },
+    Field {
+        name: "interval",
+        since: Version(0, 0, 0),
+        read: |schedule, from, version, repr| {
+            schedule.interval = decode_timedelta(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| {
+            encode_timedelta(&schedule.interval, to, version, repr)
+        },
+    },
+    Field {
+        name: "webhook",
+        since: Version(0, 0, 3),
+        read: |schedule, from, version, repr| {
+            schedule.webhook = decode_webhook(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| {
+            encode_optional_text(&schedule.webhook, to, version, repr)
+        },
+    },
+    Field {
+        name: "history",
+        since: Version(0, 0, 4),
+        read: |schedule, from, version, repr| {
+            schedule.history = decode_history(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| encode_steps(&schedule.history, to, version, repr),
+    },
+    Field {
+        name: "priority",
+        since: Version(0, 0, 5),
+        read: |schedule, from, version, repr| {
+            schedule.priority = u8::decode(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| schedule.priority.encode(to, version, repr),
+    },
+    Field {
+        name: "satisfied",
+        since: Version(0, 0, 6),
+        read: |schedule, from, version, repr| {
+            schedule.satisfied = decode_satisfied(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| {
+            encode_datetimes(&schedule.satisfied, to, version, repr)
+        },
+    },
+    Field {
+        name: "tags",
+        since: Version(0, 0, 7),
+        read: |schedule, from, version, repr| {
+            schedule.tags = decode_tags(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| encode_texts(&schedule.tags, to, version, repr),
+    },
+    Field {
+        name: "protected",
+        since: Version(0, 0, 8),
+        read: |schedule, from, version, repr| {
+            schedule.protected = decode_protected(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| encode_bool(&schedule.protected, to, version, repr),
+    },
+    Field {
+        name: "steps",
+        since: Version(0, 0, 9),
+        read: |schedule, from, version, repr| {
+            schedule.steps = u64::decode(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| schedule.steps.encode(to, version, repr),
+    },
+    Field {
+        name: "bound",
+        since: Version(0, 0, 11),
+        read: |schedule, from, version, repr| {
+            schedule.bound = decode_bound(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| encode_bound(&schedule.bound, to, version, repr),
+    },
+    Field {
+        name: "note",
+        since: Version(0, 0, 13),
+        read: |schedule, from, version, repr| {
+            schedule.note = decode_note(from, version, repr)?;
+            Ok(())
+        },
+        write: |schedule, to, version, repr| {
+            encode_optional_text(&schedule.note, to, version, repr)
+        },
+    },
+];
+
+/// The version identifiers were added to files in, before which a file starts straight after its
+/// version with `next`.
+const IDENTIFIED: Version = Version(0, 0, 2);
+
+impl Encodable for RegularSchedule {
+    /// Writes the schedule in the format of `version`, which is also the version written at the
+    /// start, whatever version the schedule was read in.
+    fn encode(&self, to: &mut dyn Write, version: Version, repr: PrimitiveRepr) -> io::Result<()> {
+        version.encode(to, version, repr)?;
+        if version >= IDENTIFIED {
+            self.id.encode(to, version, repr)?;
+        }
+        for field in FIELDS.iter().filter(|field| version >= field.since) {
+            (field.write)(self, to, version, repr)?;
+        }
+        Ok(())
+    }
+}
+impl Decodable for RegularSchedule {
+    /// Reads a schedule in whatever format it was written in, which it keeps as its version.
+    fn decode(from: &mut dyn Read, _: Version, repr: PrimitiveRepr) -> io::Result<Self> {
+        ScheduleReader::read_header(from, repr)?.read_all()
+    }
+}
+
+/// Reads a `RegularSchedule` from its file one field at a time, in the order of `FIELDS`, going
+/// only as far into the file as the fields asked for, so that `next` doesn't decode a long
+/// history. Decoding a whole schedule goes through it too.
+#[derive(Debug)]
+pub struct ScheduleReader<R> {
+    from: R,
+    repr: PrimitiveRepr,
+    /// The fields read so far, with the rest left empty.
+    schedule: RegularSchedule,
+    /// How many of `FIELDS` have been read, or passed over for being newer than the file.
+    passed: usize,
+    /// Whether a field failed, after which the rest can't be found.
+    failed: bool,
+}
+impl<R: Read> ScheduleReader<R> {
+    /// Reads the version at the start of `from`, failing as `check_version` does, and the
+    /// identifier after it.
+    pub fn read_header(mut from: R, repr: PrimitiveRepr) -> io::Result<Self> {
+        let version = Version::decode(&mut from, Version::ZERO, repr)?;
+        check_version(version)?;
+        if version >= IDENTIFIED {
+            ID::<RegularSchedule>::decode(&mut from, version, repr)?;
+        }
+        Ok(Self {
+            from,
+            repr,
+            schedule: RegularSchedule {
+                version,
+                ..RegularSchedule::create(DateTime::default(), TimeDelta::zero())
+            },
+            passed: 0,
+            failed: false,
+        })
+    }
+    /// The format version the file was written in.
+    pub fn version(&self) -> Version {
+        self.schedule.version
+    }
+    /// Reads the next field the file has, giving its name and whether it could be read, or `None`
+    /// once there are no more. After one fails, so does every later one, without reading.
+    pub fn read_field(&mut self) -> Option<(&'static str, io::Result<()>)> {
+        let version = self.version();
+        let (skipped, field) = FIELDS[self.passed..]
+            .iter()
+            .enumerate()
+            .find(|(_, field)| version >= field.since)?;
+        self.passed += skipped + 1;
+        if self.failed {
+            let error = io::Error::new(ErrorKind::InvalidData, "an earlier field couldn't be read");
+            return Some((field.name, Err(error)));
+        }
+        let read = (field.read)(&mut self.schedule, &mut self.from, version, self.repr);
+        self.failed = read.is_err();
+        Some((field.name, read))
+    }
+    /// Reads on up to and including the field called `name`, unless it was read already. A
+    /// field the file is too old to have is left empty.
+    fn read_through(&mut self, name: &str) -> io::Result<()> {
+        let Some(index) = FIELDS.iter().position(|field| field.name == name) else {
+            return Ok(());
+        };
+        while self.passed <= index {
+            match self.read_field() {
+                Some((_, Err(error))) => return Err(error),
+                Some((_, Ok(()))) => {}
+                None => break,
+            }
+        }
+        Ok(())
+    }
+    pub fn read_next(&mut self) -> io::Result<DateTime<Utc>> {
+        self.read_through("next")?;
+        Ok(self.schedule.next)
+    }
+    pub fn read_interval(&mut self) -> io::Result<TimeDelta> {
+        self.read_through("interval")?;
+        Ok(self.schedule.interval)
+    }
+    pub fn read_tags(&mut self) -> io::Result<&[String]> {
+        self.read_through("tags")?;
+        Ok(&self.schedule.tags)
+    }
+    /// Reads every field that is left, giving the whole schedule.
+    pub fn read_all(mut self) -> io::Result<RegularSchedule> {
+        while let Some((_, read)) = self.read_field() {
+            read?;
+        }
+        Ok(self.schedule)
+    }
+    /// The names of the fields the file has that haven't been read yet.
+    fn unread(&self) -> impl Iterator<Item = &'static str> {
+        let version = self.version();
+        FIELDS[self.passed..]
+            .iter()
+            .filter(move |field| version >= field.since)
+            .map(|field| field.name)
+    }
+}
+
+/// What `RegularSchedule::recover` could read of a damaged schedule file.
+#[derive(Debug)]
+pub struct Recovery {
+    /// The fields that were read, with the rest left empty (and `next` and `interval` at zero),
+    /// in the `LATEST` format.
+    pub schedule: RegularSchedule,
+    /// The fields that were read, in the order they are written.
+    pub recovered: Vec<&'static str>,
+    /// The fields after the first one that couldn't be read, which are all lost.
+    pub missing: Vec<&'static str>,
+    /// Why the first missing field couldn't be read.
+    pub error: Option<io::Error>,
+}
+impl Recovery {
+    /// Whether `field` was lost. The schedule can only be used once `next` and `interval` are
+    /// replaced, if they were; every other field is fine empty.
+    pub fn lost(&self, field: &str) -> bool {
+        self.missing.contains(&field)
+    }
+}
+
+impl RegularSchedule {
+    /// Reads as much of a damaged schedule file as it can, one field at a time, stopping at the
+    /// first field that is cut short or invalid. Fails only if the file doesn't start with the
+    /// version and identifier of a schedule, since nothing after them could be trusted.
+    pub fn recover(mut bytes: &[u8]) -> io::Result<Recovery> {
+        let mut reader = ScheduleReader::read_header(&mut bytes, PrimitiveRepr::Varint)?;
+        let mut recovered = vec!["version"];
+        if reader.version() >= IDENTIFIED {
+            recovered.push("id");
+        }
+        let mut error = None;
+        while error.is_none()
+            && let Some((field, read)) = reader.read_field()
+        {
+            match read {
+                Ok(()) => recovered.push(field),
+                Err(failed) => error = Some((field, failed)),
+            }
+        }
+        let mut missing: Vec<_> = error.iter().map(|(field, _)| *field).collect();
+        missing.extend(reader.unread());
+        Ok(Recovery {
+            schedule: Self {
+                version: LATEST,
+                ..reader.schedule
+            },
+            recovered,
+            missing,
+            error: error.map(|(_, error)| error),
+        })
+    }
+}
+
+/// The new `next` of a schedule being advanced, or an error if the addition overflowed or went
+/// past what `format::in_range` allows, so that the schedule is left as it was.
+fn checked_next(next: Option<DateTime<Utc>>) -> Result<DateTime<Utc>> {
+    next.filter(in_range).ok_or(HrtError::InvalidSchedule {
+        reason: "advancing this schedule would exceed the representable date range",
+    })
+}
+
+/// The times a `RegularSchedule` is due: `next`, `next + interval`, and so on. Ends after the first
+/// occurrence if the interval isn't positive, after the last remaining step of a bounded
+/// schedule, and before leaving the range `format::in_range` allows.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Occurrences {
+    next: Option<DateTime<Utc>>,
+    interval: TimeDelta,
+    /// `None` for schedules that repeat forever.
+    remaining: Option<u32>,
+}
+impl Iterator for Occurrences {
+    type Item = DateTime<Utc>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.checked_sub(1)?;
+        }
+        let current = self.next?;
+        self.next = if self.interval > TimeDelta::zero() {
+            current.checked_add_signed(self.interval).filter(in_range)
+        } else {
+            None
+        };
+        Some(current)
+    }
+}
+
+/// Collects the parts of a `RegularSchedule` so that all validation happens in `build`, which both
+/// the command line and library users go through. It holds the parts to the same limits as
+/// decoding does, so anything it builds can be saved and read back.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScheduleBuilder {
+    start: Option<DateTime<Utc>>,
+    interval: Option<TimeDelta>,
+    count: Option<u32>,
+    tags: Vec<String>,
+    note: Option<String>,
+    priority: Option<u8>,
+}
+impl ScheduleBuilder {
+    /// When the schedule is first due.
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+    /// How far apart occurrences are; must be positive.
+    pub fn interval(mut self, interval: TimeDelta) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+    /// How many times the schedule is stepped before it is finished, if not forever; must be at
+    /// least 1.
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = Some(count);
+        self
+    }
+    /// Adds a tag, held to the rules of `RegularSchedule::set_tags`.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+    /// What the schedule is for; at most `limits::NOTE` bytes.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+    /// From 1 to 5, like `RegularSchedule::set_priority`.
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+    pub fn build(self) -> Result<RegularSchedule> {
+        let invalid = |reason| Err(HrtError::InvalidSchedule { reason });
+        let Some(start) = self.start else {
+            return invalid("a start must be given");
+        };
+        if !in_range(&start) {
+            return invalid("the start must be within the years 1 to 9999");
+        }
+        let Some(interval) = self.interval else {
+            return invalid("an interval must be given");
+        };
+        if interval <= TimeDelta::zero() {
+            return invalid("the interval must be longer than zero");
+        }
+        if self.count == Some(0) {
+            return invalid("the count must be at least 1");
+        }
+        let mut schedule = RegularSchedule::create(start, interval);
+        schedule.bound = self.count.map(|count| Bound {
+            remaining: count,
+            total: count,
+        });
+        schedule.set_tags(self.tags)?;
+        schedule.set_note(self.note)?;
+        schedule.set_priority(self.priority)?;
+        Ok(schedule)
+    }
+}
+
+impl Schedule for RegularSchedule {
+    fn next_at(&self) -> DateTime<Utc> {
+        self.next
+    }
+    fn advance(&mut self) -> Result<()> {
+        self.next = checked_next(self.next.checked_add_signed(self.interval))?;
+        Ok(())
+    }
+    fn describe(&self) -> ScheduleInfo {
+        ScheduleInfo {
+            type_name: self.type_name(),
+            next: self.next,
+            interval: self.interval,
+            protected: self.protected,
+            bound: self.bound,
+            last_stepped: self.history.last().map(|step| step.at),
+        }
+    }
+    fn type_name(&self) -> &'static str {
+        Self::NAME
+    }
+}
+impl ScheduleID for RegularSchedule {
+    const BYTES: [u8; 8] = *b"regular ";
+    const NAME: &'static str = "regular schedule";
+}
+
+/// JSON (or any other serde format) interchange for schedules. The binary format stays the source
+/// of truth, so every field of `FIELDS` is serialized, with dates as RFC 3339 and `interval` as
+/// whole seconds plus nanoseconds, and deserialized schedules are checked by encoding them in
+/// their version and decoding them again, which holds them to the same limits files are.
+#[cfg(feature = "serde")]
+mod interchange {
+    use std::marker::PhantomData;
+
+    use chrono::{DateTime, TimeDelta, Utc};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    use super::{Bound, ID, LATEST, RegularSchedule, Step, decode_schedule, dotted, parse_version};
+
+    #[derive(Serialize, Deserialize)]
+    struct Interval {
+        seconds: i64,
+        /// Has the same sign as `seconds`.
+        nanoseconds: i32,
+    }
+    #[derive(Serialize, Deserialize)]
+    struct Stepped {
+        at: String,
+        note: Option<String>,
+    }
+    #[derive(Serialize, Deserialize)]
+    struct Steps {
+        remaining: u32,
+        total: u32,
+    }
+    /// Everything but `next` and `interval` may be left out, as interchange from before the other
+    /// fields were serialized does.
+    #[derive(Serialize, Deserialize)]
+    struct Regular {
+        /// `LATEST` if left out.
+        #[serde(default)]
+        version: Option<String>,
+        next: String,
+        interval: Interval,
+        #[serde(default)]
+        webhook: Option<String>,
+        #[serde(default)]
+        history: Vec<Stepped>,
+        #[serde(default)]
+        priority: Option<u8>,
+        #[serde(default)]
+        satisfied: Vec<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(default)]
+        protected: bool,
+        #[serde(default)]
+        steps: u64,
+        #[serde(default)]
+        bound: Option<Steps>,
+        #[serde(default)]
+        note: Option<String>,
+    }
+
+    fn date<E: Error>(field: &str, text: &str) -> Result<DateTime<Utc>, E> {
+        DateTime::parse_from_rfc3339(text)
+            .map(|date| date.with_timezone(&Utc))
+            .map_err(|error| E::custom(format!("invalid `{field}`: {error}")))
+    }
+
+    impl Serialize for RegularSchedule {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Regular {
+                version: Some(dotted(self.version)),
+                next: self.next.to_rfc3339(),
+                interval: Interval {
+                    seconds: self.interval.num_seconds(),
+                    nanoseconds: self.interval.subsec_nanos(),
+                },
+                webhook: self.webhook.clone(),
+                history: self
+                    .history
+                    .iter()
+                    .map(|step| Stepped {
+                        at: step.at.to_rfc3339(),
+                        note: step.note.clone(),
+                    })
+                    .collect(),
+                priority: self.priority(),
+                satisfied: self.satisfied.iter().map(DateTime::to_rfc3339).collect(),
+                tags: self.tags.clone(),
+                protected: self.protected,
+                steps: self.steps,
+                bound: self
+                    .bound
+                    .map(|Bound { remaining, total }| Steps { remaining, total }),
+                note: self.note.clone(),
+            }
+            .serialize(serializer)
+        }
+    }
+    impl<'de> Deserialize<'de> for RegularSchedule {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let regular = Regular::deserialize(deserializer)?;
+            let version = match &regular.version {
+                Some(version) => parse_version(version)
+                    .ok_or_else(|| D::Error::custom(format!("invalid `version`: {version}")))?,
+                None => LATEST,
+            };
+            let interval = TimeDelta::try_seconds(regular.interval.seconds)
+                .and_then(|seconds| {
+                    seconds
+                        .checked_add(&TimeDelta::nanoseconds(regular.interval.nanoseconds.into()))
+                })
+                .ok_or_else(|| D::Error::custom("`interval` is out of range"))?;
+            let history = regular
+                .history
+                .into_iter()
+                .map(|step| {
+                    Ok(Step {
+                        at: date("history", &step.at)?,
+                        note: step.note,
+                    })
+                })
+                .collect::<Result<_, D::Error>>()?;
+            let satisfied = regular
+                .satisfied
+                .iter()
+                .map(|due| date("satisfied", due))
+                .collect::<Result<_, D::Error>>()?;
+            let schedule = RegularSchedule {
+                version,
+                id: ID(PhantomData),
+                next: date("next", &regular.next)?,
+                interval,
+                webhook: regular.webhook,
+                history,
+                priority: regular.priority.unwrap_or(0),
+                satisfied,
+                tags: regular.tags,
+                protected: regular.protected,
+                steps: regular.steps,
+                bound: regular
+                    .bound
+                    .map(|Steps { remaining, total }| Bound { remaining, total }),
+                note: regular.note,
+            };
+            let bytes = schedule.encode_as(version).map_err(D::Error::custom)?;
+            decode_schedule(&bytes).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn bounded(count: u32) -> RegularSchedule {
+        RegularSchedule::builder()
+            .start(date(2020, 1, 1))
+            .interval(TimeDelta::days(1))
+            .count(count)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn occurrences_repeat_every_interval() {
+        let schedule = RegularSchedule::create(date(2020, 1, 1), TimeDelta::days(1));
+        let occurrences: Vec<_> = schedule.occurrences().take(3).collect();
+        assert_eq!(
+            occurrences,
+            [date(2020, 1, 1), date(2020, 1, 2), date(2020, 1, 3)]
+        );
+    }
+
+    #[test]
+    fn occurrences_end_with_the_last_remaining_step() {
+        assert_eq!(bounded(2).occurrences().count(), 2);
+        let mut schedule = bounded(2);
+        schedule.step(date(2020, 1, 1)).unwrap();
+        let occurrences: Vec<_> = schedule.occurrences().collect();
+        assert_eq!(occurrences, [date(2020, 1, 2)]);
+        schedule.step(date(2020, 1, 2)).unwrap();
+        assert_eq!(schedule.occurrences().next(), None);
+    }
+
+    #[test]
+    fn occurrences_of_an_empty_interval_end_at_once() {
+        let schedule = RegularSchedule::create(date(2020, 1, 1), TimeDelta::zero());
+        let occurrences: Vec<_> = schedule.occurrences().collect();
+        assert_eq!(occurrences, [date(2020, 1, 1)]);
+        let schedule = RegularSchedule::create(date(2020, 1, 1), TimeDelta::days(-1));
+        assert_eq!(schedule.occurrences().count(), 1);
+    }
+
+    #[test]
+    fn occurrences_stop_before_leaving_the_range() {
+        let schedule = RegularSchedule::create(date(9999, 12, 30), TimeDelta::days(1));
+        let occurrences: Vec<_> = schedule.occurrences().collect();
+        assert_eq!(occurrences, [date(9999, 12, 30), date(9999, 12, 31)]);
+        let schedule = RegularSchedule::create(date(2020, 1, 1), TimeDelta::MAX);
+        assert_eq!(schedule.occurrences().count(), 1);
+    }
+
+    #[test]
+    fn advancing_past_the_last_year_fails_and_changes_nothing() {
+        let mut schedule = RegularSchedule::create(date(9999, 12, 31), TimeDelta::days(1));
+        let before = schedule.clone();
+        assert!(matches!(
+            schedule.step(date(9999, 12, 31)),
+            Err(HrtError::InvalidSchedule { .. })
+        ));
+        assert_eq!(schedule, before);
+        assert!(schedule.advance().is_err());
+        assert_eq!(schedule, before);
+
+        let mut schedule = RegularSchedule::create(date(9999, 12, 30), TimeDelta::days(1));
+        let before = schedule.clone();
+        let now = date(9999, 12, 31) + TimeDelta::hours(12);
+        assert!(schedule.roll_forward(now).is_err());
+        assert_eq!(schedule, before);
+    }
+
+    #[test]
+    fn maximal_intervals_fail_instead_of_overflowing() {
+        let mut schedule = RegularSchedule::create(date(2020, 1, 1), TimeDelta::MAX);
+        let before = schedule.clone();
+        assert!(schedule.step(date(2020, 1, 1)).is_err());
+        assert!(schedule.roll_forward(date(2021, 1, 1)).is_err());
+        assert_eq!(schedule, before);
+        assert_eq!(schedule.occurrences().count(), 1);
+
+        // tiny intervals far behind are counted rather than looped over
+        let mut schedule = RegularSchedule::create(date(1, 1, 1), TimeDelta::nanoseconds(1));
+        assert_eq!(schedule.roll_forward(date(2020, 1, 1)).unwrap(), u64::MAX);
+        assert_eq!(schedule.next, date(2020, 1, 1) + TimeDelta::nanoseconds(1));
+        let mut schedule = RegularSchedule::create(date(1, 1, 1), TimeDelta::seconds(1));
+        let skipped = schedule.roll_forward(date(2020, 1, 1)).unwrap();
+        assert_eq!(schedule.next, date(2020, 1, 1) + TimeDelta::seconds(1));
+        assert_eq!(
+            skipped,
+            (date(2020, 1, 1) - date(1, 1, 1)).num_seconds() as u64 + 1
+        );
+    }
+
+    /// A schedule with something in every field, for the decoder to be fed pieces of.
+    fn everything() -> RegularSchedule {
+        let mut schedule = RegularSchedule::builder()
+            .start(date(2020, 1, 1))
+            .interval(TimeDelta::hours(12))
+            .count(10)
+            .tag("pills")
+            .note("with food")
+            .priority(2)
+            .build()
+            .unwrap();
+        schedule.set_webhook(Some("http://example.com/hook".to_string()));
+        for day in 1..=3 {
+            schedule
+                .step_noted(date(2020, 1, day), Some(format!("step {day}")))
+                .unwrap();
+        }
+        schedule
+    }
+
+    #[test]
+    fn schedules_are_neglected_an_interval_after_their_first_occurrence() {
+        let mut schedule = RegularSchedule::create(date(2020, 1, 1), TimeDelta::days(1));
+        let info = schedule.describe();
+        assert!(info.never_stepped());
+        // the grace period ends at the second occurrence, and only counts once it has passed
+        assert!(!info.neglected(date(2019, 12, 1)));
+        assert!(!info.neglected(date(2020, 1, 1)));
+        assert!(!info.neglected(date(2020, 1, 2)));
+        assert!(info.neglected(date(2020, 1, 2) + TimeDelta::seconds(1)));
+
+        schedule.step(date(2020, 1, 1)).unwrap();
+        let info = schedule.describe();
+        assert_eq!(info.last_stepped, Some(date(2020, 1, 1)));
+        assert!(!info.never_stepped());
+        assert!(!info.neglected(date(2030, 1, 1)));
+    }
+
+    #[test]
+    fn grace_periods_past_the_last_date_never_end() {
+        let info = ScheduleInfo {
+            interval: TimeDelta::MAX,
+            ..RegularSchedule::create(date(2020, 1, 1), TimeDelta::days(1)).describe()
+        };
+        assert!(info.never_stepped());
+        assert!(!info.neglected(date(9999, 12, 31)));
+    }
+
+    /// `schedule` encoded in `LATEST`, with the version at the start replaced by `version`.
+    fn with_version(schedule: &RegularSchedule, version: Version) -> Vec<u8> {
+        let bytes = encode_schedule(schedule).unwrap();
+        let mut latest = Vec::new();
+        LATEST
+            .encode(&mut latest, Version::ZERO, PrimitiveRepr::Varint)
+            .unwrap();
+        assert!(bytes.starts_with(&latest));
+        let mut replaced = Vec::new();
+        version
+            .encode(&mut replaced, Version::ZERO, PrimitiveRepr::Varint)
+            .unwrap();
+        replaced.extend(&bytes[latest.len()..]);
+        replaced
+    }
+
+    #[test]
+    fn versions_are_written_dotted() {
+        assert_eq!(dotted(Version(0, 0, 12)), "0.0.12");
+        assert_eq!(dotted(Version(1, 20, 300)), "1.20.300");
+        assert_eq!(parse_version("0.0.12"), Some(Version(0, 0, 12)));
+        assert_eq!(parse_version(&dotted(LATEST)), Some(LATEST));
+        for text in [
+            "",
+            "0",
+            "0.0",
+            "0.0.12.1",
+            "0.0.x",
+            "0..12",
+            "0.0.-1",
+            "0.0.70000",
+        ] {
+            assert_eq!(parse_version(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn newer_versions_are_refused_before_decoding() {
+        assert!(check_version(LATEST).is_ok());
+        assert!(check_version(MIN_SUPPORTED).is_ok());
+        let newer = Version(LATEST.0, LATEST.1, LATEST.2 + 1);
+        let error = check_version(newer).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "this file was written by a newer hrtracker ({} > {}); please upgrade",
+                dotted(newer),
+                dotted(LATEST)
+            )
+        );
+        assert!(check_version(Version(LATEST.0 + 1, 0, 0)).is_err());
+
+        let schedule = everything();
+        let current = with_version(&schedule, LATEST);
+        assert_eq!(peek_version(&current).unwrap(), LATEST);
+        assert_eq!(
+            decode_schedule::<RegularSchedule>(&current).unwrap(),
+            schedule
+        );
+        let newer_bytes = with_version(&schedule, newer);
+        assert_eq!(peek_version(&newer_bytes).unwrap(), newer);
+        let error = decode_schedule::<RegularSchedule>(&newer_bytes).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            check_version(newer).unwrap_err().to_string()
+        );
+        assert!(ScheduleReader::read_header(&newer_bytes[..], PrimitiveRepr::Varint).is_err());
+        // every version so far can still be read, so nothing is too old yet
+        assert_eq!(MIN_SUPPORTED, Version::ZERO);
+    }
+
+    #[test]
+    fn partial_reads_match_the_full_decode_in_every_version() {
+        let plain = RegularSchedule::create(date(2020, 1, 1), TimeDelta::hours(12));
+        let mut tagged = plain.clone();
+        tagged.tags = vec!["pills".to_string(), "daily".to_string()];
+        tagged.protected = true;
+        let everything = everything();
+        let versions = |from: Version| (from.2..=LATEST.2).map(|patch| Version(0, 0, patch));
+        let mut cases: Vec<_> = versions(Version::ZERO)
+            .map(|version| (&plain, version))
+            .collect();
+        cases.extend(versions(tagged.minimum_version().0).map(|version| (&tagged, version)));
+        cases.push((&everything, LATEST));
+        for (schedule, version) in cases {
+            let bytes = schedule.encode_as(version).unwrap();
+            let full: RegularSchedule = decode_schedule(&bytes).unwrap();
+            let mut reader =
+                ScheduleReader::read_header(&bytes[..], PrimitiveRepr::Varint).unwrap();
+            assert_eq!(reader.version(), version);
+            assert_eq!(reader.read_tags().unwrap(), full.tags, "{version:?}");
+            assert_eq!(reader.read_interval().unwrap(), full.interval);
+            assert_eq!(reader.read_next().unwrap(), full.next);
+            assert_eq!(reader.read_all().unwrap(), full, "{version:?}");
+        }
+    }
+
+    /// The start of `schedule` encoded in `LATEST`, up to and including `next`.
+    fn through_next(schedule: &RegularSchedule) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let repr = PrimitiveRepr::Varint;
+        LATEST.encode(&mut bytes, LATEST, repr).unwrap();
+        schedule.id.encode(&mut bytes, LATEST, repr).unwrap();
+        encode_datetime(&schedule.next, &mut bytes, LATEST, repr).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reading_next_stops_after_it() {
+        let schedule = everything();
+        let bytes = encode_schedule(&schedule).unwrap();
+        let through_next = through_next(&schedule);
+        assert!(bytes.starts_with(&through_next));
+
+        let mut reader = ScheduleReader::read_header(&bytes[..], PrimitiveRepr::Varint).unwrap();
+        assert_eq!(reader.read_next().unwrap(), schedule.next);
+        assert_eq!(reader.from.len(), bytes.len() - through_next.len());
+        assert_eq!(reader.unread().next(), Some("interval"));
+        // reading a field again doesn't go any further
+        assert_eq!(reader.read_next().unwrap(), schedule.next);
+        assert_eq!(reader.from.len(), bytes.len() - through_next.len());
+        assert_eq!(reader.read_all().unwrap(), schedule);
+    }
+
+    #[test]
+    fn fields_after_a_failed_one_fail_without_reading() {
+        let schedule = everything();
+        let bytes = encode_schedule(&schedule).unwrap();
+        // a byte into the interval's seconds, which take more than one
+        let cut = &bytes[..through_next(&schedule).len() + 1];
+
+        let mut reader = ScheduleReader::read_header(cut, PrimitiveRepr::Varint).unwrap();
+        assert_eq!(reader.read_next().unwrap(), schedule.next);
+        assert!(reader.read_interval().is_err());
+        let (field, read) = reader.read_field().unwrap();
+        assert_eq!(field, "webhook");
+        assert_eq!(
+            read.unwrap_err().to_string(),
+            "an earlier field couldn't be read"
+        );
+        assert!(reader.read_tags().is_err());
+        assert!(reader.read_all().is_err());
+    }
+
+    /// xorshift64, so that every run feeds the decoder the same bytes.
+    struct Random(u64);
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+    }
+
+    #[test]
+    fn truncated_schedules_fail_to_decode() {
+        let bytes = encode_schedule(&everything()).unwrap();
+        for len in 0..bytes.len() {
+            assert!(
+                decode_schedule::<RegularSchedule>(&bytes[..len]).is_err(),
+                "{len} of {} bytes decoded",
+                bytes.len()
+            );
+        }
+    }
+
+    #[test]
+    fn mutated_schedules_never_panic() {
+        let bytes = encode_schedule(&everything()).unwrap();
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..20_000 {
+            let mut mutated = bytes.clone();
+            for _ in 0..=random.below(4) {
+                let index = random.below(mutated.len());
+                mutated[index] = random.next() as u8;
+            }
+            if let Ok(schedule) = decode_schedule::<RegularSchedule>(&mutated) {
+                // anything that decodes is within the limits, so it can be used and saved again
+                assert!(schedule.history().len() <= limits::HISTORY);
+                assert!(schedule.tags().len() <= limits::TAGS);
+                assert!(
+                    schedule
+                        .note()
+                        .is_none_or(|note| note.len() <= limits::NOTE)
+                );
+                let _ = schedule.occurrences().take(3).count();
+                let _ = schedule.encode_as(schedule.version());
+            }
+        }
+    }
+
+    #[test]
+    fn random_bytes_never_panic() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..20_000 {
+            let len = random.below(64);
+            let bytes: Vec<u8> = (0..len).map(|_| random.next() as u8).collect();
+            let _ = decode_schedule::<RegularSchedule>(&bytes);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn schedules_round_trip_through_json() {
+        let mut schedule = RegularSchedule::builder()
+            .start(date(2020, 1, 1))
+            .interval(TimeDelta::milliseconds(43_200_250))
+            .count(5)
+            .tag("pills")
+            .note("with food")
+            .priority(4)
+            .build()
+            .unwrap();
+        schedule.set_webhook(Some("http://example.com/hook".to_string()));
+        schedule.set_protected(true);
+        schedule.step(date(2020, 1, 1)).unwrap();
+        schedule
+            .step_noted(date(2020, 1, 2), Some("late".to_string()))
+            .unwrap();
+
+        let bytes = encode_schedule(&schedule).unwrap();
+        let decoded: RegularSchedule = decode_schedule(&bytes).unwrap();
+        let json = serde_json::to_string(&decoded).unwrap();
+        let parsed: RegularSchedule = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, schedule);
+        assert_eq!(encode_schedule(&parsed).unwrap(), bytes);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_keeps_the_version_a_schedule_was_read_with() {
+        let old = Version(0, 0, 11);
+        let bytes = RegularSchedule::create(date(2020, 1, 1), TimeDelta::hours(12))
+            .encode_as(old)
+            .unwrap();
+        let decoded: RegularSchedule = decode_schedule(&bytes).unwrap();
+        assert_eq!(decoded.version(), old);
+        let json = serde_json::to_string(&decoded).unwrap();
+        let parsed: RegularSchedule = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.version(), old);
+        assert_eq!(parsed.encode_as(old).unwrap(), bytes);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_may_leave_out_everything_but_next_and_interval() {
+        let json =
+            r#"{"next":"2020-01-01T00:00:00Z","interval":{"seconds":86400,"nanoseconds":0}}"#;
+        let parsed: RegularSchedule = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            parsed,
+            RegularSchedule::create(date(2020, 1, 1), TimeDelta::days(1))
+        );
+        let wrong = json.replace("2020-01-01T00:00:00Z", "yesterday");
+        assert!(serde_json::from_str::<RegularSchedule>(&wrong).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! The JSON API `serve` answers, over the server in `http`.
+
+use std::{net::TcpListener, thread, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::{HrtError, Result},
+    http, json,
+    listing::{self, SortKey},
+    parse::validate_name,
+    schedule::RegularSchedule,
+    store::{Loaded, Store, load_all, open_named},
+};
+
+/// How long reading a request and writing its response may each take, so that a client that
+/// never finishes its request can't keep a thread forever.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Steps the schedule of the name it is given, for `respond`.
+pub type Stepper<'a> = &'a dyn Fn(&str) -> Result<()>;
+
+/// What `respond` answers a request with.
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    /// A JSON value, which is `{"error": message}` for anything but a success.
+    pub body: String,
+    /// The schedules that couldn't be read, which a listing leaves out.
+    pub unreadable: Vec<HrtError>,
+}
+impl Response {
+    pub fn ok(body: String) -> Self {
+        Self {
+            status: 200,
+            body,
+            unreadable: Vec::new(),
+        }
+    }
+    pub fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: format!(r#"{{"error":{}}}"#, json::string(message)),
+            unreadable: Vec::new(),
+        }
+    }
+    /// `error` as a 404 if it is about a schedule that doesn't exist, and a 500 otherwise.
+    fn failed(error: HrtError) -> Self {
+        match error {
+            HrtError::NotFound { .. } | HrtError::InvalidName { .. } => {
+                Self::error(404, &error.to_string())
+            }
+            error => Self::error(500, &error.to_string()),
+        }
+    }
+}
+
+/// Answers `request` from `store` at `now`. `step` steps the schedule of the name it is given, as
+/// `POST /schedules/{name}/step` asks, or is `None` to refuse those requests.
+pub fn respond(
+    store: &(impl Store + Sync),
+    request: &http::Request,
+    step: Option<Stepper>,
+    now: DateTime<Utc>,
+) -> Response {
+    let segments: Option<Vec<String>> = request
+        .path
+        .trim_start_matches('/')
+        .split('/')
+        .map(http::percent_decode)
+        .collect();
+    let Some(segments) = segments else {
+        return Response::error(400, "the path is not valid percent-encoded UTF-8");
+    };
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    // a name decoded from `%2F` or starting with `.` would reach outside the schedules, so it
+    // never gets near the store
+    if let ["schedules", name, ..] = &segments[..]
+        && let Err(error) = validate_name(name)
+    {
+        return Response::failed(error);
+    }
+    match (request.method.as_str(), &segments[..]) {
+        ("GET", ["healthz"]) => Response::ok(r#"{"status":"ok"}"#.to_string()),
+        ("GET", ["schedules"]) => {
+            let Loaded {
+                mut schedules,
+                unreadable,
+                ..
+            } = match load_all::<RegularSchedule>(store) {
+                Ok(loaded) => loaded,
+                Err(error) => return Response::failed(error),
+            };
+            listing::sort(&mut schedules, SortKey::Next, now);
+            let objects: Vec<_> = schedules
+                .iter()
+                .map(|(name, schedule)| json::schedule(name, schedule, now))
+                .collect();
+            Response {
+                unreadable,
+                ..Response::ok(format!("[{}]", objects.join(",")))
+            }
+        }
+        ("GET", ["schedules", name]) => match open_named::<RegularSchedule>(store, name) {
+            Ok(schedule) => Response::ok(json::schedule(name, &schedule, now)),
+            Err(error) => Response::failed(error),
+        },
+        ("POST", ["schedules", name, "step"]) => {
+            let Some(step) = step else {
+                return Response::error(
+                    405,
+                    "stepping is disabled; start `serve` with --allow-step to enable it",
+                );
+            };
+            let stepped = step(name).and_then(|_| open_named::<RegularSchedule>(store, name));
+            match stepped {
+                Ok(schedule) => Response::ok(json::schedule(name, &schedule, now)),
+                Err(error) => Response::failed(error),
+            }
+        }
+        (_, ["healthz"] | ["schedules"] | ["schedules", _] | ["schedules", _, "step"]) => {
+            Response::error(405, "method not allowed")
+        }
+        _ => Response::error(404, "not found"),
+    }
+}
+
+/// Answers every connection to `listener` until interrupted, each on its own thread, with the
+/// response `answer` gives to its request. Requests that can't be read are answered with a 400.
+pub fn run(listener: &TcpListener, answer: impl Fn(&http::Request) -> Response + Sync) {
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let answer = &answer;
+            scope.spawn(move || {
+                let _ = stream.set_read_timeout(Some(TIMEOUT));
+                let _ = stream.set_write_timeout(Some(TIMEOUT));
+                let response = match http::read_request(&mut stream) {
+                    Ok(request) => answer(&request),
+                    Err(error) => Response::error(400, &error.to_string()),
+                };
+                let _ = http::write_response(&mut stream, response.status, &response.body);
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::{
+        schedule::Schedule,
+        store::{MemStore, save_named},
+    };
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    const NOW: &str = "2025-03-01T10:00:00Z";
+
+    /// `water`, due an hour ago and daily, `pills`, due in a day, and `broken`, which isn't a
+    /// schedule at all.
+    fn store() -> MemStore {
+        let store = MemStore::new();
+        for (name, next) in [
+            ("water", "2025-03-01T09:00:00Z"),
+            ("pills", "2025-03-02T10:00:00Z"),
+        ] {
+            let schedule = RegularSchedule::create(date(next), TimeDelta::days(1));
+            save_named(&store, name, &schedule).unwrap();
+        }
+        store.save("broken", b"\xff").unwrap();
+        store
+    }
+
+    fn request(method: &str, path: &str) -> http::Request {
+        http::Request {
+            method: method.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    fn get(store: &MemStore, path: &str) -> Response {
+        respond(store, &request("GET", path), None, date(NOW))
+    }
+
+    const WATER: &str = r#"{"name":"water","next":"2025-03-01T09:00:00Z","seconds_until_next":-3600,"interval_seconds":86400,"overdue":true,"priority":null}"#;
+
+    #[test]
+    fn schedules_are_listed_soonest_first() {
+        let response = get(&store(), "/schedules");
+        assert_eq!(response.status, 200);
+        assert!(response.body.starts_with(&format!("[{WATER},")));
+        assert!(response.body.contains(r#"{"name":"pills","#));
+        // the broken schedule is reported instead of listed
+        assert_eq!(response.unreadable.len(), 1);
+        assert_eq!(get(&store(), "/schedules/water").body, WATER);
+    }
+
+    #[test]
+    fn names_outside_the_schedules_are_never_looked_up() {
+        let store = store();
+        let response = get(&store, "/schedules/missing");
+        assert_eq!(
+            (response.status, response.body.as_str()),
+            (404, r#"{"error":"schedule `missing` does not exist"}"#)
+        );
+        for path in ["/schedules/%2Fetc", "/schedules/.index/step"] {
+            assert_eq!(get(&store, path).status, 404, "{path}");
+        }
+        assert_eq!(get(&store, "/schedules/%zz").status, 400);
+        assert_eq!(get(&store, "/elsewhere").status, 404);
+        let response = respond(
+            &store,
+            &request("DELETE", "/schedules/water"),
+            None,
+            date(NOW),
+        );
+        assert_eq!(response.status, 405);
+        assert_eq!(get(&store, "/healthz").body, r#"{"status":"ok"}"#);
+    }
+
+    #[test]
+    fn stepping_needs_a_stepper() {
+        let store = store();
+        let step = request("POST", "/schedules/water/step");
+        let response = respond(&store, &step, None, date(NOW));
+        assert_eq!(response.status, 405);
+        assert!(response.body.contains("--allow-step"));
+        let stepper = |name: &str| {
+            let mut schedule: RegularSchedule = open_named(&store, name)?;
+            schedule.step(date(NOW))?;
+            save_named(&store, name, &schedule)
+        };
+        let response = respond(&store, &step, Some(&stepper), date(NOW));
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains(r#""next":"2025-03-02T09:00:00Z""#));
+        let schedule: RegularSchedule = open_named(&store, "water").unwrap();
+        assert_eq!(schedule.next_at(), date("2025-03-02T09:00:00Z"));
+        let response = respond(
+            &store,
+            &request("POST", "/schedules/gone/step"),
+            Some(&stepper),
+            date(NOW),
+        );
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn connections_are_answered_until_interrupted() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            run(&listener, |request| {
+                Response::ok(json::string(&request.path))
+            })
+        });
+        let exchange = |request: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = exchange("GET /some/path?query HTTP/1.1\r\nHost: here\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n\"/some/path\""));
+        let response = exchange("nonsense\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.ends_with(r#"{"error":"malformed request line"}"#));
+    }
+}
@@ -0,0 +1,643 @@
+//! Where schedules are kept: the data directory, or memory.
+
+use std::{
+    collections::BTreeMap,
+    io, panic,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError, mpsc},
+    thread,
+    time::Duration,
+};
+#[cfg(feature = "std-fs")]
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io::{ErrorKind, Read, Write},
+    num::NonZero,
+    time::UNIX_EPOCH,
+};
+
+use decent::{Decodable, Encodable, PrimitiveRepr};
+
+#[cfg(feature = "std-fs")]
+use crate::format::limits;
+use crate::{
+    error::{HrtError, Result},
+    schedule::{Schedule, ScheduleReader, decode_schedule_trailing, encode_schedule},
+};
+
+/// Resolves `$HRTRACKER_DIR`, or `$HOME/.hrtracker` if that isn't set, creating it if needed.
+/// Empty variables count as unset. Every action finds the data directory through this, and two
+/// runs creating it at once both succeed, since an existing directory is fine; a file in its
+/// place is not.
+#[cfg(feature = "std-fs")]
+pub fn data_dir() -> Result<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    let folder = match (var("HRTRACKER_DIR"), var("HOME")) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(home)) => PathBuf::from(home).join(".hrtracker"),
+        (None, None) => return Err(HrtError::NoHome),
+    };
+    let created = fs::create_dir_all(&folder);
+    // `create_dir_all` fails on a file with a confusing "already exists", so check for that first
+    if folder.exists() && !folder.is_dir() {
+        return Err(HrtError::io(
+            &folder,
+            io::Error::new(ErrorKind::NotADirectory, "it exists but is not a directory"),
+        ));
+    }
+    created.map_err(|error| HrtError::io(&folder, error))?;
+    Ok(folder)
+}
+
+/// The hidden file beside `path` that `write_atomic` and `write_synced` write to first.
+#[cfg(feature = "std-fs")]
+fn temporary_path(path: &Path) -> Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| {
+        HrtError::io(
+            path,
+            io::Error::new(ErrorKind::InvalidInput, "not a file path"),
+        )
+    })?;
+    let mut temporary = OsString::from(".");
+    temporary.push(name);
+    temporary.push(".tmp");
+    Ok(path.with_file_name(temporary))
+}
+
+/// Writes `bytes` to `path` through a temporary file beside it that is then renamed over it, so
+/// that anything reading `path` sees either the old contents or the new ones, never a mix.
+#[cfg(feature = "std-fs")]
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let temporary = temporary_path(path)?;
+    fs::write(&temporary, bytes).map_err(|error| HrtError::io(&temporary, error))?;
+    fs::rename(&temporary, path).map_err(|error| {
+        // don't leave the temporary file behind if it can't take the place of the real one
+        let _ = fs::remove_file(&temporary);
+        HrtError::io(path, error)
+    })
+}
+
+/// Like `write_atomic`, but making sure the new contents are on the disk before returning: the
+/// temporary file is synced before it is renamed, and on Unix the directory is synced after, so
+/// that the rename survives a power cut too.
+#[cfg(feature = "std-fs")]
+pub fn write_synced(path: &Path, bytes: &[u8]) -> Result<()> {
+    let temporary = temporary_path(path)?;
+    let written = File::create(&temporary).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(error) = written.and_then(|()| fs::rename(&temporary, path)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(HrtError::io(path, error));
+    }
+    // other platforms can't open a directory to sync it
+    #[cfg(unix)]
+    {
+        let folder = match path.parent() {
+            Some(folder) if !folder.as_os_str().is_empty() => folder,
+            _ => Path::new("."),
+        };
+        File::open(folder)
+            .and_then(|folder| folder.sync_all())
+            .map_err(|error| HrtError::io(folder, error))?;
+    }
+    Ok(())
+}
+
+/// Reads the schedule file at `path`, failing with `HrtError::NotFound` for `name` if it doesn't
+/// exist, or `HrtError::TooLarge` if it is over `limit` bytes. The size is checked before anything
+/// is read, and reading stops just past `limit` anyway, in case the file is growing or its size is
+/// misreported, so a huge file is never read whole.
+#[cfg(feature = "std-fs")]
+pub fn read_capped(path: &Path, name: &str, limit: u64) -> Result<Vec<u8>> {
+    let failed = |error: io::Error| match error.kind() {
+        ErrorKind::NotFound => HrtError::NotFound {
+            name: name.to_string(),
+        },
+        _ => HrtError::io(path, error),
+    };
+    let too_large = |size| HrtError::TooLarge {
+        path: path.to_path_buf(),
+        size,
+        limit,
+    };
+    let file = File::open(path).map_err(failed)?;
+    let size = file.metadata().map_err(failed)?.len();
+    if size > limit {
+        return Err(too_large(size));
+    }
+    let mut bytes = Vec::new();
+    file.take(limit.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(failed)?;
+    if bytes.len() as u64 > limit {
+        return Err(too_large(bytes.len() as u64));
+    }
+    Ok(bytes)
+}
+
+/// The name a schedule file is shown as: its file name, converted lossily if it isn't UTF-8.
+/// Schedule files have no extension, so anything after a dot is part of the name, as in `v1.2`
+/// or the backup `x.bak`.
+pub fn schedule_display_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// A place to keep encoded schedules by name. Stores only ever see bytes; encoding and decoding
+/// happen in `open_named` and `save_named`, on whole files in memory, so the many small reads and
+/// writes of varints never reach the disk and need no buffering.
+pub trait Store {
+    /// The names of every schedule, excluding hrtracker's own dotfiles and directories, in no
+    /// particular order.
+    fn list(&self) -> Result<Vec<String>>;
+    /// Fails with `HrtError::NotFound` if there is no schedule called `name`.
+    fn load(&self, name: &str) -> Result<Vec<u8>>;
+    /// Creates or replaces the schedule called `name`.
+    fn save(&self, name: &str, bytes: &[u8]) -> Result<()>;
+    /// Fails with `HrtError::NotFound` if there is no schedule called `name`.
+    fn delete(&self, name: &str) -> Result<()>;
+    /// What `index` checks its entry for the schedule called `name` against, or `None` if it can't
+    /// be told, in which case the schedule is always decoded. Stores that can't tell when a
+    /// schedule changed don't need to do anything.
+    fn stamp(&self, _name: &str) -> Result<Option<Stamp>> {
+        Ok(None)
+    }
+    /// Keeps other processes from changing the store until the returned lock is dropped. Stores
+    /// that can't be shared between processes don't need to do anything.
+    fn lock(&self) -> Result<StoreLock> {
+        Ok(StoreLock::unlocked())
+    }
+}
+
+/// The size and modification time of a schedule's file, which change whenever it is saved.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Stamp {
+    pub size: u64,
+    /// Since the Unix epoch.
+    pub modified: Duration,
+}
+
+/// Held while a read-modify-write is in progress; see `Store::lock`.
+#[derive(Debug)]
+pub struct StoreLock {
+    #[cfg(feature = "std-fs")]
+    file: Option<File>,
+}
+impl StoreLock {
+    /// A lock that doesn't hold anything, for stores with no need of one.
+    pub fn unlocked() -> Self {
+        Self {
+            #[cfg(feature = "std-fs")]
+            file: None,
+        }
+    }
+}
+#[cfg(feature = "std-fs")]
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        if let Some(file) = &self.file {
+            // closing the file releases the lock anyway, so a failure here changes nothing
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// Keeps each schedule as a file in a directory, named after the schedule.
+#[cfg(feature = "std-fs")]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirStore {
+    pub folder: PathBuf,
+    /// The most bytes a schedule file may be for `load` to read it.
+    pub max_size: u64,
+    /// Whether `save` waits for each schedule to reach the disk, with `write_synced`, instead of
+    /// only replacing it atomically with `write_atomic`.
+    pub durable: bool,
+}
+#[cfg(feature = "std-fs")]
+impl DirStore {
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Self {
+            folder: folder.into(),
+            max_size: limits::FILE,
+            durable: false,
+        }
+    }
+    /// Refuses to load schedule files over `max_size` bytes, instead of over `limits::FILE`.
+    pub fn with_max_size(self, max_size: u64) -> Self {
+        Self { max_size, ..self }
+    }
+    /// Makes `save` sync every schedule to the disk before returning, which is slower.
+    pub fn with_durable(self, durable: bool) -> Self {
+        Self { durable, ..self }
+    }
+    /// The file `lock` takes, relative to the folder.
+    pub const LOCK: &str = ".lock";
+
+    /// The file the schedule called `name` is kept in.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.folder.join(name)
+    }
+}
+#[cfg(feature = "std-fs")]
+impl Store for DirStore {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let entries =
+            fs::read_dir(&self.folder).map_err(|error| HrtError::io(&self.folder, error))?;
+        for entry in entries {
+            let entry = entry.map_err(|error| HrtError::io(&self.folder, error))?;
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with('.') && !is_dir {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+    fn load(&self, name: &str) -> Result<Vec<u8>> {
+        read_capped(&self.path(name), name, self.max_size)
+    }
+    fn save(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path(name);
+        match self.durable {
+            true => write_synced(&path, bytes),
+            false => write_atomic(&path, bytes),
+        }
+    }
+    fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path(name);
+        fs::remove_file(&path).map_err(|error| match error.kind() {
+            ErrorKind::NotFound => HrtError::NotFound {
+                name: name.to_string(),
+            },
+            _ => HrtError::io(path, error),
+        })
+    }
+    fn stamp(&self, name: &str) -> Result<Option<Stamp>> {
+        let Ok(metadata) = fs::metadata(self.path(name)) else {
+            return Ok(None);
+        };
+        let modified = metadata.modified().ok();
+        Ok(modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| Stamp {
+                size: metadata.len(),
+                modified,
+            }))
+    }
+    fn lock(&self) -> Result<StoreLock> {
+        let path = self.folder.join(Self::LOCK);
+        let file = File::create(&path).map_err(|error| HrtError::io(&path, error))?;
+        file.lock().map_err(|error| HrtError::io(&path, error))?;
+        Ok(StoreLock { file: Some(file) })
+    }
+}
+
+/// Keeps schedules in memory, for tests and for tools that don't want to touch the disk.
+#[derive(Debug, Default)]
+pub struct MemStore {
+    schedules: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+impl MemStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn schedules(&self) -> MutexGuard<'_, BTreeMap<String, Vec<u8>>> {
+        // every operation leaves the map consistent, so a panic elsewhere can't poison it
+        self.schedules
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl Store for MemStore {
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self
+            .schedules()
+            .keys()
+            .filter(|name| !name.starts_with('.'))
+            .cloned()
+            .collect())
+    }
+    fn load(&self, name: &str) -> Result<Vec<u8>> {
+        self.schedules()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HrtError::NotFound {
+                name: name.to_string(),
+            })
+    }
+    fn save(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        self.schedules().insert(name.to_string(), bytes.to_vec());
+        Ok(())
+    }
+    fn delete(&self, name: &str) -> Result<()> {
+        self.schedules()
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| HrtError::NotFound {
+                name: name.to_string(),
+            })
+    }
+}
+
+/// Opens the schedule called `name` in `store`.
+pub fn open_named<S: Schedule + Decodable>(store: &impl Store, name: &str) -> Result<S> {
+    open_named_trailing(store, name).map(|(schedule, _)| schedule)
+}
+
+/// Like `open_named`, also giving how many unexpected bytes followed the schedule in its file, as
+/// `decode_schedule_trailing` does.
+pub fn open_named_trailing<S: Schedule + Decodable>(
+    store: &impl Store,
+    name: &str,
+) -> Result<(S, usize)> {
+    let bytes = store.load(name)?;
+    decode_schedule_trailing(&bytes).map_err(|source| HrtError::Decode {
+        path: PathBuf::from(name),
+        size: bytes.len() as u64,
+        source,
+    })
+}
+
+/// Reads only as much of the schedule called `name` in `store` as `read` asks its
+/// `ScheduleReader` for, failing like `open_named` if that can't be decoded.
+pub fn read_named<T>(
+    store: &impl Store,
+    name: &str,
+    read: impl FnOnce(&mut ScheduleReader<&[u8]>) -> io::Result<T>,
+) -> Result<T> {
+    let bytes = store.load(name)?;
+    ScheduleReader::read_header(&bytes[..], PrimitiveRepr::Varint)
+        .and_then(|mut reader| read(&mut reader))
+        .map_err(|source| HrtError::Decode {
+            path: PathBuf::from(name),
+            size: bytes.len() as u64,
+            source,
+        })
+}
+
+/// Saves `schedule` as `name` in `store`.
+pub fn save_named<S: Schedule + Encodable>(
+    store: &impl Store,
+    name: &str,
+    schedule: &S,
+) -> Result<()> {
+    let bytes = encode_schedule(schedule).map_err(|error| HrtError::io(name, error))?;
+    store.save(name, &bytes)
+}
+
+/// Every schedule in a store that could be decoded, along with the errors for those that couldn't.
+#[derive(Debug)]
+pub struct Loaded<S> {
+    /// Each schedule along with the name it is shown as.
+    pub schedules: Vec<(String, S)>,
+    pub unreadable: Vec<HrtError>,
+    /// The schedules that decoded but were followed by unexpected bytes, and how many, in order.
+    pub trailing: Vec<(String, usize)>,
+}
+
+/// The most threads `open_all` uses unless `$HRTRACKER_THREADS` says otherwise; past this, the
+/// disk is more of a limit than decoding.
+pub const MAX_THREADS: usize = 8;
+
+/// The fewest schedules `open_all` gives each thread, since below this, starting them costs more
+/// than it saves.
+pub const PER_THREAD: usize = 32;
+
+/// How many threads `open_all` may use: `$HRTRACKER_THREADS` if it is a whole number above zero,
+/// and otherwise the available parallelism, up to `MAX_THREADS`.
+pub fn threads() -> usize {
+    match configured_threads() {
+        Some(threads) => threads,
+        None => thread::available_parallelism().map_or(1, |threads| threads.get().min(MAX_THREADS)),
+    }
+}
+
+/// `$HRTRACKER_THREADS`, which like `$HRTRACKER_DIR` is only read with `std-fs`, so that embedding
+/// the library without it leaves the environment alone.
+#[cfg(feature = "std-fs")]
+fn configured_threads() -> Option<usize> {
+    env::var("HRTRACKER_THREADS")
+        .ok()
+        .and_then(|threads| threads.trim().parse::<NonZero<usize>>().ok())
+        .map(NonZero::get)
+}
+#[cfg(not(feature = "std-fs"))]
+fn configured_threads() -> Option<usize> {
+    None
+}
+
+/// Opens each of `names` in `store` as `open_named_trailing` does, giving the results in the same
+/// order. Many schedules are decoded by a pool of up to `threads()` threads, which take names
+/// from a channel as they finish the last; a few are simply decoded one after another.
+pub fn open_all<S: Schedule + Decodable + Send>(
+    store: &(impl Store + Sync),
+    names: &[String],
+) -> Vec<Result<(S, usize)>> {
+    let threads = threads().min(names.len() / PER_THREAD);
+    if threads <= 1 {
+        return names
+            .iter()
+            .map(|name| open_named_trailing(store, name))
+            .collect();
+    }
+    let (send, receive) = mpsc::channel();
+    for index in 0..names.len() {
+        // the receiver is still held here, so this can't fail
+        let _ = send.send(index);
+    }
+    drop(send);
+    let receive = Mutex::new(receive);
+    let mut opened: Vec<(usize, Result<(S, usize)>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut opened = Vec::new();
+                    loop {
+                        // only held while taking a name, so the others can take theirs meanwhile
+                        let next = receive
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        let Ok(index) = next else {
+                            return opened;
+                        };
+                        opened.push((index, open_named_trailing(store, &names[index])));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    opened.sort_by_key(|(index, _)| *index);
+    opened.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Decodes every schedule in `store`, in order of their names, with `open_all`. Schedules that
+/// can't be read are collected in `Loaded::unreadable` rather than failing the whole listing.
+pub fn load_all<S: Schedule + Decodable + Send>(store: &(impl Store + Sync)) -> Result<Loaded<S>> {
+    let mut loaded = Loaded {
+        schedules: Vec::new(),
+        unreadable: Vec::new(),
+        trailing: Vec::new(),
+    };
+    let mut names = store.list()?;
+    // stores list in whatever order they like, which differs between machines
+    names.sort();
+    let opened = open_all(store, &names);
+    for (name, opened) in names.into_iter().zip(opened) {
+        match opened {
+            // listed names are what the store knows each schedule by, so they are kept as they are
+            Ok((schedule, trailing)) => {
+                if trailing > 0 {
+                    loaded.trailing.push((name.clone(), trailing));
+                }
+                loaded.schedules.push((name, schedule));
+            }
+            Err(error) => loaded.unreadable.push(error),
+        }
+    }
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use crate::schedule::RegularSchedule;
+
+    fn schedule() -> RegularSchedule {
+        let next: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        RegularSchedule::create(next, TimeDelta::days(1))
+    }
+
+    #[test]
+    fn memory_stores_hide_dotfiles_and_report_missing_schedules() {
+        let store = MemStore::new();
+        store.save("water", b"a").unwrap();
+        store.save(".index", b"b").unwrap();
+        assert_eq!(store.list().unwrap(), ["water"]);
+        assert_eq!(store.load("water").unwrap(), b"a");
+        store.delete("water").unwrap();
+        assert!(matches!(store.load("water"), Err(HrtError::NotFound { name }) if name == "water"));
+        assert!(matches!(
+            store.delete("water"),
+            Err(HrtError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn display_names_are_file_names() {
+        assert_eq!(schedule_display_name(Path::new("/data/v1.2")), "v1.2");
+        assert_eq!(schedule_display_name(Path::new("x.bak")), "x.bak");
+    }
+
+    #[test]
+    fn loading_sorts_and_collects_what_fails() {
+        let store = MemStore::new();
+        save_named(&store, "water", &schedule()).unwrap();
+        let mut trailing = encode_schedule(&schedule()).unwrap();
+        trailing.push(0);
+        store.save("pills", &trailing).unwrap();
+        store.save("broken", b"\xff").unwrap();
+        let loaded = load_all::<RegularSchedule>(&store).unwrap();
+        assert_eq!(
+            loaded.schedules,
+            [
+                ("pills".to_string(), schedule()),
+                ("water".to_string(), schedule())
+            ]
+        );
+        assert_eq!(loaded.trailing, [("pills".to_string(), 1)]);
+        assert!(
+            matches!(&loaded.unreadable[..], [HrtError::Decode { path, .. }] if path == Path::new("broken"))
+        );
+    }
+
+    #[test]
+    fn opening_many_keeps_their_order() {
+        let store = MemStore::new();
+        let names: Vec<_> = (0..PER_THREAD * 4)
+            .map(|index| format!("s{index}"))
+            .collect();
+        for (index, name) in names.iter().enumerate() {
+            let mut schedule = schedule();
+            schedule.interval = TimeDelta::minutes(index as i64 + 1);
+            save_named(&store, name, &schedule).unwrap();
+        }
+        let opened = open_all::<RegularSchedule>(&store, &names);
+        for (index, opened) in opened.into_iter().enumerate() {
+            let (schedule, _) = opened.unwrap();
+            assert_eq!(schedule.interval, TimeDelta::minutes(index as i64 + 1));
+        }
+    }
+
+    #[cfg(feature = "std-fs")]
+    fn folder(test: &str) -> PathBuf {
+        let folder = env::temp_dir().join(format!("hrtracker-store-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn directories_list_only_schedule_files() {
+        let folder = folder("list");
+        let store = DirStore::new(&folder);
+        store.save("water", b"a").unwrap();
+        fs::write(folder.join(".index"), b"b").unwrap();
+        fs::create_dir(folder.join("hooks")).unwrap();
+        assert_eq!(store.list().unwrap(), ["water"]);
+        assert!(
+            store
+                .stamp("water")
+                .unwrap()
+                .is_some_and(|stamp| stamp.size == 1)
+        );
+        assert_eq!(store.stamp("missing").unwrap(), None);
+        store.with_durable(true).save("water", b"bb").unwrap();
+        assert!(!folder.join(".water.tmp").exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn large_files_are_refused_before_reading() {
+        let folder = folder("capped");
+        fs::write(folder.join("water"), [0; 10]).unwrap();
+        assert_eq!(
+            read_capped(&folder.join("water"), "water", 10)
+                .unwrap()
+                .len(),
+            10
+        );
+        assert!(matches!(
+            read_capped(&folder.join("water"), "water", 9),
+            Err(HrtError::TooLarge {
+                size: 10,
+                limit: 9,
+                ..
+            })
+        ));
+        assert!(matches!(
+            read_capped(&folder.join("missing"), "missing", 9),
+            Err(HrtError::NotFound { name }) if name == "missing"
+        ));
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+//! Terminal colors.
+
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+};
+
+use crate::error::{HrtError, Result};
+
+pub const RESET: &str = "\x1b[0m";
+pub const BOLD: &str = "\x1b[1m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const REVERSE: &str = "\x1b[7m";
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+impl ColorChoice {
+    pub fn parse(repr: &str) -> Result<Self> {
+        match repr {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            unknown => Err(HrtError::Usage(format!(
+                "`{unknown}` is not a valid color choice (expected `always`, `never`, or `auto`)"
+            ))),
+        }
+    }
+    /// Whether escape codes should be emitted; `auto` colors only when stdout is a terminal and
+    /// `NO_COLOR` is unset or empty.
+    pub fn enabled(&self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Wraps `text` in `code` and a reset when `enabled` (and `code` is not empty), and leaves it
+/// untouched otherwise.
+pub fn paint(text: impl Display, code: &str, enabled: bool) -> String {
+    if enabled && !code.is_empty() {
+        format!("{code}{text}{RESET}")
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_choice() {
+        assert_eq!(ColorChoice::parse("auto").unwrap(), ColorChoice::Auto);
+        assert_eq!(ColorChoice::parse("always").unwrap(), ColorChoice::Always);
+        assert_eq!(ColorChoice::parse("never").unwrap(), ColorChoice::Never);
+        assert!(ColorChoice::parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn always_and_never_ignore_the_terminal() {
+        assert!(ColorChoice::Always.enabled());
+        assert!(!ColorChoice::Never.enabled());
+    }
+
+    #[test]
+    fn paints_only_when_enabled_with_a_code() {
+        assert_eq!(paint("due", RED, true), "\x1b[31mdue\x1b[0m");
+        assert_eq!(paint("due", RED, false), "due");
+        assert_eq!(paint("due", "", true), "due");
+    }
+}
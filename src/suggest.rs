@@ -0,0 +1,155 @@
+//! "Did you mean" suggestions for mistyped schedule and action names, and names that only differ
+//! by case.
+
+use std::collections::BTreeMap;
+
+/// The Levenshtein distance between `a` and `b`: how many characters must be inserted, removed,
+/// or replaced to turn one into the other.
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // the distances from a prefix of `a` to each prefix of `b`, one row at a time
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The most edits a name of `len` characters may be from a suggestion: a third of its length,
+/// but always at least one.
+pub fn threshold(len: usize) -> usize {
+    (len / 3).max(1)
+}
+
+/// The names in `names` closest to `name`, if any are within `threshold`. Ties are all returned,
+/// sorted.
+pub fn closest<'a>(name: &str, names: &'a [String]) -> Vec<&'a str> {
+    let limit = threshold(name.chars().count());
+    let scored: Vec<(usize, &str)> = names
+        .iter()
+        .map(|candidate| (distance(name, candidate), candidate.as_str()))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    let Some(best) = scored.iter().map(|(distance, _)| *distance).min() else {
+        return Vec::new();
+    };
+    let mut closest: Vec<&str> = scored
+        .into_iter()
+        .filter(|(distance, _)| *distance == best)
+        .map(|(_, candidate)| candidate)
+        .collect();
+    closest.sort();
+    closest
+}
+
+/// What to say about `name` not being one of `names`, which are `what`: the closest of them, or
+/// every one of them if none are close.
+pub fn hint(name: &str, names: &[String], what: &str) -> String {
+    let quoted = |names: &[&str], separator: &str| {
+        let quoted: Vec<_> = names.iter().map(|name| format!("`{name}`")).collect();
+        quoted.join(separator)
+    };
+    let closest = closest(name, names);
+    if !closest.is_empty() {
+        return format!("did you mean {}?", quoted(&closest, " or "));
+    }
+    let mut names: Vec<&str> = names.iter().map(String::as_str).collect();
+    names.sort();
+    format!("the {what} are {}", quoted(&names, ", "))
+}
+
+/// `name` with its case folded, so that names a case-insensitive filesystem would treat as the
+/// same file fold to the same string.
+pub fn fold(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// A name in `names` that differs from `name` only by case, if there is one.
+pub fn case_variant<'a>(name: &str, names: &'a [String]) -> Option<&'a str> {
+    let folded = fold(name);
+    names
+        .iter()
+        .find(|other| *other != name && fold(other) == folded)
+        .map(String::as_str)
+}
+
+/// Every group of names in `names` that only differ by case, each sorted, in order of their
+/// first names.
+pub fn case_collisions(names: &[String]) -> Vec<Vec<&str>> {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for name in names {
+        groups.entry(fold(name)).or_default().push(name);
+    }
+    let mut collisions: Vec<Vec<&str>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect();
+    collisions.sort();
+    collisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn distances_count_single_character_edits() {
+        assert_eq!(distance("water", "water"), 0);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("flaw", "lawn"), 2);
+        // a swap is two edits, and characters count rather than bytes
+        assert_eq!(distance("stpe", "step"), 2);
+        assert_eq!(distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn only_names_within_the_threshold_are_suggested() {
+        assert_eq!([0, 2, 3, 5, 6, 9].map(threshold), [1, 1, 1, 1, 2, 3]);
+        let known = names(&["water", "wafer", "pills"]);
+        assert_eq!(closest("watr", &known), ["water"]);
+        assert_eq!(closest("water", &known), ["water"]);
+        assert!(closest("xyzzy", &known).is_empty());
+        // ties are all given, sorted
+        let known = names(&["hat", "cat", "bath", "bad", "cattle"]);
+        assert_eq!(closest("bat", &known), ["bad", "bath", "cat", "hat"]);
+    }
+
+    #[test]
+    fn hints_suggest_or_list_every_name() {
+        let known = names(&["water", "pills", "plants"]);
+        assert_eq!(hint("watr", &known, "schedules"), "did you mean `water`?");
+        assert_eq!(
+            hint("plls", &names(&["pills", "palls"]), "schedules"),
+            "did you mean `palls` or `pills`?"
+        );
+        assert_eq!(
+            hint("garbage", &known, "schedules"),
+            "the schedules are `pills`, `plants`, `water`"
+        );
+    }
+
+    #[test]
+    fn names_differing_by_case_are_found() {
+        let known = names(&["Water", "water", "pills", "WATER", "Pills2"]);
+        assert_eq!(case_variant("water", &known), Some("Water"));
+        assert_eq!(case_variant("PILLS", &known), Some("pills"));
+        assert_eq!(case_variant("plants", &known), None);
+        assert_eq!(case_collisions(&known), [vec!["WATER", "Water", "water"]]);
+    }
+}
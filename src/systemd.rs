@@ -0,0 +1,192 @@
+//! systemd timer and service units that check a schedule.
+
+use chrono::{Datelike, NaiveDateTime, TimeDelta, Timelike};
+
+use crate::error::{HrtError, Result};
+
+/// The stem shared by both unit files for `name`, like `hrtracker-water-plants`. systemd only
+/// accepts a few characters in unit names, so other names are refused rather than escaped.
+pub fn unit_name(name: &str) -> Result<String> {
+    let unit = format!("hrtracker-{name}");
+    let allowed = |char: char| char.is_ascii_alphanumeric() || ":-_.".contains(char);
+    if !unit.chars().all(allowed) {
+        return Err(HrtError::InvalidName {
+            name: name.to_string(),
+            rule: "unit names may only contain ASCII letters, digits, `:`, `-`, `_`, and `.`",
+        });
+    }
+    // the longest suffix is `.service`, and unit names are limited to 255 bytes
+    if unit.len() + ".service".len() > 255 {
+        return Err(HrtError::InvalidName {
+            name: name.to_string(),
+            rule: "unit names must be at most 255 bytes long",
+        });
+    }
+    Ok(unit)
+}
+
+/// The `OnCalendar=` value that fires every `interval` in line with `next`, if the interval is a
+/// whole number of seconds dividing a minute, minutes dividing an hour, or hours dividing a day,
+/// or exactly a day or a week.
+pub fn on_calendar(next: NaiveDateTime, interval: TimeDelta) -> Option<String> {
+    if interval <= TimeDelta::zero() || interval.subsec_nanos() != 0 {
+        return None;
+    }
+    let (hour, minute, second) = (
+        next.hour() as i64,
+        next.minute() as i64,
+        next.second() as i64,
+    );
+    let divides = |seconds: i64, unit: i64, whole: i64| {
+        seconds % unit == 0 && whole % (seconds / unit) == 0 && seconds / unit < whole
+    };
+    let seconds = interval.num_seconds();
+    let spec = if divides(seconds, 1, 60) {
+        format!("*-*-* *:*:{:02}/{seconds}", second % seconds)
+    } else if divides(seconds, 60, 60) {
+        let minutes = seconds / 60;
+        format!("*-*-* *:{:02}/{minutes}:{second:02}", minute % minutes)
+    } else if divides(seconds, 3600, 24) {
+        let hours = seconds / 3600;
+        format!("*-*-* {:02}/{hours}:{minute:02}:{second:02}", hour % hours)
+    } else if seconds == 24 * 3600 {
+        format!("*-*-* {hour:02}:{minute:02}:{second:02}")
+    } else if seconds == 7 * 24 * 3600 {
+        format!("{} *-*-* {hour:02}:{minute:02}:{second:02}", next.weekday())
+    } else {
+        return None;
+    };
+    Some(spec)
+}
+
+/// The `.timer` unit for `name`. Intervals that `on_calendar` can't express fall back to
+/// `OnUnitActiveSec=`, which repeats at the right rate but isn't lined up with `next`.
+pub fn timer(name: &str, next: NaiveDateTime, interval: TimeDelta) -> String {
+    let trigger = match on_calendar(next, interval) {
+        Some(spec) => format!("OnCalendar={spec}\nPersistent=true\n"),
+        None => {
+            let seconds = interval.num_seconds().max(1);
+            format!("OnActiveSec={seconds}s\nOnUnitActiveSec={seconds}s\n")
+        }
+    };
+    format!(
+        "[Unit]\n\
+         Description=Check whether the hrtracker schedule `{name}` is due\n\
+         \n\
+         [Timer]\n\
+         {trigger}\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n"
+    )
+}
+
+/// The `.service` unit the timer starts, running `command` through `sh -c` with `environment`
+/// set. Exit code 5 counts as success, since that is how `hrtracker due` reports something due.
+pub fn service(name: &str, command: &str, environment: &[(&str, &str)]) -> String {
+    let environment: String = environment
+        .iter()
+        .map(|(key, value)| format!("Environment={}\n", quote(&format!("{key}={value}"))))
+        .collect();
+    format!(
+        "[Unit]\n\
+         Description=Check whether the hrtracker schedule `{name}` is due\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         {environment}\
+         ExecStart=/bin/sh -c {}\n\
+         SuccessExitStatus=5\n",
+        // `ExecStart=` expands `$VARIABLE`s itself, unlike `Environment=`
+        quote(&command.replace('$', "$$"))
+    )
+}
+
+/// Quotes `text` as one word of a unit file setting, escaping systemd's `%` specifiers so that
+/// they reach the command untouched.
+pub fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(hour, minute, second)
+            .unwrap()
+    }
+
+    #[test]
+    fn units_match_the_expected_files() {
+        assert_eq!(
+            timer("water", at(9, 30, 0), TimeDelta::days(1)),
+            include_str!("../tests/golden/water.timer")
+        );
+        assert_eq!(
+            timer("pills", at(9, 30, 0), TimeDelta::minutes(90)),
+            include_str!("../tests/golden/pills.timer")
+        );
+        assert_eq!(
+            service(
+                "water",
+                r#"hrtracker due water || echo "$HOME" 100%"#,
+                &[("HRTRACKER_DIR", "/var/lib/hrtracker")]
+            ),
+            include_str!("../tests/golden/water.service")
+        );
+    }
+
+    #[test]
+    fn calendar_specs_line_up_with_next() {
+        let spec = |next, interval| on_calendar(next, interval);
+        assert_eq!(
+            spec(at(9, 30, 40), TimeDelta::seconds(15)).as_deref(),
+            Some("*-*-* *:*:10/15")
+        );
+        assert_eq!(
+            spec(at(9, 50, 5), TimeDelta::minutes(20)).as_deref(),
+            Some("*-*-* *:10/20:05")
+        );
+        assert_eq!(
+            spec(at(14, 0, 0), TimeDelta::hours(6)).as_deref(),
+            Some("*-*-* 02/6:00:00")
+        );
+        // the start of 2020 was a Wednesday
+        assert_eq!(
+            spec(at(9, 30, 0), TimeDelta::weeks(1)).as_deref(),
+            Some("Wed *-*-* 09:30:00")
+        );
+    }
+
+    #[test]
+    fn other_intervals_have_no_calendar_spec() {
+        for interval in [
+            TimeDelta::seconds(7),
+            TimeDelta::minutes(90),
+            TimeDelta::hours(25),
+            TimeDelta::days(2),
+            TimeDelta::milliseconds(1500),
+            TimeDelta::zero(),
+        ] {
+            assert_eq!(on_calendar(at(0, 0, 0), interval), None, "{interval}");
+        }
+    }
+
+    #[test]
+    fn unit_names_are_checked() {
+        assert_eq!(unit_name("water-plants").unwrap(), "hrtracker-water-plants");
+        assert!(unit_name("water plants").is_err());
+        assert!(unit_name("wässern").is_err());
+        assert!(unit_name(&"x".repeat(237)).is_ok());
+        assert!(unit_name(&"x".repeat(238)).is_err());
+    }
+}
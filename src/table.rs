@@ -0,0 +1,84 @@
+//! Aligned columns for terminal output.
+
+use crate::style;
+
+/// A piece of text and the escape code it is painted with.
+pub struct Cell {
+    pub text: String,
+    pub style: &'static str,
+}
+impl Cell {
+    pub fn plain(text: impl ToString) -> Self {
+        Self::styled(text, "")
+    }
+    pub fn styled(text: impl ToString, style: &'static str) -> Self {
+        Self {
+            text: text.to_string(),
+            style,
+        }
+    }
+}
+
+/// The number of terminal columns `text` occupies, counted per character so that multi-byte
+/// UTF-8 doesn't skew alignment.
+pub fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Renders `rows` (and optionally a `header`) as left-aligned columns separated by two spaces.
+/// Widths are computed from the unstyled text, so escape codes never affect alignment.
+pub fn render(header: Option<&[&str]>, rows: &[Vec<Cell>], color: bool) -> String {
+    let header: Option<Vec<Cell>> = header.map(|header| header.iter().map(Cell::plain).collect());
+    let all_rows = || header.iter().chain(rows);
+    let columns = all_rows().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            all_rows()
+                .filter_map(|row| row.get(column))
+                .map(|cell| width(&cell.text))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut out = String::new();
+    for row in all_rows() {
+        let mut line = String::new();
+        for (column, cell) in row.iter().enumerate() {
+            if column > 0 {
+                line.push_str("  ");
+            }
+            line.push_str(&style::paint(&cell.text, cell.style, color));
+            if column + 1 < row.len() {
+                line.push_str(&" ".repeat(widths[column] - width(&cell.text)));
+            }
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_as_wide_as_their_widest_text() {
+        let rows = vec![
+            vec![Cell::styled("é", style::RED), Cell::plain("x")],
+            vec![Cell::plain("long"), Cell::plain(2)],
+            vec![Cell::plain("ragged")],
+        ];
+        assert_eq!(width("héllo"), 5);
+        assert_eq!(
+            render(Some(&["N", "V"]), &rows, false),
+            "N       V\né       x\nlong    2\nragged\n"
+        );
+        // escape codes don't count towards the width
+        assert_eq!(
+            render(None, &rows[..1], true),
+            format!("{}  x\n", style::paint("é", style::RED, true))
+        );
+        assert_eq!(render(None, &[], false), "");
+    }
+}
@@ -0,0 +1,195 @@
+//! The plain text form of a schedule that `edit` opens in an editor: `key = value` lines, like the
+//! config file.
+
+use chrono::{SecondsFormat, TimeDelta};
+
+use crate::{
+    error::{HrtError, Result},
+    format::in_range,
+    parse::{parse_datetime, parse_timedelta},
+    schedule::RegularSchedule,
+    webhook::Url,
+};
+
+/// An interval in the form `parse_timedelta` reads, like `1d+02:30:00` or `00:00:01.5`.
+pub fn interval(interval: TimeDelta) -> String {
+    let seconds = interval.num_seconds();
+    let mut clock = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if interval.subsec_nanos() != 0 {
+        let fraction = format!("{:09}", interval.subsec_nanos());
+        clock.push('.');
+        clock.push_str(fraction.trim_end_matches('0'));
+    }
+    match seconds / 86400 {
+        0 => clock,
+        days => format!("{days}d+{clock}"),
+    }
+}
+
+/// Writes out the parts of `schedule` that can be edited, with a comment explaining each one.
+pub fn render(name: &str, schedule: &RegularSchedule) -> String {
+    format!(
+        "# schedule `{name}`; lines starting with `#` are ignored, and history can't be edited\n\
+         \n\
+         # when it is next due, as RFC 3339 or any other date hrtracker accepts\n\
+         next = {}\n\
+         # how far apart occurrences are, like 24:00:00 or 1d+12:00:00\n\
+         interval = {}\n\
+         # from 1 to 5, or empty for the default of 3\n\
+         priority = {}\n\
+         # the URL to send a JSON request to when it is stepped or becomes due, or empty for none\n\
+         webhook = {}\n\
+         # separated by commas\n\
+         tags = {}\n",
+        schedule.next.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        interval(schedule.interval),
+        schedule
+            .priority()
+            .map_or_else(String::new, |priority| priority.to_string()),
+        schedule.webhook().unwrap_or_default(),
+        schedule.tags().join(", "),
+    )
+}
+
+/// Applies `text`, as written by `render` and then edited, to a copy of `schedule`, which keeps its
+/// history. Keys that are left out are left alone. Fails with the number of the offending line and
+/// why it is wrong.
+pub fn parse(
+    text: &str,
+    schedule: &RegularSchedule,
+) -> std::result::Result<RegularSchedule, String> {
+    let mut edited = schedule.clone();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fail = |reason: String| format!("line {}: {reason}", index + 1);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| fail("expected a `key = value` pair".to_string()))?;
+        let value = value.trim();
+        let applied: Result<()> = match key.trim() {
+            "next" => parse_datetime(value).and_then(|next| {
+                if !in_range(&next) {
+                    return Err(HrtError::InvalidSchedule {
+                        reason: "the date is outside the years 1 to 9999",
+                    });
+                }
+                edited.next = next;
+                Ok(())
+            }),
+            "interval" => parse_timedelta(value).and_then(|interval| {
+                if interval <= TimeDelta::zero() {
+                    return Err(HrtError::InvalidSchedule {
+                        reason: "the interval must be longer than zero",
+                    });
+                }
+                edited.interval = interval;
+                Ok(())
+            }),
+            "priority" if value.is_empty() => edited.set_priority(None),
+            "priority" => match value.parse() {
+                Ok(priority) => edited.set_priority(Some(priority)),
+                Err(_) => Err(HrtError::Usage(format!(
+                    "`{value}` is not a priority from 1 to 5"
+                ))),
+            },
+            "webhook" if value.is_empty() => {
+                edited.set_webhook(None);
+                Ok(())
+            }
+            "webhook" => Url::parse(value).map(|_| edited.set_webhook(Some(value.to_string()))),
+            "tags" => edited.set_tags(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            other => Err(HrtError::Usage(format!(
+                "`{other}` is not something that can be edited"
+            ))),
+        };
+        applied.map_err(|error| fail(error.to_string()))?;
+    }
+    Ok(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn schedule() -> RegularSchedule {
+        let next: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        RegularSchedule::create(next, TimeDelta::days(1))
+    }
+
+    #[test]
+    fn intervals_are_written_with_days_and_fractions() {
+        assert_eq!(interval(TimeDelta::hours(24)), "1d+00:00:00");
+        assert_eq!(interval(TimeDelta::minutes(150)), "02:30:00");
+        assert_eq!(interval(TimeDelta::milliseconds(1500)), "00:00:01.5");
+        assert_eq!(
+            interval(TimeDelta::hours(26) + TimeDelta::minutes(30)),
+            "1d+02:30:00"
+        );
+    }
+
+    #[test]
+    fn rendered_text_parses_back_unchanged() {
+        let mut schedule = schedule();
+        schedule.set_priority(Some(2)).unwrap();
+        schedule
+            .set_tags(vec!["health".to_string(), "home".to_string()])
+            .unwrap();
+        let text = render("water", &schedule);
+        assert!(text.contains("\nnext = 2025-03-01T09:00:00Z\n"));
+        assert!(text.contains("\ninterval = 1d+00:00:00\n"));
+        assert!(text.contains("\npriority = 2\n"));
+        assert!(text.contains("\ntags = health, home\n"));
+        assert_eq!(parse(&text, &schedule), Ok(schedule));
+    }
+
+    #[test]
+    fn edits_are_applied_and_missing_keys_left_alone() {
+        let edited = parse(
+            "# a comment\n\ninterval = 12:00:00\npriority =\ntags = b, , a\n",
+            &schedule(),
+        )
+        .unwrap();
+        assert_eq!(edited.interval, TimeDelta::hours(12));
+        assert_eq!(edited.next, schedule().next);
+        assert_eq!(edited.priority(), None);
+        assert_eq!(edited.tags(), ["a", "b"]);
+    }
+
+    #[test]
+    fn mistakes_name_their_line() {
+        let fail = |text| parse(text, &schedule()).unwrap_err();
+        assert_eq!(fail("\nnonsense"), "line 2: expected a `key = value` pair");
+        assert_eq!(
+            fail("color = red"),
+            "line 1: `color` is not something that can be edited"
+        );
+        assert_eq!(
+            fail("# zero\ninterval = 00:00:00"),
+            "line 2: invalid schedule: the interval must be longer than zero"
+        );
+        assert_eq!(
+            fail("priority = high"),
+            "line 1: `high` is not a priority from 1 to 5"
+        );
+        assert_eq!(
+            fail("priority = 7"),
+            "line 1: invalid schedule: the priority must be from 1 to 5"
+        );
+    }
+}
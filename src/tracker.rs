@@ -0,0 +1,88 @@
+//! A handle to a store that can be shared between threads.
+
+use std::sync::{Mutex, PoisonError};
+#[cfg(feature = "std-fs")]
+use std::{fs, path::PathBuf};
+
+use chrono::Utc;
+
+#[cfg(feature = "std-fs")]
+use crate::{error::HrtError, store::DirStore};
+use crate::{
+    error::Result,
+    format::limits,
+    schedule::{HISTORY_CAP, RegularSchedule},
+    store::{Loaded, Store, load_all, open_named, save_named},
+};
+
+/// Wraps a `Store` so that every operation can be called through `&self` from several threads at
+/// once. Changes are serialized within the process by a mutex and across processes by
+/// `Store::lock`, so two `step`s can never both read the same `next` and lose an update.
+#[derive(Debug)]
+pub struct Tracker<S: Store> {
+    store: S,
+    writing: Mutex<()>,
+    /// How many steps `step` leaves in a history.
+    history_cap: usize,
+}
+#[cfg(feature = "std-fs")]
+impl Tracker<DirStore> {
+    /// A tracker for the schedules in `dir`, creating it if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|error| HrtError::io(&dir, error))?;
+        Ok(Self::new(DirStore::new(dir)))
+    }
+}
+impl<S: Store> Tracker<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            writing: Mutex::new(()),
+            history_cap: HISTORY_CAP,
+        }
+    }
+    /// Makes `step` keep the latest `cap` steps of a history rather than `HISTORY_CAP`, like
+    /// `--history-cap`. Caps past `limits::HISTORY`, which is as long as a history can be read
+    /// back, are lowered to it.
+    pub fn with_history_cap(self, cap: usize) -> Self {
+        Self {
+            history_cap: cap.clamp(1, limits::HISTORY),
+            ..self
+        }
+    }
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+    /// Runs `f` while holding both the in-process and the cross-process lock.
+    fn exclusive<T>(&self, f: impl FnOnce(&S) -> Result<T>) -> Result<T> {
+        // the mutex guards no data, so a panic while it was held can't have left anything broken
+        let _writing = self.writing.lock().unwrap_or_else(PoisonError::into_inner);
+        let _lock = self.store.lock()?;
+        f(&self.store)
+    }
+    /// Every schedule, along with the errors for those that couldn't be read.
+    pub fn list(&self) -> Result<Loaded<RegularSchedule>>
+    where
+        S: Sync,
+    {
+        self.exclusive(load_all)
+    }
+    pub fn get(&self, name: &str) -> Result<RegularSchedule> {
+        self.exclusive(|store| open_named(store, name))
+    }
+    /// Advances `name` to its next occurrence, returning the updated schedule.
+    pub fn step(&self, name: &str) -> Result<RegularSchedule> {
+        self.exclusive(|store| {
+            let mut schedule: RegularSchedule = open_named(store, name)?;
+            schedule.step(Utc::now())?;
+            schedule.prune_history(self.history_cap, None);
+            save_named(store, name, &schedule)?;
+            Ok(schedule)
+        })
+    }
+    /// Saves `schedule` as `name`, replacing any schedule already called that.
+    pub fn create(&self, name: &str, schedule: &RegularSchedule) -> Result<()> {
+        self.exclusive(|store| save_named(store, name, schedule))
+    }
+}
@@ -0,0 +1,416 @@
+//! The interactive screen of `tui`. The terminal is put into raw mode with `stty`, so there is no
+//! terminal library to depend on.
+
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    panic,
+    process::{Command as Process, Stdio},
+    sync::Arc,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    display,
+    error::{HrtError, Result},
+    listing::{self, SortKey},
+    schedule::{RegularSchedule, Schedule, ScheduleInfo},
+    store::{Loaded, Store, load_all},
+    style, table,
+};
+
+/// The line of keys at the top of the screen.
+pub const KEYS: &str = "j/k or arrows: move   s: step   n: next   d: delete   q: quit";
+
+/// A key press, as far as `interact` tells them apart.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Key {
+    Up,
+    Down,
+    Char(u8),
+}
+impl Key {
+    /// The key that sent `bytes`, which hold a whole escape sequence or the first byte of
+    /// anything else. Nothing at all means the input was closed, which quits like `q`.
+    pub fn parse(bytes: &[u8]) -> Self {
+        match bytes {
+            b"\x1b[A" | b"\x1bOA" => Self::Up,
+            b"\x1b[B" | b"\x1bOB" => Self::Down,
+            [byte, ..] => Self::Char(*byte),
+            [] => Self::Char(b'q'),
+        }
+    }
+}
+
+/// Where `interact` reads keys from and draws to.
+pub trait Screen {
+    /// Waits for a key press.
+    fn key(&mut self) -> Result<Key>;
+    /// How many lines there is room for.
+    fn height(&self) -> usize;
+    /// Replaces what is shown with `screen`, whose lines end in `\n`.
+    fn draw(&mut self, screen: &str) -> Result<()>;
+}
+
+/// The terminal in raw mode on the alternate screen, which is left again when dropped.
+#[derive(Debug)]
+pub struct Terminal {
+    /// The settings to restore, from `stty -g`.
+    saved: String,
+}
+impl Terminal {
+    fn enter() -> Result<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(Self { saved })
+    }
+}
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        restore(&self.saved);
+    }
+}
+impl Screen for Terminal {
+    /// Escape sequences arrive in a single read, so they are never split.
+    fn key(&mut self) -> Result<Key> {
+        let mut buffer = [0; 8];
+        let read = io::stdin().lock().read(&mut buffer)?;
+        Ok(Key::parse(&buffer[..read]))
+    }
+    /// The number of rows `stty` says the terminal has, or 24 if it can't tell.
+    fn height(&self) -> usize {
+        stty(&["size"])
+            .ok()
+            .and_then(|size| size.split_whitespace().next()?.parse().ok())
+            .unwrap_or(24)
+    }
+    fn draw(&mut self, screen: &str) -> Result<()> {
+        // raw mode doesn't turn line feeds into carriage return and line feed
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[H\x1b[2J{}", screen.replace('\n', "\r\n"))?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+fn restore(saved: &str) {
+    let _ = stty(&[saved]);
+    print!("\x1b[?25h\x1b[?1049l");
+    let _ = io::stdout().flush();
+}
+
+/// Runs `stty` on the terminal, returning what it printed.
+fn stty(args: &[&str]) -> Result<String> {
+    let output = Process::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("`stty` failed with {}", output.status)).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Runs `body` on the `Terminal`, which is restored afterwards, even if `body` panics. Fails
+/// unless stdin and stdout are both a terminal.
+pub fn session<T>(body: impl FnOnce(&mut Terminal) -> Result<T>) -> Result<T> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(HrtError::Usage("`tui` needs a terminal".to_string()));
+    }
+    let mut terminal = Terminal::enter()?;
+    // a panic message would be lost on the alternate screen, so leave it before printing one
+    let saved = terminal.saved.clone();
+    let previous = Arc::new(panic::take_hook());
+    let chained = Arc::clone(&previous);
+    panic::set_hook(Box::new(move |info| {
+        restore(&saved);
+        chained(info);
+    }));
+    let result = body(&mut terminal);
+    // put back whichever hook was there before, rather than the default one
+    drop(panic::take_hook());
+    panic::set_hook(match Arc::try_unwrap(previous) {
+        Ok(previous) => previous,
+        Err(previous) => Box::new(move |info| previous(info)),
+    });
+    drop(terminal);
+    result
+}
+
+/// How `interact` shows the schedules, as chosen on the command line.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct View {
+    pub display: display::Options,
+    pub sort: SortKey,
+    pub reverse: bool,
+    /// Schedules due within this long are shown in yellow.
+    pub soon: TimeDelta,
+    pub color: bool,
+}
+impl View {
+    /// The whole screen: `KEYS`, the schedules as `list` would show them (scrolled to keep
+    /// `selected` in the `height` lines there are), and `status`.
+    pub fn screen(
+        &self,
+        schedules: &[(String, ScheduleInfo)],
+        selected: usize,
+        status: &str,
+        height: usize,
+        now: DateTime<Utc>,
+    ) -> String {
+        // the key line, the header, a blank line, and the status
+        let visible = height.saturating_sub(4).max(1);
+        let first = selected.saturating_sub(visible - 1);
+        let rows: Vec<_> = schedules
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(index, (name, schedule))| {
+                let delta = schedule.next.signed_duration_since(now);
+                let urgency = if index == selected && self.color {
+                    style::REVERSE
+                } else if delta < TimeDelta::zero() {
+                    style::RED
+                } else if delta <= self.soon {
+                    style::YELLOW
+                } else {
+                    ""
+                };
+                vec![
+                    table::Cell::plain(if index == selected { ">" } else { " " }),
+                    table::Cell::styled(
+                        name,
+                        if urgency.is_empty() {
+                            style::BOLD
+                        } else {
+                            urgency
+                        },
+                    ),
+                    table::Cell::styled(self.display.datetime(&schedule.next), urgency),
+                    table::Cell::styled(self.display.relative(delta), urgency),
+                    table::Cell::styled(self.display.interval(schedule.interval), urgency),
+                ]
+            })
+            .collect();
+        let header: &[&str] = &["", "NAME", "NEXT", "IN", "INTERVAL"];
+        let mut screen = format!("{KEYS}\n");
+        if schedules.is_empty() {
+            screen.push_str("\nthere are no schedules\n");
+        } else {
+            screen.push_str(&table::render(Some(header), &rows, self.color));
+        }
+        screen.push('\n');
+        screen.push_str(status);
+        screen
+    }
+}
+
+/// What a key press asks the caller of `interact` to do to a schedule.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Command {
+    Step,
+    /// Once the deletion has been confirmed with `y`.
+    Delete,
+}
+
+/// Shows the schedules in `store` on `screen` as `view` says, reading them again after every key
+/// press, until `q` or ctrl-c is pressed. `apply` carries out each `Command` on the schedule of
+/// the name it is given, and what happened is shown on the status line.
+pub fn interact(
+    store: &(impl Store + Sync),
+    view: &View,
+    screen: &mut impl Screen,
+    mut apply: impl FnMut(Command, &str) -> Result<()>,
+) -> Result<()> {
+    let mut selected = 0;
+    let mut status = String::new();
+    loop {
+        let now = Utc::now();
+        let Loaded {
+            schedules,
+            unreadable,
+            ..
+        } = load_all::<RegularSchedule>(store)?;
+        let mut schedules: Vec<_> = schedules
+            .into_iter()
+            .map(|(name, schedule)| (name, schedule.describe()))
+            .collect();
+        listing::order(&mut schedules, view.sort, view.reverse, now);
+        selected = selected.min(schedules.len().saturating_sub(1));
+        if status.is_empty() && !unreadable.is_empty() {
+            status = format!("{} schedule(s) could not be read", unreadable.len());
+        }
+        let draw = |screen: &mut _, status: &str| {
+            let height = Screen::height(screen);
+            Screen::draw(
+                screen,
+                &view.screen(&schedules, selected, status, height, now),
+            )
+        };
+        draw(screen, &status)?;
+        status.clear();
+        let current = schedules.get(selected).map(|(name, _)| name.clone());
+        match (screen.key()?, current) {
+            // ctrl-c arrives as a byte in raw mode
+            (Key::Char(b'q' | 3), _) => return Ok(()),
+            (Key::Up | Key::Char(b'k'), _) => selected = selected.saturating_sub(1),
+            (Key::Down | Key::Char(b'j'), _) => selected += 1,
+            (Key::Char(b's'), Some(name)) => {
+                status = match apply(Command::Step, &name) {
+                    Ok(()) => format!("stepped `{name}`"),
+                    Err(error) => error.to_string(),
+                }
+            }
+            (Key::Char(b'n'), Some(name)) => {
+                let (_, schedule) = &schedules[selected];
+                status = format!(
+                    "`{name}` is next at {} ({})",
+                    view.display.datetime(&schedule.next),
+                    view.display
+                        .relative(schedule.next.signed_duration_since(now))
+                );
+            }
+            (Key::Char(b'd'), Some(name)) => {
+                draw(screen, &format!("delete `{name}`? (y/n)"))?;
+                if let Key::Char(b'y' | b'Y') = screen.key()? {
+                    status = match apply(Command::Delete, &name) {
+                        Ok(()) => format!("deleted `{name}`"),
+                        Err(error) => error.to_string(),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::store::{MemStore, save_named};
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    /// A screen that presses `keys` in turn, and then `q`, keeping every frame it is given.
+    struct Scripted {
+        keys: VecDeque<Key>,
+        frames: Vec<String>,
+    }
+    impl Screen for Scripted {
+        fn key(&mut self) -> Result<Key> {
+            Ok(self.keys.pop_front().unwrap_or(Key::Char(b'q')))
+        }
+        fn height(&self) -> usize {
+            24
+        }
+        fn draw(&mut self, screen: &str) -> Result<()> {
+            self.frames.push(screen.to_string());
+            Ok(())
+        }
+    }
+
+    fn info(next: DateTime<Utc>) -> ScheduleInfo {
+        RegularSchedule::create(next, TimeDelta::days(1)).describe()
+    }
+
+    #[test]
+    fn keys_are_told_apart_by_what_they_send() {
+        assert_eq!(Key::parse(b"\x1b[A"), Key::Up);
+        assert_eq!(Key::parse(b"\x1bOB"), Key::Down);
+        assert_eq!(Key::parse(b"jk"), Key::Char(b'j'));
+        assert_eq!(Key::parse(b"\x1b[C"), Key::Char(0x1b));
+        assert_eq!(Key::parse(b""), Key::Char(b'q'));
+    }
+
+    #[test]
+    fn screens_scroll_to_the_selected_schedule() {
+        let now = date("2025-03-01T09:00:00Z");
+        let schedules: Vec<_> = [("a", -1), ("b", 1), ("c", 48)]
+            .into_iter()
+            .map(|(name, hours)| (name.to_string(), info(now + TimeDelta::hours(hours))))
+            .collect();
+        let view = View::default();
+        // room for two schedules, so `a` scrolls off
+        let screen = view.screen(&schedules, 2, "status", 6, now);
+        let lines: Vec<_> = screen.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], KEYS);
+        assert!(lines[1].starts_with("   NAME  NEXT"));
+        assert!(lines[2].starts_with("   b     2025-03-01 10:00:00 UTC"));
+        assert!(lines[3].starts_with(">  c     2025-03-03 09:00:00 UTC"));
+        assert_eq!(lines[4..], ["", "status"]);
+        let colored = View {
+            color: true,
+            soon: TimeDelta::hours(2),
+            ..View::default()
+        };
+        let screen = colored.screen(&schedules, 0, "", 24, now);
+        assert!(screen.contains(&style::paint("a", style::REVERSE, true)));
+        assert!(screen.contains(&style::paint("b", style::YELLOW, true)));
+        assert!(screen.contains(&style::paint("c", style::BOLD, true)));
+        let empty = view.screen(&[], 0, "", 24, now);
+        assert_eq!(empty, format!("{KEYS}\n\nthere are no schedules\n\n"));
+    }
+
+    #[test]
+    fn keys_step_and_delete_the_selected_schedule() {
+        let store = MemStore::new();
+        for (name, next) in [
+            ("water", "2020-01-01T00:00:00Z"),
+            ("pills", "2100-01-01T00:00:00Z"),
+        ] {
+            let schedule = RegularSchedule::create(date(next), TimeDelta::days(1));
+            save_named(&store, name, &schedule).unwrap();
+        }
+        let mut screen = Scripted {
+            keys: [
+                Key::Down,
+                Key::Char(b's'),
+                Key::Char(b'd'),
+                Key::Char(b'n'),
+                Key::Up,
+                Key::Char(b'n'),
+                Key::Char(b'd'),
+                Key::Char(b'y'),
+            ]
+            .into(),
+            frames: Vec::new(),
+        };
+        let mut applied = Vec::new();
+        interact(&store, &View::default(), &mut screen, |command, name| {
+            applied.push((command, name.to_string()));
+            match command {
+                Command::Step => Ok(()),
+                Command::Delete => store.delete(name),
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            applied,
+            [
+                (Command::Step, "pills".to_string()),
+                (Command::Delete, "water".to_string())
+            ]
+        );
+        let frames = &screen.frames;
+        assert_eq!(frames.len(), 9);
+        assert!(frames[1].contains("\n>  pills"));
+        assert!(frames[2].ends_with("\nstepped `pills`"));
+        // answering anything but `y` leaves the schedule alone
+        assert!(frames[3].ends_with("\ndelete `pills`? (y/n)"));
+        assert!(frames[4].ends_with('\n'));
+        assert!(frames[6].contains("\n`water` is next at 2020-01-01 00:00:00 UTC ("));
+        assert!(frames[7].ends_with("\ndelete `water`? (y/n)"));
+        assert!(frames[8].contains("\n>  pills"));
+        assert!(!frames[8].contains("water  "));
+        assert!(frames[8].ends_with("\ndeleted `water`"));
+    }
+}
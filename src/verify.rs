@@ -0,0 +1,130 @@
+//! Checking that schedule files decode, and encode back to the same bytes, for `verify`.
+
+use crate::{
+    error::{HrtError, Result},
+    schedule::{RegularSchedule, decode_schedule_trailing, encode_schedule_as},
+    store::Store,
+};
+
+/// What `verify` found out about one schedule file.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Verification {
+    pub name: String,
+    /// Why the file failed, if it did: it couldn't be read or decoded, or decoding and encoding
+    /// it again gave different bytes, which means something would change it just by saving it.
+    pub failure: Option<String>,
+    /// Whether `<name>.bak` is byte for byte the same as the file, if there is one.
+    pub backup: Option<bool>,
+}
+impl Verification {
+    /// Checks the schedule called `name` in `store`. Fails only if it doesn't exist.
+    pub fn of(store: &impl Store, name: &str) -> Result<Self> {
+        let bytes = store.load(name);
+        if let Err(HrtError::NotFound { .. }) = bytes {
+            return Err(HrtError::NotFound {
+                name: name.to_string(),
+            });
+        }
+        let backup = match store.load(&format!("{name}.bak")) {
+            Err(HrtError::NotFound { .. }) => None,
+            backup => Some(matches!((&backup, &bytes), (Ok(backup), Ok(bytes)) if backup == bytes)),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            failure: bytes
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| check(&bytes))
+                .err(),
+            backup,
+        })
+    }
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Decodes `bytes` and encodes the schedule again in the version it was read with, failing with
+/// what went wrong unless that gives back `bytes` exactly.
+pub fn check(bytes: &[u8]) -> std::result::Result<(), String> {
+    let (schedule, trailing): (RegularSchedule, _) =
+        decode_schedule_trailing(bytes).map_err(|error| format!("doesn't decode: {error}"))?;
+    if trailing > 0 {
+        return Err(format!("{trailing} unexpected trailing bytes"));
+    }
+    let encoded = encode_schedule_as(&schedule, schedule.version())
+        .map_err(|error| format!("doesn't encode again: {error}"))?;
+    if encoded == bytes {
+        return Ok(());
+    }
+    let at = bytes
+        .iter()
+        .zip(&encoded)
+        .position(|(old, new)| old != new)
+        .unwrap_or(bytes.len().min(encoded.len()));
+    Err(format!(
+        "encodes again differently, from byte {at} ({} bytes instead of {})",
+        encoded.len(),
+        bytes.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use crate::{schedule::encode_schedule, store::MemStore};
+
+    fn encoded() -> Vec<u8> {
+        let next: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        encode_schedule(&RegularSchedule::create(next, TimeDelta::days(1))).unwrap()
+    }
+
+    #[test]
+    fn schedules_encode_back_to_the_same_bytes() {
+        assert_eq!(check(&encoded()), Ok(()));
+    }
+
+    #[test]
+    fn garbage_and_trailing_bytes_fail() {
+        assert!(check(b"\xff").unwrap_err().starts_with("doesn't decode: "));
+        let mut bytes = encoded();
+        bytes.extend([0, 0]);
+        assert_eq!(
+            check(&bytes),
+            Err("2 unexpected trailing bytes".to_string())
+        );
+    }
+
+    #[test]
+    fn backups_are_compared_when_there_are_any() {
+        let store = MemStore::new();
+        store.save("water", &encoded()).unwrap();
+        let verification = Verification::of(&store, "water").unwrap();
+        assert!(verification.passed());
+        assert_eq!(verification.backup, None);
+
+        store.save("water.bak", &encoded()).unwrap();
+        assert_eq!(
+            Verification::of(&store, "water").unwrap().backup,
+            Some(true)
+        );
+        store.save("water.bak", b"\xff").unwrap();
+        assert_eq!(
+            Verification::of(&store, "water").unwrap().backup,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn broken_files_fail_and_missing_ones_are_errors() {
+        let store = MemStore::new();
+        store.save("broken", b"\xff").unwrap();
+        let verification = Verification::of(&store, "broken").unwrap();
+        assert!(!verification.passed());
+        assert!(matches!(
+            Verification::of(&store, "missing"),
+            Err(HrtError::NotFound { name }) if name == "missing"
+        ));
+    }
+}
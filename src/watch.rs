@@ -0,0 +1,116 @@
+//! Noticing schedules as they become due, for `watch`.
+
+use std::{collections::HashSet, thread, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::Result,
+    listing::{self, SortKey},
+    schedule::{RegularSchedule, Schedule},
+    store::{Loaded, Store, load_all},
+};
+
+/// How long `run` waits between looks at the store.
+pub const POLL: Duration = Duration::from_secs(15);
+
+/// The occurrences already announced, so that each is only announced once.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Watcher {
+    notified: HashSet<(String, DateTime<Utc>)>,
+}
+impl Watcher {
+    /// The schedules in `schedules` that are due at `now` and weren't announced for the
+    /// occurrence that is due, soonest due first. They count as announced from then on, until
+    /// they are stepped and their new `next` passes too.
+    pub fn poll(
+        &mut self,
+        schedules: Vec<(String, RegularSchedule)>,
+        now: DateTime<Utc>,
+    ) -> Vec<(String, RegularSchedule)> {
+        let mut due: Vec<_> = schedules
+            .into_iter()
+            .filter(|(_, schedule)| schedule.next_at() <= now)
+            .collect();
+        listing::sort(&mut due, SortKey::Next, now);
+        // forget schedules that have been stepped since, so the set doesn't grow forever
+        self.notified.retain(|(name, next)| {
+            due.iter()
+                .any(|(due_name, schedule)| due_name == name && schedule.next == *next)
+        });
+        due.retain(|(name, schedule)| self.notified.insert((name.clone(), schedule.next_at())));
+        due
+    }
+}
+
+/// Polls `store` every `POLL` until interrupted, calling `announce` with the name of each schedule
+/// `Watcher::poll` finds newly due (straight away, for those that already are), the schedule, and
+/// the time it was found at.
+pub fn run(
+    store: &(impl Store + Sync),
+    mut announce: impl FnMut(&str, &RegularSchedule, DateTime<Utc>),
+) -> Result<()> {
+    let mut watcher = Watcher::default();
+    loop {
+        let now = Utc::now();
+        let Loaded { schedules, .. } = load_all::<RegularSchedule>(store)?;
+        for (name, schedule) in watcher.poll(schedules, now) {
+            announce(&name, &schedule, now);
+        }
+        thread::sleep(POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    fn schedules() -> Vec<(String, RegularSchedule)> {
+        [
+            ("water", "2025-03-01T09:00:00Z"),
+            ("pills", "2025-03-01T08:00:00Z"),
+            ("rest", "2025-03-02T09:00:00Z"),
+        ]
+        .into_iter()
+        .map(|(name, next)| {
+            let schedule = RegularSchedule::create(date(next), TimeDelta::days(1));
+            (name.to_string(), schedule)
+        })
+        .collect()
+    }
+
+    fn names(due: &[(String, RegularSchedule)]) -> Vec<&str> {
+        due.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn due_schedules_are_announced_once_each() {
+        let mut watcher = Watcher::default();
+        let now = date("2025-03-01T10:00:00Z");
+        assert_eq!(names(&watcher.poll(schedules(), now)), ["pills", "water"]);
+        assert!(watcher.poll(schedules(), now).is_empty());
+        let later = date("2025-03-02T09:00:00Z");
+        assert_eq!(names(&watcher.poll(schedules(), later)), ["rest"]);
+    }
+
+    #[test]
+    fn stepped_schedules_are_announced_again_once_due_again() {
+        let mut watcher = Watcher::default();
+        let now = date("2025-03-01T10:00:00Z");
+        watcher.poll(schedules(), now);
+        let mut stepped = schedules();
+        stepped[0].1.step(now).unwrap();
+        assert!(watcher.poll(stepped.clone(), now).is_empty());
+        // only `pills` is left from before
+        assert_eq!(watcher.notified.len(), 1);
+        let tomorrow = date("2025-03-02T09:30:00Z");
+        // those due at the same time are in order of name
+        assert_eq!(names(&watcher.poll(stepped, tomorrow)), ["rest", "water"]);
+    }
+}
@@ -0,0 +1,375 @@
+//! Webhooks: a small JSON `POST` sent when a schedule is stepped or becomes due, over a minimal
+//! HTTP/1.1 client that push notifications share. `https://` URLs need the `tls` feature.
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::{HrtError, Result},
+    json,
+    schedule::RegularSchedule,
+};
+
+/// The priority push notifications are sent with for schedules that have none, which is the
+/// middle of the 1 to 5 range.
+pub const PUSH_PRIORITY: u8 = 3;
+
+/// How long connecting, sending the request, and reading the response may each take, so that a
+/// dead endpoint can't hang whatever triggered it.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a webhook is being called.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Event {
+    /// The schedule was stepped.
+    Step,
+    /// The schedule became due.
+    Due,
+}
+impl Event {
+    /// How the event is written in the request body.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Step => "step",
+            Self::Due => "due",
+        }
+    }
+}
+
+/// The parts of an `http://` or `https://` URL needed to send a request.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Url {
+    pub tls: bool,
+    /// The host as written, including the brackets around an IPv6 address.
+    pub host: String,
+    pub port: u16,
+    /// The path and query, starting with `/`.
+    pub path: String,
+}
+impl Url {
+    pub fn parse(url: &str) -> Result<Self> {
+        let invalid = |reason: &str| HrtError::Webhook {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        };
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else {
+            return Err(invalid("only `http://` and `https://` URLs are supported"));
+        };
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        if authority.contains('@') {
+            return Err(invalid("URLs with credentials are not supported"));
+        }
+        // a colon inside the brackets of an IPv6 address isn't a port separator
+        let port_start = authority
+            .rfind(':')
+            .filter(|&index| !authority[index..].contains(']'));
+        let (host, port) = match port_start {
+            Some(index) => {
+                let port = authority[index + 1..]
+                    .parse()
+                    .map_err(|_| invalid("the port is not a number"))?;
+                (&authority[..index], port)
+            }
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(invalid("the URL has no host"));
+        }
+        let path = match path.starts_with('/') {
+            true => path.to_string(),
+            false => format!("/{path}"),
+        };
+        Ok(Self {
+            tls,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+    /// The host without brackets around it, for resolving and for TLS.
+    pub fn bare_host(&self) -> &str {
+        self.host.trim_start_matches('[').trim_end_matches(']')
+    }
+}
+
+/// The JSON body sent for `event` on the schedule called `name`.
+pub fn body(name: &str, event: Event, next: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    format!(
+        r#"{{"name":{},"event":"{}","next":{},"timestamp":{}}}"#,
+        json::string(name),
+        event.name(),
+        json::timestamp(next),
+        json::timestamp(now)
+    )
+}
+
+/// Sends `event` to the webhook of `schedule`, called `name`, if it has one.
+pub fn call(
+    name: &str,
+    schedule: &RegularSchedule,
+    event: Event,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    match schedule.webhook() {
+        Some(url) => post(url, &body(name, event, schedule.next, now)),
+        None => Ok(()),
+    }
+}
+
+/// Sends a push notification with `title` and `message` to `url` as an ntfy-style `PUT`, at
+/// `priority` or `PUSH_PRIORITY`, retrying once if it fails.
+pub fn push(url: &str, title: &str, message: &str, priority: Option<u8>) -> Result<()> {
+    let priority = priority.unwrap_or(PUSH_PRIORITY).to_string();
+    let headers = [
+        ("Title", title),
+        ("Priority", priority.as_str()),
+        ("Content-Type", "text/plain; charset=utf-8"),
+    ];
+    let send = || request("PUT", url, &headers, message);
+    send().or_else(|_| send())
+}
+
+/// Sends `body` to `url` as JSON, failing unless the response has a 2xx status.
+pub fn post(url: &str, body: &str) -> Result<()> {
+    request("POST", url, &[("Content-Type", "application/json")], body)
+}
+
+/// Sends a `method` request with `headers` and `body` to `url`, failing unless the response has a
+/// 2xx status. Line breaks in header values are replaced with spaces, so that they can't end the
+/// header early.
+pub fn request(method: &str, url: &str, headers: &[(&str, &str)], body: &str) -> Result<()> {
+    let parsed = Url::parse(url)?;
+    let failed = |reason: String| HrtError::Webhook {
+        url: url.to_string(),
+        reason,
+    };
+    let stream = connect(&parsed).map_err(|error| failed(error.to_string()))?;
+    let host = match parsed.port {
+        80 if !parsed.tls => parsed.host.clone(),
+        443 if parsed.tls => parsed.host.clone(),
+        port => format!("{}:{port}", parsed.host),
+    };
+    let headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {}\r\n", value.replace(['\r', '\n'], " ")))
+        .collect();
+    let request = format!(
+        "{method} {} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         User-Agent: hrtracker/{}\r\n\
+         {headers}\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        parsed.path,
+        env!("CARGO_PKG_VERSION"),
+        body.len()
+    );
+    let status = if parsed.tls {
+        send(tls(&parsed, stream).map_err(failed)?, &request)
+    } else {
+        send(stream, &request)
+    }
+    .map_err(|error| failed(error.to_string()))?;
+    match status {
+        200..300 => Ok(()),
+        status => Err(failed(format!("the server responded with status {status}"))),
+    }
+}
+
+fn connect(url: &Url) -> io::Result<TcpStream> {
+    let mut last = None;
+    for address in (url.bare_host(), url.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(error) => last = Some(error),
+        }
+    }
+    Err(last.unwrap_or_else(|| io::Error::other("the host has no addresses")))
+}
+
+#[cfg(feature = "tls")]
+fn tls(url: &Url, stream: TcpStream) -> std::result::Result<impl Read + Write, String> {
+    let connector = native_tls::TlsConnector::new().map_err(|error| error.to_string())?;
+    connector
+        .connect(url.bare_host(), stream)
+        .map_err(|error| error.to_string())
+}
+#[cfg(not(feature = "tls"))]
+fn tls(_: &Url, _: TcpStream) -> std::result::Result<TcpStream, String> {
+    Err("`https://` URLs need hrtracker to be built with the `tls` feature".to_string())
+}
+
+/// Writes `request` and reads back the status code of the response.
+fn send(mut stream: impl Read + Write, request: &str) -> io::Result<u16> {
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+    // only the status line matters, so the rest of the response is never read
+    let mut response = Vec::new();
+    let mut buffer = [0; 256];
+    while !response.windows(2).any(|window| window == b"\r\n") && response.len() < 4096 {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        response.extend_from_slice(&buffer[..read]);
+    }
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response");
+    let line = String::from_utf8_lossy(&response);
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(version), Some(status)) if version.starts_with("HTTP/") => {
+            status.parse().map_err(|_| invalid())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn date(repr: &str) -> DateTime<Utc> {
+        repr.parse().unwrap()
+    }
+
+    /// Answers a request on a local port with each of `statuses` in turn, returning the URL to
+    /// send them to and the requests as they arrived.
+    fn server(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/topic", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    // read all of it, since closing with some of it unread resets the connection
+                    while !complete(&request) {
+                        let read = stream.read(&mut buffer).unwrap();
+                        if read == 0 {
+                            break;
+                        }
+                        request.extend_from_slice(&buffer[..read]);
+                    }
+                    write!(
+                        stream,
+                        "HTTP/1.1 {status} Whatever\r\nContent-Length: 0\r\n\r\n"
+                    )
+                    .unwrap();
+                    String::from_utf8(request).unwrap()
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    /// Whether `request` has its whole body, as long as its `Content-Length` says.
+    fn complete(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let Some((head, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        body.len() >= length
+    }
+
+    #[test]
+    fn urls_are_split_into_their_parts() {
+        let url = Url::parse("https://[::1]:8443?topic=a").unwrap();
+        assert!(url.tls);
+        assert_eq!((url.host.as_str(), url.port), ("[::1]", 8443));
+        assert_eq!((url.bare_host(), url.path.as_str()), ("::1", "/?topic=a"));
+        let url = Url::parse("http://example.com").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+        for bad in [
+            "ftp://example.com",
+            "http://user@example.com/",
+            "http://example.com:http/",
+            "http:///path",
+        ] {
+            assert!(Url::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn bodies_name_the_event() {
+        assert_eq!(
+            body(
+                "water",
+                Event::Due,
+                date("2025-03-02T09:00:00Z"),
+                date("2025-03-01T09:00:00Z")
+            ),
+            r#"{"name":"water","event":"due","next":"2025-03-02T09:00:00Z","timestamp":"2025-03-01T09:00:00Z"}"#
+        );
+    }
+
+    #[test]
+    fn requests_fail_without_a_successful_status() {
+        let (url, server) = server(vec![204, 404]);
+        request("POST", &url, &[("X-Note", "two\r\nlines")], "{}").unwrap();
+        let error = request("POST", &url, &[], "{}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("request to `{url}` failed: the server responded with status 404")
+        );
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /topic HTTP/1.1\r\n"));
+        // the line break can't start a header of its own
+        assert!(requests[0].contains("\r\nX-Note: two  lines\r\n"));
+        assert!(requests[0].ends_with("Content-Length: 2\r\nConnection: close\r\n\r\n{}"));
+    }
+
+    #[test]
+    fn pushes_are_retried_once() {
+        let (url, server) = server(vec![500, 200, 500, 500]);
+        push(&url, "water is due", "drink", None).unwrap();
+        assert!(push(&url, "water is due", "drink", Some(5)).is_err());
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0], requests[1]);
+        assert!(requests[0].starts_with("PUT /topic HTTP/1.1\r\n"));
+        assert!(requests[0].contains("\r\nTitle: water is due\r\nPriority: 3\r\n"));
+        assert!(requests[0].ends_with("\r\n\r\ndrink"));
+        assert!(requests[2].contains("\r\nPriority: 5\r\n"));
+    }
+
+    #[test]
+    fn webhooks_are_only_called_when_set() {
+        let now = date("2025-03-01T09:00:00Z");
+        let mut schedule = RegularSchedule::create(now, TimeDelta::days(1));
+        call("water", &schedule, Event::Step, now).unwrap();
+        let (url, server) = server(vec![200]);
+        schedule.set_webhook(Some(url));
+        call("water", &schedule, Event::Step, now).unwrap();
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /topic HTTP/1.1\r\n"));
+        assert!(requests[0].ends_with(&body("water", Event::Step, schedule.next, now)));
+    }
+}
@@ -0,0 +1,196 @@
+//! The command line as a user sees it, by running the binary.
+
+mod common;
+
+use std::fs;
+
+use common::{DataDir, code, stderr};
+
+#[test]
+fn list_is_only_colored_when_asked_or_on_a_terminal() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "now", "1d+00"]);
+    // stdout is a pipe here, so `auto` leaves colors out
+    assert!(!dir.ok(&["list"]).contains('\x1b'));
+    assert!(!dir.ok(&["list", "--color", "never"]).contains('\x1b'));
+    assert!(dir.ok(&["list", "--color", "always"]).contains("\x1b["));
+    let output = dir.run_with(&["list", "--color", "auto"], &[("NO_COLOR", "1")]);
+    assert!(!common::stdout(&output).contains('\x1b'));
+}
+
+/// Two schedules long overdue, so that nothing in them depends on when the test runs: `water`
+/// daily and stepped twice, and `pills` every 12 hours and never stepped.
+fn overdue_schedules() -> DataDir {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "2020-01-01T09:00:00Z", "1d+00"]);
+    dir.ok(&["step", "water", "--at", "2020-01-01T10:00:00Z"]);
+    dir.ok(&["step", "water", "--at", "2020-01-02T08:30:00Z"]);
+    dir.ok(&["new", "pills", "2020-01-01T00:00:00Z", "12"]);
+    dir
+}
+
+/// `output` with the tab-separated `column` of each line replaced by `-`, for the columns that
+/// depend on the current time.
+fn mask(output: &str, column: usize) -> String {
+    output
+        .lines()
+        .map(|line| {
+            let mut fields: Vec<_> = line.split('\t').collect();
+            fields[column] = "-";
+            fields.join("\t") + "\n"
+        })
+        .collect()
+}
+
+#[test]
+fn porcelain_output_matches_the_golden_files() {
+    let dir = overdue_schedules();
+    assert_eq!(
+        mask(&dir.ok(&["list", "--porcelain"]), 2),
+        include_str!("golden/list.txt")
+    );
+    assert_eq!(
+        dir.ok(&["history", "water", "--porcelain"]),
+        include_str!("golden/history.txt")
+    );
+    assert_eq!(
+        dir.ok(&["count", "--porcelain"]),
+        include_str!("golden/count.txt")
+    );
+    assert_eq!(
+        dir.ok(&["diff", "pills", "water", "--porcelain"]),
+        include_str!("golden/diff.txt")
+    );
+}
+
+#[test]
+fn seconds_columns_stay_numbers_with_iso_durations() {
+    let dir = overdue_schedules();
+    let iso = ["--duration-format", "iso"];
+    let list = dir.ok(&[&["list", "--porcelain"][..], &iso].concat());
+    assert_eq!(
+        mask(&list, 2),
+        "pills\t2020-01-01T00:00:00Z\t-\tPT12H\nwater\t2020-01-03T09:00:00Z\t-\tP1D\n"
+    );
+    let export = dir.ok(&[&["export", "--csv"][..], &iso].concat());
+    let intervals: Vec<_> = export
+        .lines()
+        .map(|line| line.split(',').nth(2).unwrap())
+        .collect();
+    assert_eq!(intervals, ["interval_seconds", "43200", "86400"]);
+}
+
+#[test]
+fn saves_replace_the_file_instead_of_writing_over_it() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "2020-01-01T09:00:00Z", "1d+00"]);
+    let water = dir.path.join("water");
+    for durable in [&[][..], &["--durable"]] {
+        // a second link to the old file keeps what it held if the file is replaced, but would see
+        // the new contents if it were written over
+        let old = dir.path.join("old");
+        fs::hard_link(&water, &old).unwrap();
+        let before = fs::read(&water).unwrap();
+        dir.ok(&[&["step", "water"][..], durable].concat());
+        assert_eq!(fs::read(&old).unwrap(), before);
+        assert_ne!(fs::read(&water).unwrap(), before);
+        assert!(!dir.path.join(".water.tmp").exists());
+        fs::remove_file(&old).unwrap();
+    }
+}
+
+#[test]
+fn introspection_matches_the_golden_file() {
+    let dir = overdue_schedules();
+    let output = dir.ok(&["_introspect"]);
+    // one line for tools, compared by value so that the golden file can be indented
+    assert_eq!(output.lines().count(), 1);
+    let introspected: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let golden: serde_json::Value =
+        serde_json::from_str(include_str!("golden/introspect.json")).unwrap();
+    assert_eq!(introspected, golden);
+    assert_eq!(dir.ok(&["_names"]), "pills\nwater\n");
+}
+
+#[test]
+fn exit_codes_match_the_documented_table() {
+    let dir = DataDir::new();
+    assert_eq!(
+        code(&dir.run(&["new", "water", "2020-01-01T00:00:00Z", "1d+00"])),
+        0
+    );
+    assert_eq!(code(&dir.run(&["frobnicate"])), 2);
+    assert_eq!(code(&dir.run(&["new", "pills", "soon", "1d+00"])), 2);
+    assert_eq!(code(&dir.run(&["show", "missing"])), 3);
+    fs::write(dir.path.join("broken"), b"not a schedule").unwrap();
+    assert_eq!(code(&dir.run(&["show", "broken"])), 4);
+    fs::remove_file(dir.path.join("broken")).unwrap();
+    assert_eq!(code(&dir.run(&["due"])), 5);
+    assert_eq!(code(&dir.run(&["count", "--fail-if-overdue"])), 5);
+    assert_eq!(code(&dir.run(&["count"])), 0);
+
+    dir.ok(&["new", "once", "now", "1d+00", "--count", "1"]);
+    dir.ok(&["step", "once"]);
+    assert_eq!(code(&dir.run(&["step", "once"])), 6);
+}
+
+#[test]
+fn doctor_fails_on_names_that_only_differ_by_case() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "now", "1d+00"]);
+    assert_eq!(code(&dir.run(&["doctor"])), 0);
+    assert_eq!(code(&dir.run(&["new", "Water", "now", "1d+00"])), 2);
+    dir.ok(&["new", "Water", "now", "1d+00", "--force"]);
+    assert_eq!(code(&dir.run(&["doctor"])), 1);
+}
+
+/// Fails unless `output` is a clean error exit with `code`, rather than a panic.
+fn assert_clean_error(output: &std::process::Output, code: i32) {
+    let stderr = stderr(output);
+    assert_eq!(common::code(output), code, "{stderr}");
+    assert!(stderr.starts_with("error: "), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn a_missing_data_directory_is_a_clean_error() {
+    let dir = DataDir::new();
+    let output = dir
+        .command(&["list"])
+        .env_remove("HRTRACKER_DIR")
+        .env_remove("HOME")
+        .output()
+        .unwrap();
+    assert_clean_error(&output, 1);
+    assert!(stderr(&output).contains("set `HOME` or `HRTRACKER_DIR`"));
+
+    let output = dir
+        .command(&["list"])
+        .env("HRTRACKER_DIR", "")
+        .env_remove("HOME")
+        .output()
+        .unwrap();
+    assert_clean_error(&output, 1);
+
+    let file = dir.path.join("file");
+    fs::write(&file, "").unwrap();
+    let output = dir
+        .command(&["list"])
+        .env("HRTRACKER_DIR", &file)
+        .output()
+        .unwrap();
+    assert_clean_error(&output, 1);
+    assert!(stderr(&output).contains("not a directory"));
+}
+
+#[test]
+fn bad_input_is_a_clean_error() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "now", "1d+00"]);
+    assert_clean_error(&dir.run(&["new", "pills", "now", "24"]), 2);
+    assert_clean_error(&dir.run(&["new", "../escape", "now", "1d+00"]), 2);
+    assert_clean_error(&dir.run(&["step", "water", "--at"]), 2);
+    assert_clean_error(&dir.run(&["forecast", "water", "many"]), 2);
+    fs::write(dir.path.join("empty"), "").unwrap();
+    assert_clean_error(&dir.run(&["show", "empty"]), 4);
+}
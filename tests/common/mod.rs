@@ -0,0 +1,93 @@
+//! Runs the `hrtracker` binary against a data directory of its own, so that tests never touch the
+//! real one and can run in parallel.
+
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The variables that would change what hrtracker does from outside the test.
+const CLEARED: &[&str] = &[
+    "NO_COLOR",
+    "HRTRACKER_THREADS",
+    "HRTRACKER_DEFAULT_START",
+    "HRTRACKER_DEFAULT_INTERVAL",
+    "VISUAL",
+    "EDITOR",
+];
+
+/// A fresh data directory, removed when dropped.
+pub struct DataDir {
+    pub path: PathBuf,
+}
+impl DataDir {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "hrtracker-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("the temporary directory is writable");
+        Self { path }
+    }
+    /// Runs hrtracker with `args`, in UTC and with nothing on stdin.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args)
+            .output()
+            .expect("the hrtracker binary runs")
+    }
+    /// Like `run`, with `env` set as well.
+    pub fn run_with(&self, args: &[&str], env: &[(&str, &str)]) -> Output {
+        let mut command = self.command(args);
+        command.envs(env.iter().copied());
+        command.output().expect("the hrtracker binary runs")
+    }
+    /// The stdout of running hrtracker with `args`, which must succeed.
+    pub fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "`hrtracker {}` failed with {:?}: {}",
+            args.join(" "),
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).expect("the output is UTF-8")
+    }
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_hrtracker"));
+        command
+            .args(args)
+            .env("HRTRACKER_DIR", &self.path)
+            .env("TZ", "UTC")
+            .stdin(Stdio::null());
+        for variable in CLEARED {
+            command.env_remove(variable);
+        }
+        command
+    }
+}
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The exit code of `output`, which must not have been ended by a signal.
+pub fn code(output: &Output) -> i32 {
+    output.status.code().expect("hrtracker exited normally")
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,146 @@
+//! The C ABI, called from Rust and from the C program in `tests/ffi/smoke.c`.
+
+use std::{
+    env,
+    ffi::CString,
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    ptr,
+};
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use hrtracker::{ffi::*, schedule::RegularSchedule};
+
+/// A directory of its own for each test, holding a daily schedule `water` first due at the start of
+/// 2020, which is `NEXT` seconds after the Unix epoch.
+struct Dir(PathBuf);
+impl Dir {
+    fn new(test: &str) -> Self {
+        let dir = env::temp_dir().join(format!("hrtracker-ffi-{test}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        RegularSchedule::create(start, TimeDelta::days(1))
+            .save(dir.join("water"))
+            .unwrap();
+        Self(dir)
+    }
+    fn water(&self) -> PathBuf {
+        self.0.join("water")
+    }
+}
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+const NEXT: i64 = 1_577_836_800;
+
+fn c_path(path: &Path) -> CString {
+    CString::new(path.to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn null_arguments_are_reported() {
+    unsafe {
+        assert!(hrt_open(ptr::null()).is_null());
+        assert_eq!(hrt_next_unix(ptr::null()), HRT_INVALID);
+        assert_eq!(hrt_interval_seconds(ptr::null()), HRT_INVALID);
+        let path = c_path(Path::new("water"));
+        assert_eq!(hrt_step(ptr::null_mut(), path.as_ptr()), HRT_ERR_NULL);
+        hrt_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn unreadable_files_give_null() {
+    let dir = Dir::new("unreadable");
+    fs::write(dir.0.join("broken"), b"not a schedule").unwrap();
+    unsafe {
+        assert!(hrt_open(c_path(&dir.0.join("missing")).as_ptr()).is_null());
+        assert!(hrt_open(c_path(&dir.0.join("broken")).as_ptr()).is_null());
+        assert!(hrt_open(c"\xff".as_ptr()).is_null());
+    }
+}
+
+#[test]
+fn stepping_saves_the_schedule() {
+    let dir = Dir::new("step");
+    let path = c_path(&dir.water());
+    unsafe {
+        let schedule = hrt_open(path.as_ptr());
+        assert!(!schedule.is_null());
+        assert_eq!(hrt_next_unix(schedule), NEXT);
+        assert_eq!(hrt_interval_seconds(schedule), 86_400);
+
+        // a failed save leaves the schedule as it was
+        let elsewhere = c_path(&dir.0.join("missing").join("water"));
+        assert_eq!(hrt_step(schedule, elsewhere.as_ptr()), HRT_ERR_IO);
+        assert_eq!(hrt_next_unix(schedule), NEXT);
+
+        assert_eq!(hrt_step(schedule, path.as_ptr()), HRT_OK);
+        assert_eq!(hrt_next_unix(schedule), NEXT + 86_400);
+        hrt_free(schedule);
+    }
+    let saved = RegularSchedule::open(dir.water()).unwrap();
+    assert_eq!(saved.next.timestamp(), NEXT + 86_400);
+    assert_eq!(saved.total_steps(), 1);
+}
+
+/// The directory `cargo` put the `cdylib` in, if it was built: the one above this test's own
+/// `deps` directory, or `deps` itself.
+fn library_dir() -> Option<PathBuf> {
+    let name = format!(
+        "{}hrtracker{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    );
+    let deps = env::current_exe().ok()?.parent()?.to_path_buf();
+    [deps.parent().map(Path::to_path_buf), Some(deps)]
+        .into_iter()
+        .flatten()
+        .find(|dir| dir.join(&name).is_file())
+}
+
+#[cfg(unix)]
+#[test]
+fn the_c_program_runs_against_the_library() {
+    let Some(library) = library_dir() else {
+        eprintln!("skipped: the cdylib wasn't built");
+        return;
+    };
+    let dir = Dir::new("c");
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let program = dir.0.join("smoke");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let compiled = Command::new(&compiler)
+        .arg(manifest.join("tests/ffi/smoke.c"))
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg("-L")
+        .arg(&library)
+        .arg(format!("-Wl,-rpath,{}", library.display()))
+        .arg("-lhrtracker")
+        .arg("-o")
+        .arg(&program)
+        .status();
+    match compiled {
+        Ok(status) => assert!(status.success(), "the C program didn't compile"),
+        Err(error) => {
+            eprintln!("skipped: `{compiler}` can't be run: {error}");
+            return;
+        }
+    }
+    let output = Command::new(&program)
+        .arg(dir.water())
+        .arg(NEXT.to_string())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "the C program failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
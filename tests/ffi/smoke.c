@@ -0,0 +1,45 @@
+/* Checks the bindings in `include/hrtracker.h` against the schedule file at `argv[1]`, which is
+ * due at `argv[2]` (in seconds since the Unix epoch) and repeats daily. Run by `tests/ffi.rs`,
+ * which exits with the number of the first check that failed. */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "hrtracker.h"
+
+#define CHECK(number, condition)                                                                  \
+    if (!(condition)) {                                                                           \
+        fprintf(stderr, "check %d failed: %s\n", number, #condition);                             \
+        return number;                                                                            \
+    }
+
+int main(int argc, char **argv) {
+    if (argc != 3) {
+        fprintf(stderr, "usage: smoke <path> <next>\n");
+        return 100;
+    }
+    const char *path = argv[1];
+    int64_t next = strtoll(argv[2], NULL, 10);
+
+    CHECK(1, hrt_open(NULL) == NULL);
+    CHECK(2, hrt_next_unix(NULL) == HRT_INVALID);
+    CHECK(3, hrt_interval_seconds(NULL) == HRT_INVALID);
+    CHECK(4, hrt_step(NULL, path) == HRT_ERR_NULL);
+    hrt_free(NULL);
+
+    HrtSchedule *schedule = hrt_open(path);
+    CHECK(5, schedule != NULL);
+    CHECK(6, hrt_next_unix(schedule) == next);
+    CHECK(7, hrt_interval_seconds(schedule) == 86400);
+    CHECK(8, hrt_step(schedule, NULL) == HRT_ERR_NULL);
+    CHECK(9, hrt_next_unix(schedule) == next);
+    CHECK(10, hrt_step(schedule, path) == HRT_OK);
+    CHECK(11, hrt_next_unix(schedule) == next + 86400);
+    hrt_free(schedule);
+
+    schedule = hrt_open(path);
+    CHECK(12, schedule != NULL);
+    CHECK(13, hrt_next_unix(schedule) == next + 86400);
+    hrt_free(schedule);
+    return 0;
+}
@@ -0,0 +1,80 @@
+//! The library as another tool would use it, through its public API alone.
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use decent::PrimitiveRepr;
+use hrtracker::{
+    error::HrtError,
+    format::{decode_datetime, encode_datetime},
+    parse::{parse_datetime, parse_timedelta, validate_name},
+    schedule::{LATEST, RegularSchedule, decode_schedule, encode_schedule},
+    store::{MemStore, Store, load_all, open_named, save_named},
+};
+
+#[test]
+fn datetimes_round_trip_through_the_codecs() {
+    let date = Utc.with_ymd_and_hms(2025, 3, 1, 9, 30, 15).unwrap();
+    let mut bytes = Vec::new();
+    encode_datetime(&date, &mut bytes, LATEST, PrimitiveRepr::Varint).unwrap();
+    let decoded = decode_datetime(&mut &bytes[..], LATEST, PrimitiveRepr::Varint).unwrap();
+    assert_eq!(decoded, date);
+}
+
+#[test]
+fn parsed_schedules_round_trip_through_a_store() {
+    let start = parse_datetime("2025-03-01T09:00:00Z").unwrap();
+    let interval = parse_timedelta("1d+00").unwrap();
+    assert_eq!(interval, TimeDelta::days(1));
+
+    let mut schedule = RegularSchedule::create(start, interval);
+    schedule
+        .step(parse_datetime("2025-03-01T10:00:00Z").unwrap())
+        .unwrap();
+    assert_eq!(schedule.next, start + interval);
+
+    let store = MemStore::new();
+    validate_name("water").unwrap();
+    save_named(&store, "water", &schedule).unwrap();
+    let opened: RegularSchedule = open_named(&store, "water").unwrap();
+    assert_eq!(opened, schedule);
+    assert_eq!(opened.total_steps(), 1);
+}
+
+#[test]
+fn encoded_schedules_decode_to_the_same_schedule() {
+    let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let schedule = RegularSchedule::create(start, TimeDelta::hours(12));
+    let bytes = encode_schedule(&schedule).unwrap();
+    let decoded: RegularSchedule = decode_schedule(&bytes).unwrap();
+    assert_eq!(decoded, schedule);
+}
+
+#[test]
+fn missing_and_unreadable_schedules_are_reported() {
+    let store = MemStore::new();
+    assert!(matches!(
+        open_named::<RegularSchedule>(&store, "missing"),
+        Err(HrtError::NotFound { name }) if name == "missing"
+    ));
+
+    let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    save_named(
+        &store,
+        "water",
+        &RegularSchedule::create(start, TimeDelta::days(1)),
+    )
+    .unwrap();
+    store.save("broken", b"not a schedule").unwrap();
+    let loaded = load_all::<RegularSchedule>(&store).unwrap();
+    let names: Vec<_> = loaded.schedules.iter().map(|(name, _)| &name[..]).collect();
+    assert_eq!(names, ["water"]);
+    assert!(matches!(&loaded.unreadable[..], [HrtError::Decode { .. }]));
+}
+
+#[test]
+fn names_are_validated() {
+    assert!(validate_name("water-plants").is_ok());
+    assert!(matches!(
+        validate_name("../escape"),
+        Err(HrtError::InvalidName { .. })
+    ));
+}
@@ -0,0 +1,73 @@
+//! The async storage API driving a whole create, step, and list cycle on a tokio runtime.
+
+use std::{env, fs, future::Future, process};
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use hrtracker::{
+    error::HrtError,
+    nonblocking::{AsyncStore, open_async, save_async},
+    schedule::RegularSchedule,
+    store::{DirStore, MemStore},
+};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("the runtime starts")
+        .block_on(future)
+}
+
+/// Creates `water`, steps it, and checks that listing and reopening it see the step.
+async fn cycle(store: &impl AsyncStore) {
+    let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+    let schedule = RegularSchedule::create(start, TimeDelta::days(1));
+    save_async(store, "water", &schedule).await.unwrap();
+
+    let mut opened: RegularSchedule = open_async(store, "water").await.unwrap();
+    assert_eq!(opened, schedule);
+    opened.step(start).unwrap();
+    save_async(store, "water", &opened).await.unwrap();
+
+    assert_eq!(store.list().await.unwrap(), ["water"]);
+    let stepped: RegularSchedule = open_async(store, "water").await.unwrap();
+    assert_eq!(stepped.next, start + TimeDelta::days(1));
+    assert_eq!(stepped.total_steps(), 1);
+
+    store.delete("water").await.unwrap();
+    assert!(store.list().await.unwrap().is_empty());
+    assert!(matches!(
+        open_async::<RegularSchedule>(store, "water").await,
+        Err(HrtError::NotFound { .. })
+    ));
+    assert!(matches!(
+        store.delete("water").await,
+        Err(HrtError::NotFound { .. })
+    ));
+}
+
+#[test]
+fn a_memory_store_goes_through_a_whole_cycle() {
+    block_on(cycle(&MemStore::new()));
+}
+
+#[test]
+fn a_directory_store_goes_through_a_whole_cycle() {
+    let dir = env::temp_dir().join(format!("hrtracker-nonblocking-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    block_on(cycle(&DirStore::new(&dir)));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn oversized_files_are_refused() {
+    let dir = env::temp_dir().join(format!("hrtracker-nonblocking-large-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("large"), [0; 64]).unwrap();
+    let store = DirStore::new(&dir).with_max_size(16);
+    let loaded = block_on(store.load("large"));
+    let _ = fs::remove_dir_all(&dir);
+    assert!(matches!(loaded, Err(HrtError::TooLarge { size: 64, .. })));
+}
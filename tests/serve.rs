@@ -0,0 +1,149 @@
+//! `serve`, running as its own process and answering requests over a real socket.
+
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Child, ChildStdout, Stdio},
+};
+
+use common::DataDir;
+use hrtracker::http;
+
+/// A running `hrtracker serve`, killed when dropped.
+struct Server {
+    child: Child,
+    /// Kept open, since `serve` fails to print once nothing reads what it prints.
+    _stdout: BufReader<ChildStdout>,
+    addr: String,
+}
+impl Server {
+    /// Starts serving `dir` on a free port, with `extra` arguments.
+    fn start(dir: &DataDir, extra: &[&str]) -> Self {
+        let mut args = vec!["serve", "--addr", "127.0.0.1:0", "--porcelain"];
+        args.extend(extra);
+        let mut child = dir
+            .command(&args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("`serve` starts");
+        // with `--porcelain`, the first line is the address it is listening on
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut addr = String::new();
+        stdout.read_line(&mut addr).unwrap();
+        Self {
+            child,
+            _stdout: stdout,
+            addr: addr.trim().to_string(),
+        }
+    }
+    /// Sends `raw` as the whole request, returning the status and body of the response.
+    fn send(&self, raw: &[u8]) -> (u16, String) {
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        stream.write_all(raw).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or_else(|| panic!("no status in {response:?}"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, body.to_string())
+    }
+    fn request(&self, method: &str, path: &str) -> (u16, String) {
+        self.send(format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+    }
+}
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn schedules_are_served_as_json() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "2020-01-01T00:00:00Z", "1d+00"]);
+    let server = Server::start(&dir, &[]);
+
+    assert_eq!(
+        server.request("GET", "/healthz"),
+        (200, r#"{"status":"ok"}"#.to_string())
+    );
+    let (status, body) = server.request("GET", "/schedules");
+    assert_eq!(status, 200);
+    assert!(body.starts_with(r#"[{"name":"water","next":"2020-01-01T00:00:00Z","#));
+    let (status, body) = server.request("GET", "/schedules/water?pretty");
+    assert_eq!(status, 200);
+    assert!(body.contains(r#""interval_seconds":86400,"overdue":true"#));
+    assert_eq!(server.request("GET", "/schedules/missing").0, 404);
+    assert_eq!(server.request("GET", "/elsewhere").0, 404);
+    assert_eq!(server.request("DELETE", "/schedules/water").0, 405);
+
+    // every request reads the data directory again
+    dir.ok(&["new", "pills", "2020-01-01T00:00:00Z", "12"]);
+    assert_eq!(server.request("GET", "/schedules/pills").0, 200);
+}
+
+#[test]
+fn stepping_needs_allow_step() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "2020-01-01T00:00:00Z", "1d+00"]);
+    let server = Server::start(&dir, &[]);
+    assert_eq!(server.request("POST", "/schedules/water/step").0, 405);
+    drop(server);
+
+    let server = Server::start(&dir, &["--allow-step"]);
+    let (status, body) = server.request("POST", "/schedules/water/step");
+    assert_eq!(status, 200);
+    assert!(body.contains(r#""next":"2020-01-02T00:00:00Z""#), "{body}");
+    assert_eq!(server.request("POST", "/schedules/missing/step").0, 404);
+}
+
+#[test]
+fn names_outside_the_schedules_are_not_found() {
+    let dir = DataDir::new();
+    dir.ok(&["new", "water", "2020-01-01T00:00:00Z", "1d+00"]);
+    let outside = DataDir::new();
+    outside.ok(&["new", "secret", "2020-01-01T00:00:00Z", "1d+00"]);
+    let secret = fs::read(outside.path.join("secret")).unwrap();
+    // a schedule where only a dotfile should be
+    fs::write(dir.path.join(".hidden"), &secret).unwrap();
+    let outside_name = outside.path.file_name().unwrap().to_str().unwrap();
+    let escaped = format!("/schedules/..%2F{outside_name}%2Fsecret");
+
+    let server = Server::start(&dir, &["--allow-step"]);
+    for path in [
+        escaped.as_str(),
+        "/schedules/.hidden",
+        "/schedules/%2Ehidden",
+        "/schedules/..",
+        "/schedules/water%2F..%2F.hidden",
+    ] {
+        assert_eq!(server.request("GET", path).0, 404, "GET {path}");
+        let step = format!("{path}/step");
+        assert_eq!(server.request("POST", &step).0, 404, "POST {step}");
+    }
+    assert_eq!(fs::read(outside.path.join("secret")).unwrap(), secret);
+    assert_eq!(fs::read(dir.path.join(".hidden")).unwrap(), secret);
+    assert_eq!(server.request("GET", "/schedules/water").0, 200);
+}
+
+#[test]
+fn malformed_requests_dont_stop_the_server() {
+    let dir = DataDir::new();
+    let server = Server::start(&dir, &[]);
+    assert_eq!(server.send(b"garbage\r\n\r\n").0, 400);
+    assert_eq!(server.send(b"GET healthz HTTP/1.1\r\n\r\n").0, 400);
+    assert_eq!(server.send(b"GET /healthz SPDY/3\r\n\r\n").0, 400);
+    assert_eq!(server.request("GET", "/schedules/%zz").0, 400);
+    // just over the limit, so that the server reads all of it before answering
+    assert_eq!(server.send(&[b'a'; http::MAX_HEAD + 1]).0, 400);
+    // a client that leaves without finishing its request
+    drop(TcpStream::connect(&server.addr).unwrap());
+    assert_eq!(server.request("GET", "/healthz").0, 200);
+}
@@ -0,0 +1,79 @@
+//! Many threads stepping the same schedules through `Tracker`, which must never lose a step.
+
+use std::thread;
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use hrtracker::{schedule::RegularSchedule, store::MemStore, tracker::Tracker};
+
+const THREADS: usize = 8;
+const STEPS: usize = 50;
+
+fn water() -> RegularSchedule {
+    let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+    RegularSchedule::create(start, TimeDelta::hours(1))
+}
+
+/// Runs `step` `STEPS` times in each of `THREADS` threads, which take turns using `trackers`.
+fn step_concurrently<T: Sync>(trackers: &[T], step: impl Fn(&T) + Sync) {
+    thread::scope(|scope| {
+        for index in 0..THREADS {
+            let tracker = &trackers[index % trackers.len()];
+            let step = &step;
+            scope.spawn(move || {
+                for _ in 0..STEPS {
+                    step(tracker);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn concurrent_steps_are_never_lost() {
+    let tracker = Tracker::new(MemStore::new()).with_history_cap(100);
+    tracker.create("water", &water()).unwrap();
+    step_concurrently(&[&tracker], |tracker| {
+        tracker.step("water").unwrap();
+    });
+    let stepped = tracker.get("water").unwrap();
+    let total = THREADS * STEPS;
+    assert_eq!(stepped.total_steps(), total as u64);
+    assert_eq!(stepped.next, water().next + TimeDelta::hours(total as i64));
+    assert_eq!(stepped.history().len(), 100);
+    assert_eq!(stepped.satisfied().len(), 100);
+}
+
+#[test]
+fn concurrent_steps_of_different_schedules_stay_apart() {
+    let tracker = Tracker::new(MemStore::new());
+    for name in ["water", "pills"] {
+        tracker.create(name, &water()).unwrap();
+    }
+    step_concurrently(&[&tracker], |tracker| {
+        tracker.step("water").unwrap();
+        tracker.step("pills").unwrap();
+    });
+    let loaded = tracker.list().unwrap();
+    assert!(loaded.unreadable.is_empty());
+    for (_, schedule) in &loaded.schedules {
+        assert_eq!(schedule.total_steps(), (THREADS * STEPS) as u64);
+    }
+}
+
+/// Separate trackers on the same directory only share its lock file, as separate processes would.
+#[cfg(feature = "std-fs")]
+#[test]
+fn trackers_on_the_same_directory_never_lose_steps() {
+    use std::{env, fs, process};
+
+    let dir = env::temp_dir().join(format!("hrtracker-tracker-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let trackers: Vec<_> = (0..2).map(|_| Tracker::open(&dir).unwrap()).collect();
+    trackers[0].create("water", &water()).unwrap();
+    step_concurrently(&trackers, |tracker| {
+        tracker.step("water").unwrap();
+    });
+    let total_steps = trackers[1].get("water").unwrap().total_steps();
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(total_steps, (THREADS * STEPS) as u64);
+}